@@ -0,0 +1,27 @@
+use robotech::cfg::CfgError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("获取认证配置失败: {0}")]
+    AuthConfig(#[from] CfgError),
+    #[error("没有配置播放令牌的密钥")]
+    AuthNoSecret,
+    #[error("播放令牌格式不正确")]
+    AuthTokenFormat,
+    #[error("播放令牌签名不正确")]
+    AuthTokenSignature,
+    #[error("播放令牌已过期")]
+    AuthTokenExpired,
+    #[error("没有访问权限: {0}")]
+    AuthForbidden(String),
+    #[error("流的地址和摄像头ID不能都为空")]
+    AuthNoTarget,
+    #[error("摄像头{0}不存在")]
+    AuthCameraNotFound(String),
+    #[error("查询摄像头异常: {0}")]
+    AuthCameraQuery(String),
+    #[error("任务{0}不存在")]
+    AuthJobNotFound(String),
+    #[error("查询任务异常: {0}")]
+    AuthJobQuery(String),
+}
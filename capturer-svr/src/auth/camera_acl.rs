@@ -0,0 +1,122 @@
+use crate::auth::auth_error::AuthError;
+use crate::config::capturer_config::{get_capturer_config, AclConfig, AclPermission};
+use tracing::debug;
+
+/// 规则中表示所有摄像头的通配符
+const ALL_CAMERAS: &str = "*";
+
+/// 摄像头访问控制
+///
+/// 按配置的角色及规则检查用户对摄像头的权限，没有开启访问控制时都允许
+pub struct CameraAcl;
+
+impl CameraAcl {
+    /// 检查用户对摄像头的权限
+    ///
+    /// ## 参数
+    /// * `user_id` - 用户ID
+    /// * `camera_id` - 摄像头ID
+    /// * `camera_group` - 摄像头的分组
+    /// * `permission` - 需要的权限
+    pub fn check(
+        user_id: u64,
+        camera_id: &str,
+        camera_group: Option<&str>,
+        permission: AclPermission,
+    ) -> Result<(), AuthError> {
+        let capturer_config = get_capturer_config()?;
+        let acl = &capturer_config.acl;
+        if Self::allowed(acl, user_id, camera_id, camera_group, Some(permission)) {
+            return Ok(());
+        }
+        debug!(
+            "用户{user_id}没有摄像头{camera_id}的{}权限",
+            permission.name()
+        );
+        Err(AuthError::AuthForbidden(format!(
+            "摄像头{camera_id}的{}权限",
+            permission.name()
+        )))
+    }
+
+    /// 检查用户访问没有登记为摄像头的流地址(开启访问控制后只有管理员可以访问)
+    pub fn check_unregistered(user_id: u64) -> Result<(), AuthError> {
+        if Self::is_admin(user_id)? {
+            return Ok(());
+        }
+        debug!("用户{user_id}不能访问没有登记为摄像头的流地址");
+        Err(AuthError::AuthForbidden(
+            "没有登记为摄像头的流地址".to_string(),
+        ))
+    }
+
+    /// 用户是否为管理员(没有开启访问控制时所有用户都视为管理员)
+    pub fn is_admin(user_id: u64) -> Result<bool, AuthError> {
+        let capturer_config = get_capturer_config()?;
+        let acl = &capturer_config.acl;
+        Ok(!acl.enabled || acl.admin_users.contains(&user_id))
+    }
+
+    /// 检查用户是否为管理员(用于查看运维信息)
+    pub fn check_admin(user_id: u64) -> Result<(), AuthError> {
+        if Self::is_admin(user_id)? {
+            return Ok(());
+        }
+        debug!("用户{user_id}不是管理员");
+        Err(AuthError::AuthForbidden("管理员".to_string()))
+    }
+
+    /// 用户是否有摄像头的任一权限(用于过滤摄像头列表)
+    pub fn can_access(
+        user_id: u64,
+        camera_id: &str,
+        camera_group: Option<&str>,
+    ) -> Result<bool, AuthError> {
+        let capturer_config = get_capturer_config()?;
+        Ok(Self::allowed(
+            &capturer_config.acl,
+            user_id,
+            camera_id,
+            camera_group,
+            None,
+        ))
+    }
+
+    /// 按规则判断是否允许，`permission`为None时匹配任一权限
+    fn allowed(
+        acl: &AclConfig,
+        user_id: u64,
+        camera_id: &str,
+        camera_group: Option<&str>,
+        permission: Option<AclPermission>,
+    ) -> bool {
+        if !acl.enabled || acl.admin_users.contains(&user_id) {
+            return true;
+        }
+        let user_roles: Vec<&str> = acl
+            .roles
+            .iter()
+            .filter(|role| role.users.contains(&user_id))
+            .map(|role| role.name.as_str())
+            .collect();
+        acl.rules.iter().any(|rule| {
+            let user_matched = rule.users.contains(&user_id)
+                || rule
+                    .roles
+                    .iter()
+                    .any(|role| user_roles.contains(&role.as_str()));
+            let camera_matched = rule
+                .cameras
+                .iter()
+                .any(|id| id == ALL_CAMERAS || id == camera_id)
+                || camera_group.is_some_and(|camera_group| {
+                    rule.camera_groups.iter().any(|group| group == camera_group)
+                });
+            let permission_matched = match permission {
+                Some(permission) => rule.permissions.contains(&permission),
+                None => !rule.permissions.is_empty(),
+            };
+            user_matched && camera_matched && permission_matched
+        })
+    }
+}
@@ -0,0 +1,3 @@
+pub mod auth_error;
+pub mod camera_acl;
+pub mod playback_token;
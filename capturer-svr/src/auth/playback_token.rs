@@ -0,0 +1,156 @@
+use crate::auth::auth_error::AuthError;
+use crate::config::capturer_config::get_capturer_config;
+use crate::utils::hex_utils::{decode_hex, encode_hex};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 未配置密钥时读取的环境变量
+const PLAYBACK_TOKEN_SECRET_ENV: &str = "CAPTURER_PLAYBACK_TOKEN_SECRET";
+
+/// 播放令牌授权访问的目标
+#[derive(Debug, Clone)]
+pub enum PlaybackTarget {
+    /// 摄像头(直播及录像回放)
+    Camera(String),
+    /// 流地址(直播)
+    Url(String),
+}
+
+impl PlaybackTarget {
+    /// 根据请求中的摄像头ID或流地址确定目标，同时指定时以摄像头为准(与解析流地址一致)
+    pub fn from_request(camera_id: Option<&str>, stream_url: Option<&str>) -> Option<Self> {
+        match (camera_id, stream_url) {
+            (Some(camera_id), _) => Some(PlaybackTarget::Camera(camera_id.to_string())),
+            (None, Some(stream_url)) => Some(PlaybackTarget::Url(stream_url.to_string())),
+            (None, None) => None,
+        }
+    }
+
+    /// 参与签名的目标
+    fn signing_subject(&self) -> String {
+        match self {
+            PlaybackTarget::Camera(camera_id) => format!("camera:{camera_id}"),
+            PlaybackTarget::Url(stream_url) => format!("url:{stream_url}"),
+        }
+    }
+}
+
+/// 播放令牌
+///
+/// 令牌的格式为`用户ID.过期时间戳(秒).签名`，签名为HMAC-SHA256(目标、用户ID及过期时间)的十六进制；
+/// 令牌本身不包含目标，校验时按请求中的摄像头ID或流地址重新计算签名
+pub struct PlaybackToken;
+
+impl PlaybackToken {
+    /// 签发播放令牌
+    ///
+    /// ## 参数
+    /// * `target` - 授权访问的目标
+    /// * `user_id` - 令牌所属的用户ID
+    /// * `ttl` - 有效期，未指定时按配置的默认有效期，超过配置的最长有效期时按最长有效期
+    ///
+    /// ## 返回值
+    /// 返回(令牌, 过期时间)
+    pub fn issue(
+        target: &PlaybackTarget,
+        user_id: u64,
+        ttl: Option<Duration>,
+    ) -> Result<(String, DateTime<Utc>), AuthError> {
+        let capturer_config = get_capturer_config()?;
+        let config = &capturer_config.playback_token;
+        let mut ttl = match ttl {
+            Some(ttl) => ttl,
+            None => Self::default_ttl()?,
+        };
+        if let Some(max_ttl) = config.max_ttl {
+            ttl = ttl.min(max_ttl);
+        }
+        let expires_at = Utc::now() + ttl;
+        let expires_ts = expires_at.timestamp();
+        let signature = Self::sign(&Self::secret()?, target, user_id, expires_ts)?;
+        Ok((
+            format!("{user_id}.{expires_ts}.{}", encode_hex(&signature)),
+            expires_at,
+        ))
+    }
+
+    /// 校验播放令牌
+    ///
+    /// ## 返回值
+    /// 返回令牌所属的用户ID
+    pub fn verify(token: &str, target: &PlaybackTarget) -> Result<u64, AuthError> {
+        let mut parts = token.splitn(3, '.');
+        let (Some(user_id), Some(expires_ts), Some(signature)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(AuthError::AuthTokenFormat);
+        };
+        let user_id = user_id
+            .parse::<u64>()
+            .map_err(|_| AuthError::AuthTokenFormat)?;
+        let expires_ts = expires_ts
+            .parse::<i64>()
+            .map_err(|_| AuthError::AuthTokenFormat)?;
+        let signature = decode_hex(signature).ok_or(AuthError::AuthTokenFormat)?;
+
+        let mut mac = Self::mac(&Self::secret()?, target, user_id, expires_ts)?;
+        // 先校验签名(常量时间比较)，再校验是否过期，避免通过过期的响应探测签名
+        mac.verify_slice(&signature)
+            .map_err(|_| AuthError::AuthTokenSignature)?;
+        if Utc::now().timestamp() > expires_ts {
+            return Err(AuthError::AuthTokenExpired);
+        }
+        Ok(user_id)
+    }
+
+    /// 配置的默认有效期
+    pub fn default_ttl() -> Result<Duration, AuthError> {
+        Ok(get_capturer_config()?
+            .playback_token
+            .ttl
+            .unwrap_or(Duration::from_secs(5 * 60)))
+    }
+
+    /// 签名的密钥
+    fn secret() -> Result<String, AuthError> {
+        get_capturer_config()?
+            .playback_token
+            .secret
+            .clone()
+            .or_else(|| std::env::var(PLAYBACK_TOKEN_SECRET_ENV).ok())
+            .filter(|secret| !secret.is_empty())
+            .ok_or(AuthError::AuthNoSecret)
+    }
+
+    fn sign(
+        secret: &str,
+        target: &PlaybackTarget,
+        user_id: u64,
+        expires_ts: i64,
+    ) -> Result<Vec<u8>, AuthError> {
+        Ok(Self::mac(secret, target, user_id, expires_ts)?
+            .finalize()
+            .into_bytes()
+            .to_vec())
+    }
+
+    fn mac(
+        secret: &str,
+        target: &PlaybackTarget,
+        user_id: u64,
+        expires_ts: i64,
+    ) -> Result<HmacSha256, AuthError> {
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_bytes()).map_err(|_| AuthError::AuthNoSecret)?;
+        mac.update(target.signing_subject().as_bytes());
+        mac.update(b"\n");
+        mac.update(user_id.to_string().as_bytes());
+        mac.update(b"\n");
+        mac.update(expires_ts.to_string().as_bytes());
+        Ok(mac)
+    }
+}
@@ -0,0 +1,33 @@
+use crate::config::capturer_config::CameraConfig;
+use chrono::{DateTime, Utc};
+
+/// 摄像头的来源
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraSource {
+    /// 来自配置文件(只读)
+    Config,
+    /// 通过接口创建(保存在数据库中)
+    Api,
+}
+
+impl CameraSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraSource::Config => "config",
+            CameraSource::Api => "api",
+        }
+    }
+}
+
+/// 已登记的摄像头
+#[derive(Debug, Clone)]
+pub struct RegisteredCamera {
+    /// 摄像头配置
+    pub camera: CameraConfig,
+    /// 摄像头的来源
+    pub source: CameraSource,
+    /// 创建的时间(来自配置文件的摄像头为None)
+    pub created_at: Option<DateTime<Utc>>,
+    /// 最后修改的时间(来自配置文件的摄像头为None)
+    pub updated_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,21 @@
+use robotech::cfg::CfgError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CameraError {
+    #[error("获取摄像头配置失败: {0}")]
+    CameraConfig(#[from] CfgError),
+    #[error("创建摄像头数据库目录失败: {0}")]
+    CameraIo(#[from] std::io::Error),
+    #[error("读写摄像头数据库失败: {0}")]
+    CameraDb(#[from] rusqlite::Error),
+    #[error("解析摄像头数据失败: {0}")]
+    CameraParse(#[from] serde_json::Error),
+    #[error("等待摄像头数据库操作完成失败: {0}")]
+    CameraJoin(String),
+    #[error("摄像头不存在: {0}")]
+    CameraNotFound(String),
+    #[error("摄像头已存在: {0}")]
+    CameraExists(String),
+    #[error("配置文件中的摄像头不能通过接口修改: {0}")]
+    CameraReadOnly(String),
+}
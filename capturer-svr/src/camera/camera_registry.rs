@@ -0,0 +1,189 @@
+use crate::camera::camera_eo::{CameraSource, RegisteredCamera};
+use crate::camera::camera_error::CameraError;
+use crate::camera::camera_store::get_camera_store;
+use crate::config::capturer_config::{get_capturer_config, CameraConfig};
+use crate::credential::credential_store::get_credential_store;
+use crate::record::recorder::get_recorder;
+use crate::stream::stream_manager::get_stream_manager;
+use rustc_hash::FxHashMap;
+use std::sync::{LazyLock, RwLock};
+use tracing::{debug, warn};
+
+/// 流地址 -> 拉流失败时依次切换的流地址(同步摄像头时重建，避免每次切换都查询所有摄像头)
+static FAILOVER_STREAM_URLS: LazyLock<RwLock<FxHashMap<String, Vec<String>>>> =
+    LazyLock::new(Default::default);
+
+/// 摄像头登记
+///
+/// 合并配置文件和数据库中的摄像头，ID相同时以配置文件为准；
+/// 配置文件中的摄像头只读，数据库中的摄像头通过接口增删改
+pub struct CameraRegistry;
+
+impl CameraRegistry {
+    /// 列出所有摄像头(配置文件中的在前)
+    pub async fn list() -> Result<Vec<RegisteredCamera>, CameraError> {
+        let mut cameras: Vec<RegisteredCamera> = get_capturer_config()?
+            .cameras
+            .iter()
+            .cloned()
+            .map(Self::from_config)
+            .collect();
+        for camera in get_camera_store()?.list().await? {
+            if !cameras
+                .iter()
+                .any(|registered| registered.camera.id == camera.camera.id)
+            {
+                cameras.push(camera);
+            }
+        }
+        Ok(cameras)
+    }
+
+    /// 根据ID获取摄像头
+    pub async fn get(id: &str) -> Result<Option<RegisteredCamera>, CameraError> {
+        if let Some(camera) = get_capturer_config()?
+            .cameras
+            .iter()
+            .find(|camera| camera.id == id)
+        {
+            return Ok(Some(Self::from_config(camera.clone())));
+        }
+        get_camera_store()?.get(id).await
+    }
+
+    /// 根据流地址(主码流、子码流或备用的流地址)查找摄像头
+    pub async fn find_by_stream_url(
+        stream_url: &str,
+    ) -> Result<Option<RegisteredCamera>, CameraError> {
+        Ok(Self::list().await?.into_iter().find(|registered| {
+            registered.camera.main_stream_url == stream_url
+                || registered.camera.sub_stream_url.as_deref() == Some(stream_url)
+                || registered
+                    .camera
+                    .fallback_stream_urls
+                    .iter()
+                    .any(|fallback_stream_url| fallback_stream_url == stream_url)
+        }))
+    }
+
+    /// 返回流地址拉流失败时依次切换的流地址
+    ///
+    /// 主码流切换到摄像头备用的流地址；子码流先切换到主码流，再切换到备用的流地址；
+    /// 不属于任何摄像头的流地址没有可切换的流地址
+    pub fn fallback_stream_urls(stream_url: &str) -> Vec<String> {
+        FAILOVER_STREAM_URLS
+            .read()
+            .ok()
+            .and_then(|failover_stream_urls| failover_stream_urls.get(stream_url).cloned())
+            .unwrap_or_default()
+    }
+
+    /// 新增摄像头
+    pub async fn create(camera: CameraConfig) -> Result<RegisteredCamera, CameraError> {
+        Self::check_writable(&camera.id, false)?;
+        let registered = get_camera_store()?.insert(camera).await?;
+        Self::sync_cameras().await?;
+        Ok(registered)
+    }
+
+    /// 修改摄像头
+    pub async fn update(camera: CameraConfig) -> Result<RegisteredCamera, CameraError> {
+        Self::check_writable(&camera.id, true)?;
+        let registered = get_camera_store()?.update(camera).await?;
+        Self::sync_cameras().await?;
+        Ok(registered)
+    }
+
+    /// 删除摄像头
+    pub async fn delete(id: &str) -> Result<(), CameraError> {
+        Self::check_writable(id, true)?;
+        get_camera_store()?.delete(id).await?;
+        // 摄像头的凭据一并删除，失败时不影响删除摄像头
+        let result = match get_credential_store() {
+            Ok(credential_store) => credential_store.delete(id).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("删除摄像头{id}的凭据失败: {e}");
+        }
+        Self::sync_cameras().await
+    }
+
+    /// 同步所有摄像头
+    ///
+    /// 先重建凭据存储中流地址的索引及切换流地址的索引，再将常驻拉流的摄像头同步到流管理器，并同步录像的摄像头；
+    /// 初始化摄像头存储、热加载配置(流管理器被替换)及通过接口修改摄像头后调用
+    pub async fn sync_cameras() -> Result<(), CameraError> {
+        let cameras: Vec<CameraConfig> = Self::list()
+            .await?
+            .into_iter()
+            .map(|registered| registered.camera)
+            .collect();
+        debug!("同步摄像头");
+        match get_credential_store() {
+            Ok(credential_store) => credential_store.index_cameras(&cameras),
+            Err(e) => warn!("凭据存储不可用，不能为摄像头注入凭据: {e}"),
+        }
+        Self::index_failover_stream_urls(&cameras);
+        get_stream_manager()?.sync_always_on_cameras(cameras);
+        // 录像的摄像头引用登记的摄像头，流地址可能已变化(启动时录像器在此之后初始化)
+        if let Ok(recorder) = get_recorder() {
+            recorder
+                .sync_cameras(get_capturer_config()?.record.cameras.clone())
+                .await;
+        }
+        Ok(())
+    }
+
+    /// 重建流地址切换的索引
+    fn index_failover_stream_urls(cameras: &[CameraConfig]) {
+        let mut failover_stream_urls = FxHashMap::default();
+        for camera in cameras {
+            failover_stream_urls.insert(
+                camera.main_stream_url.clone(),
+                camera.fallback_stream_urls.clone(),
+            );
+            if let Some(sub_stream_url) = &camera.sub_stream_url
+                && *sub_stream_url != camera.main_stream_url
+            {
+                let stream_urls = std::iter::once(&camera.main_stream_url)
+                    .chain(&camera.fallback_stream_urls)
+                    .filter(|stream_url| *stream_url != sub_stream_url)
+                    .cloned()
+                    .collect();
+                failover_stream_urls.insert(sub_stream_url.clone(), stream_urls);
+            }
+        }
+        match FAILOVER_STREAM_URLS.write() {
+            Ok(mut lock) => *lock = failover_stream_urls,
+            Err(e) => warn!("重建流地址切换的索引失败: {e}"),
+        }
+    }
+
+    /// 检查摄像头是否可以通过接口修改
+    ///
+    /// 配置文件中的摄像头只读；新增时ID不能与配置文件中的摄像头重复
+    fn check_writable(id: &str, exists: bool) -> Result<(), CameraError> {
+        if get_capturer_config()?
+            .cameras
+            .iter()
+            .any(|camera| camera.id == id)
+        {
+            return Err(if exists {
+                CameraError::CameraReadOnly(id.to_string())
+            } else {
+                CameraError::CameraExists(id.to_string())
+            });
+        }
+        Ok(())
+    }
+
+    fn from_config(camera: CameraConfig) -> RegisteredCamera {
+        RegisteredCamera {
+            camera,
+            source: CameraSource::Config,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::camera::camera_eo::{CameraSource, RegisteredCamera};
+use crate::camera::camera_error::CameraError;
+use crate::config::capturer_config::{get_capturer_config, CameraConfig};
+use chrono::{DateTime, Utc};
+use robotech::cfg::CfgError;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::info;
+
+/// 全局静态的摄像头存储实例
+static CAMERA_STORE: OnceLock<CameraStore> = OnceLock::new();
+
+/// 查询摄像头的字段
+const CAMERA_COLUMNS: &str = "id, name, group_name, main_stream_url, sub_stream_url, tags, always_on, created_at, updated_at, fallback_stream_urls";
+
+/// 初始化摄像头存储
+///
+/// 打开(不存在时创建)配置的SQLite数据库，并创建摄像头表(旧的表补充新增的字段)
+pub fn init_camera_store() -> Result<(), CameraError> {
+    let db_path = get_capturer_config()?.camera_store.db_path.clone();
+    info!("初始化摄像头存储: {db_path}");
+    if let Some(parent) = Path::new(&db_path).parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    let conn = Connection::open(&db_path)?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS cameras (
+            id TEXT PRIMARY KEY,
+            name TEXT,
+            group_name TEXT,
+            main_stream_url TEXT NOT NULL,
+            sub_stream_url TEXT,
+            tags TEXT NOT NULL DEFAULT '[]',
+            always_on INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            fallback_stream_urls TEXT NOT NULL DEFAULT '[]'
+        );
+        CREATE INDEX IF NOT EXISTS idx_cameras_group_name ON cameras (group_name);",
+    )?;
+    let has_fallback_stream_urls = conn
+        .prepare("SELECT 1 FROM pragma_table_info('cameras') WHERE name = 'fallback_stream_urls'")?
+        .exists([])?;
+    if !has_fallback_stream_urls {
+        info!("摄像头表增加备用流地址的字段");
+        conn.execute_batch(
+            "ALTER TABLE cameras ADD COLUMN fallback_stream_urls TEXT NOT NULL DEFAULT '[]';",
+        )?;
+    }
+    CAMERA_STORE
+        .set(CameraStore {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+        .map_err(|_| CfgError::Init("CameraStore init failed".to_string()).into())
+}
+
+pub fn get_camera_store() -> Result<&'static CameraStore, CameraError> {
+    CAMERA_STORE
+        .get()
+        .ok_or(CfgError::NotInit("CameraStore not initialized".to_string()).into())
+}
+
+/// 摄像头存储
+///
+/// 保存通过接口管理的摄像头；SQLite的操作是同步的，放到阻塞线程中执行
+pub struct CameraStore {
+    /// 数据库连接
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl CameraStore {
+    /// 列出所有摄像头(按ID排序)
+    pub async fn list(&self) -> Result<Vec<RegisteredCamera>, CameraError> {
+        self.execute(|conn| {
+            let mut stmt =
+                conn.prepare(&format!("SELECT {CAMERA_COLUMNS} FROM cameras ORDER BY id"))?;
+            let rows = stmt.query_map([], Self::map_row)?;
+            let mut cameras = Vec::new();
+            for row in rows {
+                cameras.push(row??);
+            }
+            Ok(cameras)
+        })
+        .await
+    }
+
+    /// 根据ID获取摄像头
+    pub async fn get(&self, id: &str) -> Result<Option<RegisteredCamera>, CameraError> {
+        let id = id.to_string();
+        self.execute(move |conn| {
+            conn.query_row(
+                &format!("SELECT {CAMERA_COLUMNS} FROM cameras WHERE id = ?1"),
+                params![id],
+                Self::map_row,
+            )
+            .optional()?
+            .transpose()
+        })
+        .await
+    }
+
+    /// 新增摄像头
+    pub async fn insert(&self, camera: CameraConfig) -> Result<RegisteredCamera, CameraError> {
+        self.execute(move |conn| {
+            let now = Utc::now();
+            let tags = serde_json::to_string(&camera.tags)?;
+            let fallback_stream_urls = serde_json::to_string(&camera.fallback_stream_urls)?;
+            let inserted = conn.execute(
+                "INSERT OR IGNORE INTO cameras
+                    (id, name, group_name, main_stream_url, sub_stream_url, tags, always_on, created_at, updated_at, fallback_stream_urls)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8, ?9)",
+                params![
+                    camera.id,
+                    camera.name,
+                    camera.group,
+                    camera.main_stream_url,
+                    camera.sub_stream_url,
+                    tags,
+                    camera.always_on,
+                    now.timestamp_millis(),
+                    fallback_stream_urls,
+                ],
+            )?;
+            if inserted == 0 {
+                return Err(CameraError::CameraExists(camera.id));
+            }
+            Ok(RegisteredCamera {
+                camera,
+                source: CameraSource::Api,
+                created_at: Some(now),
+                updated_at: Some(now),
+            })
+        })
+        .await
+    }
+
+    /// 修改摄像头
+    pub async fn update(&self, camera: CameraConfig) -> Result<RegisteredCamera, CameraError> {
+        self.execute(move |conn| {
+            let now = Utc::now();
+            let tags = serde_json::to_string(&camera.tags)?;
+            let fallback_stream_urls = serde_json::to_string(&camera.fallback_stream_urls)?;
+            let updated = conn.execute(
+                "UPDATE cameras SET name = ?2, group_name = ?3, main_stream_url = ?4, sub_stream_url = ?5,
+                    tags = ?6, always_on = ?7, updated_at = ?8, fallback_stream_urls = ?9
+                    WHERE id = ?1",
+                params![
+                    camera.id,
+                    camera.name,
+                    camera.group,
+                    camera.main_stream_url,
+                    camera.sub_stream_url,
+                    tags,
+                    camera.always_on,
+                    now.timestamp_millis(),
+                    fallback_stream_urls,
+                ],
+            )?;
+            if updated == 0 {
+                return Err(CameraError::CameraNotFound(camera.id));
+            }
+            let created_at = conn
+                .query_row(
+                    "SELECT created_at FROM cameras WHERE id = ?1",
+                    params![camera.id],
+                    |row| row.get::<_, i64>(0),
+                )
+                .ok()
+                .and_then(DateTime::from_timestamp_millis);
+            Ok(RegisteredCamera {
+                camera,
+                source: CameraSource::Api,
+                created_at,
+                updated_at: Some(now),
+            })
+        })
+        .await
+    }
+
+    /// 删除摄像头
+    pub async fn delete(&self, id: &str) -> Result<(), CameraError> {
+        let id = id.to_string();
+        self.execute(move |conn| {
+            if conn.execute("DELETE FROM cameras WHERE id = ?1", params![id])? == 0 {
+                return Err(CameraError::CameraNotFound(id));
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// 在阻塞线程中执行数据库操作
+    async fn execute<T, F>(&self, f: F) -> Result<T, CameraError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, CameraError> + Send + 'static,
+    {
+        let conn = Arc::clone(&self.conn);
+        tokio::task::spawn_blocking(move || {
+            let conn = conn
+                .lock()
+                .map_err(|e| CameraError::CameraJoin(format!("无法获取数据库连接锁: {e}")))?;
+            f(&conn)
+        })
+        .await
+        .map_err(|e| CameraError::CameraJoin(e.to_string()))?
+    }
+
+    /// 将查询的行转换为摄像头
+    fn map_row(row: &Row) -> rusqlite::Result<Result<RegisteredCamera, CameraError>> {
+        let tags: String = row.get(5)?;
+        let tags = match serde_json::from_str(&tags) {
+            Ok(tags) => tags,
+            Err(e) => return Ok(Err(e.into())),
+        };
+        let fallback_stream_urls: String = row.get(9)?;
+        let fallback_stream_urls = match serde_json::from_str(&fallback_stream_urls) {
+            Ok(fallback_stream_urls) => fallback_stream_urls,
+            Err(e) => return Ok(Err(e.into())),
+        };
+        Ok(Ok(RegisteredCamera {
+            camera: CameraConfig {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                group: row.get(2)?,
+                main_stream_url: row.get(3)?,
+                sub_stream_url: row.get(4)?,
+                fallback_stream_urls,
+                tags,
+                always_on: row.get(6)?,
+            },
+            source: CameraSource::Api,
+            created_at: DateTime::from_timestamp_millis(row.get(7)?),
+            updated_at: DateTime::from_timestamp_millis(row.get(8)?),
+        }))
+    }
+}
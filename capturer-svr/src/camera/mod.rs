@@ -0,0 +1,4 @@
+pub mod camera_eo;
+pub mod camera_error;
+pub mod camera_registry;
+pub mod camera_store;
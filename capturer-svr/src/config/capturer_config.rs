@@ -1,9 +1,11 @@
 use arc_swap::ArcSwap;
+use ipnet::IpNet;
 use robotech::cfg::CfgError;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::net::IpAddr;
 use std::sync::{Arc, OnceLock};
 use std::time::Duration;
-use tracing::info;
+use tracing::{info, warn};
 use wheel_rs::serde::duration_option_serde;
 
 static CAPTURER_CONFIG: OnceLock<ArcSwap<CapturerConfig>> = OnceLock::new();
@@ -39,12 +41,401 @@ pub fn update_capturer_config(capturer_config: CapturerConfig) -> Result<(), Cfg
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct CapturerConfig {
+    /// 服务节点名称(写入抓拍的元数据中，未配置时取环境变量HOSTNAME)
+    pub node_name: Option<String>,
     #[serde(default = "CmdConfig::default")]
     pub cmd: CmdConfig,
     #[serde(default = "SessionConfig::default")]
     pub session: SessionConfig,
     #[serde(default = "OssConfig::default")]
     pub oss: OssConfig,
+    #[serde(default = "CaptureConfig::default")]
+    pub capture: CaptureConfig,
+    #[serde(default = "SpoolConfig::default")]
+    pub spool: SpoolConfig,
+    /// 定时抓拍任务
+    #[serde(default)]
+    pub jobs: Vec<JobConfig>,
+    #[serde(default = "SnapshotArchiveConfig::default")]
+    pub snapshot_archive: SnapshotArchiveConfig,
+    #[serde(default = "RingBufferConfig::default")]
+    pub ring_buffer: RingBufferConfig,
+    #[serde(default = "RecordConfig::default")]
+    pub record: RecordConfig,
+    /// 摄像头
+    #[serde(default)]
+    pub cameras: Vec<CameraConfig>,
+    #[serde(default = "CameraStoreConfig::default")]
+    pub camera_store: CameraStoreConfig,
+    #[serde(default = "UrlPolicyConfig::default")]
+    pub url_policy: UrlPolicyConfig,
+    #[serde(default = "PlaybackTokenConfig::default")]
+    pub playback_token: PlaybackTokenConfig,
+    #[serde(default = "AclConfig::default")]
+    pub acl: AclConfig,
+    #[serde(default = "QuotaConfig::default")]
+    pub quota: QuotaConfig,
+    #[serde(default = "BudgetConfig::default")]
+    pub budget: BudgetConfig,
+    #[serde(default = "TranscodeConfig::default")]
+    pub transcode: TranscodeConfig,
+}
+
+/// 摄像头配置
+///
+/// 客户端通过摄像头ID访问流，流地址(含认证信息)不需要经过浏览器
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct CameraConfig {
+    /// 摄像头ID
+    pub id: String,
+    /// 摄像头名称
+    pub name: Option<String>,
+    /// 分组
+    pub group: Option<String>,
+    /// 主码流的地址
+    pub main_stream_url: String,
+    /// 子码流的地址
+    pub sub_stream_url: Option<String>,
+    /// 备用的流地址(如转发服务器的地址)，主码流或子码流拉流失败或卡住时按顺序切换，
+    /// 切换后定时尝试切回。备用的流地址同样注入摄像头的凭据，地址中不能带有用户信息
+    #[serde(default)]
+    pub fallback_stream_urls: Vec<String>,
+    /// 标签
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 是否常驻拉流(默认false)，开启后没有观看者时也保持主码流的会话，断开后自动重新拉流
+    #[serde(default)]
+    pub always_on: bool,
+}
+
+/// 摄像头存储配置
+///
+/// 通过接口管理的摄像头及摄像头的凭据保存在本地的SQLite数据库中(修改数据库路径或密钥需要重启服务)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct CameraStoreConfig {
+    /// 数据库文件的路径(默认为capturer.db)
+    #[serde(default = "camera_db_path_default")]
+    pub db_path: String,
+    /// 摄像头凭据的加密密钥(64位十六进制，即32字节的AES-256密钥)，
+    /// 未配置时取环境变量CAPTURER_CREDENTIAL_KEY，都没有时不能保存凭据
+    pub credential_key: Option<String>,
+}
+
+/// 流地址策略配置
+///
+/// 在调用ffmpeg之前检查流地址，防止通过流地址读取本地文件或访问内部服务(SSRF)；
+/// 拒绝的列表优先，允许的主机和网段都为空时不限制主机
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct UrlPolicyConfig {
+    /// 允许的协议(默认为rtsp、rtsps、rtmp、rtmps)
+    ///
+    /// ffmpeg访问http(s)的地址时会跟随重定向，且解析域名与检查时的结果可能不同(DNS重绑定)，
+    /// 无法保证最终访问的地址符合策略，需要时再显式开启
+    #[serde(default = "url_policy_allowed_schemes_default")]
+    pub allowed_schemes: Vec<String>,
+    /// 允许的主机(支持`*.example.com`的形式匹配子域名)
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// 拒绝的主机(支持`*.example.com`的形式匹配子域名)
+    #[serde(default)]
+    pub denied_hosts: Vec<String>,
+    /// 允许的网段(如`192.168.0.0/16`)，主机为域名时按解析后的地址匹配
+    #[serde(default)]
+    pub allowed_cidrs: Vec<String>,
+    /// 拒绝的网段(默认拒绝本机及链路本地地址，包括云服务器的元数据地址169.254.169.254)
+    #[serde(default = "url_policy_denied_cidrs_default")]
+    pub denied_cidrs: Vec<String>,
+    /// 允许的端口(为空时不限制；地址中没有端口时按协议的默认端口)
+    #[serde(default)]
+    pub allowed_ports: Vec<u16>,
+    /// 传给ffmpeg的-protocol_whitelist参数，限制ffmpeg可以使用的协议(包括嵌套打开的协议)，
+    /// 开启http(s)协议时需要同时加入http、https、crypto等
+    #[serde(default = "url_policy_protocol_whitelist_default")]
+    pub protocol_whitelist: String,
+    /// 解析后的网段，每份配置只在第一次检查时解析一次
+    #[serde(skip)]
+    nets: OnceLock<UrlPolicyNets>,
+}
+
+/// 解析后的网段
+#[derive(Debug, Clone)]
+struct UrlPolicyNets {
+    /// 允许的网段
+    allowed: Vec<IpNet>,
+    /// 拒绝的网段
+    denied: Vec<IpNet>,
+}
+
+impl UrlPolicyConfig {
+    /// 解析后的允许的网段(忽略无效的网段)
+    pub fn allowed_nets(&self) -> &[IpNet] {
+        &self.nets().allowed
+    }
+
+    /// 解析后的拒绝的网段(忽略无效的网段)
+    pub fn denied_nets(&self) -> &[IpNet] {
+        &self.nets().denied
+    }
+
+    fn nets(&self) -> &UrlPolicyNets {
+        self.nets.get_or_init(|| UrlPolicyNets {
+            allowed: parse_cidrs(&self.allowed_cidrs),
+            denied: parse_cidrs(&self.denied_cidrs),
+        })
+    }
+}
+
+/// 解析配置的网段，忽略无效的网段
+fn parse_cidrs(cidrs: &[String]) -> Vec<IpNet> {
+    cidrs
+        .iter()
+        .filter_map(|cidr| match cidr.parse::<IpNet>() {
+            Ok(cidr) => Some(cidr),
+            Err(_) => match cidr.parse::<IpAddr>() {
+                Ok(ip) => Some(IpNet::from(ip)),
+                Err(e) => {
+                    warn!("忽略无效的网段{cidr}: {e}");
+                    None
+                }
+            },
+        })
+        .collect()
+}
+
+/// 播放令牌配置
+///
+/// 浏览器播放视频流时无法设置请求头，可以先通过请求头认证获取签名的播放令牌，
+/// 再将令牌作为查询参数访问视频流
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PlaybackTokenConfig {
+    /// 签名的密钥，未配置时取环境变量CAPTURER_PLAYBACK_TOKEN_SECRET，都没有时不能签发令牌
+    pub secret: Option<String>,
+    /// 令牌默认的有效期(单位为秒，默认5*60)
+    #[serde(with = "duration_option_serde", default = "playback_token_ttl_default")]
+    pub ttl: Option<Duration>,
+    /// 令牌最长的有效期(单位为秒，默认24*60*60)
+    #[serde(
+        with = "duration_option_serde",
+        default = "playback_token_max_ttl_default"
+    )]
+    pub max_ttl: Option<Duration>,
+}
+
+/// 摄像头访问控制配置
+///
+/// 开启后用户只能按规则访问摄像头，按流地址访问时按地址对应的摄像头检查，
+/// 没有登记为摄像头的流地址只有管理员可以访问；增删改摄像头及其凭据需要摄像头的管理权限
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct AclConfig {
+    /// 是否开启访问控制(默认false)
+    #[serde(default)]
+    pub enabled: bool,
+    /// 管理员用户ID(不受访问控制的限制)
+    #[serde(default)]
+    pub admin_users: Vec<u64>,
+    /// 角色
+    #[serde(default)]
+    pub roles: Vec<AclRoleConfig>,
+    /// 访问规则
+    #[serde(default)]
+    pub rules: Vec<AclRuleConfig>,
+}
+
+/// 访问控制的角色
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct AclRoleConfig {
+    /// 角色名称(如租户名称)
+    pub name: String,
+    /// 属于该角色的用户ID
+    #[serde(default)]
+    pub users: Vec<u64>,
+}
+
+/// 访问控制的规则
+///
+/// 规则授予用户(直接指定或通过角色)对摄像头(按ID或分组，`*`表示所有)的权限
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct AclRuleConfig {
+    /// 用户ID
+    #[serde(default)]
+    pub users: Vec<u64>,
+    /// 角色名称
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// 摄像头ID
+    #[serde(default)]
+    pub cameras: Vec<String>,
+    /// 摄像头分组
+    #[serde(default)]
+    pub camera_groups: Vec<String>,
+    /// 授予的权限
+    #[serde(default)]
+    pub permissions: Vec<AclPermission>,
+}
+
+/// 摄像头的访问权限
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AclPermission {
+    /// 观看直播
+    Live,
+    /// 抓拍图片
+    Snapshot,
+    /// 录制短视频
+    Clip,
+    /// 回放录像
+    Playback,
+    /// 管理摄像头(增删改摄像头及其凭据)
+    Manage,
+}
+
+impl AclPermission {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AclPermission::Live => "live",
+            AclPermission::Snapshot => "snapshot",
+            AclPermission::Clip => "clip",
+            AclPermission::Playback => "playback",
+            AclPermission::Manage => "manage",
+        }
+    }
+}
+
+/// 用户配额配置
+///
+/// 上限未配置时不限制；按用户配置的上限覆盖默认的上限，用户没有配置的上限按默认的上限
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct QuotaConfig {
+    /// 每个用户每分钟的抓拍次数上限(抓拍图片及录制短视频)
+    pub captures_per_minute: Option<u32>,
+    /// 每个用户同时观看直播的数量上限
+    pub max_live_streams: Option<u32>,
+    /// 每个用户每月上传到OSS的字节数上限
+    pub monthly_oss_bytes: Option<u64>,
+    /// 按用户配置的配额
+    #[serde(default)]
+    pub users: Vec<QuotaUserConfig>,
+}
+
+/// 按用户配置的配额
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct QuotaUserConfig {
+    /// 用户ID
+    pub user_id: u64,
+    /// 每分钟的抓拍次数上限
+    pub captures_per_minute: Option<u32>,
+    /// 同时观看直播的数量上限
+    pub max_live_streams: Option<u32>,
+    /// 每月上传到OSS的字节数上限
+    pub monthly_oss_bytes: Option<u64>,
+}
+
+impl CameraConfig {
+    /// 摄像头的流地址，没有配置子码流时使用主码流
+    pub fn stream_url(&self, sub_stream: bool) -> &str {
+        match &self.sub_stream_url {
+            Some(sub_stream_url) if sub_stream => sub_stream_url,
+            _ => &self.main_stream_url,
+        }
+    }
+}
+
+/// 预录缓冲配置
+///
+/// 为指定的流保持常驻会话，并滚动缓冲最近一段时长的视频，
+/// 触发事件时可以导出从事件前开始的短视频
+///
+/// 拒绝未知的配置项，已移到`session.reconnect-interval`的`reconnect-interval`仍配置时加载配置失败
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RingBufferConfig {
+    /// 开启预录缓冲的流地址
+    #[serde(default)]
+    pub stream_urls: Vec<String>,
+    /// 缓冲的时长(单位为秒，默认30)
+    #[serde(
+        with = "duration_option_serde",
+        default = "ring_buffer_duration_default"
+    )]
+    pub duration: Option<Duration>,
+}
+
+/// 录像配置
+///
+/// 为指定的摄像头保持常驻会话，连续录制按关键帧对齐的分段文件，
+/// 保存在`<录像目录>/<摄像头ID>/<yyyyMMdd>/`下
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct RecordConfig {
+    /// 录像目录(默认为recordings)
+    #[serde(default = "record_dir_default")]
+    pub dir: String,
+    /// 每个分段的时长(单位为秒，默认5*60)，分段在达到时长后的下一个关键帧处切分
+    #[serde(with = "duration_option_serde", default = "segment_duration_default")]
+    pub segment_duration: Option<Duration>,
+    /// 分段文件的格式(默认为flv)
+    #[serde(default)]
+    pub format: RecordFormat,
+    /// 录像的保留时长(单位为秒，默认7*24*60*60)
+    #[serde(with = "duration_option_serde", default = "record_retention_default")]
+    pub retention: Option<Duration>,
+    /// 录像目录的总大小上限(单位为MB，未配置时不限制)，超出时从最早的分段开始删除
+    pub max_total_size_mb: Option<u64>,
+    /// 清理过期录像的间隔(单位为秒，默认60)
+    #[serde(
+        with = "duration_option_serde",
+        default = "record_cleanup_interval_default"
+    )]
+    pub cleanup_interval: Option<Duration>,
+    /// 录像的摄像头
+    #[serde(default)]
+    pub cameras: Vec<RecordCameraConfig>,
+}
+
+/// 录像分段文件的格式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    /// 直接写入FLV
+    #[default]
+    Flv,
+    /// 分段写完后转封装为MP4
+    Mp4,
+}
+
+impl RecordFormat {
+    /// 文件扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordFormat::Flv => "flv",
+            RecordFormat::Mp4 => "mp4",
+        }
+    }
+}
+
+/// 录像的摄像头配置
+///
+/// 引用登记的摄像头(配置文件或数据库中的)，拉流时按摄像头的流地址注入凭据；
+/// 拒绝未知的配置项，不再支持的`stream-url`仍配置时加载配置失败
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RecordCameraConfig {
+    /// 摄像头ID(同时用作录像的目录名)
+    pub id: String,
+    /// 是否录制子码流(默认false，摄像头没有配置子码流时录制主码流)
+    #[serde(default)]
+    pub sub_stream: bool,
+    /// 分段文件的格式(未配置时使用全局的配置)
+    pub format: Option<RecordFormat>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -62,6 +453,12 @@ pub struct CmdConfig {
         default = "receiver_count_check_interval_default"
     )]
     pub receiver_count_check_interval: Option<Duration>,
+    /// 终止ffmpeg进程时，发送SIGTERM后等待其退出的宽限时间，超时后发送SIGKILL(单位为秒，默认5)
+    #[serde(
+        with = "duration_option_serde",
+        default = "terminate_grace_period_default"
+    )]
+    pub terminate_grace_period: Option<Duration>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -76,6 +473,65 @@ pub struct SessionConfig {
     /// 会话超时时间(单位为秒，默认30*60)
     #[serde(with = "duration_option_serde", default = "timeout_period_default")]
     pub timeout_period: Option<Duration>,
+    /// 常驻会话(预录缓冲、录像)断开后重新拉流的间隔(单位为秒，默认5)
+    #[serde(with = "duration_option_serde", default = "reconnect_interval_default")]
+    pub reconnect_interval: Option<Duration>,
+    /// 会话的ffmpeg进程退出后第一次重新拉流的间隔，之后每次失败间隔翻倍(单位为秒，默认1)
+    #[serde(
+        with = "duration_option_serde",
+        default = "respawn_initial_interval_default"
+    )]
+    pub respawn_initial_interval: Option<Duration>,
+    /// 会话重新拉流的最大间隔(单位为秒，默认30)
+    #[serde(
+        with = "duration_option_serde",
+        default = "respawn_max_interval_default"
+    )]
+    pub respawn_max_interval: Option<Duration>,
+    /// 会话连续重新拉流失败达到该次数后关闭会话(默认10，0表示不限次数)
+    #[serde(default = "respawn_max_attempts_default")]
+    pub respawn_max_attempts: u32,
+    /// 会话的ffmpeg进程超过该时间没有输出数据时认为拉流卡住(单位为秒，默认15，为0时不检查)
+    #[serde(with = "duration_option_serde", default = "stall_timeout_default")]
+    pub stall_timeout: Option<Duration>,
+    /// 拉流卡住时的处理方式(默认respawn)
+    #[serde(default)]
+    pub stall_action: StallAction,
+    /// 切换到备用的流地址后，尝试切回主码流的间隔(单位为秒，默认60)
+    #[serde(
+        with = "duration_option_serde",
+        default = "primary_retry_interval_default"
+    )]
+    pub primary_retry_interval: Option<Duration>,
+    /// 程序退出时等待所有会话关闭(终止并回收ffmpeg进程)及后台任务退出的宽限时间(单位为秒，默认10)
+    #[serde(
+        with = "duration_option_serde",
+        default = "shutdown_grace_period_default"
+    )]
+    pub shutdown_grace_period: Option<Duration>,
+    /// 会话事件(拉流卡住、重新拉流、切换拉流的地址)回调的地址(POST JSON)，未配置时只记录日志
+    #[serde(default)]
+    pub event_webhook: Option<String>,
+}
+
+/// 会话拉流卡住时的处理方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum StallAction {
+    /// 终止卡住的ffmpeg进程并重新拉流，观看者的连接不中断
+    #[default]
+    Respawn,
+    /// 关闭会话，观看者的流结束
+    Close,
+}
+
+impl StallAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StallAction::Respawn => "respawn",
+            StallAction::Close => "close",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -87,24 +543,277 @@ pub struct OssConfig {
     /// 转jpeg质量参数(1-31，数值越小质量越高，默认1)
     #[serde(default = "jpeg_quality_default")]
     pub jpeg_quality: u8,
+    /// 是否在抓拍的JPEG中嵌入XMP元数据(默认true)
+    #[serde(default = "embed_xmp_default")]
+    pub embed_xmp: bool,
+    /// 是否在抓拍的图片旁上传JSON格式的元数据文件(默认false)
+    #[serde(default)]
+    pub upload_sidecar: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct CaptureConfig {
+    /// 全局同时执行抓拍的ffmpeg进程的最大数量(默认8)，即ffmpeg进程预算中一次性抓拍池的大小
+    #[serde(default = "max_concurrent_default")]
+    pub max_concurrent: usize,
+    /// 等待抓拍许可的超时时间(单位为秒，默认30)
+    #[serde(with = "duration_option_serde", default = "acquire_timeout_default")]
+    pub acquire_timeout: Option<Duration>,
+    /// 批量抓拍时单个批次内的并发数量(默认4)
+    #[serde(default = "batch_concurrency_default")]
+    pub batch_concurrency: usize,
+    /// 批量抓拍单次请求的最大流数量(默认500)
+    #[serde(default = "max_batch_size_default")]
+    pub max_batch_size: usize,
+    /// 录制短视频的最大时长(秒，默认60)
+    #[serde(default = "max_clip_seconds_default")]
+    pub max_clip_seconds: u32,
+    /// 会话按需录像的最大时长(单位为秒，默认1小时)，超过后停止写入，等待调用停止录像上传
+    #[serde(
+        with = "duration_option_serde",
+        default = "max_record_duration_default"
+    )]
+    pub max_record_duration: Option<Duration>,
+}
+
+/// ffmpeg进程预算配置
+///
+/// 限制全局同时运行的ffmpeg进程数量，按转码会话、直通会话及一次性抓拍(抓拍图片、录制短视频等)分池，
+/// 一次性抓拍池的大小及等待超时时间沿用抓拍配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct BudgetConfig {
+    /// 全局同时运行的ffmpeg进程的最大数量(默认64)
+    #[serde(default = "max_processes_default")]
+    pub max_processes: usize,
+    /// 转码会话(H.265等转为H.264)的最大数量(默认4)
+    #[serde(default = "transcode_sessions_default")]
+    pub transcode_sessions: usize,
+    /// 直通会话(H.264不转码)的最大数量(默认32)
+    #[serde(default = "passthrough_sessions_default")]
+    pub passthrough_sessions: usize,
+    /// 会话等待进程预算的超时时间(单位为秒，默认10)
+    #[serde(
+        with = "duration_option_serde",
+        default = "session_acquire_timeout_default"
+    )]
+    pub session_acquire_timeout: Option<Duration>,
+    /// 预算用尽时是否先关闭空闲的会话(没有观看者、等待超时清除的会话)，默认true
+    #[serde(default = "evict_idle_sessions_default")]
+    pub evict_idle_sessions: bool,
+}
+
+/// 拉流转码的配置
+///
+/// 视频不是H.264时使用libx264转码为H.264(FLV需要)；热加载时配置变化的转码会话平滑地重新拉流。
+/// 加载配置时校验编码预设、码率控制等级及编码档次，不合法时加载配置失败(避免转码会话无法拉流)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct TranscodeConfig {
+    /// 编码预设(默认superfast，低延迟，比ultrafast稍慢但CPU占用更低)，见[`TRANSCODE_PRESETS`]
+    #[serde(
+        default = "transcode_preset_default",
+        deserialize_with = "transcode_preset_deserialize"
+    )]
+    pub preset: String,
+    /// 码率控制等级(0-51，数值越小质量越高，默认32)
+    #[serde(
+        default = "transcode_crf_default",
+        deserialize_with = "transcode_crf_deserialize"
+    )]
+    pub crf: u8,
+    /// 编码档次(默认baseline，编码复杂度最低)，见[`TRANSCODE_PROFILES`]
+    #[serde(
+        default = "transcode_profile_default",
+        deserialize_with = "transcode_profile_deserialize"
+    )]
+    pub profile: String,
+    /// 编码的线程数(默认1，限制线程数以减少CPU占用)
+    #[serde(default = "transcode_threads_default")]
+    pub threads: u8,
+}
+
+/// 定时抓拍任务配置
+///
+/// `cron`和`interval`必须且只能配置一个，`stream-url`和`camera-id`必须且只能配置一个
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct JobConfig {
+    /// 任务ID
+    pub id: String,
+    /// 抓拍流的地址
+    pub stream_url: Option<String>,
+    /// 抓拍的摄像头ID，每次运行时按任务的用户检查摄像头的抓拍权限，
+    /// 并使用摄像头当前登记的流地址(拉流时注入摄像头的凭据)
+    pub camera_id: Option<String>,
+    /// 是否抓拍摄像头的子码流(默认false，摄像头没有配置子码流时使用主码流)
+    #[serde(default)]
+    pub sub_stream: bool,
+    /// cron表达式(包含秒，如`0 */5 * * * *`表示每5分钟)
+    pub cron: Option<String>,
+    /// 抓拍间隔(如`10m`)
+    #[serde(with = "duration_option_serde", default)]
+    pub interval: Option<Duration>,
+    /// 存储桶(未配置时使用oss.bucket)
+    pub bucket: Option<String>,
+    /// 上传的文件名模板(不含扩展名)，支持的占位符:
+    /// `{job_id}`、`{yyyy}`、`{MM}`、`{dd}`、`{HH}`、`{mm}`、`{ss}`、`{ts}`
+    #[serde(default = "key_template_default")]
+    pub key_template: String,
+    /// 抓拍完成后回调的地址(POST JSON)
+    pub webhook: Option<String>,
+    /// 上传时使用的用户ID(默认0)
+    #[serde(default)]
+    pub user_id: u64,
+    /// 是否在本地归档抓拍的图片，用于生成延时视频(默认false)
+    #[serde(default)]
+    pub archive: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotArchiveConfig {
+    /// 定时任务抓拍图片的本地归档目录(默认为snapshots)
+    #[serde(default = "snapshot_archive_dir_default")]
+    pub dir: String,
+    /// 归档图片的保留时长(单位为秒，默认30天)
+    #[serde(with = "duration_option_serde", default = "retention_default")]
+    pub retention: Option<Duration>,
+    /// 清理过期归档图片的检查间隔(单位为秒，默认1小时)
+    #[serde(with = "duration_option_serde", default = "cleanup_interval_default")]
+    pub cleanup_interval: Option<Duration>,
+    /// 单个延时视频的最大帧数，超出时均匀抽帧(默认20000)
+    #[serde(default = "timelapse_max_frames_default")]
+    pub timelapse_max_frames: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct SpoolConfig {
+    /// 上传失败的抓拍暂存目录(默认为spool)
+    #[serde(default = "spool_dir_default")]
+    pub dir: String,
+    /// 扫描暂存目录的间隔(单位为秒，默认5)
+    #[serde(with = "duration_option_serde", default = "scan_interval_default")]
+    pub scan_interval: Option<Duration>,
+    /// 首次重试的间隔(单位为秒，默认5)，之后每次失败翻倍
+    #[serde(
+        with = "duration_option_serde",
+        default = "retry_initial_interval_default"
+    )]
+    pub retry_initial_interval: Option<Duration>,
+    /// 重试的最大间隔(单位为秒，默认10*60)
+    #[serde(with = "duration_option_serde", default = "retry_max_interval_default")]
+    pub retry_max_interval: Option<Duration>,
+    /// 重试上传的最大次数(默认100，0表示不限次数)，达到后移入死信目录不再重试
+    #[serde(default = "retry_max_attempts_default")]
+    pub retry_max_attempts: u32,
+    /// 死信目录(默认为spool/dead-letter)，保存多次重试仍上传失败的条目，由人工处理
+    #[serde(default = "dead_letter_dir_default")]
+    pub dead_letter_dir: String,
 }
 
 impl Default for CapturerConfig {
     fn default() -> Self {
         CapturerConfig {
+            node_name: None,
             cmd: CmdConfig::default(),
             session: SessionConfig::default(),
             oss: OssConfig::default(),
+            capture: CaptureConfig::default(),
+            spool: SpoolConfig::default(),
+            jobs: Vec::new(),
+            snapshot_archive: SnapshotArchiveConfig::default(),
+            ring_buffer: RingBufferConfig::default(),
+            record: RecordConfig::default(),
+            cameras: Vec::new(),
+            camera_store: CameraStoreConfig::default(),
+            url_policy: UrlPolicyConfig::default(),
+            playback_token: PlaybackTokenConfig::default(),
+            acl: AclConfig::default(),
+            quota: QuotaConfig::default(),
+            budget: BudgetConfig::default(),
+            transcode: TranscodeConfig::default(),
+        }
+    }
+}
+
+impl Default for UrlPolicyConfig {
+    fn default() -> Self {
+        UrlPolicyConfig {
+            allowed_schemes: url_policy_allowed_schemes_default(),
+            allowed_hosts: Vec::new(),
+            denied_hosts: Vec::new(),
+            allowed_cidrs: Vec::new(),
+            denied_cidrs: url_policy_denied_cidrs_default(),
+            allowed_ports: Vec::new(),
+            protocol_whitelist: url_policy_protocol_whitelist_default(),
+            nets: OnceLock::new(),
+        }
+    }
+}
+
+fn url_policy_allowed_schemes_default() -> Vec<String> {
+    ["rtsp", "rtsps", "rtmp", "rtmps"]
+        .map(String::from)
+        .to_vec()
+}
+
+fn url_policy_denied_cidrs_default() -> Vec<String> {
+    [
+        "127.0.0.0/8",
+        "0.0.0.0/8",
+        "169.254.0.0/16",
+        "::1/128",
+        "fe80::/10",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn url_policy_protocol_whitelist_default() -> String {
+    "rtsp,rtsps,rtmp,rtmps,rtp,srtp,udp,tcp,tls".to_string()
+}
+
+impl Default for PlaybackTokenConfig {
+    fn default() -> Self {
+        PlaybackTokenConfig {
+            secret: None,
+            ttl: playback_token_ttl_default(),
+            max_ttl: playback_token_max_ttl_default(),
         }
     }
 }
 
+fn playback_token_ttl_default() -> Option<Duration> {
+    Some(Duration::from_secs(5 * 60))
+}
+
+fn playback_token_max_ttl_default() -> Option<Duration> {
+    Some(Duration::from_secs(24 * 60 * 60))
+}
+
+impl Default for CameraStoreConfig {
+    fn default() -> Self {
+        CameraStoreConfig {
+            db_path: camera_db_path_default(),
+            credential_key: None,
+        }
+    }
+}
+
+fn camera_db_path_default() -> String {
+    "capturer.db".to_string()
+}
+
 impl Default for CmdConfig {
     fn default() -> Self {
         CmdConfig {
             read_buffer_size: read_buffer_size_default(),
             channel_capacity: channel_capacity_default(),
             receiver_count_check_interval: receiver_count_check_interval_default(),
+            terminate_grace_period: terminate_grace_period_default(),
         }
     }
 }
@@ -113,11 +822,24 @@ fn receiver_count_check_interval_default() -> Option<Duration> {
     Some(Duration::from_secs(5))
 }
 
+fn terminate_grace_period_default() -> Option<Duration> {
+    Some(Duration::from_secs(5))
+}
+
 impl Default for SessionConfig {
     fn default() -> Self {
         SessionConfig {
             timeout_check_interval: timeout_check_interval_default(),
             timeout_period: timeout_period_default(),
+            reconnect_interval: reconnect_interval_default(),
+            respawn_initial_interval: respawn_initial_interval_default(),
+            respawn_max_interval: respawn_max_interval_default(),
+            respawn_max_attempts: respawn_max_attempts_default(),
+            stall_timeout: stall_timeout_default(),
+            stall_action: StallAction::default(),
+            primary_retry_interval: primary_retry_interval_default(),
+            shutdown_grace_period: shutdown_grace_period_default(),
+            event_webhook: None,
         }
     }
 }
@@ -127,10 +849,279 @@ impl Default for OssConfig {
         OssConfig {
             bucket: bucket_default(),
             jpeg_quality: jpeg_quality_default(),
+            embed_xmp: embed_xmp_default(),
+            upload_sidecar: false,
+        }
+    }
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        BudgetConfig {
+            max_processes: max_processes_default(),
+            transcode_sessions: transcode_sessions_default(),
+            passthrough_sessions: passthrough_sessions_default(),
+            session_acquire_timeout: session_acquire_timeout_default(),
+            evict_idle_sessions: evict_idle_sessions_default(),
+        }
+    }
+}
+
+fn max_processes_default() -> usize {
+    64
+}
+
+fn transcode_sessions_default() -> usize {
+    4
+}
+
+fn passthrough_sessions_default() -> usize {
+    32
+}
+
+fn session_acquire_timeout_default() -> Option<Duration> {
+    Some(Duration::from_secs(10))
+}
+
+fn evict_idle_sessions_default() -> bool {
+    true
+}
+
+impl Default for TranscodeConfig {
+    fn default() -> Self {
+        TranscodeConfig {
+            preset: transcode_preset_default(),
+            crf: transcode_crf_default(),
+            profile: transcode_profile_default(),
+            threads: transcode_threads_default(),
+        }
+    }
+}
+
+/// libx264支持的编码预设
+pub const TRANSCODE_PRESETS: [&str; 10] = [
+    "ultrafast",
+    "superfast",
+    "veryfast",
+    "faster",
+    "fast",
+    "medium",
+    "slow",
+    "slower",
+    "veryslow",
+    "placebo",
+];
+
+/// 转码支持的编码档次(FLV播放器只支持8位4:2:0的H.264)
+pub const TRANSCODE_PROFILES: [&str; 3] = ["baseline", "main", "high"];
+
+/// libx264的码率控制等级的最大值
+const TRANSCODE_CRF_MAX: u8 = 51;
+
+fn transcode_preset_default() -> String {
+    "superfast".to_string()
+}
+
+fn transcode_preset_deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    let preset = String::deserialize(deserializer)?;
+    if !TRANSCODE_PRESETS.contains(&preset.as_str()) {
+        return Err(serde::de::Error::custom(format!(
+            "transcode.preset不支持{preset}，可选: {}",
+            TRANSCODE_PRESETS.join(", ")
+        )));
+    }
+    Ok(preset)
+}
+
+fn transcode_crf_default() -> u8 {
+    32
+}
+
+fn transcode_crf_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let crf = u8::deserialize(deserializer)?;
+    if crf > TRANSCODE_CRF_MAX {
+        return Err(serde::de::Error::custom(format!(
+            "transcode.crf的范围为0-{TRANSCODE_CRF_MAX}，不能为{crf}"
+        )));
+    }
+    Ok(crf)
+}
+
+fn transcode_profile_default() -> String {
+    "baseline".to_string()
+}
+
+fn transcode_profile_deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    let profile = String::deserialize(deserializer)?;
+    if !TRANSCODE_PROFILES.contains(&profile.as_str()) {
+        return Err(serde::de::Error::custom(format!(
+            "transcode.profile不支持{profile}，可选: {}",
+            TRANSCODE_PROFILES.join(", ")
+        )));
+    }
+    Ok(profile)
+}
+
+fn transcode_threads_default() -> u8 {
+    1
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig {
+            max_concurrent: max_concurrent_default(),
+            acquire_timeout: acquire_timeout_default(),
+            batch_concurrency: batch_concurrency_default(),
+            max_batch_size: max_batch_size_default(),
+            max_clip_seconds: max_clip_seconds_default(),
+            max_record_duration: max_record_duration_default(),
+        }
+    }
+}
+
+fn max_concurrent_default() -> usize {
+    8
+}
+
+fn acquire_timeout_default() -> Option<Duration> {
+    Some(Duration::from_secs(30))
+}
+
+fn batch_concurrency_default() -> usize {
+    4
+}
+
+fn max_batch_size_default() -> usize {
+    500
+}
+
+fn max_clip_seconds_default() -> u32 {
+    60
+}
+
+fn max_record_duration_default() -> Option<Duration> {
+    Some(Duration::from_secs(60 * 60))
+}
+
+pub fn key_template_default() -> String {
+    "{job_id}/{yyyy}{MM}{dd}/{HH}{mm}{ss}".to_string()
+}
+
+impl Default for SnapshotArchiveConfig {
+    fn default() -> Self {
+        SnapshotArchiveConfig {
+            dir: snapshot_archive_dir_default(),
+            retention: retention_default(),
+            cleanup_interval: cleanup_interval_default(),
+            timelapse_max_frames: timelapse_max_frames_default(),
+        }
+    }
+}
+
+fn snapshot_archive_dir_default() -> String {
+    "snapshots".to_string()
+}
+
+fn retention_default() -> Option<Duration> {
+    Some(Duration::from_secs(30 * 24 * 60 * 60))
+}
+
+fn cleanup_interval_default() -> Option<Duration> {
+    Some(Duration::from_secs(60 * 60))
+}
+
+fn timelapse_max_frames_default() -> usize {
+    20000
+}
+
+impl Default for RingBufferConfig {
+    fn default() -> Self {
+        RingBufferConfig {
+            stream_urls: Vec::new(),
+            duration: ring_buffer_duration_default(),
         }
     }
 }
 
+fn ring_buffer_duration_default() -> Option<Duration> {
+    Some(Duration::from_secs(30))
+}
+
+fn reconnect_interval_default() -> Option<Duration> {
+    Some(Duration::from_secs(5))
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        RecordConfig {
+            dir: record_dir_default(),
+            segment_duration: segment_duration_default(),
+            format: RecordFormat::default(),
+            retention: record_retention_default(),
+            max_total_size_mb: None,
+            cleanup_interval: record_cleanup_interval_default(),
+            cameras: Vec::new(),
+        }
+    }
+}
+
+fn record_dir_default() -> String {
+    "recordings".to_string()
+}
+
+fn segment_duration_default() -> Option<Duration> {
+    Some(Duration::from_secs(5 * 60))
+}
+
+fn record_retention_default() -> Option<Duration> {
+    Some(Duration::from_secs(7 * 24 * 60 * 60))
+}
+
+fn record_cleanup_interval_default() -> Option<Duration> {
+    Some(Duration::from_secs(60))
+}
+
+impl Default for SpoolConfig {
+    fn default() -> Self {
+        SpoolConfig {
+            dir: spool_dir_default(),
+            scan_interval: scan_interval_default(),
+            retry_initial_interval: retry_initial_interval_default(),
+            retry_max_interval: retry_max_interval_default(),
+            retry_max_attempts: retry_max_attempts_default(),
+            dead_letter_dir: dead_letter_dir_default(),
+        }
+    }
+}
+
+fn spool_dir_default() -> String {
+    "spool".to_string()
+}
+
+fn scan_interval_default() -> Option<Duration> {
+    Some(Duration::from_secs(5))
+}
+
+fn retry_initial_interval_default() -> Option<Duration> {
+    Some(Duration::from_secs(5))
+}
+
+fn retry_max_interval_default() -> Option<Duration> {
+    Some(Duration::from_secs(10 * 60))
+}
+
+fn retry_max_attempts_default() -> u32 {
+    100
+}
+
+fn dead_letter_dir_default() -> String {
+    "spool/dead-letter".to_string()
+}
+
 fn bucket_default() -> String {
     "capturer".to_string()
 }
@@ -138,6 +1129,10 @@ fn bucket_default() -> String {
 fn jpeg_quality_default() -> u8 {
     1
 }
+
+fn embed_xmp_default() -> bool {
+    true
+}
 fn timeout_check_interval_default() -> Option<Duration> {
     Some(Duration::from_secs(60))
 }
@@ -146,6 +1141,30 @@ fn timeout_period_default() -> Option<Duration> {
     Some(Duration::from_secs(30 * 60))
 }
 
+fn respawn_initial_interval_default() -> Option<Duration> {
+    Some(Duration::from_secs(1))
+}
+
+fn respawn_max_interval_default() -> Option<Duration> {
+    Some(Duration::from_secs(30))
+}
+
+fn respawn_max_attempts_default() -> u32 {
+    10
+}
+
+fn stall_timeout_default() -> Option<Duration> {
+    Some(Duration::from_secs(15))
+}
+
+fn primary_retry_interval_default() -> Option<Duration> {
+    Some(Duration::from_secs(60))
+}
+
+fn shutdown_grace_period_default() -> Option<Duration> {
+    Some(Duration::from_secs(10))
+}
+
 fn channel_capacity_default() -> usize {
     500
 }
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Formatter};
+
+/// 摄像头的凭据
+///
+/// 只在构建ffmpeg参数时注入到流地址中，Debug输出时不包含密码
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Credential {
+    /// 用户名
+    pub username: String,
+    /// 密码
+    pub password: String,
+}
+
+impl Debug for Credential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .finish()
+    }
+}
@@ -0,0 +1,17 @@
+use robotech::cfg::CfgError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CredentialError {
+    #[error("获取凭据配置失败: {0}")]
+    CredentialConfig(#[from] CfgError),
+    #[error("读写凭据数据库失败: {0}")]
+    CredentialDb(#[from] rusqlite::Error),
+    #[error("解析凭据数据失败: {0}")]
+    CredentialParse(#[from] serde_json::Error),
+    #[error("等待凭据数据库操作完成失败: {0}")]
+    CredentialJoin(String),
+    #[error("凭据的加密密钥无效: {0}")]
+    CredentialKey(String),
+    #[error("凭据加解密失败: {0}")]
+    CredentialCipher(String),
+}
@@ -0,0 +1,282 @@
+use crate::config::capturer_config::{get_capturer_config, CameraConfig};
+use crate::credential::credential_eo::Credential;
+use crate::credential::credential_error::CredentialError;
+use crate::utils::hex_utils::decode_hex;
+use crate::utils::url_utils::with_userinfo;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use chrono::Utc;
+use robotech::cfg::CfgError;
+use rusqlite::{params, Connection};
+use rustc_hash::FxHashMap;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use tracing::{info, warn};
+
+/// 全局静态的凭据存储实例
+static CREDENTIAL_STORE: OnceLock<CredentialStore> = OnceLock::new();
+
+/// 未配置密钥时读取的环境变量
+const CREDENTIAL_KEY_ENV: &str = "CAPTURER_CREDENTIAL_KEY";
+
+/// AES-GCM的随机数长度
+const NONCE_LEN: usize = 12;
+
+/// 初始化凭据存储
+///
+/// 凭据与摄像头保存在同一个SQLite数据库中，启动时将密文加载到内存，
+/// 并为配置文件中的摄像头建立流地址的索引
+pub fn init_credential_store() -> Result<(), CredentialError> {
+    let capturer_config = get_capturer_config()?;
+    let camera_store_config = &capturer_config.camera_store;
+    info!("初始化凭据存储: {}", camera_store_config.db_path);
+    let cipher = match camera_store_config
+        .credential_key
+        .clone()
+        .or_else(|| std::env::var(CREDENTIAL_KEY_ENV).ok())
+    {
+        Some(key) => Some(parse_key(&key)?),
+        None => {
+            warn!("没有配置凭据的加密密钥，不能保存摄像头的凭据");
+            None
+        }
+    };
+    let conn = Connection::open(&camera_store_config.db_path)?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS camera_credentials (
+            camera_id TEXT PRIMARY KEY,
+            secret BLOB NOT NULL,
+            updated_at INTEGER NOT NULL
+        );",
+    )?;
+    let mut secrets = FxHashMap::default();
+    {
+        let mut stmt = conn.prepare("SELECT camera_id, secret FROM camera_credentials")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })?;
+        for row in rows {
+            let (camera_id, secret) = row?;
+            secrets.insert(camera_id, secret);
+        }
+    }
+    info!("加载了{}个摄像头的凭据", secrets.len());
+
+    let credential_store = CredentialStore {
+        conn: Arc::new(Mutex::new(conn)),
+        cipher,
+        secrets: RwLock::new(secrets),
+        stream_urls: RwLock::new(FxHashMap::default()),
+    };
+    credential_store.index_cameras(&capturer_config.cameras);
+    CREDENTIAL_STORE
+        .set(credential_store)
+        .map_err(|_| CfgError::Init("CredentialStore init failed".to_string()).into())
+}
+
+pub fn get_credential_store() -> Result<&'static CredentialStore, CredentialError> {
+    CREDENTIAL_STORE
+        .get()
+        .ok_or(CfgError::NotInit("CredentialStore not initialized".to_string()).into())
+}
+
+/// # 为流地址注入凭据
+///
+/// 流地址(主码流、子码流或备用的流地址)属于某个有凭据的摄像头时，返回带有`user:password@`的地址；否则原样返回。
+/// 只用于构建ffmpeg的参数，返回的地址不能写入日志或错误信息
+pub fn inject_credential(stream_url: &str) -> String {
+    match get_credential_store() {
+        Ok(credential_store) => credential_store.inject(stream_url),
+        Err(_) => stream_url.to_string(),
+    }
+}
+
+/// 解析十六进制的密钥
+fn parse_key(key: &str) -> Result<Aes256Gcm, CredentialError> {
+    let bytes = decode_hex(key.trim())
+        .filter(|bytes| bytes.len() == 32)
+        .ok_or_else(|| CredentialError::CredentialKey("密钥必须是64位十六进制字符".to_string()))?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes)))
+}
+
+/// 凭据存储
+///
+/// 凭据加密后保存(随机数+密文，摄像头ID作为附加数据)，内存中也只保留密文，
+/// 注入到流地址时才解密
+pub struct CredentialStore {
+    /// 数据库连接
+    conn: Arc<Mutex<Connection>>,
+    /// 加解密器，没有配置密钥时为None
+    cipher: Option<Aes256Gcm>,
+    /// 摄像头ID -> 凭据的密文
+    secrets: RwLock<FxHashMap<String, Vec<u8>>>,
+    /// 摄像头的流地址(主码流、子码流及备用的流地址) -> 摄像头ID
+    stream_urls: RwLock<FxHashMap<String, String>>,
+}
+
+impl CredentialStore {
+    /// 摄像头是否有凭据
+    pub fn contains(&self, camera_id: &str) -> bool {
+        self.secrets
+            .read()
+            .map(|secrets| secrets.contains_key(camera_id))
+            .unwrap_or(false)
+    }
+
+    /// 保存摄像头的凭据(已有时覆盖)
+    pub async fn set(
+        &self,
+        camera_id: &str,
+        credential: Credential,
+    ) -> Result<(), CredentialError> {
+        let secret = self.encrypt(camera_id, &credential)?;
+        let conn = Arc::clone(&self.conn);
+        let id = camera_id.to_string();
+        let db_secret = secret.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().map_err(|e| {
+                CredentialError::CredentialJoin(format!("无法获取数据库连接锁: {e}"))
+            })?;
+            conn.execute(
+                "INSERT INTO camera_credentials (camera_id, secret, updated_at) VALUES (?1, ?2, ?3)
+                    ON CONFLICT(camera_id) DO UPDATE SET secret = ?2, updated_at = ?3",
+                params![id, db_secret, Utc::now().timestamp_millis()],
+            )?;
+            Ok::<_, CredentialError>(())
+        })
+        .await
+        .map_err(|e| CredentialError::CredentialJoin(e.to_string()))??;
+        if let Ok(mut secrets) = self.secrets.write() {
+            secrets.insert(camera_id.to_string(), secret);
+        }
+        info!("保存了摄像头{camera_id}的凭据");
+        Ok(())
+    }
+
+    /// 删除摄像头的凭据
+    ///
+    /// ## 返回值
+    /// 摄像头原来是否有凭据
+    pub async fn delete(&self, camera_id: &str) -> Result<bool, CredentialError> {
+        let conn = Arc::clone(&self.conn);
+        let id = camera_id.to_string();
+        let deleted = tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().map_err(|e| {
+                CredentialError::CredentialJoin(format!("无法获取数据库连接锁: {e}"))
+            })?;
+            Ok::<_, CredentialError>(
+                conn.execute(
+                    "DELETE FROM camera_credentials WHERE camera_id = ?1",
+                    params![id],
+                )? > 0,
+            )
+        })
+        .await
+        .map_err(|e| CredentialError::CredentialJoin(e.to_string()))??;
+        if let Ok(mut secrets) = self.secrets.write() {
+            secrets.remove(camera_id);
+        }
+        if deleted {
+            info!("删除了摄像头{camera_id}的凭据");
+        }
+        Ok(deleted)
+    }
+
+    /// 重建流地址到摄像头的索引
+    ///
+    /// 初始化、热加载配置及通过接口修改摄像头后调用
+    pub fn index_cameras(&self, cameras: &[CameraConfig]) {
+        let mut stream_urls = FxHashMap::default();
+        for camera in cameras {
+            stream_urls.insert(camera.main_stream_url.clone(), camera.id.clone());
+            if let Some(sub_stream_url) = &camera.sub_stream_url {
+                stream_urls.insert(sub_stream_url.clone(), camera.id.clone());
+            }
+            for fallback_stream_url in &camera.fallback_stream_urls {
+                stream_urls.insert(fallback_stream_url.clone(), camera.id.clone());
+            }
+        }
+        match self.stream_urls.write() {
+            Ok(mut lock) => *lock = stream_urls,
+            Err(_) => warn!("无法获取流地址索引的写锁"),
+        }
+    }
+
+    /// 为流地址注入凭据(流地址已带有用户信息时不覆盖)
+    pub fn inject(&self, stream_url: &str) -> String {
+        let Some(camera_id) = self
+            .stream_urls
+            .read()
+            .ok()
+            .and_then(|stream_urls| stream_urls.get(stream_url).cloned())
+        else {
+            return stream_url.to_string();
+        };
+        let Some(secret) = self
+            .secrets
+            .read()
+            .ok()
+            .and_then(|secrets| secrets.get(&camera_id).cloned())
+        else {
+            return stream_url.to_string();
+        };
+        match self.decrypt(&camera_id, &secret) {
+            Ok(credential) => with_userinfo(stream_url, &credential.username, &credential.password),
+            Err(e) => {
+                warn!("解密摄像头{camera_id}的凭据失败: {e}");
+                stream_url.to_string()
+            }
+        }
+    }
+
+    /// 加密凭据，返回随机数+密文
+    fn encrypt(
+        &self,
+        camera_id: &str,
+        credential: &Credential,
+    ) -> Result<Vec<u8>, CredentialError> {
+        let cipher = self.cipher()?;
+        let plaintext = serde_json::to_vec(credential)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: camera_id.as_bytes(),
+                },
+            )
+            .map_err(|e| CredentialError::CredentialCipher(e.to_string()))?;
+        let mut secret = nonce.to_vec();
+        secret.extend_from_slice(&ciphertext);
+        Ok(secret)
+    }
+
+    /// 解密凭据
+    fn decrypt(&self, camera_id: &str, secret: &[u8]) -> Result<Credential, CredentialError> {
+        let cipher = self.cipher()?;
+        if secret.len() <= NONCE_LEN {
+            return Err(CredentialError::CredentialCipher(
+                "密文长度无效".to_string(),
+            ));
+        }
+        let (nonce, ciphertext) = secret.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: camera_id.as_bytes(),
+                },
+            )
+            .map_err(|e| CredentialError::CredentialCipher(e.to_string()))?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn cipher(&self) -> Result<&Aes256Gcm, CredentialError> {
+        self.cipher.as_ref().ok_or_else(|| {
+            CredentialError::CredentialKey(format!(
+                "没有配置camera-store.credential-key或环境变量{CREDENTIAL_KEY_ENV}"
+            ))
+        })
+    }
+}
@@ -0,0 +1,3 @@
+pub mod credential_eo;
+pub mod credential_error;
+pub mod credential_store;
@@ -0,0 +1,170 @@
+use crate::policy::url_policy::{validate_fallback_stream_urls, validate_stream_url};
+use crate::utils::url_utils::mask_stream_url;
+use serde::Deserialize;
+use std::fmt::{Debug, Formatter};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(ToSchema, Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraListDto {
+    /// 按分组过滤
+    pub group: Option<String>,
+    /// 按标签过滤
+    pub tag: Option<String>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+#[derive(ToSchema, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraCreateDto {
+    /// 摄像头ID
+    #[validate(
+        required(message = "摄像头ID不能为空"),
+        length(min = 1, max = 64, message = "摄像头ID的长度必须在1-64之间")
+    )]
+    pub id: Option<String>,
+    /// 摄像头名称
+    pub name: Option<String>,
+    /// 分组
+    pub group: Option<String>,
+    /// 主码流的地址
+    #[validate(
+        required(message = "主码流的地址不能为空"),
+        length(min = 1, message = "主码流的地址不能为空"),
+        custom(function = "validate_stream_url")
+    )]
+    pub main_stream_url: Option<String>,
+    /// 子码流的地址
+    #[validate(custom(function = "validate_stream_url"))]
+    pub sub_stream_url: Option<String>,
+    /// 备用的流地址，主码流或子码流拉流失败或卡住时按顺序切换(不能带有用户信息，使用摄像头的凭据)
+    #[validate(custom(function = "validate_fallback_stream_urls"))]
+    pub fallback_stream_urls: Option<Vec<String>>,
+    /// 标签
+    pub tags: Option<Vec<String>>,
+    /// 是否常驻拉流(默认false)
+    pub always_on: Option<bool>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+// Debug输出时流地址脱敏，避免注入的凭据被请求日志记录
+impl Debug for CameraCreateDto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CameraCreateDto")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("group", &self.group)
+            .field(
+                "main_stream_url",
+                &self.main_stream_url.as_deref().map(mask_stream_url),
+            )
+            .field(
+                "sub_stream_url",
+                &self.sub_stream_url.as_deref().map(mask_stream_url),
+            )
+            .field(
+                "fallback_stream_urls",
+                &self.fallback_stream_urls.as_ref().map(|stream_urls| {
+                    stream_urls
+                        .iter()
+                        .map(|stream_url| mask_stream_url(stream_url))
+                        .collect::<Vec<_>>()
+                }),
+            )
+            .field("tags", &self.tags)
+            .field("always_on", &self.always_on)
+            .field("_current_user_id", &self._current_user_id)
+            .finish()
+    }
+}
+
+#[derive(ToSchema, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraUpdateDto {
+    /// 摄像头名称
+    pub name: Option<String>,
+    /// 分组
+    pub group: Option<String>,
+    /// 主码流的地址
+    #[validate(
+        required(message = "主码流的地址不能为空"),
+        length(min = 1, message = "主码流的地址不能为空"),
+        custom(function = "validate_stream_url")
+    )]
+    pub main_stream_url: Option<String>,
+    /// 子码流的地址
+    #[validate(custom(function = "validate_stream_url"))]
+    pub sub_stream_url: Option<String>,
+    /// 备用的流地址，主码流或子码流拉流失败或卡住时按顺序切换(不能带有用户信息，使用摄像头的凭据)
+    #[validate(custom(function = "validate_fallback_stream_urls"))]
+    pub fallback_stream_urls: Option<Vec<String>>,
+    /// 标签
+    pub tags: Option<Vec<String>>,
+    /// 是否常驻拉流(默认false)
+    pub always_on: Option<bool>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+impl Debug for CameraUpdateDto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CameraUpdateDto")
+            .field("name", &self.name)
+            .field("group", &self.group)
+            .field(
+                "main_stream_url",
+                &self.main_stream_url.as_deref().map(mask_stream_url),
+            )
+            .field(
+                "sub_stream_url",
+                &self.sub_stream_url.as_deref().map(mask_stream_url),
+            )
+            .field(
+                "fallback_stream_urls",
+                &self.fallback_stream_urls.as_ref().map(|stream_urls| {
+                    stream_urls
+                        .iter()
+                        .map(|stream_url| mask_stream_url(stream_url))
+                        .collect::<Vec<_>>()
+                }),
+            )
+            .field("tags", &self.tags)
+            .field("always_on", &self.always_on)
+            .field("_current_user_id", &self._current_user_id)
+            .finish()
+    }
+}
+
+/// 摄像头凭据(Debug输出时不包含密码，避免被请求日志记录)
+#[derive(ToSchema, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraCredentialDto {
+    /// 用户名
+    #[validate(
+        required(message = "用户名不能为空"),
+        length(min = 1, message = "用户名不能为空")
+    )]
+    pub username: Option<String>,
+    /// 密码
+    #[validate(required(message = "密码不能为空"))]
+    pub password: Option<String>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+impl Debug for CameraCredentialDto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CameraCredentialDto")
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("_current_user_id", &self._current_user_id)
+            .finish()
+    }
+}
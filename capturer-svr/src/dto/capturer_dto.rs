@@ -1,33 +1,209 @@
+use crate::policy::url_policy::{validate_stream_url, validate_stream_urls};
+use crate::utils::url_utils::mask_stream_url;
 use serde::Deserialize;
+use std::fmt::{Debug, Formatter};
 use utoipa::ToSchema;
 use validator::Validate;
 
-#[derive(ToSchema, Debug, Deserialize, Validate)]
+#[derive(ToSchema, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct CapturerCaptureToJpegDto {
-    /// 抓拍流的地址
+    /// 抓拍流的地址，与cameraId必须指定一个
     #[validate(
-        required(message = "抓拍流的地址不能为空"),
-        length(min = 1, message = "抓拍流的地址不能为空")
+        length(min = 1, message = "抓拍流的地址不能为空"),
+        custom(function = "validate_stream_url")
     )]
     pub stream_url: Option<String>,
+    /// 摄像头ID，与streamUrl必须指定一个(同时指定时使用摄像头的流地址)
+    #[validate(length(min = 1, message = "摄像头ID不能为空"))]
+    pub camera_id: Option<String>,
+    /// 是否使用摄像头的子码流(默认false，摄像头没有配置子码流时使用主码流)
+    pub sub_stream: Option<bool>,
     /// 存储桶
     pub bucket: Option<String>,
+    /// 是否在图片旁上传JSON格式的元数据文件(未指定时按配置)
+    pub sidecar: Option<bool>,
     /// 当前用户ID
     #[serde(skip_deserializing)]
     pub _current_user_id: u64,
 }
 
-#[derive(ToSchema, Debug, Deserialize, Validate)]
+// Debug输出时流地址脱敏，避免注入的凭据被请求日志记录
+impl Debug for CapturerCaptureToJpegDto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CapturerCaptureToJpegDto")
+            .field(
+                "stream_url",
+                &self.stream_url.as_deref().map(mask_stream_url),
+            )
+            .field("camera_id", &self.camera_id)
+            .field("sub_stream", &self.sub_stream)
+            .field("bucket", &self.bucket)
+            .field("sidecar", &self.sidecar)
+            .field("_current_user_id", &self._current_user_id)
+            .finish()
+    }
+}
+
+#[derive(ToSchema, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturerCaptureBatchDto {
+    /// 抓拍流的地址列表，与cameraIds至少指定一个
+    #[validate(custom(function = "validate_stream_urls"))]
+    pub stream_urls: Option<Vec<String>>,
+    /// 摄像头ID列表，与streamUrls至少指定一个
+    pub camera_ids: Option<Vec<String>>,
+    /// 是否使用摄像头的子码流(默认false，摄像头没有配置子码流时使用主码流)
+    pub sub_stream: Option<bool>,
+    /// 存储桶
+    pub bucket: Option<String>,
+    /// 是否在图片旁上传JSON格式的元数据文件(未指定时按配置)
+    pub sidecar: Option<bool>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+impl Debug for CapturerCaptureBatchDto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CapturerCaptureBatchDto")
+            .field(
+                "stream_urls",
+                &self.stream_urls.as_ref().map(|stream_urls| {
+                    stream_urls
+                        .iter()
+                        .map(|stream_url| mask_stream_url(stream_url))
+                        .collect::<Vec<_>>()
+                }),
+            )
+            .field("camera_ids", &self.camera_ids)
+            .field("sub_stream", &self.sub_stream)
+            .field("bucket", &self.bucket)
+            .field("sidecar", &self.sidecar)
+            .field("_current_user_id", &self._current_user_id)
+            .finish()
+    }
+}
+
+#[derive(ToSchema, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturerCaptureClipDto {
+    /// 录制流的地址，与cameraId必须指定一个
+    #[validate(
+        length(min = 1, message = "录制流的地址不能为空"),
+        custom(function = "validate_stream_url")
+    )]
+    pub stream_url: Option<String>,
+    /// 摄像头ID，与streamUrl必须指定一个(同时指定时使用摄像头的流地址)
+    #[validate(length(min = 1, message = "摄像头ID不能为空"))]
+    pub camera_id: Option<String>,
+    /// 是否使用摄像头的子码流(默认false，摄像头没有配置子码流时使用主码流)
+    pub sub_stream: Option<bool>,
+    /// 录制的时长(秒，不能超过配置的最大时长)
+    #[validate(
+        required(message = "录制的时长不能为空"),
+        range(min = 1, message = "录制的时长不能小于1秒")
+    )]
+    pub seconds: Option<u32>,
+    /// 存储桶
+    pub bucket: Option<String>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+impl Debug for CapturerCaptureClipDto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CapturerCaptureClipDto")
+            .field(
+                "stream_url",
+                &self.stream_url.as_deref().map(mask_stream_url),
+            )
+            .field("camera_id", &self.camera_id)
+            .field("sub_stream", &self.sub_stream)
+            .field("seconds", &self.seconds)
+            .field("bucket", &self.bucket)
+            .field("_current_user_id", &self._current_user_id)
+            .finish()
+    }
+}
+
+#[derive(ToSchema, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturerCaptureEventClipDto {
+    /// 录制流的地址(必须已开启预录缓冲)，与cameraId必须指定一个
+    #[validate(
+        length(min = 1, message = "录制流的地址不能为空"),
+        custom(function = "validate_stream_url")
+    )]
+    pub stream_url: Option<String>,
+    /// 摄像头ID，与streamUrl必须指定一个(同时指定时使用摄像头的流地址)
+    #[validate(length(min = 1, message = "摄像头ID不能为空"))]
+    pub camera_id: Option<String>,
+    /// 是否使用摄像头的子码流(默认false，摄像头没有配置子码流时使用主码流)
+    pub sub_stream: Option<bool>,
+    /// 事件前录制的时长(秒，未指定时导出整个预录缓冲)
+    pub pre_seconds: Option<u32>,
+    /// 事件后录制的时长(秒，不能超过配置的最大时长)
+    #[validate(required(message = "事件后录制的时长不能为空"))]
+    pub post_seconds: Option<u32>,
+    /// 存储桶
+    pub bucket: Option<String>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+impl Debug for CapturerCaptureEventClipDto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CapturerCaptureEventClipDto")
+            .field(
+                "stream_url",
+                &self.stream_url.as_deref().map(mask_stream_url),
+            )
+            .field("camera_id", &self.camera_id)
+            .field("sub_stream", &self.sub_stream)
+            .field("pre_seconds", &self.pre_seconds)
+            .field("post_seconds", &self.post_seconds)
+            .field("bucket", &self.bucket)
+            .field("_current_user_id", &self._current_user_id)
+            .finish()
+    }
+}
+
+#[derive(ToSchema, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct CapturerGetStreamDto {
-    /// 抓拍流的地址
+    /// 抓拍流的地址，与cameraId必须指定一个
     #[validate(
-        required(message = "抓拍流的地址不能为空"),
-        length(min = 1, message = "抓拍流的地址不能为空")
+        length(min = 1, message = "抓拍流的地址不能为空"),
+        custom(function = "validate_stream_url")
     )]
     pub stream_url: Option<String>,
+    /// 摄像头ID，与streamUrl必须指定一个(同时指定时使用摄像头的流地址)
+    #[validate(length(min = 1, message = "摄像头ID不能为空"))]
+    #[serde(alias = "camera")]
+    pub camera_id: Option<String>,
+    /// 是否使用摄像头的子码流(默认false，摄像头没有配置子码流时使用主码流)
+    pub sub_stream: Option<bool>,
+    /// 播放令牌(浏览器无法设置请求头时使用，未指定时按请求头认证)
+    pub token: Option<String>,
     /// 当前用户ID
     #[serde(skip_deserializing)]
     pub _current_user_id: u64,
 }
+
+impl Debug for CapturerGetStreamDto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CapturerGetStreamDto")
+            .field(
+                "stream_url",
+                &self.stream_url.as_deref().map(mask_stream_url),
+            )
+            .field("camera_id", &self.camera_id)
+            .field("sub_stream", &self.sub_stream)
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .field("_current_user_id", &self._current_user_id)
+            .finish()
+    }
+}
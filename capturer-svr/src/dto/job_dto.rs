@@ -0,0 +1,91 @@
+use crate::policy::url_policy::{validate_stream_url, validate_webhook_url};
+use crate::utils::url_utils::mask_stream_url;
+use serde::Deserialize;
+use std::fmt::{Debug, Formatter};
+use std::time::Duration;
+use utoipa::ToSchema;
+use validator::Validate;
+use wheel_rs::serde::duration_option_serde;
+
+#[derive(ToSchema, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct JobCreateDto {
+    /// 任务ID
+    #[validate(
+        required(message = "任务ID不能为空"),
+        length(min = 1, max = 64, message = "任务ID的长度必须在1-64之间")
+    )]
+    pub id: Option<String>,
+    /// 抓拍流的地址，与cameraId必须指定一个
+    #[validate(
+        length(min = 1, message = "抓拍流的地址不能为空"),
+        custom(function = "validate_stream_url")
+    )]
+    pub stream_url: Option<String>,
+    /// 摄像头ID，与streamUrl必须指定一个(同时指定时使用摄像头)；
+    /// 每次运行时按创建者检查摄像头的抓拍权限，并使用摄像头当前登记的流地址
+    #[validate(length(min = 1, message = "摄像头ID不能为空"))]
+    pub camera_id: Option<String>,
+    /// 是否抓拍摄像头的子码流(默认false，摄像头没有配置子码流时使用主码流)
+    pub sub_stream: Option<bool>,
+    /// cron表达式(包含秒，如`0 */5 * * * *`表示每5分钟)，与interval必须且只能指定一个
+    pub cron: Option<String>,
+    /// 抓拍间隔(如`10m`)，与cron必须且只能指定一个
+    #[serde(with = "duration_option_serde", default)]
+    #[schema(value_type = Option<String>)]
+    pub interval: Option<Duration>,
+    /// 存储桶
+    pub bucket: Option<String>,
+    /// 上传的文件名模板(不含扩展名)，支持的占位符:
+    /// `{job_id}`、`{yyyy}`、`{MM}`、`{dd}`、`{HH}`、`{mm}`、`{ss}`、`{ts}`
+    pub key_template: Option<String>,
+    /// 抓拍完成后回调的地址(POST JSON，只允许http或https，按流地址策略拒绝的主机和网段检查)
+    #[validate(custom(function = "validate_webhook_url"))]
+    pub webhook: Option<String>,
+    /// 是否在本地归档抓拍的图片，用于生成延时视频
+    pub archive: Option<bool>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+// Debug输出时流地址脱敏，避免注入的凭据被请求日志记录
+impl Debug for JobCreateDto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobCreateDto")
+            .field("id", &self.id)
+            .field(
+                "stream_url",
+                &self.stream_url.as_deref().map(mask_stream_url),
+            )
+            .field("camera_id", &self.camera_id)
+            .field("sub_stream", &self.sub_stream)
+            .field("cron", &self.cron)
+            .field("interval", &self.interval)
+            .field("bucket", &self.bucket)
+            .field("key_template", &self.key_template)
+            .field("webhook", &self.webhook)
+            .field("archive", &self.archive)
+            .field("_current_user_id", &self._current_user_id)
+            .finish()
+    }
+}
+
+#[derive(ToSchema, Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct JobTimelapseDto {
+    /// 开始时间戳(毫秒)
+    #[validate(required(message = "开始时间不能为空"))]
+    pub start_ts: Option<i64>,
+    /// 结束时间戳(毫秒)
+    #[validate(required(message = "结束时间不能为空"))]
+    pub end_ts: Option<i64>,
+    /// 视频帧率(1-60，默认25)
+    #[validate(range(min = 1, max = 60, message = "视频帧率必须在1-60之间"))]
+    pub fps: Option<u8>,
+    /// 存储桶
+    pub bucket: Option<String>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
@@ -1 +1,7 @@
+pub mod camera_dto;
 pub mod capturer_dto;
+pub mod job_dto;
+pub mod quota_dto;
+pub mod record_dto;
+pub mod session_dto;
+pub mod token_dto;
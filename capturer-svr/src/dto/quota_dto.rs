@@ -0,0 +1,13 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(ToSchema, Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaUsageDto {
+    /// 要查询的用户ID(未指定时查询当前用户，查询其它用户需要是管理员)
+    pub user_id: Option<u64>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
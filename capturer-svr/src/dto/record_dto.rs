@@ -0,0 +1,65 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(ToSchema, Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordListDto {
+    /// 摄像头ID
+    #[validate(
+        required(message = "摄像头ID不能为空"),
+        length(min = 1, message = "摄像头ID不能为空")
+    )]
+    pub camera_id: Option<String>,
+    /// 开始时间戳(毫秒)
+    #[validate(required(message = "开始时间不能为空"))]
+    pub start_ts: Option<i64>,
+    /// 结束时间戳(毫秒)
+    #[validate(required(message = "结束时间不能为空"))]
+    pub end_ts: Option<i64>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+#[derive(ToSchema, Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordPlaybackDto {
+    /// 摄像头ID
+    #[validate(
+        required(message = "摄像头ID不能为空"),
+        length(min = 1, message = "摄像头ID不能为空")
+    )]
+    pub camera_id: Option<String>,
+    /// 开始回放的时间戳(毫秒)
+    #[validate(required(message = "开始时间不能为空"))]
+    pub start_ts: Option<i64>,
+    /// 结束回放的时间戳(毫秒)，未指定时HTTP-FLV回放到最新的录像，HLS回放到当前时间
+    pub end_ts: Option<i64>,
+    /// 播放令牌(浏览器无法设置请求头时使用，未指定时按请求头认证)
+    pub token: Option<String>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+#[derive(ToSchema, Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordHlsSegmentDto {
+    /// 摄像头ID
+    #[validate(
+        required(message = "摄像头ID不能为空"),
+        length(min = 1, message = "摄像头ID不能为空")
+    )]
+    pub camera_id: Option<String>,
+    /// 分段的开始时间戳(毫秒)
+    #[validate(required(message = "分段的开始时间不能为空"))]
+    pub segment_ts: Option<i64>,
+    /// 分段在回放中的时间偏移(毫秒)
+    pub offset_ms: Option<u64>,
+    /// 播放令牌(由播放列表带入分段的地址)
+    pub token: Option<String>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
@@ -0,0 +1,44 @@
+use crate::policy::url_policy::validate_stream_url;
+use crate::utils::url_utils::mask_stream_url;
+use serde::Deserialize;
+use std::fmt::{Debug, Formatter};
+use std::time::Duration;
+use utoipa::ToSchema;
+use validator::Validate;
+use wheel_rs::serde::duration_option_serde;
+
+#[derive(ToSchema, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPlaybackIssueDto {
+    /// 摄像头ID，与streamUrl必须指定一个(令牌可用于该摄像头的直播及录像回放)
+    #[validate(length(min = 1, message = "摄像头ID不能为空"))]
+    pub camera_id: Option<String>,
+    /// 直播流的地址，与cameraId必须指定一个
+    #[validate(
+        length(min = 1, message = "直播流的地址不能为空"),
+        custom(function = "validate_stream_url")
+    )]
+    pub stream_url: Option<String>,
+    /// 有效期(如`10m`，未指定时按配置的默认有效期，不能超过配置的最长有效期)
+    #[serde(with = "duration_option_serde", default)]
+    #[schema(value_type = Option<String>)]
+    pub ttl: Option<Duration>,
+    /// 当前用户ID
+    #[serde(skip_deserializing)]
+    pub _current_user_id: u64,
+}
+
+// Debug输出时流地址脱敏，避免注入的凭据被请求日志记录
+impl Debug for TokenPlaybackIssueDto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenPlaybackIssueDto")
+            .field("camera_id", &self.camera_id)
+            .field(
+                "stream_url",
+                &self.stream_url.as_deref().map(mask_stream_url),
+            )
+            .field("ttl", &self.ttl)
+            .field("_current_user_id", &self._current_user_id)
+            .finish()
+    }
+}
@@ -0,0 +1,377 @@
+use crate::config::capturer_config::{BudgetConfig, CaptureConfig, CapturerConfig};
+use crate::ffmpeg::ffmpeg_error::FfmpegError;
+use robotech::cfg::CfgError;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
+use tokio::time::{timeout_at, Instant};
+use tracing::{debug, info};
+
+/// 全局静态的ffmpeg进程预算实例
+static FFMPEG_BUDGET: OnceLock<FfmpegBudget> = OnceLock::new();
+
+pub fn init_ffmpeg_budget(capturer_config: &CapturerConfig) -> Result<(), CfgError> {
+    info!("初始化ffmpeg进程预算");
+    FFMPEG_BUDGET
+        .set(FfmpegBudget::new(
+            &capturer_config.budget,
+            &capturer_config.capture,
+        ))
+        .map_err(|_| CfgError::Init("FfmpegBudget init failed".to_string()))
+}
+
+pub fn get_ffmpeg_budget() -> Result<&'static FfmpegBudget, CfgError> {
+    FFMPEG_BUDGET.get().ok_or(CfgError::NotInit(
+        "FfmpegBudget not initialized".to_string(),
+    ))
+}
+
+/// 按新的配置调整ffmpeg进程预算
+///
+/// 不替换预算实例，直接调整各池的大小，已持有的许可继续计入预算
+pub fn update_ffmpeg_budget(capturer_config: &CapturerConfig) -> Result<(), CfgError> {
+    get_ffmpeg_budget()?.resize(&capturer_config.budget, &capturer_config.capture);
+    Ok(())
+}
+
+/// ffmpeg进程预算的池
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetPool {
+    /// 转码会话
+    Transcode,
+    /// 直通会话
+    Passthrough,
+    /// 一次性抓拍(抓拍图片、录制短视频、转封装等)
+    Capture,
+}
+
+impl BudgetPool {
+    /// 所有的池
+    pub const ALL: [BudgetPool; 3] = [
+        BudgetPool::Transcode,
+        BudgetPool::Passthrough,
+        BudgetPool::Capture,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BudgetPool::Transcode => "transcode",
+            BudgetPool::Passthrough => "passthrough",
+            BudgetPool::Capture => "capture",
+        }
+    }
+}
+
+/// 池的使用情况
+#[derive(Debug, Clone)]
+pub struct BudgetPoolStats {
+    /// 池的名称(全局总预算为`total`)
+    pub name: &'static str,
+    /// 池的大小
+    pub capacity: usize,
+    /// 正在使用的数量
+    pub in_use: usize,
+    /// 正在排队等待的数量
+    pub waiting: usize,
+    /// 累计获取成功的次数
+    pub acquired_total: u64,
+    /// 累计等待超时的次数
+    pub timeouts_total: u64,
+}
+
+/// ffmpeg进程预算
+///
+/// 每个ffmpeg进程需要同时获取所属池及全局总预算的许可，获取不到时排队等待，等待超时则返回错误；
+/// 许可在进程结束(许可被释放)时归还
+pub struct FfmpegBudget {
+    /// 全局总预算
+    total: PoolSemaphore,
+    /// 转码会话池
+    transcode: PoolSemaphore,
+    /// 直通会话池
+    passthrough: PoolSemaphore,
+    /// 一次性抓拍池
+    capture: PoolSemaphore,
+    /// 会话等待许可的超时时间(毫秒)
+    session_acquire_timeout_ms: AtomicU64,
+    /// 一次性抓拍等待许可的超时时间(毫秒)
+    capture_acquire_timeout_ms: AtomicU64,
+    /// 累计因预算用尽而关闭的空闲会话数量
+    evictions_total: AtomicU64,
+}
+
+impl FfmpegBudget {
+    fn new(budget_config: &BudgetConfig, capture_config: &CaptureConfig) -> Self {
+        let budget = Self {
+            total: PoolSemaphore::new(budget_config.max_processes),
+            transcode: PoolSemaphore::new(budget_config.transcode_sessions),
+            passthrough: PoolSemaphore::new(budget_config.passthrough_sessions),
+            capture: PoolSemaphore::new(capture_config.max_concurrent),
+            session_acquire_timeout_ms: AtomicU64::new(0),
+            capture_acquire_timeout_ms: AtomicU64::new(0),
+            evictions_total: AtomicU64::new(0),
+        };
+        budget.set_timeouts(budget_config, capture_config);
+        budget
+    }
+
+    /// 尝试立即获取许可，预算用尽时返回None
+    pub fn try_acquire(&'static self, pool: BudgetPool) -> Option<BudgetPermit> {
+        let pool_semaphore = self.pool(pool);
+        let pool_permit = pool_semaphore.try_acquire()?;
+        let total_permit = match self.total.try_acquire() {
+            Some(total_permit) => total_permit,
+            None => {
+                pool_semaphore.release(pool_permit);
+                return None;
+            }
+        };
+        Some(BudgetPermit::new(self, pool, pool_permit, total_permit))
+    }
+
+    /// 获取许可，预算用尽时排队等待，等待超时则返回错误
+    pub async fn acquire(&'static self, pool: BudgetPool) -> Result<BudgetPermit, FfmpegError> {
+        let timeout = Duration::from_millis(match pool {
+            BudgetPool::Capture => self.capture_acquire_timeout_ms.load(Ordering::Relaxed),
+            BudgetPool::Transcode | BudgetPool::Passthrough => {
+                self.session_acquire_timeout_ms.load(Ordering::Relaxed)
+            }
+        });
+        let deadline = Instant::now() + timeout;
+        let pool_semaphore = self.pool(pool);
+        debug!(
+            "获取ffmpeg进程预算<{}>的许可, 剩余许可数量: {}, 全局剩余许可数量: {}",
+            pool.name(),
+            pool_semaphore.semaphore.available_permits(),
+            self.total.semaphore.available_permits()
+        );
+        // 先获取池的许可再获取全局总预算的许可，顺序固定
+        let pool_permit = pool_semaphore.acquire(deadline).await.ok_or_else(|| {
+            FfmpegError::FfmpegBusy(format!("等待ffmpeg进程预算<{}>超时", pool.name()))
+        })?;
+        let total_permit = match self.total.acquire(deadline).await {
+            Some(total_permit) => total_permit,
+            None => {
+                pool_semaphore.release(pool_permit);
+                return Err(FfmpegError::FfmpegBusy(
+                    "等待ffmpeg进程全局预算超时".to_string(),
+                ));
+            }
+        };
+        Ok(BudgetPermit::new(self, pool, pool_permit, total_permit))
+    }
+
+    /// 池是否已用尽
+    pub fn is_exhausted(&self, pool: BudgetPool) -> bool {
+        self.pool(pool).semaphore.available_permits() == 0
+    }
+
+    /// 记录一次因预算用尽而关闭的空闲会话
+    pub fn record_eviction(&self) {
+        self.evictions_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 累计因预算用尽而关闭的空闲会话数量
+    pub fn evictions_total(&self) -> u64 {
+        self.evictions_total.load(Ordering::Relaxed)
+    }
+
+    /// 全局总预算及各池的使用情况
+    pub fn stats(&self) -> Vec<BudgetPoolStats> {
+        let mut stats = vec![self.total.stats("total")];
+        stats.extend(
+            BudgetPool::ALL
+                .iter()
+                .map(|pool| self.pool(*pool).stats(pool.name())),
+        );
+        stats
+    }
+
+    fn resize(&self, budget_config: &BudgetConfig, capture_config: &CaptureConfig) {
+        info!(
+            "调整ffmpeg进程预算: 全局{}, 转码会话{}, 直通会话{}, 一次性抓拍{}",
+            budget_config.max_processes,
+            budget_config.transcode_sessions,
+            budget_config.passthrough_sessions,
+            capture_config.max_concurrent
+        );
+        self.total.resize(budget_config.max_processes);
+        self.transcode.resize(budget_config.transcode_sessions);
+        self.passthrough.resize(budget_config.passthrough_sessions);
+        self.capture.resize(capture_config.max_concurrent);
+        self.set_timeouts(budget_config, capture_config);
+    }
+
+    fn set_timeouts(&self, budget_config: &BudgetConfig, capture_config: &CaptureConfig) {
+        let session_acquire_timeout = budget_config
+            .session_acquire_timeout
+            .unwrap_or(Duration::from_secs(10));
+        let Some(capture_acquire_timeout) = capture_config.acquire_timeout else {
+            unreachable!("等待抓拍许可的超时时间必须配置");
+        };
+        self.session_acquire_timeout_ms.store(
+            session_acquire_timeout.as_millis() as u64,
+            Ordering::Relaxed,
+        );
+        self.capture_acquire_timeout_ms.store(
+            capture_acquire_timeout.as_millis() as u64,
+            Ordering::Relaxed,
+        );
+    }
+
+    fn pool(&self, pool: BudgetPool) -> &PoolSemaphore {
+        match pool {
+            BudgetPool::Transcode => &self.transcode,
+            BudgetPool::Passthrough => &self.passthrough,
+            BudgetPool::Capture => &self.capture,
+        }
+    }
+}
+
+/// ffmpeg进程预算的许可
+///
+/// 许可被释放时归还所属池及全局总预算的许可
+pub struct BudgetPermit {
+    budget: &'static FfmpegBudget,
+    pool: BudgetPool,
+    pool_permit: Option<OwnedSemaphorePermit>,
+    total_permit: Option<OwnedSemaphorePermit>,
+}
+
+impl BudgetPermit {
+    fn new(
+        budget: &'static FfmpegBudget,
+        pool: BudgetPool,
+        pool_permit: OwnedSemaphorePermit,
+        total_permit: OwnedSemaphorePermit,
+    ) -> Self {
+        Self {
+            budget,
+            pool,
+            pool_permit: Some(pool_permit),
+            total_permit: Some(total_permit),
+        }
+    }
+
+    /// 许可所属的池
+    pub fn pool(&self) -> BudgetPool {
+        self.pool
+    }
+}
+
+impl Drop for BudgetPermit {
+    fn drop(&mut self) {
+        if let Some(pool_permit) = self.pool_permit.take() {
+            self.budget.pool(self.pool).release(pool_permit);
+        }
+        if let Some(total_permit) = self.total_permit.take() {
+            self.budget.total.release(total_permit);
+        }
+    }
+}
+
+/// 可调整大小的信号量
+///
+/// 缩小时先收回空闲的许可，不足的部分记为欠额，之后归还的许可直接作废抵扣欠额
+struct PoolSemaphore {
+    semaphore: Arc<Semaphore>,
+    /// 池的大小
+    capacity: AtomicUsize,
+    /// 缩小时尚未收回的许可数量
+    shrink_debt: AtomicUsize,
+    /// 正在排队等待的数量
+    waiting: AtomicUsize,
+    /// 累计获取成功的次数
+    acquired_total: AtomicU64,
+    /// 累计等待超时的次数
+    timeouts_total: AtomicU64,
+}
+
+impl PoolSemaphore {
+    fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(capacity)),
+            capacity: AtomicUsize::new(capacity),
+            shrink_debt: AtomicUsize::new(0),
+            waiting: AtomicUsize::new(0),
+            acquired_total: AtomicU64::new(0),
+            timeouts_total: AtomicU64::new(0),
+        }
+    }
+
+    fn try_acquire(&self) -> Option<OwnedSemaphorePermit> {
+        match Arc::clone(&self.semaphore).try_acquire_owned() {
+            Ok(permit) => {
+                self.acquired_total.fetch_add(1, Ordering::Relaxed);
+                Some(permit)
+            }
+            Err(TryAcquireError::NoPermits | TryAcquireError::Closed) => None,
+        }
+    }
+
+    async fn acquire(&self, deadline: Instant) -> Option<OwnedSemaphorePermit> {
+        self.waiting.fetch_add(1, Ordering::Relaxed);
+        let result = timeout_at(deadline, Arc::clone(&self.semaphore).acquire_owned()).await;
+        self.waiting.fetch_sub(1, Ordering::Relaxed);
+        match result {
+            Ok(Ok(permit)) => {
+                self.acquired_total.fetch_add(1, Ordering::Relaxed);
+                Some(permit)
+            }
+            Ok(Err(_)) | Err(_) => {
+                self.timeouts_total.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// 归还许可，有缩小的欠额时作废许可
+    fn release(&self, permit: OwnedSemaphorePermit) {
+        if self
+            .shrink_debt
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |debt| {
+                debt.checked_sub(1)
+            })
+            .is_ok()
+        {
+            permit.forget();
+        }
+    }
+
+    fn resize(&self, capacity: usize) {
+        let capacity = capacity.max(1);
+        let old_capacity = self.capacity.swap(capacity, Ordering::AcqRel);
+        if capacity > old_capacity {
+            // 先抵扣缩小的欠额，剩余的增加许可
+            let increased = capacity - old_capacity;
+            let mut paid = 0;
+            let _ = self
+                .shrink_debt
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |debt| {
+                    paid = debt.min(increased);
+                    Some(debt - paid)
+                });
+            self.semaphore.add_permits(increased - paid);
+        } else if capacity < old_capacity {
+            let removed = old_capacity - capacity;
+            let forgotten = self.semaphore.forget_permits(removed);
+            self.shrink_debt
+                .fetch_add(removed - forgotten, Ordering::AcqRel);
+        }
+    }
+
+    fn stats(&self, name: &'static str) -> BudgetPoolStats {
+        let capacity = self.capacity.load(Ordering::Acquire);
+        let outstanding = capacity + self.shrink_debt.load(Ordering::Acquire);
+        BudgetPoolStats {
+            name,
+            capacity,
+            in_use: outstanding.saturating_sub(self.semaphore.available_permits()),
+            waiting: self.waiting.load(Ordering::Relaxed),
+            acquired_total: self.acquired_total.load(Ordering::Relaxed),
+            timeouts_total: self.timeouts_total.load(Ordering::Relaxed),
+        }
+    }
+}
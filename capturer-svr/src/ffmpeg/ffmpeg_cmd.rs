@@ -1,11 +1,14 @@
+use crate::config::capturer_config::TranscodeConfig;
+use crate::credential::credential_store::inject_credential;
 use crate::ffmpeg::ffmpeg_eo::{AudioCodecType, FfprobeCmdInfo, StreamMetadata, VideoCodecType};
 use crate::ffmpeg::ffmpeg_error::FfmpegError;
+use crate::ffmpeg::ffmpeg_budget::{get_ffmpeg_budget, BudgetPool};
+use crate::ffmpeg::ffmpeg_process::{FfmpegPipe, FfmpegProcess, FfmpegProgram};
+use crate::policy::url_policy::UrlPolicy;
+use crate::utils::url_utils::mask_stream_url;
 use bytes::Bytes;
-use tokio::process::Child;
 use tokio::sync::broadcast::Sender;
-use tokio::sync::oneshot;
 use tracing::{debug, info};
-use wheel_rs::cmd;
 
 /// ffmpeg命令执行模块
 ///
@@ -23,26 +26,35 @@ impl FfmpegCmd {
     /// - 分辨率（宽高）
     /// - 帧率
     ///
+    /// ffprobe进程同样计入ffmpeg进程预算，探测前需获取一次性抓拍的许可
+    ///
     /// ## 参数
     /// * `stream_url` - 探测流的地址
     ///
     /// ## 返回值
     /// 返回包含流媒体元数据的Result
     pub async fn probe_stream_info(stream_url: &str) -> Result<StreamMetadata, FfmpegError> {
-        info!("probe_stream_info {stream_url}....");
-        let stdout = cmd::std::execute(
-            "ffprobe",
+        info!("probe_stream_info {}....", mask_stream_url(stream_url));
+        UrlPolicy::check_resolved(stream_url).await?;
+        let protocol_whitelist = UrlPolicy::protocol_whitelist()?;
+        let input_url = inject_credential(stream_url);
+        let _budget_permit = get_ffmpeg_budget()?.acquire(BudgetPool::Capture).await?;
+        let stdout = FfmpegProcess::output(
+            FfmpegProgram::Ffprobe,
             &[
-                "-v",            // 设置ffprobe的日志级别参数
-                "error",         // 日志级别为error，只显示错误信息
-                "-show_streams", // 显示输入文件的所有流信息
-                "-show_entries", // 指定要显示的流条目参数
+                "-v",                  // 设置ffprobe的日志级别参数
+                "error",               // 日志级别为error，只显示错误信息
+                "-protocol_whitelist", // 允许使用的协议参数
+                &protocol_whitelist,   // 按流地址策略配置的协议
+                "-show_streams",       // 显示输入文件的所有流信息
+                "-show_entries",       // 指定要显示的流条目参数
                 "stream=codec_type,codec_name,width,height,r_frame_rate,sample_rate", // 指定要显示的具体字段：编码类型、编码名称、宽度、高度、帧率、采样率
                 "-of",      // 设置输出格式参数
                 "json",     // 输出格式为JSON，便于程序解析
-                stream_url, // 输入的RTSP流地址
+                &input_url, // 输入的RTSP流地址(已注入凭据)
             ],
-        )?;
+        )
+        .await?;
 
         let stdout = String::from_utf8(stdout).map_err(|e| FfmpegError::FfprobeParseUtf8(e))?;
         debug!("运行ffprobe命令成功: {}", stdout);
@@ -122,26 +134,193 @@ impl FfmpegCmd {
         stream_url: &str,
         jpeg_quality: u8,
     ) -> Result<Vec<u8>, FfmpegError> {
-        info!("capture_to_jpeg {stream_url}....");
+        info!("capture_to_jpeg {}....", mask_stream_url(stream_url));
+        UrlPolicy::check_resolved(stream_url).await?;
+        let protocol_whitelist = UrlPolicy::protocol_whitelist()?;
+        let input_url = inject_credential(stream_url);
         let jpeg_quality = &jpeg_quality.to_string();
-        Ok(cmd::std::execute(
-            "ffmpeg",
+        FfmpegProcess::output(
+            FfmpegProgram::Ffmpeg,
+            &[
+                "-protocol_whitelist", // 允许使用的协议参数
+                &protocol_whitelist,   // 按流地址策略配置的协议
+                "-rtsp_transport",     // 设置RTSP传输方式参数
+                "tcp",                 // 使用TCP协议传输（更稳定）
+                "-i",                  // 指定输入源参数
+                &input_url,            // 输入的RTSP流地址(已注入凭据)
+                "-vframes",            // 设置要输出的视频帧数参数
+                "1",                   // 只抓取一帧画面
+                "-f",                  // 指定输出格式参数
+                "image2pipe",          // 图像格式（JPEG、PNG等通用图像格式容器）
+                "-c:v",                // 设置视频编解码器参数
+                "mjpeg",               // 使用MJPEG编码
+                "-q:v",                // 设置视频质量参数
+                jpeg_quality,          // JPEG质量等级，1-31，数值越小质量越高
+                "pipe:1",              // 输出到标准输出管道
+            ],
+        )
+        .await
+    }
+
+    /// # 将序列图片合成为MP4视频
+    ///
+    /// 输入目录中的图片需按`000000.jpg`、`000001.jpg`...顺序命名，
+    /// 合成的视频使用H.264编码，宽高裁剪为偶数以满足yuv420p的要求。
+    ///
+    /// ## 参数
+    /// * `images_dir` - 序列图片所在的目录
+    /// * `fps` - 合成视频的帧率
+    /// * `output_path` - 输出MP4文件的路径
+    pub async fn images_to_mp4(
+        images_dir: &str,
+        fps: u8,
+        output_path: &str,
+    ) -> Result<(), FfmpegError> {
+        info!("images_to_mp4 {images_dir} -> {output_path}....");
+        let fps = fps.to_string();
+        let input_pattern = format!("{images_dir}/%06d.jpg");
+        FfmpegProcess::output(
+            FfmpegProgram::Ffmpeg,
+            &[
+                "-y",             // 覆盖已存在的输出文件
+                "-framerate",     // 输入的帧率参数
+                &fps,             // 每秒合成的图片数量
+                "-i",             // 输入源参数
+                &input_pattern,   // 序列图片
+                "-vf",            // 视频滤镜参数
+                "scale=trunc(iw/2)*2:trunc(ih/2)*2", // 宽高裁剪为偶数
+                "-c:v",           // 视频编解码器设置参数
+                "libx264",        // 使用H.264编码
+                "-preset",        // 编码预设参数
+                "veryfast",       // 较快的编码速度
+                "-pix_fmt",       // 像素格式参数
+                "yuv420p",        // 兼容大多数播放器
+                "-movflags",      // MP4封装参数
+                "+faststart",     // 将moov移到文件头部，便于边下边播
+                output_path,      // 输出文件
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// # 录制短视频为MP4
+    ///
+    /// 从流中录制指定时长的视频，视频直通不转码，音频转为AAC以满足MP4封装的要求。
+    ///
+    /// ## 参数
+    /// * `stream_url` - 录制流的地址
+    /// * `seconds` - 录制的时长(秒)
+    /// * `output_path` - 输出MP4文件的路径
+    pub async fn record_clip_to_mp4(
+        stream_url: &str,
+        seconds: u32,
+        output_path: &str,
+    ) -> Result<(), FfmpegError> {
+        info!(
+            "record_clip_to_mp4 {} -> {output_path}....",
+            mask_stream_url(stream_url)
+        );
+        UrlPolicy::check_resolved(stream_url).await?;
+        let protocol_whitelist = UrlPolicy::protocol_whitelist()?;
+        let seconds = seconds.to_string();
+        let input_url = inject_credential(stream_url);
+        FfmpegProcess::output(
+            FfmpegProgram::Ffmpeg,
             &[
-                "-rtsp_transport", // 设置RTSP传输方式参数
-                "tcp",             // 使用TCP协议传输（更稳定）
-                "-i",              // 指定输入源参数
-                stream_url,        // 输入的RTSP流地址
-                "-vframes",        // 设置要输出的视频帧数参数
-                "1",               // 只抓取一帧画面
-                "-f",              // 指定输出格式参数
-                "image2pipe",      // 图像格式（JPEG、PNG等通用图像格式容器）
-                "-c:v",            // 设置视频编解码器参数
-                "mjpeg",           // 使用MJPEG编码
-                "-q:v",            // 设置视频质量参数
-                jpeg_quality,      // JPEG质量等级，1-31，数值越小质量越高
-                "pipe:1",          // 输出到标准输出管道
+                "-y",                  // 覆盖已存在的输出文件
+                "-protocol_whitelist", // 允许使用的协议参数
+                &protocol_whitelist,   // 按流地址策略配置的协议
+                "-rtsp_transport",     // 设置RTSP传输方式参数
+                "tcp",                 // 使用TCP协议传输（更稳定）
+                "-i",                  // 输入源参数
+                &input_url,            // 输入的RTSP流地址(已注入凭据)
+                "-t",                  // 录制时长参数
+                &seconds,              // 录制的秒数
+                "-c:v",                // 视频编解码器设置参数
+                "copy",                // 直通，不转码
+                "-c:a",                // 音频编解码器设置参数
+                "aac",                 // 音频转为 aac (mp4 需要)
+                "-movflags",           // MP4封装参数
+                "+faststart",          // 将moov移到文件头部，便于边下边播
+                output_path,           // 输出文件
             ],
-        )?)
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// # 将FLV文件转封装为MP4
+    ///
+    /// 音视频均直通不转码，只更换封装格式。
+    ///
+    /// ## 参数
+    /// * `input_path` - 输入FLV文件的路径
+    /// * `output_path` - 输出MP4文件的路径
+    pub async fn remux_flv_to_mp4(input_path: &str, output_path: &str) -> Result<(), FfmpegError> {
+        info!("remux_flv_to_mp4 {input_path} -> {output_path}....");
+        FfmpegProcess::output(
+            FfmpegProgram::Ffmpeg,
+            &[
+                "-y",          // 覆盖已存在的输出文件
+                "-f",          // 指定输入格式参数
+                "flv",         // 输入为 flv
+                "-i",          // 输入源参数
+                input_path,    // 输入文件
+                "-c",          // 编解码器设置参数
+                "copy",        // 音视频均直通，不转码
+                "-movflags",   // MP4封装参数
+                "+faststart",  // 将moov移到文件头部，便于边下边播
+                output_path,   // 输出文件
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// # 将录像文件转封装后输出到标准输出管道
+    ///
+    /// 音视频均直通不转码，从指定位置之前最近的关键帧开始输出。
+    /// 子进程计入ffmpeg进程预算的一次性抓拍池，预算用尽时排队等待，等待超时则返回错误。
+    ///
+    /// ## 参数
+    /// * `input_path` - 输入文件的路径
+    /// * `seek_ms` - 开始输出的位置(毫秒)
+    /// * `output_ts_offset_ms` - 输出时间戳的偏移(毫秒)，用于多个文件拼接时时间戳连续
+    /// * `format` - 输出的封装格式(如flv、mpegts)
+    ///
+    /// ## 返回值
+    /// 返回输出的管道，从中读取输出的数据，被丢弃时终止子进程并归还预算
+    pub async fn remux_to_pipe(
+        input_path: &str,
+        seek_ms: u32,
+        output_ts_offset_ms: u64,
+        format: &str,
+    ) -> Result<FfmpegPipe, FfmpegError> {
+        info!("remux_to_pipe {input_path} -> {format}....");
+        let budget_permit = get_ffmpeg_budget()?.acquire(BudgetPool::Capture).await?;
+        let seek = format!("{}.{:03}", seek_ms / 1000, seek_ms % 1000);
+        let output_ts_offset = format!(
+            "{}.{:03}",
+            output_ts_offset_ms / 1000,
+            output_ts_offset_ms % 1000
+        );
+        let (process, stdout) = FfmpegProcess::spawn_piped(&[
+            "-v",                // 日志级别参数
+            "error",             // 只输出错误
+            "-ss",               // 定位参数(放在输入前，定位到之前最近的关键帧)
+            &seek,               // 开始输出的位置
+            "-i",                // 输入源参数
+            input_path,          // 输入文件
+            "-c",                // 编解码器设置参数
+            "copy",              // 音视频均直通，不转码
+            "-output_ts_offset", // 输出时间戳偏移参数
+            &output_ts_offset,   // 输出时间戳的偏移
+            "-f",                // 指定输出格式参数
+            format,              // 输出格式
+            "pipe:1",            // 输出到标准输出管道
+        ])?;
+        Ok(FfmpegPipe::new(process, stdout, budget_permit))
     }
 
     /// # 拉流转码（智能转码：H.265 转 H.264，H.264 直通）
@@ -153,38 +332,45 @@ impl FfmpegCmd {
     ///
     /// ## 参数
     /// * `stream_url` - 拉流的地址
-    /// * `frame_tx` - 用于发送视频帧数据的通道
-    /// * `metadata` - 用于存储流媒体元数据的共享引用
+    /// * `stream_metadata` - 拉流前探测到的流媒体元数据(探测时已按流地址策略检查过地址)
+    /// * `transcode_config` - 拉流转码的配置(由流管理器提供，热加载后会话重新拉流时使用新的配置)
+    /// * `data_sender` - 用于发送ffmpeg输出的FLV数据的通道
     ///
     /// ## 返回值
-    /// 返回ffmpeg子进程的句柄
+    /// 返回ffmpeg子进程，被丢弃时终止子进程
     pub async fn pull_and_transcode_stream(
         stream_url: &str,
+        stream_metadata: &StreamMetadata,
+        transcode_config: &TranscodeConfig,
         data_sender: Sender<Bytes>,
-        process_exit_sender: oneshot::Sender<()>,
         read_buffer_size: usize,
-    ) -> Result<Child, FfmpegError> {
-        info!("pull_and_transcode_stream {stream_url}....");
-        // 先探测流信息
-        let stream_metadata = Self::probe_stream_info(stream_url).await?;
+    ) -> Result<FfmpegProcess, FfmpegError> {
+        info!("pull_and_transcode_stream {}....", mask_stream_url(stream_url));
 
         // 构建基础参数
+        let protocol_whitelist = UrlPolicy::protocol_whitelist()?;
+        let input_url = inject_credential(stream_url);
+        let crf = transcode_config.crf.to_string();
+        let threads = transcode_config.threads.to_string();
         let mut ffmpeg_args = vec![
-            "-rtsp_transport", // 设置RTSP传输方式参数
-            "tcp",             // 强制 TCP，防止丢包花屏
-            "-i",              // 输入源参数
-            stream_url,        // 输入的RTSP流地址
-            "-f",              // 输出格式参数
-            "flv",             // 输出格式必须为 flv
-            "-flvflags",       // FLV 容器格式
+            "-protocol_whitelist", // 允许使用的协议参数
+            &protocol_whitelist,   // 按流地址策略配置的协议
+            "-rtsp_transport",     // 设置RTSP传输方式参数
+            "tcp",                 // 强制 TCP，防止丢包花屏
+            "-i",                  // 输入源参数
+            &input_url,            // 输入的RTSP流地址(已注入凭据)
+            "-f",                  // 输出格式参数
+            "flv",                 // 输出格式必须为 flv
+            "-flvflags",           // FLV 容器格式
             "no_duration_filesize", // 指示 ffmpeg 在输出 FLV 文件时不计算和写入文件的总时长(duration)和大小(filesize)到 FLV 的头部信息中
-                                    // "-g",                   // 关键帧间隔参数
-                                    // "25",                   // 关键帧间隔为 25 帧（每 25 帧插入一个关键帧）
+                                    // "-g", // 关键帧间隔参数
+                                    // "25", // 关键帧间隔为 25 帧（每 25 帧插入一个关键帧）
         ];
 
         // 根据编码类型添加特定参数
         match stream_metadata
             .video_codec
+            .clone()
             .ok_or_else(|| FfmpegError::FfprobeParse("未发现视频编解码器".to_string()))?
         {
             // H.264 直通，不转码（性能最优）
@@ -197,23 +383,23 @@ impl FfmpegCmd {
             // H.265或未知编码使用H.264转码
             VideoCodecType::H265 | VideoCodecType::Other(_) => {
                 ffmpeg_args.extend_from_slice(&[
-                    "-c:v",        // 视频编解码器设置参数
-                    "libx264",     // 使用H.264编码(flv 需要)
-                    "-preset",     // 编码预设参数
-                    "superfast",   // 超快速编码（低延迟，较低压缩率，比ultrafast稍慢但CPU占用更低）
-                    "-tune",       // 编码调优参数
-                    "zerolatency", // 零延迟调优
-                    "-crf",        // 码率控制参数
-                    "32",          // 码率控制等级，范围0-51，数值越小质量越高
-                    "-profile:v",  // 编码档次
-                    "baseline",    // baseline档次，编码复杂度最低
-                    "-threads",    // 线程数
-                    "1",           // 限制线程数以减少CPU占用
+                    "-c:v",                    // 视频编解码器设置参数
+                    "libx264",                 // 使用H.264编码(flv 需要)
+                    "-preset",                 // 编码预设参数
+                    &transcode_config.preset,  // 编码预设(默认superfast)
+                    "-tune",                   // 编码调优参数
+                    "zerolatency",             // 零延迟调优
+                    "-crf",                    // 码率控制参数
+                    &crf,                      // 码率控制等级，范围0-51，数值越小质量越高
+                    "-profile:v",              // 编码档次
+                    &transcode_config.profile, // 编码档次(默认baseline)
+                    "-threads",                // 线程数
+                    &threads,                  // 编码的线程数
                 ]);
             }
         }
 
-        match stream_metadata.audio_codec {
+        match &stream_metadata.audio_codec {
             Some(AudioCodecType::AAC) => {
                 ffmpeg_args.extend_from_slice(&[
                     "-c:a", // 音频编解码器设置参数
@@ -253,12 +439,11 @@ impl FfmpegCmd {
         ]);
 
         // 执行ffmpeg命令
-        Ok(cmd::spawn::execute(
-            "ffmpeg",
-            &ffmpeg_args,
-            data_sender,
-            process_exit_sender,
-            read_buffer_size,
-        )?)
+        FfmpegProcess::spawn(&ffmpeg_args, data_sender, read_buffer_size)
+    }
+
+    /// 拉流时是否需要转码(视频不是H.264时转码)，决定会话占用的ffmpeg进程预算的池
+    pub fn needs_transcode(stream_metadata: &StreamMetadata) -> bool {
+        !matches!(stream_metadata.video_codec, Some(VideoCodecType::H264))
     }
 }
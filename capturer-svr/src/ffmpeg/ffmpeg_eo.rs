@@ -27,6 +27,17 @@ pub enum VideoCodecType {
     Other(String),
 }
 
+impl VideoCodecType {
+    /// 编解码器名称
+    pub fn name(&self) -> &str {
+        match self {
+            VideoCodecType::H264 => "h264",
+            VideoCodecType::H265 => "hevc",
+            VideoCodecType::Other(codec_name) => codec_name,
+        }
+    }
+}
+
 /// 音频编解码器类型枚举
 #[derive(Debug, PartialEq, Clone)]
 pub enum AudioCodecType {
@@ -50,6 +61,24 @@ pub enum AudioCodecType {
     Other(String),
 }
 
+impl AudioCodecType {
+    /// 编解码器名称
+    pub fn name(&self) -> &str {
+        match self {
+            AudioCodecType::Unknown => "unknown",
+            AudioCodecType::AAC => "aac",
+            AudioCodecType::MP2 => "mp2",
+            AudioCodecType::MP3 => "mp3",
+            AudioCodecType::G711mulaw => "pcm_mulaw",
+            AudioCodecType::G711alaw => "pcm_alaw",
+            AudioCodecType::G726 => "adpcm_g726le",
+            AudioCodecType::NotSupported(codec_name) | AudioCodecType::Other(codec_name) => {
+                codec_name
+            }
+        }
+    }
+}
+
 /// 流媒体元数据结构
 #[derive(Debug, Default, Clone)]
 pub struct StreamMetadata {
@@ -1,14 +1,17 @@
+use crate::policy::policy_error::UrlPolicyError;
+use crate::utils::url_utils::mask_credentials;
 use bytes::Bytes;
 use robotech::cfg::CfgError;
 use std::io::Error;
 use std::string::FromUtf8Error;
 use tokio::sync::broadcast::error::SendError;
-use wheel_rs::cmd::cmd_error::CmdError;
 
 #[derive(Debug, thiserror::Error)]
 pub enum FfmpegError {
     #[error("执行ffprobe命令失败: {0}")]
-    FfprobeCmd(#[from] CmdError),
+    FfprobeCmd(String),
+    #[error("执行ffmpeg命令失败: {0}")]
+    FfmpegCmd(String),
     #[error("执行ffprobe后按utf8编码解析信息失败: {0}")]
     FfprobeParseUtf8(FromUtf8Error),
     #[error("执行ffprobe后按json格式解析信息失败: {0}")]
@@ -17,6 +20,8 @@ pub enum FfmpegError {
     FfprobeParse(String),
     #[error("获取ffmpeg配置失败: {0}")]
     FfmpegConfig(#[from] CfgError),
+    #[error("启动ffmpeg失败: {0}")]
+    FfmpegSpawn(Error),
     #[error("执行ffmpeg后获取stdout失败: {0}")]
     FfmpegTakeStdout(String),
     #[error("关闭ffmpeg失败: {0}")]
@@ -25,4 +30,28 @@ pub enum FfmpegError {
     FfmpegSend(SendError<Bytes>),
     #[error("读取ffmpeg会话失败: {0}")]
     FfmpegSessionRead(String),
+    #[error("会话不存在: {0}")]
+    FfmpegSessionNotFound(u64),
+    #[error("会话录像失败: {0}")]
+    FfmpegSessionRecording(String),
+    #[error("流地址不被允许: {0}")]
+    FfmpegUrlPolicy(#[from] UrlPolicyError),
+    #[error("ffmpeg繁忙: {0}")]
+    FfmpegBusy(String),
+    #[error("流管理器已关闭: {0}")]
+    FfmpegShutdown(String),
+}
+
+impl FfmpegError {
+    /// 执行ffprobe命令失败的错误
+    ///
+    /// 命令的错误输出中可能带有注入了凭据的流地址，转换时脱敏
+    pub fn ffprobe_cmd(message: &str) -> Self {
+        FfmpegError::FfprobeCmd(mask_credentials(message))
+    }
+
+    /// 执行ffmpeg命令失败的错误，同样脱敏
+    pub fn ffmpeg_cmd(message: &str) -> Self {
+        FfmpegError::FfmpegCmd(mask_credentials(message))
+    }
 }
@@ -0,0 +1,521 @@
+use crate::config::capturer_config::get_capturer_config;
+use crate::ffmpeg::ffmpeg_budget::BudgetPermit;
+use crate::ffmpeg::ffmpeg_error::FfmpegError;
+use crate::utils::task_utils::get_background_tasks;
+use bytes::Bytes;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::LazyLock;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+use tokio::process::{Child, ChildStderr, ChildStdout, Command};
+use tokio::sync::broadcast::Sender;
+use tokio::sync::{oneshot, watch};
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// ffmpeg子进程的argv[0]的标记，程序重新启动时据此识别上一个实例遗留的子进程
+pub const FFMPEG_PROCESS_MARKER: &str = "capturer-svr-ffmpeg";
+
+/// 本实例启动的子进程的argv[0]，见[`FfmpegProcess::instance_marker`]
+static INSTANCE_MARKER: LazyLock<String> =
+    LazyLock::new(|| FfmpegProcess::instance_marker(FFMPEG_PROCESS_MARKER));
+
+/// 子进程的程序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FfmpegProgram {
+    /// 拉流、转码、抓拍、合成视频等
+    Ffmpeg,
+    /// 探测流信息
+    Ffprobe,
+}
+
+impl FfmpegProgram {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FfmpegProgram::Ffmpeg => "ffmpeg",
+            FfmpegProgram::Ffprobe => "ffprobe",
+        }
+    }
+
+    /// 执行命令失败的错误(已脱敏)
+    fn error(&self, message: &str) -> FfmpegError {
+        match self {
+            FfmpegProgram::Ffmpeg => FfmpegError::ffmpeg_cmd(message),
+            FfmpegProgram::Ffprobe => FfmpegError::ffprobe_cmd(message),
+        }
+    }
+}
+
+/// ffmpeg子进程
+///
+/// 所有ffmpeg及ffprobe子进程都由此启动。子进程的唯一所有者(不能克隆)。子进程在单独的进程组中运行，由后台的回收任务持有子进程的句柄，
+/// 等待其退出并回收；终止时先向进程组发送SIGTERM，超过宽限时间仍未退出再发送SIGKILL，
+/// 直到确认子进程已被回收。
+/// 回收及读取输出的任务由全局的后台任务跟踪，程序退出时终止子进程，并等待其被回收。
+/// 信号只在子进程被回收前发送，此时进程ID不会被复用，不会误杀其它进程。
+/// 子进程的argv[0]为带有本实例的进程ID及启动时间的标记(见[`FfmpegProcess::instance_marker`])，
+/// 程序崩溃后遗留的子进程在下次启动时被清理。
+/// 被丢弃时在后台终止子进程
+pub struct FfmpegProcess {
+    /// 子进程ID
+    pid: u32,
+    /// 请求回收任务终止子进程的发送者
+    terminate_sender: Option<oneshot::Sender<()>>,
+    /// 子进程退出并被回收的接收者(退出前为None，之后为是否成功退出)
+    exit_receiver: watch::Receiver<Option<bool>>,
+}
+
+impl FfmpegProcess {
+    /// 启动ffmpeg子进程，并将其标准输出按块发送到数据通道
+    ///
+    /// ## 参数
+    /// * `args` - ffmpeg的参数
+    /// * `data_sender` - 发送子进程输出的数据的通道，子进程的输出结束后通道关闭
+    /// * `read_buffer_size` - 每次读取输出的缓冲区大小
+    pub fn spawn(
+        args: &[&str],
+        data_sender: Sender<Bytes>,
+        read_buffer_size: usize,
+    ) -> Result<Self, FfmpegError> {
+        let (process, stdout) = Self::spawn_piped(args)?;
+        let background_tasks = get_background_tasks();
+        background_tasks.spawn(Self::read_stdout(
+            process.pid,
+            stdout,
+            data_sender,
+            read_buffer_size,
+            background_tasks.child_token(),
+        ));
+        Ok(process)
+    }
+
+    /// 启动ffmpeg子进程，由调用者直接读取其标准输出
+    ///
+    /// 调用者读取得慢时ffmpeg随之阻塞，输出不会丢失
+    pub fn spawn_piped(args: &[&str]) -> Result<(Self, ChildStdout), FfmpegError> {
+        let (process, stdout, _) = Self::spawn_program(FfmpegProgram::Ffmpeg, args, false)?;
+        Ok((process, stdout))
+    }
+
+    /// 运行子进程直到其退出，返回其标准输出
+    ///
+    /// 子进程同样由后台的回收任务回收：调用者不再等待(如请求被中断)或程序退出时终止子进程。
+    /// 子进程没有成功退出时返回其错误输出(已脱敏)
+    ///
+    /// ## 参数
+    /// * `program` - 运行的程序
+    /// * `args` - 程序的参数
+    pub async fn output(program: FfmpegProgram, args: &[&str]) -> Result<Vec<u8>, FfmpegError> {
+        let (mut process, mut stdout, stderr) = Self::spawn_program(program, args, true)?;
+        let mut stderr = stderr.ok_or_else(|| {
+            FfmpegError::FfmpegTakeStdout(format!("子进程{}没有错误输出", process.pid))
+        })?;
+        let mut output = Vec::new();
+        let mut error_output = Vec::new();
+        // 同时读取标准输出和错误输出，避免其中一个管道写满后子进程阻塞
+        let (stdout_result, stderr_result) = tokio::join!(
+            stdout.read_to_end(&mut output),
+            stderr.read_to_end(&mut error_output)
+        );
+        stdout_result
+            .and(stderr_result)
+            .map_err(|e| program.error(&format!("读取子进程{}的输出失败: {e}", process.pid)))?;
+        if !process.wait_exit().await {
+            return Err(program.error(String::from_utf8_lossy(&error_output).trim()));
+        }
+        Ok(output)
+    }
+
+    /// 启动子进程，由后台的回收任务等待其退出并回收
+    ///
+    /// 子进程及其派生的进程在单独的进程组中运行，终止时一起终止；
+    /// 以本实例的标记作为argv[0]，便于识别遗留的子进程
+    fn spawn_program(
+        program: FfmpegProgram,
+        args: &[&str],
+        capture_stderr: bool,
+    ) -> Result<(Self, ChildStdout, Option<ChildStderr>), FfmpegError> {
+        let grace_period = get_capturer_config()?
+            .cmd
+            .terminate_grace_period
+            .unwrap_or(Duration::from_secs(5));
+        let mut command = Command::new(program.name());
+        command
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(if capture_stderr {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            // 回收任务被意外取消时兜底终止子进程(此时子进程尚未被回收)
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        command.process_group(0).arg0(INSTANCE_MARKER.as_str());
+        let mut child = command.spawn().map_err(FfmpegError::FfmpegSpawn)?;
+        let pid = child
+            .id()
+            .ok_or_else(|| FfmpegError::FfmpegSessionRead("无法获取子进程ID".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| FfmpegError::FfmpegTakeStdout(format!("子进程{pid}没有标准输出")))?;
+        let stderr = child.stderr.take();
+
+        let (terminate_sender, terminate_receiver) = oneshot::channel();
+        let (exit_sender, exit_receiver) = watch::channel(None);
+        let background_tasks = get_background_tasks();
+        background_tasks.spawn(Self::reap(
+            pid,
+            child,
+            terminate_receiver,
+            exit_sender,
+            grace_period,
+            background_tasks.child_token(),
+        ));
+        Ok((
+            Self {
+                pid,
+                terminate_sender: Some(terminate_sender),
+                exit_receiver,
+            },
+            stdout,
+            stderr,
+        ))
+    }
+
+    /// 子进程ID
+    pub fn id(&self) -> u32 {
+        self.pid
+    }
+
+    /// 等待子进程退出并被回收
+    ///
+    /// ## 返回值
+    /// 子进程成功退出时返回true
+    pub async fn wait_exit(&mut self) -> bool {
+        // 回收任务结束时发送者被丢弃，此时子进程也已被回收
+        self.exit_receiver
+            .wait_for(|exit| exit.is_some())
+            .await
+            .is_ok_and(|exit| *exit == Some(true))
+    }
+
+    /// 请求终止子进程，不等待其退出
+    pub fn start_terminate(&mut self) {
+        if let Some(terminate_sender) = self.terminate_sender.take() {
+            let _ = terminate_sender.send(());
+        }
+    }
+
+    /// 终止子进程，并等待其退出并被回收
+    pub async fn terminate(mut self) {
+        self.start_terminate();
+        let _ = self.wait_exit().await;
+    }
+
+    /// 本实例启动的子进程的argv[0]: `标记:本程序的进程ID:本程序的启动时间`
+    ///
+    /// 遗留的子进程按记录的进程ID及启动时间确认启动它的实例是否仍在运行，
+    /// 同时运行的其它实例(不论其可执行文件的路径)的子进程不会被误杀
+    pub fn instance_marker(marker: &str) -> String {
+        let pid = std::process::id();
+        format!(
+            "{marker}:{pid}:{}",
+            Self::read_start_time(pid).unwrap_or_default()
+        )
+    }
+
+    /// 终止上一个实例遗留的ffmpeg子进程
+    ///
+    /// 程序崩溃或被强制终止后，子进程被收养并继续运行。按argv[0]的标记识别本程序启动的子进程，
+    /// 记录的实例已不在运行时即为遗留的子进程：先向其进程组发送SIGTERM，超过宽限时间仍未退出再发送SIGKILL。
+    /// 只支持Linux(通过/proc查找进程)，其它平台不做处理
+    ///
+    /// ## 返回值
+    /// 返回发现的遗留的子进程的数量
+    pub async fn sweep_orphans(grace_period: Duration) -> usize {
+        Self::sweep_marked_orphans(FFMPEG_PROCESS_MARKER, grace_period).await
+    }
+
+    /// 终止argv[0]带有指定标记的遗留的子进程，见[`FfmpegProcess::sweep_orphans`]
+    #[cfg(target_os = "linux")]
+    pub async fn sweep_marked_orphans(marker: &str, grace_period: Duration) -> usize {
+        let orphans = Self::find_orphans(marker);
+        if orphans.is_empty() {
+            debug!("没有遗留的ffmpeg子进程");
+            return 0;
+        }
+        warn!(
+            "发现{}个上一个实例遗留的ffmpeg子进程，终止: {orphans:?}",
+            orphans.len()
+        );
+        for &pid in &orphans {
+            Self::signal_orphan(pid, libc::SIGTERM);
+        }
+        let deadline = tokio::time::Instant::now() + grace_period;
+        loop {
+            // 发送SIGKILL前重新确认，避免误杀复用了进程ID的其它进程
+            let remaining: Vec<u32> = orphans
+                .iter()
+                .copied()
+                .filter(|&pid| Self::is_orphan(pid, marker))
+                .collect();
+            if remaining.is_empty() {
+                info!("遗留的ffmpeg子进程已全部退出");
+                break;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                warn!("遗留的ffmpeg子进程超过{grace_period:?}没有退出，强制终止: {remaining:?}");
+                for pid in remaining {
+                    Self::signal_orphan(pid, libc::SIGKILL);
+                }
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        orphans.len()
+    }
+
+    /// 非Linux平台无法查找遗留的子进程，不做处理
+    #[cfg(not(target_os = "linux"))]
+    pub async fn sweep_marked_orphans(_marker: &str, _grace_period: Duration) -> usize {
+        debug!("当前平台不支持清理遗留的ffmpeg子进程");
+        0
+    }
+
+    /// 查找遗留的子进程
+    #[cfg(target_os = "linux")]
+    fn find_orphans(marker: &str) -> Vec<u32> {
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            warn!("无法读取/proc，不清理遗留的ffmpeg子进程");
+            return vec![];
+        };
+        entries
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+            .filter(|&pid| Self::is_orphan(pid, marker))
+            .collect()
+    }
+
+    /// 进程是否为遗留的子进程
+    ///
+    /// argv[0]为`标记:实例的进程ID:实例的启动时间`，且记录的实例已不在运行(进程不存在或进程ID已被复用)。
+    /// 同时运行的其它实例(如在其它路径运行或原地升级前的实例)的子进程不会被误杀
+    #[cfg(target_os = "linux")]
+    fn is_orphan(pid: u32, marker: &str) -> bool {
+        // 已退出(僵尸进程)的cmdline为空
+        let Ok(cmdline) = std::fs::read(format!("/proc/{pid}/cmdline")) else {
+            return false;
+        };
+        let Some(instance) = cmdline
+            .split(|b| *b == 0)
+            .next()
+            .and_then(|arg0| std::str::from_utf8(arg0).ok())
+            .and_then(|arg0| arg0.strip_prefix(marker)?.strip_prefix(':'))
+        else {
+            return false;
+        };
+        let Some((instance_pid, instance_start_time)) = instance.split_once(':') else {
+            return false;
+        };
+        let (Ok(instance_pid), Ok(instance_start_time)) = (
+            instance_pid.parse::<u32>(),
+            instance_start_time.parse::<u64>(),
+        ) else {
+            return false;
+        };
+        Self::read_start_time(instance_pid) != Some(instance_start_time)
+    }
+
+    /// 读取进程的启动时间(系统启动后的时钟周期数)，与进程ID一起唯一标识一个进程
+    #[cfg(target_os = "linux")]
+    fn read_start_time(pid: u32) -> Option<u64> {
+        // 第22个字段，即进程状态之后的第19个
+        Self::read_stat(pid)?.get(19)?.parse().ok()
+    }
+
+    /// 非Linux平台不清理遗留的子进程，不需要启动时间
+    #[cfg(not(target_os = "linux"))]
+    fn read_start_time(_pid: u32) -> Option<u64> {
+        None
+    }
+
+    /// 读取进程的stat中进程状态及之后的字段(`ppid pgrp ...`)
+    #[cfg(target_os = "linux")]
+    fn read_stat(pid: u32) -> Option<Vec<String>> {
+        let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        // 格式为`pid (comm) state ppid pgrp ...`，comm中可能含有空格和括号
+        Some(
+            stat.get(stat.rfind(')')? + 1..)?
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+        )
+    }
+
+    /// 向遗留的子进程所在的进程组发送信号
+    ///
+    /// 无法获取进程组或与本程序在同一个进程组时只向其本身发送
+    #[cfg(target_os = "linux")]
+    fn signal_orphan(pid: u32, signal: libc::c_int) {
+        // SAFETY: getpgrp总是成功，不涉及内存访问
+        let own_pgrp = unsafe { libc::getpgrp() } as u32;
+        let pgrp = Self::read_stat(pid)
+            .and_then(|stat| stat.get(2)?.parse::<u32>().ok())
+            .unwrap_or_default();
+        let target = if pgrp > 1 && pgrp != own_pgrp {
+            -(pgrp as libc::pid_t)
+        } else {
+            pid as libc::pid_t
+        };
+        // SAFETY: kill只发送信号，不涉及内存访问
+        if unsafe { libc::kill(target, signal) } != 0 {
+            let e = std::io::Error::last_os_error();
+            debug!("向遗留的子进程{pid}发送信号失败: {e}");
+        }
+    }
+
+    /// 读取子进程的标准输出并发送到数据通道，程序退出时不再读取
+    async fn read_stdout(
+        pid: u32,
+        mut stdout: ChildStdout,
+        data_sender: Sender<Bytes>,
+        read_buffer_size: usize,
+        cancel_token: CancellationToken,
+    ) {
+        let mut buf = vec![0u8; read_buffer_size];
+        loop {
+            let result = tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                result = stdout.read(&mut buf) => result,
+            };
+            match result {
+                Ok(0) => break,
+                Ok(n) => {
+                    let _ = data_sender.send(Bytes::copy_from_slice(&buf[..n]));
+                }
+                Err(e) => {
+                    warn!("读取子进程{pid}的输出失败: {e}");
+                    break;
+                }
+            }
+        }
+        debug!("子进程{pid}的输出已结束");
+    }
+
+    /// 等待子进程退出并回收，收到终止的请求或程序退出时先后发送SIGTERM和SIGKILL
+    async fn reap(
+        pid: u32,
+        mut child: Child,
+        terminate_receiver: oneshot::Receiver<()>,
+        exit_sender: watch::Sender<Option<bool>>,
+        grace_period: Duration,
+        cancel_token: CancellationToken,
+    ) {
+        let terminate = async {
+            tokio::select! {
+                // 请求终止或进程句柄被丢弃
+                _ = terminate_receiver => {}
+                _ = cancel_token.cancelled() => debug!("程序正在退出，终止子进程{pid}"),
+            }
+        };
+        let status = tokio::select! {
+            status = child.wait() => status,
+            _ = terminate => {
+                debug!("终止子进程{pid}");
+                Self::signal_group(&mut child, pid, Signal::Term);
+                match timeout(grace_period, child.wait()).await {
+                    Ok(status) => status,
+                    Err(_) => {
+                        warn!("子进程{pid}超过{grace_period:?}没有退出，强制终止");
+                        Self::signal_group(&mut child, pid, Signal::Kill);
+                        child.wait().await
+                    }
+                }
+            }
+        };
+        let success = status.as_ref().is_ok_and(|status| status.success());
+        match status {
+            Ok(status) => info!("子进程{pid}已退出并被回收: {status}"),
+            Err(e) => warn!("等待子进程{pid}退出失败: {e}"),
+        }
+        let _ = exit_sender.send(Some(success));
+    }
+
+    /// 向子进程所在的进程组发送信号
+    ///
+    /// 只能在子进程被回收前调用，此时进程组ID(即子进程ID)不会被复用
+    #[cfg(unix)]
+    fn signal_group(child: &mut Child, pid: u32, signal: Signal) {
+        let signal = match signal {
+            Signal::Term => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+        };
+        // SAFETY: kill只发送信号，不涉及内存访问
+        if unsafe { libc::kill(-(pid as libc::pid_t), signal) } != 0 {
+            let e = std::io::Error::last_os_error();
+            debug!("向子进程{pid}的进程组发送信号失败: {e}，改为只终止子进程");
+            let _ = child.start_kill();
+        }
+    }
+
+    /// 非unix平台没有进程组，直接终止子进程
+    #[cfg(not(unix))]
+    fn signal_group(child: &mut Child, pid: u32, _signal: Signal) {
+        if let Err(e) = child.start_kill() {
+            debug!("终止子进程{pid}失败: {e}");
+        }
+    }
+}
+
+impl Drop for FfmpegProcess {
+    /// 进程句柄被丢弃时，请求回收任务在后台终止子进程
+    fn drop(&mut self) {
+        self.start_terminate();
+    }
+}
+
+/// 输出到管道的ffmpeg子进程
+///
+/// 从中读取子进程的标准输出，被丢弃时终止子进程并归还ffmpeg进程预算的许可
+pub struct FfmpegPipe {
+    /// 子进程的标准输出
+    stdout: ChildStdout,
+    /// 子进程
+    _process: FfmpegProcess,
+    /// ffmpeg进程预算的许可
+    _budget_permit: BudgetPermit,
+}
+
+impl FfmpegPipe {
+    pub fn new(process: FfmpegProcess, stdout: ChildStdout, budget_permit: BudgetPermit) -> Self {
+        Self {
+            stdout,
+            _process: process,
+            _budget_permit: budget_permit,
+        }
+    }
+}
+
+impl AsyncRead for FfmpegPipe {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_read(cx, buf)
+    }
+}
+
+/// 终止子进程时发送的信号
+#[derive(Debug, Clone, Copy)]
+enum Signal {
+    /// 请求退出(SIGTERM)，ffmpeg收到后会正常结束输出
+    Term,
+    /// 强制终止(SIGKILL)
+    Kill,
+}
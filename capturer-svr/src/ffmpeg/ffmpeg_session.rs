@@ -1,32 +1,74 @@
+use crate::config::capturer_config::TranscodeConfig;
+use crate::ffmpeg::ffmpeg_budget::BudgetPool;
+use crate::ffmpeg::ffmpeg_eo::StreamMetadata;
+use crate::stream::flv_tag::{FlvSequenceHeaders, FlvTag};
+use crate::stream::session_recording::SessionRecording;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use tracing::debug;
-use std::sync::{Arc, OnceLock, RwLock};
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicU64};
+use std::sync::{Arc, Mutex, RwLock};
 use tokio::sync::broadcast::Sender;
-use wheel_rs::cmd::std::kill_process_by_id;
+use tokio::sync::Notify;
+use tokio_util::sync::{CancellationToken, DropGuard};
 
 /// ffmpeg会话结构体
 ///
-/// 代表一个正在进行的ffmpeg流处理会话，包含相关信息和资源
-#[derive(Clone)]
+/// 代表一个正在进行的ffmpeg流处理会话，包含相关信息和资源。
+/// 会话只保存在会话映射表中(不能克隆)，子进程由会话的监督者持有；
+/// 会话被销毁时取消会话的令牌，会话的监督者终止子进程，会话的其它后台任务退出
 pub struct FfmpegSession {
-    /// 子进程ID
-    pub child_id: u32,
-    /// 数据发送者
+    /// 会话ID(进程内唯一)
+    pub id: u64,
+    /// 会话创建的时间
+    pub created_at: DateTime<Utc>,
+    /// 当前的子进程ID(重新拉流后更新)
+    pub child_id: Arc<AtomicU32>,
+    /// 数据发送者(发送拼接后的FLV文件头及标签，重新拉流时保持不变)
     pub data_sender: Arc<Sender<Bytes>>,
-    /// 视频格式头缓存
-    pub header: Arc<OnceLock<Bytes>>,
+    /// 解复用后的FLV标签发送者
+    pub tag_sender: Arc<Sender<FlvTag>>,
+    /// 最近一次解析到的FLV文件头、脚本数据和序列头
+    pub sequence_headers: Arc<RwLock<FlvSequenceHeaders>>,
+    /// 最近一次拉流时探测到的流媒体元数据
+    pub stream_metadata: Arc<RwLock<StreamMetadata>>,
+    /// 当前拉流的地址(主码流不可用时为备用的流地址)
+    pub active_stream_url: Arc<RwLock<String>>,
+    /// 子进程退出后重新拉流的次数
+    pub respawn_count: Arc<AtomicU64>,
+    /// 最后一次收到子进程输出数据的时间戳(毫秒)，还没有收到数据时为0
+    pub last_data_ts: Arc<AtomicI64>,
+    /// 会话占用的ffmpeg进程预算的池(重新拉流后编码变化时随之调整)
+    pub budget_pool: Arc<RwLock<BudgetPool>>,
+    /// 拉流转码的配置(流管理器的配置变化时更新，之后重新拉流时使用)
+    pub transcode_config: Arc<RwLock<TranscodeConfig>>,
     /// 最后访问时间
     ///
     /// None表示当前会话处于活跃状态
     /// Some(DateTime)表示会话最后一次被访问的时间，用于判断是否超时
     pub last_access_datetime: Arc<RwLock<Option<DateTime<Utc>>>>,
+    /// 正在进行的按需录像
+    pub recording: Arc<Mutex<Option<SessionRecording>>>,
+    /// 通知会话的监督者平滑地重新拉流(如转码的配置变化)
+    pub restart_notify: Arc<Notify>,
+    /// 会话被销毁时取消会话的令牌
+    pub cancel_guard: DropGuard,
+    /// 会话的监督者退出(已终止子进程并归还ffmpeg进程预算的许可)时取消的令牌
+    pub supervisor_exited: CancellationToken,
+}
+
+impl FfmpegSession {
+    /// 会话当前占用的ffmpeg进程预算的池
+    pub fn budget_pool(&self) -> BudgetPool {
+        self.budget_pool
+            .read()
+            .map_or_else(|e| *e.into_inner(), |budget_pool| *budget_pool)
+    }
 }
 
 impl Drop for FfmpegSession {
-    /// 当会话被销毁时，自动终止关联的ffmpeg子进程
+    /// 当会话被销毁时，取消会话的令牌，通知监督者终止关联的ffmpeg子进程
     fn drop(&mut self) {
-        debug!("会话关闭...");
-        let _ = kill_process_by_id(self.child_id);
+        debug!("会话{}关闭...", self.id);
     }
 }
@@ -1,4 +1,6 @@
+pub mod ffmpeg_budget;
 pub mod ffmpeg_cmd;
 pub mod ffmpeg_eo;
 pub mod ffmpeg_error;
+pub mod ffmpeg_process;
 pub mod ffmpeg_session;
@@ -1,7 +1,16 @@
+pub mod auth;
+pub mod camera;
 pub mod config;
+pub mod credential;
 pub mod dto;
 pub mod ffmpeg;
+pub mod policy;
+pub mod quota;
+pub mod record;
+pub mod scheduler;
+pub mod spool;
 pub mod stream;
 pub mod svc;
+pub mod utils;
 pub mod vo;
 pub mod web;
@@ -1,6 +1,19 @@
+use capturer_svr::camera::camera_registry::CameraRegistry;
+use capturer_svr::camera::camera_store::init_camera_store;
 use capturer_svr::config::app_config::AppConfig;
 use capturer_svr::config::capturer_config::{init_capturer_config, update_capturer_config};
-use capturer_svr::stream::stream_manager::{init_stream_manager, update_stream_manager};
+use capturer_svr::credential::credential_store::init_credential_store;
+use capturer_svr::ffmpeg::ffmpeg_budget::{init_ffmpeg_budget, update_ffmpeg_budget};
+use capturer_svr::ffmpeg::ffmpeg_process::FfmpegProcess;
+use capturer_svr::quota::quota_manager::init_quota_manager;
+use capturer_svr::record::recorder::{init_recorder, update_recorder};
+use capturer_svr::scheduler::job_scheduler::{init_job_scheduler, update_job_scheduler};
+use capturer_svr::scheduler::snapshot_archive::init_snapshot_archive;
+use capturer_svr::spool::upload_spool::init_upload_spool;
+use capturer_svr::stream::stream_manager::{
+    init_stream_manager, shutdown_stream_manager, update_stream_manager,
+};
+use capturer_svr::utils::task_utils::shutdown_background_tasks;
 use clap::Parser;
 use oss_api_client::api_client::{init_oss_api_client, update_oss_api_client};
 use robotech::app::{wait_app_exit, AppWatcher};
@@ -10,7 +23,8 @@ use robotech::macros::log_call;
 use robotech::signal::SignalManager;
 use robotech::web::{start_web_server, stop_web_service};
 use std::sync::Arc;
-use tracing::info;
+use std::time::Duration;
+use tracing::{error, info};
 
 /// 视频抓拍工具
 ///
@@ -77,10 +91,20 @@ async fn main() -> anyhow::Result<()> {
         move |app_config: Arc<AppConfig>| async move {
             // 更新capturer的配置
             update_capturer_config(app_config.capturer.clone())?;
+            // 先调整ffmpeg进程预算，之后重新拉流的会话及同步的摄像头按新的预算获取许可
+            update_ffmpeg_budget(&app_config.capturer)?;
             // 更新oss的API客户端的配置
             update_oss_api_client(app_config.api_client.clone())?;
-            // 更新流管理器
+            // 更新流管理器(保留正在进行的会话，转码的配置变化时平滑地重新拉流)
             update_stream_manager(app_config.capturer.clone())?;
+            // 为流管理器同步摄像头(含数据库中的摄像头)，失败时记录日志后继续更新其它配置
+            if let Err(e) = CameraRegistry::sync_cameras().await {
+                error!("热加载配置时同步摄像头失败: {e:?}");
+            }
+            // 更新定时任务(保留任务的运行状态及通过接口创建的任务)
+            update_job_scheduler(app_config.capturer.jobs.clone())?;
+            // 更新录像的摄像头(配置未变化的摄像头不中断录像)
+            update_recorder(app_config.capturer.record.clone()).await?;
 
             // 应用配置
             apply_app_config(app_config, port, None)
@@ -96,8 +120,34 @@ async fn main() -> anyhow::Result<()> {
     init_capturer_config(app_watcher.app_config.capturer.clone())?;
     // 初始化oss的API客户端的配置
     init_oss_api_client(app_watcher.app_config.api_client.clone())?;
-    // 初始化流管理器
+    // 初始化摄像头存储及凭据存储(须在流管理器之前，常驻拉流的摄像头需要注入凭据)
+    init_camera_store()?;
+    init_credential_store()?;
+    // 初始化配额管理器(每月上传到OSS的字节数与摄像头保存在同一个数据库中)
+    init_quota_manager()?;
+    // 初始化ffmpeg进程预算(须在流管理器之前，常驻会话启动时需要获取预算)
+    init_ffmpeg_budget(&app_watcher.app_config.capturer)?;
+    // 终止上一个实例遗留的ffmpeg子进程(须在流管理器之前，常驻会话会启动新的子进程)
+    FfmpegProcess::sweep_orphans(
+        app_watcher
+            .app_config
+            .capturer
+            .cmd
+            .terminate_grace_period
+            .unwrap_or(Duration::from_secs(5)),
+    )
+    .await;
+    // 初始化流管理器，并同步摄像头
     init_stream_manager(app_watcher.app_config.capturer.clone())?;
+    CameraRegistry::sync_cameras().await?;
+    // 初始化上传暂存
+    init_upload_spool().await?;
+    // 初始化抓拍归档
+    init_snapshot_archive();
+    // 初始化定时任务调度器
+    init_job_scheduler(app_watcher.app_config.capturer.jobs.clone())?;
+    // 初始化录像器
+    init_recorder(app_watcher.app_config.capturer.record.clone()).await?;
 
     // 应用配置
     apply_app_config(app_watcher.app_config.clone(), port, old_pid).await?;
@@ -106,6 +156,10 @@ async fn main() -> anyhow::Result<()> {
     let signal_receiver = signal_manager.watch_signal()?;
     Ok(wait_app_exit(signal_receiver, || async move {
         stop_web_service().await.expect("无法停止旧的Web服务");
+        // 关闭所有会话，等待ffmpeg进程被终止并回收
+        shutdown_stream_manager().await.expect("无法关闭流管理器");
+        // 通知录像、定时任务等后台任务退出，终止剩余的ffmpeg进程并等待其被回收
+        shutdown_background_tasks().await.expect("无法关闭后台任务");
         Ok(())
     })
     .await?)
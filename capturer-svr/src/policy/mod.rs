@@ -0,0 +1,2 @@
+pub mod policy_error;
+pub mod url_policy;
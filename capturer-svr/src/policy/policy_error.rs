@@ -0,0 +1,21 @@
+use robotech::cfg::CfgError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UrlPolicyError {
+    #[error("获取流地址策略配置失败: {0}")]
+    UrlPolicyConfig(#[from] CfgError),
+    #[error("流地址格式不正确: {0}")]
+    UrlParse(String),
+    #[error("不允许的协议: {0}")]
+    UrlScheme(String),
+    #[error("流地址缺少主机: {0}")]
+    UrlNoHost(String),
+    #[error("不允许的端口: {0}")]
+    UrlPort(u16),
+    #[error("拒绝访问的主机: {0}")]
+    UrlHostDenied(String),
+    #[error("不在允许范围内的主机: {0}")]
+    UrlHostNotAllowed(String),
+    #[error("解析主机失败: {0}")]
+    UrlResolve(String),
+}
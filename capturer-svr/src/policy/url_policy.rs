@@ -0,0 +1,285 @@
+use crate::config::capturer_config::{get_capturer_config, UrlPolicyConfig};
+use crate::policy::policy_error::UrlPolicyError;
+use crate::utils::url_utils::{has_userinfo, mask_stream_url};
+use std::borrow::Cow;
+use std::net::{IpAddr, SocketAddr};
+use tracing::debug;
+use url::{Host, Url};
+use validator::ValidationError;
+
+/// webhook允许的协议
+const WEBHOOK_SCHEMES: [&str; 2] = ["http", "https"];
+
+/// 流地址策略
+///
+/// 按配置的协议、主机、网段及端口检查流地址：
+/// - 请求参数通过`validate_stream_url`做不需要解析域名的检查
+/// - 调用ffmpeg之前通过`check_resolved`再按域名解析后的地址检查网段
+pub struct UrlPolicy;
+
+/// 检查流地址后得到的主机信息
+struct CheckedHost {
+    /// 主机(域名或IP)
+    host: String,
+    /// 端口(地址中没有端口时为协议的默认端口)
+    port: u16,
+    /// 主机为IP，已经检查过网段
+    is_ip: bool,
+    /// 主机已匹配允许的主机，不需要再匹配允许的网段
+    host_allowed: bool,
+}
+
+impl UrlPolicy {
+    /// 检查流地址(不解析域名)
+    pub fn check(stream_url: &str) -> Result<(), UrlPolicyError> {
+        let capturer_config = get_capturer_config()?;
+        Self::check_host(&capturer_config.url_policy, stream_url).map(|_| ())
+    }
+
+    /// 检查流地址，主机为域名时解析后再检查网段
+    ///
+    /// 在调用ffmpeg之前调用
+    pub async fn check_resolved(stream_url: &str) -> Result<(), UrlPolicyError> {
+        let capturer_config = get_capturer_config()?;
+        let policy = &capturer_config.url_policy;
+        let checked_host = Self::check_host(policy, stream_url)?;
+        if checked_host.is_ip || (policy.denied_cidrs.is_empty() && policy.allowed_cidrs.is_empty())
+        {
+            return Ok(());
+        }
+        let addrs = tokio::net::lookup_host((checked_host.host.as_str(), checked_host.port))
+            .await
+            .map_err(|e| UrlPolicyError::UrlResolve(format!("{}: {e}", checked_host.host)))?;
+        for addr in addrs {
+            debug!("主机{}解析为{}", checked_host.host, addr.ip());
+            Self::check_ip(policy, addr.ip(), checked_host.host_allowed).map_err(|e| match e {
+                UrlPolicyError::UrlHostDenied(ip) => {
+                    UrlPolicyError::UrlHostDenied(format!("{}({ip})", checked_host.host))
+                }
+                UrlPolicyError::UrlHostNotAllowed(ip) => {
+                    UrlPolicyError::UrlHostNotAllowed(format!("{}({ip})", checked_host.host))
+                }
+                e => e,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// 检查webhook的地址，并解析出回调时使用的地址
+    ///
+    /// webhook只允许http和https协议，按拒绝的主机和网段检查(允许的主机和网段只用于流地址)；
+    /// 主机为域名时检查解析后的所有地址，回调时固定使用返回的地址，防止检查后DNS重绑定到内部地址
+    ///
+    /// ## 返回值
+    /// 主机为域名时返回(域名, 解析后的地址)，为IP时返回None
+    pub async fn check_webhook(
+        webhook: &str,
+    ) -> Result<Option<(String, SocketAddr)>, UrlPolicyError> {
+        let capturer_config = get_capturer_config()?;
+        let policy = &capturer_config.url_policy;
+        let url = Url::parse(webhook)
+            .map_err(|e| UrlPolicyError::UrlParse(format!("{}: {e}", mask_stream_url(webhook))))?;
+        let scheme = url.scheme();
+        if !WEBHOOK_SCHEMES.contains(&scheme) {
+            return Err(UrlPolicyError::UrlScheme(scheme.to_string()));
+        }
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| UrlPolicyError::UrlParse(format!("无法确定{scheme}协议的端口")))?;
+        let (host, ip) = match url.host() {
+            Some(Host::Ipv4(ip)) => (ip.to_string(), Some(IpAddr::V4(ip))),
+            Some(Host::Ipv6(ip)) => (ip.to_string(), Some(IpAddr::V6(ip))),
+            Some(Host::Domain(domain)) => (domain.to_ascii_lowercase(), None),
+            None => return Err(UrlPolicyError::UrlNoHost(mask_stream_url(webhook))),
+        };
+        if policy
+            .denied_hosts
+            .iter()
+            .any(|pattern| host_matches(pattern, &host))
+        {
+            return Err(UrlPolicyError::UrlHostDenied(host));
+        }
+        if let Some(ip) = ip {
+            Self::check_denied_ip(policy, ip)?;
+            return Ok(None);
+        }
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+            .await
+            .map_err(|e| UrlPolicyError::UrlResolve(format!("{host}: {e}")))?
+            .collect();
+        for addr in &addrs {
+            debug!("webhook的主机{host}解析为{}", addr.ip());
+            Self::check_denied_ip(policy, addr.ip())
+                .map_err(|_| UrlPolicyError::UrlHostDenied(format!("{host}({})", addr.ip())))?;
+        }
+        let addr = addrs
+            .into_iter()
+            .next()
+            .ok_or_else(|| UrlPolicyError::UrlResolve(format!("{host}: 没有解析到地址")))?;
+        Ok(Some((host, addr)))
+    }
+
+    /// 传给ffmpeg的-protocol_whitelist参数
+    pub fn protocol_whitelist() -> Result<String, UrlPolicyError> {
+        Ok(get_capturer_config()?.url_policy.protocol_whitelist.clone())
+    }
+
+    /// 检查协议、端口及主机，主机为IP时同时检查网段
+    fn check_host(
+        policy: &UrlPolicyConfig,
+        stream_url: &str,
+    ) -> Result<CheckedHost, UrlPolicyError> {
+        let url = Url::parse(stream_url).map_err(|e| {
+            UrlPolicyError::UrlParse(format!("{}: {e}", mask_stream_url(stream_url)))
+        })?;
+        let scheme = url.scheme();
+        if !policy
+            .allowed_schemes
+            .iter()
+            .any(|allowed_scheme| allowed_scheme.eq_ignore_ascii_case(scheme))
+        {
+            return Err(UrlPolicyError::UrlScheme(scheme.to_string()));
+        }
+
+        let port = url
+            .port()
+            .or_else(|| default_port(scheme))
+            .ok_or_else(|| UrlPolicyError::UrlParse(format!("无法确定{scheme}协议的端口")))?;
+        if !policy.allowed_ports.is_empty() && !policy.allowed_ports.contains(&port) {
+            return Err(UrlPolicyError::UrlPort(port));
+        }
+
+        // 非特殊协议(如rtsp)的IPv4地址会被解析为域名，需要再按IP解析一次
+        let ip = match url.host() {
+            Some(Host::Ipv4(ip)) => Some(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => Some(IpAddr::V6(ip)),
+            Some(Host::Domain(domain)) => domain.parse::<IpAddr>().ok(),
+            None => return Err(UrlPolicyError::UrlNoHost(mask_stream_url(stream_url))),
+        };
+        let host = match ip {
+            Some(ip) => ip.to_string(),
+            None => url.host_str().unwrap_or_default().to_ascii_lowercase(),
+        };
+        if policy
+            .denied_hosts
+            .iter()
+            .any(|pattern| host_matches(pattern, &host))
+        {
+            return Err(UrlPolicyError::UrlHostDenied(host));
+        }
+        let host_allowed = policy
+            .allowed_hosts
+            .iter()
+            .any(|pattern| host_matches(pattern, &host));
+
+        match ip {
+            Some(ip) => Self::check_ip(policy, ip, host_allowed)?,
+            // 域名只能在解析后匹配允许的网段，没有配置允许的网段时直接拒绝
+            None if !host_allowed
+                && !policy.allowed_hosts.is_empty()
+                && policy.allowed_cidrs.is_empty() =>
+            {
+                return Err(UrlPolicyError::UrlHostNotAllowed(host));
+            }
+            None => {}
+        }
+        Ok(CheckedHost {
+            host,
+            port,
+            is_ip: ip.is_some(),
+            host_allowed,
+        })
+    }
+
+    /// 检查IP是否在拒绝的网段中
+    fn check_denied_ip(policy: &UrlPolicyConfig, ip: IpAddr) -> Result<(), UrlPolicyError> {
+        // IPv4映射的IPv6地址(如::ffff:127.0.0.1)按IPv4检查
+        let ip = ip.to_canonical();
+        if policy.denied_nets().iter().any(|cidr| cidr.contains(&ip)) {
+            return Err(UrlPolicyError::UrlHostDenied(ip.to_string()));
+        }
+        Ok(())
+    }
+
+    /// 检查IP是否在拒绝或允许的网段中
+    fn check_ip(
+        policy: &UrlPolicyConfig,
+        ip: IpAddr,
+        host_allowed: bool,
+    ) -> Result<(), UrlPolicyError> {
+        Self::check_denied_ip(policy, ip)?;
+        let ip = ip.to_canonical();
+        if host_allowed || (policy.allowed_hosts.is_empty() && policy.allowed_cidrs.is_empty()) {
+            return Ok(());
+        }
+        if policy.allowed_nets().iter().any(|cidr| cidr.contains(&ip)) {
+            return Ok(());
+        }
+        Err(UrlPolicyError::UrlHostNotAllowed(ip.to_string()))
+    }
+}
+
+/// 校验请求参数中的流地址
+pub fn validate_stream_url(stream_url: &str) -> Result<(), ValidationError> {
+    UrlPolicy::check(stream_url).map_err(|e| {
+        ValidationError::new("url_policy").with_message(Cow::Owned(format!("流地址不被允许: {e}")))
+    })
+}
+
+/// 校验请求参数中的webhook地址(不解析域名，回调时再按解析后的地址检查)
+pub fn validate_webhook_url(webhook: &str) -> Result<(), ValidationError> {
+    let url = Url::parse(webhook).map_err(|e| {
+        ValidationError::new("url_policy")
+            .with_message(Cow::Owned(format!("webhook的地址格式不正确: {e}")))
+    })?;
+    if !WEBHOOK_SCHEMES.contains(&url.scheme()) {
+        return Err(ValidationError::new("url_policy")
+            .with_message(Cow::Borrowed("webhook只允许http或https协议")));
+    }
+    Ok(())
+}
+
+/// 校验请求参数中的流地址列表
+pub fn validate_stream_urls(stream_urls: &[String]) -> Result<(), ValidationError> {
+    stream_urls
+        .iter()
+        .try_for_each(|stream_url| validate_stream_url(stream_url))
+}
+
+/// 校验请求参数中摄像头备用的流地址
+///
+/// 备用的流地址与主码流一样注入摄像头的凭据，不能在地址中带有用户信息(避免明文保存凭据)
+pub fn validate_fallback_stream_urls(stream_urls: &[String]) -> Result<(), ValidationError> {
+    for stream_url in stream_urls {
+        validate_stream_url(stream_url)?;
+        if has_userinfo(stream_url) {
+            return Err(ValidationError::new("fallback_stream_url_userinfo")
+                .with_message("备用的流地址不能带有用户信息，请使用摄像头的凭据".into()));
+        }
+    }
+    Ok(())
+}
+
+/// 协议的默认端口
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme.to_ascii_lowercase().as_str() {
+        "rtsp" => Some(554),
+        "rtsps" => Some(322),
+        "rtmp" => Some(1935),
+        "rtmps" => Some(443),
+        "http" => Some(80),
+        "https" => Some(443),
+        _ => None,
+    }
+}
+
+/// 主机是否匹配(`*.example.com`匹配example.com的子域名)
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.')),
+        None => pattern == host,
+    }
+}
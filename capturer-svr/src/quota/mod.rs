@@ -0,0 +1,3 @@
+pub mod quota_eo;
+pub mod quota_error;
+pub mod quota_manager;
@@ -0,0 +1,27 @@
+/// 用户的配额上限(None表示不限制)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QuotaLimits {
+    /// 每分钟的抓拍次数上限
+    pub captures_per_minute: Option<u32>,
+    /// 同时观看直播的数量上限
+    pub max_live_streams: Option<u32>,
+    /// 每月上传到OSS的字节数上限
+    pub monthly_oss_bytes: Option<u64>,
+}
+
+/// 用户的配额使用情况
+#[derive(Debug, Clone)]
+pub struct QuotaUsage {
+    /// 用户ID
+    pub user_id: u64,
+    /// 统计的月份(UTC，格式为`YYYY-MM`)
+    pub month: String,
+    /// 最近一分钟的抓拍次数
+    pub captures_last_minute: u32,
+    /// 正在观看的直播数量
+    pub live_streams: u32,
+    /// 本月上传到OSS的字节数
+    pub monthly_oss_bytes: u64,
+    /// 配额上限
+    pub limits: QuotaLimits,
+}
@@ -0,0 +1,38 @@
+use robotech::cfg::CfgError;
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum QuotaError {
+    #[error("获取配额配置失败: {0}")]
+    QuotaConfig(#[from] CfgError),
+    #[error("读写配额数据库失败: {0}")]
+    QuotaDb(#[from] rusqlite::Error),
+    #[error("等待配额数据库操作完成失败: {0}")]
+    QuotaJoin(String),
+    #[error("本次抓拍的数量{count}超过每分钟的抓拍次数上限{limit}")]
+    QuotaCaptureCount { count: u32, limit: u32 },
+    #[error("每分钟的抓拍次数超过上限{limit}")]
+    QuotaCaptureRate { limit: u32, retry_after: Duration },
+    #[error("同时观看直播的数量超过上限{limit}")]
+    QuotaLiveStreams { limit: u32, retry_after: Duration },
+    #[error("本月上传到OSS的字节数超过上限{limit}")]
+    QuotaOssBytes { limit: u64, retry_after: Duration },
+}
+
+impl QuotaError {
+    /// 超过配额时建议的重试等待时间，其它错误返回None
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            QuotaError::QuotaCaptureRate { retry_after, .. }
+            | QuotaError::QuotaLiveStreams { retry_after, .. }
+            | QuotaError::QuotaOssBytes { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        }
+    }
+
+    /// 建议的重试等待秒数，不足一秒的按一秒，避免客户端立即重试
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        self.retry_after()
+            .map(|retry_after| retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0))
+    }
+}
@@ -0,0 +1,362 @@
+use crate::config::capturer_config::{get_capturer_config, QuotaConfig};
+use crate::quota::quota_eo::{QuotaLimits, QuotaUsage};
+use crate::quota::quota_error::QuotaError;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use robotech::cfg::CfgError;
+use rusqlite::{params, Connection};
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// 全局静态的配额管理器实例
+static QUOTA_MANAGER: OnceLock<QuotaManager> = OnceLock::new();
+
+/// 统计抓拍次数的时间窗口
+pub const CAPTURE_WINDOW: Duration = Duration::from_secs(60);
+
+/// 同时观看直播的数量超过上限时建议的重试等待时间(无法预知其它直播何时结束)
+const LIVE_STREAM_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// 初始化配额管理器
+///
+/// 每月上传到OSS的字节数与摄像头保存在同一个SQLite数据库中，重启后继续累计；
+/// 抓拍次数及观看直播的数量只在内存中统计
+pub fn init_quota_manager() -> Result<(), QuotaError> {
+    let capturer_config = get_capturer_config()?;
+    let db_path = &capturer_config.camera_store.db_path;
+    info!("初始化配额管理器: {db_path}");
+    let conn = Connection::open(db_path)?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS quota_oss_usage (
+            user_id INTEGER NOT NULL,
+            month TEXT NOT NULL,
+            bytes INTEGER NOT NULL,
+            PRIMARY KEY (user_id, month)
+        );",
+    )?;
+    let month = current_month(Utc::now());
+    let mut oss_bytes = FxHashMap::default();
+    {
+        let mut stmt =
+            conn.prepare("SELECT user_id, bytes FROM quota_oss_usage WHERE month = ?1")?;
+        let rows = stmt.query_map(params![month], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        for row in rows {
+            let (user_id, bytes) = row?;
+            oss_bytes.insert(user_id as u64, (month.clone(), bytes as u64));
+        }
+    }
+    info!("加载了{}个用户本月上传到OSS的字节数", oss_bytes.len());
+
+    QUOTA_MANAGER
+        .set(QuotaManager {
+            conn: Arc::new(Mutex::new(conn)),
+            captures: Mutex::new(FxHashMap::default()),
+            live_streams: Arc::new(Mutex::new(FxHashMap::default())),
+            oss_bytes: RwLock::new(oss_bytes),
+        })
+        .map_err(|_| CfgError::Init("QuotaManager init failed".to_string()).into())
+}
+
+pub fn get_quota_manager() -> Result<&'static QuotaManager, QuotaError> {
+    QUOTA_MANAGER
+        .get()
+        .ok_or(CfgError::NotInit("QuotaManager not initialized".to_string()).into())
+}
+
+/// 配额管理器
+///
+/// 按用户ID统计每分钟的抓拍次数、同时观看直播的数量及每月上传到OSS的字节数，
+/// 上限每次检查时从配置中读取，热加载配置后立即生效
+pub struct QuotaManager {
+    /// 数据库连接
+    conn: Arc<Mutex<Connection>>,
+    /// 用户ID -> 最近一分钟内抓拍的时间
+    captures: Mutex<FxHashMap<u64, CaptureWindow>>,
+    /// 用户ID -> 正在观看的直播数量
+    live_streams: Arc<Mutex<FxHashMap<u64, u32>>>,
+    /// 用户ID -> (月份, 该月上传到OSS的字节数)
+    oss_bytes: RwLock<FxHashMap<u64, (String, u64)>>,
+}
+
+impl QuotaManager {
+    /// 占用用户的抓拍配额
+    ///
+    /// 本月上传到OSS的字节数已达上限，或最近一分钟的抓拍次数加上本次的数量超过上限时返回错误，
+    /// 不超过时记录本次的抓拍次数；本次的数量本身超过上限时无论等待多久都不能满足，返回参数错误
+    ///
+    /// ## 参数
+    /// * `user_id` - 用户ID
+    /// * `count` - 本次抓拍的数量(批量抓拍时为流的数量)
+    pub fn acquire_captures(&self, user_id: u64, count: u32) -> Result<(), QuotaError> {
+        let limits = Self::limits_of(user_id)?;
+        self.check_oss_bytes(user_id, &limits, 0)?;
+        let Some(limit) = limits.captures_per_minute else {
+            return Ok(());
+        };
+        let mut captures = self
+            .captures
+            .lock()
+            .map_err(|e| QuotaError::QuotaJoin(format!("无法获取抓拍次数锁: {e}")))?;
+        let now = Instant::now();
+        // 顺带清理时间窗口内已没有抓拍的用户，避免统计的用户只增不减
+        captures.retain(|_, window| !window.is_expired(now));
+        let result = captures
+            .entry(user_id)
+            .or_default()
+            .acquire(now, count, limit);
+        if result.is_err() {
+            debug!("用户{user_id}每分钟的抓拍次数超过上限{limit}");
+        }
+        result
+    }
+
+    /// 占用用户观看直播的配额
+    ///
+    /// 返回的许可在直播结束(许可被释放)时归还
+    pub fn acquire_live_stream(&self, user_id: u64) -> Result<LiveStreamPermit, QuotaError> {
+        let limits = Self::limits_of(user_id)?;
+        let mut live_streams = self
+            .live_streams
+            .lock()
+            .map_err(|e| QuotaError::QuotaJoin(format!("无法获取直播数量锁: {e}")))?;
+        let current = live_streams.entry(user_id).or_default();
+        if let Some(limit) = limits.max_live_streams
+            && *current >= limit
+        {
+            debug!("用户{user_id}同时观看直播的数量超过上限{limit}");
+            return Err(QuotaError::QuotaLiveStreams {
+                limit,
+                retry_after: LIVE_STREAM_RETRY_AFTER,
+            });
+        }
+        *current += 1;
+        Ok(LiveStreamPermit {
+            user_id,
+            live_streams: Arc::clone(&self.live_streams),
+        })
+    }
+
+    /// 累计用户本月上传到OSS的字节数(上传失败写入暂存的也计入，暂存稍后会上传)
+    pub async fn record_oss_bytes(&self, user_id: u64, bytes: u64) -> Result<(), QuotaError> {
+        let month = current_month(Utc::now());
+        if let Ok(mut oss_bytes) = self.oss_bytes.write() {
+            let entry = oss_bytes.entry(user_id).or_insert((month.clone(), 0));
+            if entry.0 != month {
+                *entry = (month.clone(), 0);
+            }
+            entry.1 = entry.1.saturating_add(bytes);
+        }
+        let conn = Arc::clone(&self.conn);
+        tokio::task::spawn_blocking(move || {
+            let conn = conn
+                .lock()
+                .map_err(|e| QuotaError::QuotaJoin(format!("无法获取数据库连接锁: {e}")))?;
+            conn.execute(
+                "INSERT INTO quota_oss_usage (user_id, month, bytes) VALUES (?1, ?2, ?3)
+                    ON CONFLICT(user_id, month) DO UPDATE SET bytes = bytes + ?3",
+                params![user_id as i64, month, bytes as i64],
+            )?;
+            Ok::<_, QuotaError>(())
+        })
+        .await
+        .map_err(|e| QuotaError::QuotaJoin(e.to_string()))?
+    }
+
+    /// 查询用户的配额使用情况
+    pub fn usage(&self, user_id: u64) -> Result<QuotaUsage, QuotaError> {
+        let now = Instant::now();
+        let captures_last_minute = self
+            .captures
+            .lock()
+            .map(|captures| captures.get(&user_id).map_or(0, |window| window.count(now)))
+            .unwrap_or_default();
+        let live_streams = self
+            .live_streams
+            .lock()
+            .map(|live_streams| live_streams.get(&user_id).copied().unwrap_or_default())
+            .unwrap_or_default();
+        Ok(QuotaUsage {
+            user_id,
+            month: current_month(Utc::now()),
+            captures_last_minute,
+            live_streams,
+            monthly_oss_bytes: self.monthly_oss_bytes(user_id),
+            limits: Self::limits_of(user_id)?,
+        })
+    }
+
+    /// 检查用户是否还可以上传指定字节数的文件到OSS
+    ///
+    /// 在上传(或写入暂存)前调用，本月已上传的字节数加上本次的字节数超过上限时返回错误
+    pub fn check_oss_upload(&self, user_id: u64, bytes: u64) -> Result<(), QuotaError> {
+        let limits = Self::limits_of(user_id)?;
+        self.check_oss_bytes(user_id, &limits, bytes)
+    }
+
+    /// 检查用户本月上传到OSS的字节数加上待上传的字节数是否超过上限(已达上限时不能再上传)
+    fn check_oss_bytes(
+        &self,
+        user_id: u64,
+        limits: &QuotaLimits,
+        pending_bytes: u64,
+    ) -> Result<(), QuotaError> {
+        let Some(limit) = limits.monthly_oss_bytes else {
+            return Ok(());
+        };
+        let monthly_oss_bytes = self.monthly_oss_bytes(user_id);
+        if monthly_oss_bytes >= limit || monthly_oss_bytes.saturating_add(pending_bytes) > limit {
+            debug!("用户{user_id}本月上传到OSS的字节数超过上限{limit}");
+            return Err(QuotaError::QuotaOssBytes {
+                limit,
+                retry_after: until_next_month(Utc::now()),
+            });
+        }
+        Ok(())
+    }
+
+    /// 用户本月上传到OSS的字节数
+    fn monthly_oss_bytes(&self, user_id: u64) -> u64 {
+        let month = current_month(Utc::now());
+        self.oss_bytes
+            .read()
+            .ok()
+            .and_then(|oss_bytes| {
+                oss_bytes
+                    .get(&user_id)
+                    .filter(|(entry_month, _)| *entry_month == month)
+                    .map(|(_, bytes)| *bytes)
+            })
+            .unwrap_or_default()
+    }
+
+    /// 用户的配额上限，按用户配置的上限优先
+    fn limits_of(user_id: u64) -> Result<QuotaLimits, QuotaError> {
+        let capturer_config = get_capturer_config()?;
+        let QuotaConfig {
+            captures_per_minute,
+            max_live_streams,
+            monthly_oss_bytes,
+            users,
+        } = &capturer_config.quota;
+        let user = users.iter().find(|user| user.user_id == user_id);
+        Ok(QuotaLimits {
+            captures_per_minute: user
+                .and_then(|user| user.captures_per_minute)
+                .or(*captures_per_minute),
+            max_live_streams: user
+                .and_then(|user| user.max_live_streams)
+                .or(*max_live_streams),
+            monthly_oss_bytes: user
+                .and_then(|user| user.monthly_oss_bytes)
+                .or(*monthly_oss_bytes),
+        })
+    }
+}
+
+/// 用户最近一分钟内抓拍的时间窗口
+#[derive(Debug, Default)]
+pub struct CaptureWindow {
+    /// 按时间先后排列的抓拍时间
+    captures: VecDeque<Instant>,
+}
+
+impl CaptureWindow {
+    /// 占用时间窗口内的抓拍次数
+    ///
+    /// 超过上限时返回建议的重试等待时间：需要等到足够多的抓拍移出时间窗口；
+    /// 本次的数量本身超过上限时返回参数错误
+    pub fn acquire(&mut self, now: Instant, count: u32, limit: u32) -> Result<(), QuotaError> {
+        if count > limit {
+            return Err(QuotaError::QuotaCaptureCount { count, limit });
+        }
+        self.evict(now);
+        let total = self.captures.len() + count as usize;
+        if total > limit as usize {
+            // 最早的(total - limit)次抓拍移出时间窗口后才有足够的次数
+            let retry_after = self
+                .captures
+                .get(total - limit as usize - 1)
+                .map(|at| CAPTURE_WINDOW.saturating_sub(now.duration_since(*at)))
+                .unwrap_or(CAPTURE_WINDOW);
+            return Err(QuotaError::QuotaCaptureRate { limit, retry_after });
+        }
+        self.captures
+            .extend(std::iter::repeat_n(now, count as usize));
+        Ok(())
+    }
+
+    /// 时间窗口内的抓拍次数
+    pub fn count(&self, now: Instant) -> u32 {
+        self.captures
+            .iter()
+            .filter(|at| now.duration_since(**at) < CAPTURE_WINDOW)
+            .count() as u32
+    }
+
+    /// 时间窗口内是否已没有抓拍
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.captures
+            .back()
+            .is_none_or(|at| now.duration_since(*at) >= CAPTURE_WINDOW)
+    }
+
+    /// 移除移出时间窗口的抓拍
+    fn evict(&mut self, now: Instant) {
+        while self
+            .captures
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= CAPTURE_WINDOW)
+        {
+            self.captures.pop_front();
+        }
+    }
+}
+
+/// 观看直播的配额许可
+///
+/// 随直播流一起持有，直播结束时释放并归还配额
+pub struct LiveStreamPermit {
+    user_id: u64,
+    live_streams: Arc<Mutex<FxHashMap<u64, u32>>>,
+}
+
+impl Drop for LiveStreamPermit {
+    fn drop(&mut self) {
+        match self.live_streams.lock() {
+            Ok(mut live_streams) => {
+                if let Some(current) = live_streams.get_mut(&self.user_id) {
+                    *current = current.saturating_sub(1);
+                    if *current == 0 {
+                        live_streams.remove(&self.user_id);
+                    }
+                }
+            }
+            Err(e) => warn!(
+                "无法获取直播数量锁，归还用户{}的直播配额失败: {e}",
+                self.user_id
+            ),
+        }
+    }
+}
+
+/// 统计的月份(UTC)
+fn current_month(now: DateTime<Utc>) -> String {
+    now.format("%Y-%m").to_string()
+}
+
+/// 距离下个月开始(UTC)的时间
+pub fn until_next_month(now: DateTime<Utc>) -> Duration {
+    let (year, month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .and_then(|next_month| (next_month - now).to_std().ok())
+        .unwrap_or(CAPTURE_WINDOW)
+}
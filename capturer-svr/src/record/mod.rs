@@ -0,0 +1,5 @@
+pub mod record_eo;
+pub mod record_playback;
+pub mod record_store;
+pub mod recorder;
+pub mod segment_writer;
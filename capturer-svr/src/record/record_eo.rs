@@ -0,0 +1,25 @@
+use crate::config::capturer_config::RecordFormat;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+
+/// 已完成的录像分段
+#[derive(Debug, Clone)]
+pub struct RecordSegment {
+    /// 开始录制的时间
+    pub start: DateTime<Utc>,
+    /// 时长(毫秒)
+    pub duration_ms: u32,
+    /// 文件格式
+    pub format: RecordFormat,
+    /// 文件路径
+    pub path: PathBuf,
+    /// 文件大小(字节)
+    pub size: u64,
+}
+
+impl RecordSegment {
+    /// 结束录制的时间
+    pub fn end(&self) -> DateTime<Utc> {
+        self.start + chrono::Duration::milliseconds(self.duration_ms as i64)
+    }
+}
@@ -0,0 +1,316 @@
+use crate::config::capturer_config::RecordFormat;
+use crate::ffmpeg::ffmpeg_cmd::FfmpegCmd;
+use crate::record::record_eo::RecordSegment;
+use crate::record::record_store::RecordStore;
+use crate::stream::flv_tag::{encode_flv_header, FlvDemuxer, FlvSequenceHeaders, FlvTag};
+use async_stream::try_stream;
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use std::fmt::Write;
+use std::pin::Pin;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::debug;
+
+/// 读取分段文件的缓冲区大小
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+/// 无法推算帧间隔时，相邻分段之间的时间戳间隔(毫秒)
+const DEFAULT_FRAME_INTERVAL_MS: u32 = 40;
+
+/// 录像回放
+///
+/// - HTTP-FLV: 将时间范围内的分段依次解复用为标签，改写为连续的时间戳后输出为一路FLV，
+///   播放器感知不到分段的边界(录像中断的时段会被跳过)
+/// - HLS VOD: 生成时间范围内分段的播放列表，每个分段由ffmpeg转封装为TS，时间戳按分段的开始时间连续偏移
+///
+/// 回放从请求时间处或之前最近的关键帧开始
+pub struct RecordPlayback;
+
+/// 正在读取的分段
+struct SegmentReader {
+    /// 读取的数据源(MP4分段为转封装的ffmpeg子进程的输出，读取结束丢弃时终止进程)
+    reader: Pin<Box<dyn AsyncRead + Send>>,
+    /// 读取的数据中时间戳0对应分段内的位置(毫秒)
+    offset_ms: u32,
+    /// 读取的数据中需要跳过的时长(毫秒)，从该时间点之前最近的关键帧开始输出
+    seek_ms: u32,
+}
+
+impl RecordPlayback {
+    /// 从指定时间开始回放FLV
+    ///
+    /// 未指定结束时间时，列出的分段播放完后会重新列出，以便接上回放期间新完成的分段
+    pub fn flv_stream(
+        camera_id: String,
+        start: DateTime<Utc>,
+        end: Option<DateTime<Utc>>,
+    ) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+        try_stream! {
+            let list_end = end.unwrap_or(DateTime::<Utc>::MAX_UTC);
+            let mut position = start;
+            let mut header_sent = false;
+            // 已输出的序列头，分段的序列头变化时才再次输出
+            let mut sent_headers = FlvSequenceHeaders::default();
+            // 已输出的最后一个时间戳，下一个分段从其后一帧开始
+            let mut last_timestamp: Option<u32> = None;
+            let mut frame_interval_ms = DEFAULT_FRAME_INTERVAL_MS;
+
+            loop {
+                let segments: Vec<RecordSegment> = RecordStore::list(&camera_id, position, list_end)
+                    .await?
+                    .into_iter()
+                    .filter(|segment| segment.end() > position)
+                    .collect();
+                if segments.is_empty() {
+                    break;
+                }
+                for segment in segments {
+                    debug!("回放摄像头{camera_id}的录像分段: {:?}", segment.path);
+                    let seek_ms = (position - segment.start).num_milliseconds().max(0) as u32;
+                    let mut segment_reader = Self::open_segment(&segment, seek_ms).await?;
+                    let end_ms = end.map(|end| {
+                        ((end - segment.start).num_milliseconds().max(0) as u32)
+                            .saturating_sub(segment_reader.offset_ms)
+                    });
+                    let base_timestamp = last_timestamp
+                        .map(|last_timestamp| last_timestamp + frame_interval_ms)
+                        .unwrap_or_default();
+
+                    let mut demuxer = FlvDemuxer::new();
+                    let mut segment_headers = FlvSequenceHeaders::default();
+                    // 跳过阶段缓存的从最近一个关键帧开始的标签
+                    let mut pending: Vec<FlvTag> = Vec::new();
+                    // 分段中第一个输出标签的原始时间戳，跳过阶段为None
+                    let mut first_timestamp: Option<u32> = None;
+                    let mut last_video_timestamp: Option<u32> = None;
+                    let mut buf = vec![0; READ_BUFFER_SIZE];
+                    'segment: loop {
+                        let n = segment_reader.reader.read(&mut buf).await?;
+                        if n == 0 {
+                            break;
+                        }
+                        let mut out = BytesMut::new();
+                        for tag in demuxer.push(&buf[..n]) {
+                            if segment_headers.update(&tag) {
+                                continue;
+                            }
+                            if end_ms.is_some_and(|end_ms| tag.timestamp > end_ms) {
+                                break 'segment;
+                            }
+                            if first_timestamp.is_none() {
+                                // 保留请求时间处或之前最近的关键帧开始的标签
+                                let reached = tag.timestamp >= segment_reader.seek_ms;
+                                if tag.is_keyframe()
+                                    && (!reached || tag.timestamp == segment_reader.seek_ms)
+                                {
+                                    pending.clear();
+                                }
+                                if pending.is_empty() && !tag.is_keyframe() {
+                                    continue;
+                                }
+                                pending.push(tag);
+                                if !reached {
+                                    continue;
+                                }
+
+                                first_timestamp = Some(pending[0].timestamp);
+                                if !header_sent {
+                                    let flv_header = demuxer
+                                        .flv_header()
+                                        .cloned()
+                                        .unwrap_or_else(|| encode_flv_header(true, true));
+                                    out.extend_from_slice(&flv_header);
+                                    header_sent = true;
+                                }
+                                let changed_headers =
+                                    Self::changed_headers(&mut sent_headers, &segment_headers);
+                                for header_tag in changed_headers {
+                                    header_tag.with_timestamp(base_timestamp).encode(&mut out);
+                                }
+                                for tag in std::mem::take(&mut pending) {
+                                    Self::encode_tag(
+                                        &tag,
+                                        base_timestamp,
+                                        first_timestamp.unwrap_or_default(),
+                                        &mut last_timestamp,
+                                        &mut out,
+                                    );
+                                }
+                                continue;
+                            }
+
+                            if tag.is_video() {
+                                if let Some(last_video_timestamp) = last_video_timestamp
+                                    && tag.timestamp > last_video_timestamp
+                                {
+                                    frame_interval_ms = tag.timestamp - last_video_timestamp;
+                                }
+                                last_video_timestamp = Some(tag.timestamp);
+                            }
+                            Self::encode_tag(
+                                &tag,
+                                base_timestamp,
+                                first_timestamp.unwrap_or_default(),
+                                &mut last_timestamp,
+                                &mut out,
+                            );
+                        }
+                        if !out.is_empty() {
+                            yield out.freeze();
+                        }
+                    }
+                    position = segment.end();
+                    if end.is_some_and(|end| position >= end) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 生成HLS VOD播放列表
+    ///
+    /// ## 参数
+    /// * `segment_uri` - 根据分段及其在回放中的时间偏移(毫秒)生成分段的地址
+    pub async fn hls_playlist(
+        camera_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        segment_uri: impl Fn(&RecordSegment, u64) -> String,
+    ) -> Result<String, std::io::Error> {
+        let segments = RecordStore::list(camera_id, start, end).await?;
+        let target_duration = segments
+            .iter()
+            .map(|segment| segment.duration_ms.div_ceil(1000))
+            .max()
+            .unwrap_or(1);
+
+        let mut playlist = String::new();
+        let _ = writeln!(playlist, "#EXTM3U");
+        let _ = writeln!(playlist, "#EXT-X-VERSION:3");
+        let _ = writeln!(playlist, "#EXT-X-PLAYLIST-TYPE:VOD");
+        let _ = writeln!(playlist, "#EXT-X-TARGETDURATION:{target_duration}");
+        let _ = writeln!(playlist, "#EXT-X-MEDIA-SEQUENCE:0");
+        if let Some(first) = segments.first()
+            && first.start < start
+        {
+            // 从第一个分段内请求的时间开始播放
+            let time_offset = (start - first.start).num_milliseconds() as f64 / 1000.0;
+            let _ = writeln!(
+                playlist,
+                "#EXT-X-START:TIME-OFFSET={time_offset:.3},PRECISE=YES"
+            );
+        }
+        let mut offset_ms = 0;
+        for segment in &segments {
+            let _ = writeln!(
+                playlist,
+                "#EXTINF:{:.3},",
+                segment.duration_ms as f64 / 1000.0
+            );
+            let _ = writeln!(playlist, "{}", segment_uri(segment, offset_ms));
+            offset_ms += segment.duration_ms as u64;
+        }
+        let _ = writeln!(playlist, "#EXT-X-ENDLIST");
+        Ok(playlist)
+    }
+
+    /// 将开始时间为`segment_start`的分段转封装为TS输出
+    ///
+    /// ## 参数
+    /// * `offset_ms` - 分段在回放中的时间偏移(毫秒)，使相邻分段的时间戳连续
+    pub async fn hls_segment(
+        camera_id: &str,
+        segment_start: DateTime<Utc>,
+        offset_ms: u64,
+    ) -> Result<impl Stream<Item = Result<Bytes, std::io::Error>>, std::io::Error> {
+        let segment = RecordStore::list(camera_id, segment_start, segment_start)
+            .await?
+            .into_iter()
+            .find(|segment| segment.start == segment_start)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "录像分段不存在"))?;
+        let mut pipe =
+            FfmpegCmd::remux_to_pipe(&segment.path.to_string_lossy(), 0, offset_ms, "mpegts")
+                .await
+                .map_err(std::io::Error::other)?;
+        Ok(try_stream! {
+            // 流被丢弃时管道随之丢弃，终止子进程
+            let mut buf = vec![0; READ_BUFFER_SIZE];
+            loop {
+                let n = pipe.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                yield Bytes::copy_from_slice(&buf[..n]);
+            }
+        })
+    }
+
+    /// 打开分段，MP4分段由ffmpeg从请求时间之前最近的关键帧开始转封装为FLV
+    async fn open_segment(
+        segment: &RecordSegment,
+        seek_ms: u32,
+    ) -> Result<SegmentReader, std::io::Error> {
+        match segment.format {
+            RecordFormat::Flv => Ok(SegmentReader {
+                reader: Box::pin(File::open(&segment.path).await?),
+                offset_ms: 0,
+                seek_ms,
+            }),
+            RecordFormat::Mp4 => {
+                let pipe =
+                    FfmpegCmd::remux_to_pipe(&segment.path.to_string_lossy(), seek_ms, 0, "flv")
+                        .await
+                        .map_err(std::io::Error::other)?;
+                Ok(SegmentReader {
+                    reader: Box::pin(pipe),
+                    offset_ms: seek_ms,
+                    seek_ms: 0,
+                })
+            }
+        }
+    }
+
+    /// 比较分段的序列头与已输出的序列头，返回需要输出的序列头并更新已输出的序列头
+    ///
+    /// 脚本数据只在回放开始时输出一次
+    fn changed_headers(
+        sent_headers: &mut FlvSequenceHeaders,
+        segment_headers: &FlvSequenceHeaders,
+    ) -> Vec<FlvTag> {
+        let mut changed = Vec::new();
+        if sent_headers.script.is_none()
+            && let Some(script) = &segment_headers.script
+        {
+            changed.push(script.clone());
+        }
+        for (sent, segment) in [
+            (&sent_headers.video, &segment_headers.video),
+            (&sent_headers.audio, &segment_headers.audio),
+        ] {
+            if let Some(segment) = segment
+                && sent.as_ref().is_none_or(|sent| sent.data != segment.data)
+            {
+                changed.push(segment.clone());
+            }
+        }
+        for tag in &changed {
+            sent_headers.update(tag);
+        }
+        changed
+    }
+
+    /// 按回放的时间轴改写标签的时间戳并写入输出
+    fn encode_tag(
+        tag: &FlvTag,
+        base_timestamp: u32,
+        first_timestamp: u32,
+        last_timestamp: &mut Option<u32>,
+        out: &mut BytesMut,
+    ) {
+        let timestamp = base_timestamp + tag.timestamp.saturating_sub(first_timestamp);
+        tag.with_timestamp(timestamp).encode(out);
+        *last_timestamp = Some(last_timestamp.map_or(timestamp, |last| last.max(timestamp)));
+    }
+}
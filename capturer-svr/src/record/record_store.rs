@@ -0,0 +1,264 @@
+use crate::config::capturer_config::{get_capturer_config, RecordFormat};
+use crate::ffmpeg::ffmpeg_budget::{get_ffmpeg_budget, BudgetPool};
+use crate::ffmpeg::ffmpeg_cmd::FfmpegCmd;
+use crate::record::record_eo::RecordSegment;
+use crate::utils::path_utils::to_safe_dir_name;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+/// 日期目录名的格式
+const DATE_DIR_FORMAT: &str = "%Y%m%d";
+/// 分段文件名中开始时间的格式
+const START_TIME_FORMAT: &str = "%H%M%S%3f";
+/// 正在写入的分段文件的扩展名
+const PART_EXTENSION: &str = "part";
+
+/// 录像存储
+///
+/// 分段按`<录像目录>/<摄像头ID>/<yyyyMMdd>/<HHmmssSSS>_<时长毫秒>.<flv|mp4>`保存，
+/// 正在写入的分段为`<HHmmssSSS>.flv.part`(进程异常退出遗留的也保持此名称，只参与过期清理)
+pub struct RecordStore;
+
+impl RecordStore {
+    /// 创建新分段的文件路径(同时创建日期目录)
+    pub async fn create_part_path(
+        camera_id: &str,
+        started_at: DateTime<Utc>,
+    ) -> Result<PathBuf, std::io::Error> {
+        let date_dir =
+            Self::camera_dir(camera_id)?.join(started_at.format(DATE_DIR_FORMAT).to_string());
+        fs::create_dir_all(&date_dir).await?;
+        Ok(date_dir.join(format!(
+            "{}.flv.{PART_EXTENSION}",
+            started_at.format(START_TIME_FORMAT)
+        )))
+    }
+
+    /// 完成分段: 按时长重命名，MP4格式再转封装并删除FLV文件
+    ///
+    /// 转封装的ffmpeg进程计入ffmpeg进程预算的一次性抓拍池，预算用尽时排队等待
+    pub async fn complete_segment(
+        part_path: &Path,
+        duration_ms: u32,
+        format: RecordFormat,
+    ) -> Result<PathBuf, std::io::Error> {
+        let file_name = part_path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or_default();
+        let start_time = file_name.split('.').next().unwrap_or_default();
+        let flv_path = part_path.with_file_name(format!("{start_time}_{duration_ms}.flv"));
+        fs::rename(part_path, &flv_path).await?;
+        if format == RecordFormat::Flv {
+            return Ok(flv_path);
+        }
+
+        let mp4_path = flv_path.with_extension(format.extension());
+        let _budget_permit = get_ffmpeg_budget()
+            .map_err(std::io::Error::other)?
+            .acquire(BudgetPool::Capture)
+            .await
+            .map_err(std::io::Error::other)?;
+        FfmpegCmd::remux_flv_to_mp4(&flv_path.to_string_lossy(), &mp4_path.to_string_lossy())
+            .await
+            .map_err(std::io::Error::other)?;
+        // MP4已经生成，删除FLV失败只留下多余的文件，不影响分段完成
+        if let Err(e) = fs::remove_file(&flv_path).await {
+            warn!("删除已转封装的录像{flv_path:?}失败: {e}");
+        }
+        Ok(mp4_path)
+    }
+
+    /// 列出摄像头与时间范围有重叠的已完成分段(按开始时间排序)
+    pub async fn list(
+        camera_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<RecordSegment>, std::io::Error> {
+        let camera_dir = Self::camera_dir(camera_id)?;
+        let mut segments = Vec::new();
+        for (date, date_dir) in Self::list_date_dirs(&camera_dir).await? {
+            // 分段可能跨越零点，所以多查前一天的目录
+            if date < start.date_naive().pred_opt().unwrap_or(NaiveDate::MIN)
+                || date > end.date_naive()
+            {
+                continue;
+            }
+            for (segment, is_part) in Self::list_segments(date, &date_dir).await? {
+                if !is_part && segment.start <= end && segment.end() >= start {
+                    segments.push(segment);
+                }
+            }
+        }
+        segments.sort_by_key(|segment| segment.start);
+        Ok(segments)
+    }
+
+    /// 按保留时长和总大小上限清理录像
+    ///
+    /// 先删除超过保留时长的分段；总大小仍超出上限时，从最早的已完成分段开始删除
+    pub async fn cleanup() -> Result<(), std::io::Error> {
+        let record_config = get_capturer_config()
+            .map_err(std::io::Error::other)?
+            .record
+            .clone();
+        let cut_off = record_config.retention.map(|retention| {
+            Utc::now() - chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::MAX)
+        });
+
+        let record_dir = PathBuf::from(&record_config.dir);
+        let mut read_dir = match fs::read_dir(&record_dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let mut remaining = Vec::new();
+        // 单个目录或文件处理失败时记录日志并继续，不影响其它录像的清理
+        while let Some(camera_entry) = read_dir.next_entry().await? {
+            let camera_dir = camera_entry.path();
+            let date_dirs = match Self::list_date_dirs(&camera_dir).await {
+                Ok(date_dirs) => date_dirs,
+                Err(e) => {
+                    warn!("列出录像目录{camera_dir:?}失败: {e}");
+                    continue;
+                }
+            };
+            for (date, date_dir) in date_dirs {
+                let segments = match Self::list_segments(date, &date_dir).await {
+                    Ok(segments) => segments,
+                    Err(e) => {
+                        warn!("列出录像目录{date_dir:?}失败: {e}");
+                        continue;
+                    }
+                };
+                for (segment, is_part) in segments {
+                    if cut_off.is_some_and(|cut_off| segment.end() < cut_off) {
+                        info!("删除过期录像: {:?}", segment.path);
+                        if let Err(e) = fs::remove_file(&segment.path).await {
+                            warn!("删除过期录像{:?}失败: {e}", segment.path);
+                        }
+                    } else if !is_part {
+                        remaining.push(segment);
+                    }
+                }
+                // 删除空的日期目录(目录非空时删除会失败，忽略即可)
+                let _ = fs::remove_dir(&date_dir).await;
+            }
+        }
+
+        if let Some(max_total_size_mb) = record_config.max_total_size_mb {
+            let max_total_size = max_total_size_mb.saturating_mul(1024 * 1024);
+            let mut total_size: u64 = remaining.iter().map(|segment| segment.size).sum();
+            debug!("录像总大小: {total_size}字节, 上限: {max_total_size}字节");
+            remaining.sort_by_key(|segment| segment.start);
+            for segment in remaining {
+                if total_size <= max_total_size {
+                    break;
+                }
+                warn!("录像总大小超出上限，删除最早的录像: {:?}", segment.path);
+                match fs::remove_file(&segment.path).await {
+                    Ok(()) => total_size = total_size.saturating_sub(segment.size),
+                    Err(e) => warn!("删除录像{:?}失败: {e}", segment.path),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 列出日期目录下的分段，返回(分段, 是否为正在写入的分段)
+    ///
+    /// 正在写入的分段时长按文件的修改时间估算
+    async fn list_segments(
+        date: NaiveDate,
+        date_dir: &Path,
+    ) -> Result<Vec<(RecordSegment, bool)>, std::io::Error> {
+        let mut segments = Vec::new();
+        let mut read_dir = fs::read_dir(date_dir).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
+            let Some(file_name) = path.file_name().and_then(|file_name| file_name.to_str()) else {
+                continue;
+            };
+            let mut parts = file_name.split('.');
+            let (Some(stem), Some(extension)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            let is_part = parts.next() == Some(PART_EXTENSION);
+            let format = match extension {
+                "flv" => RecordFormat::Flv,
+                "mp4" => RecordFormat::Mp4,
+                _ => continue,
+            };
+            let (start_time, duration_ms) = match stem.split_once('_') {
+                Some((start_time, duration_ms)) => (start_time, duration_ms.parse().ok()),
+                None => (stem, None),
+            };
+            let Some(start) = NaiveTime::parse_from_str(start_time, START_TIME_FORMAT)
+                .ok()
+                .map(|time| Utc.from_utc_datetime(&NaiveDateTime::new(date, time)))
+            else {
+                continue;
+            };
+            // 列出后文件可能已被重命名或删除(如分段刚完成)
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            let duration_ms = match duration_ms {
+                Some(duration_ms) => duration_ms,
+                None if is_part => metadata
+                    .modified()
+                    .ok()
+                    .map(DateTime::<Utc>::from)
+                    .map(|modified| (modified - start).num_milliseconds().max(0) as u32)
+                    .unwrap_or_default(),
+                None => continue,
+            };
+            segments.push((
+                RecordSegment {
+                    start,
+                    duration_ms,
+                    format,
+                    path,
+                    size: metadata.len(),
+                },
+                is_part,
+            ));
+        }
+        Ok(segments)
+    }
+
+    /// 列出摄像头录像目录下的日期目录
+    async fn list_date_dirs(
+        camera_dir: &Path,
+    ) -> Result<Vec<(NaiveDate, PathBuf)>, std::io::Error> {
+        let mut date_dirs = Vec::new();
+        let mut read_dir = match fs::read_dir(camera_dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(date_dirs),
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
+            if let Some(date) = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| NaiveDate::parse_from_str(file_name, DATE_DIR_FORMAT).ok())
+            {
+                date_dirs.push((date, path));
+            }
+        }
+        Ok(date_dirs)
+    }
+
+    /// 摄像头的录像目录
+    fn camera_dir(camera_id: &str) -> Result<PathBuf, std::io::Error> {
+        let record_dir = get_capturer_config()
+            .map_err(std::io::Error::other)?
+            .record
+            .dir
+            .clone();
+        Ok(PathBuf::from(record_dir).join(to_safe_dir_name(camera_id)))
+    }
+}
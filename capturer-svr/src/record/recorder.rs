@@ -0,0 +1,323 @@
+use crate::camera::camera_error::CameraError;
+use crate::camera::camera_registry::CameraRegistry;
+use crate::config::capturer_config::{get_capturer_config, RecordCameraConfig, RecordConfig};
+use crate::record::record_store::RecordStore;
+use crate::record::segment_writer::FlvSegmentWriter;
+use crate::stream::flv_tag::{FlvSequenceHeaders, FlvTag};
+use crate::stream::stream_eo::PinnedSessionEvent;
+use crate::stream::stream_manager::get_stream_manager;
+use crate::utils::task_utils::get_background_tasks;
+use chrono::Utc;
+use robotech::cfg::CfgError;
+use rustc_hash::FxHashMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::{debug, error, info, warn};
+
+/// 全局静态的录像器实例
+///
+/// 录像器在热加载配置时不会被替换，而是同步配置中的摄像头，配置未变化的摄像头不会中断录像
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+
+/// 获取流管理器失败后重试的间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+pub async fn init_recorder(record_config: RecordConfig) -> Result<(), CfgError> {
+    info!("初始化录像器");
+    RECORDER
+        .set(Recorder::new())
+        .map_err(|_| CfgError::Init("Recorder init failed".to_string()))?;
+    get_recorder()?.sync_cameras(record_config.cameras).await;
+
+    debug!("<清理过期录像>任务正在创建....");
+    let background_tasks = get_background_tasks();
+    let cancel_token = background_tasks.child_token();
+    background_tasks.spawn(async move {
+        info!("<清理过期录像>任务创建完成.");
+        loop {
+            let cleanup_interval = get_capturer_config()
+                .ok()
+                .and_then(|capturer_config| capturer_config.record.cleanup_interval)
+                .unwrap_or(Duration::from_secs(60));
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = sleep(cleanup_interval) => {}
+            }
+            if let Err(e) = RecordStore::cleanup().await {
+                error!("清理过期录像失败: {e}");
+            }
+        }
+        info!("程序正在退出，<清理过期录像>任务退出");
+    });
+    Ok(())
+}
+
+pub fn get_recorder() -> Result<&'static Recorder, CfgError> {
+    RECORDER
+        .get()
+        .ok_or(CfgError::NotInit("Recorder not initialized".to_string()))
+}
+
+pub async fn update_recorder(record_config: RecordConfig) -> Result<(), CfgError> {
+    get_recorder()?.sync_cameras(record_config.cameras).await;
+    Ok(())
+}
+
+/// 正在录像的摄像头
+struct RecordingCamera {
+    /// 摄像头配置
+    camera_config: RecordCameraConfig,
+    /// 从登记的摄像头解析出的流地址
+    stream_url: String,
+    /// 摄像头被移除时，取消录像的后台运行(结束正在写入的分段后退出)
+    _cancel_guard: DropGuard,
+}
+
+impl Drop for RecordingCamera {
+    fn drop(&mut self) {
+        debug!("摄像头{}停止录像", self.camera_config.id);
+    }
+}
+
+/// 录像器
+///
+/// 每个摄像头在独立的后台任务中保持常驻会话(有观看者时复用正在进行的会话)，
+/// 将会话的标签连续写入按关键帧对齐的分段文件；
+/// 后台任务由全局的后台任务跟踪，摄像头被移除或程序退出时结束正在写入的分段后退出
+pub struct Recorder {
+    /// 正在录像的摄像头映射表，使用摄像头ID作为键
+    cameras: RwLock<FxHashMap<String, RecordingCamera>>,
+}
+
+impl Recorder {
+    fn new() -> Self {
+        Self {
+            cameras: RwLock::new(FxHashMap::default()),
+        }
+    }
+
+    /// 同步配置文件中的摄像头
+    ///
+    /// - 配置及流地址未变化的摄像头继续录像
+    /// - 配置或流地址变化的摄像头重新开始录像
+    /// - 配置中已删除或没有登记的摄像头停止录像
+    ///
+    /// 热加载配置及登记的摄像头变化后调用；查询摄像头失败时，已在录像的摄像头继续录像
+    pub async fn sync_cameras(&self, camera_configs: Vec<RecordCameraConfig>) {
+        info!("同步配置中的{}个录像摄像头", camera_configs.len());
+        // 先查询摄像头的流地址，不能持有锁等待
+        let mut resolved = Vec::with_capacity(camera_configs.len());
+        for camera_config in camera_configs {
+            let stream_url = Self::resolve_stream_url(&camera_config).await;
+            resolved.push((camera_config, stream_url));
+        }
+
+        let Ok(mut cameras_write_lock) = self.cameras.write() else {
+            error!("无法获取录像摄像头写锁");
+            return;
+        };
+        cameras_write_lock.retain(|id, camera| {
+            resolved.iter().any(|(config, stream_url)| {
+                &config.id == id
+                    && config == &camera.camera_config
+                    && match stream_url {
+                        Ok(stream_url) => stream_url.as_ref() == Some(&camera.stream_url),
+                        Err(_) => true,
+                    }
+            })
+        });
+        for (camera_config, stream_url) in resolved {
+            let Ok(Some(stream_url)) = stream_url else {
+                continue;
+            };
+            if cameras_write_lock.contains_key(&camera_config.id) {
+                continue;
+            }
+            info!("<摄像头{}录像>任务正在创建....", camera_config.id);
+            let background_tasks = get_background_tasks();
+            let cancel_token = background_tasks.child_token();
+            background_tasks.spawn(Self::run_loop(
+                camera_config.clone(),
+                stream_url.clone(),
+                cancel_token.clone(),
+            ));
+            cameras_write_lock.insert(
+                camera_config.id.clone(),
+                RecordingCamera {
+                    camera_config,
+                    stream_url,
+                    _cancel_guard: cancel_token.drop_guard(),
+                },
+            );
+        }
+    }
+
+    /// 查询录像的摄像头的流地址
+    ///
+    /// ## 返回值
+    /// 摄像头没有登记时返回None
+    async fn resolve_stream_url(
+        camera_config: &RecordCameraConfig,
+    ) -> Result<Option<String>, CameraError> {
+        let result = CameraRegistry::get(&camera_config.id)
+            .await
+            .map(|registered| {
+                registered.map(|registered| {
+                    registered
+                        .camera
+                        .stream_url(camera_config.sub_stream)
+                        .to_string()
+                })
+            });
+        match &result {
+            Ok(Some(_)) => {}
+            Ok(None) => warn!("录像的摄像头{}没有登记，不录像", camera_config.id),
+            Err(e) => error!("查询录像的摄像头{}失败: {e}", camera_config.id),
+        }
+        result
+    }
+
+    /// 摄像头录像的后台运行
+    ///
+    /// 流管理器被替换(如热加载配置)后，从新的流管理器重新保持常驻会话；
+    /// 摄像头被移除或程序退出时退出
+    async fn run_loop(
+        camera_config: RecordCameraConfig,
+        stream_url: String,
+        cancel_token: CancellationToken,
+    ) {
+        info!("<摄像头{}录像>任务创建完成.", camera_config.id);
+        while !cancel_token.is_cancelled() {
+            let events = match get_stream_manager() {
+                Ok(stream_manager) => stream_manager.pin_session(&stream_url),
+                Err(e) => {
+                    error!("摄像头{}获取流管理器失败: {e}", camera_config.id);
+                    tokio::select! {
+                        _ = cancel_token.cancelled() => break,
+                        _ = sleep(RETRY_INTERVAL) => {}
+                    }
+                    continue;
+                }
+            };
+            Self::record(&camera_config, events, &cancel_token).await;
+        }
+        info!("<摄像头{}录像>任务退出", camera_config.id);
+    }
+
+    /// 将常驻会话的标签写入分段文件，直到会话事件结束或录像被取消
+    ///
+    /// 分段在达到配置的时长或序列头变化后，于下一个关键帧处切分
+    async fn record(
+        camera_config: &RecordCameraConfig,
+        mut events: mpsc::Receiver<PinnedSessionEvent>,
+        cancel_token: &CancellationToken,
+    ) {
+        let camera_id = camera_config.id.as_str();
+        let mut sequence_headers = FlvSequenceHeaders::default();
+        let mut sequence_headers_changed = false;
+        let mut segment: Option<FlvSegmentWriter> = None;
+        loop {
+            let event = tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                event = events.recv() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+            };
+            let tag = match event {
+                PinnedSessionEvent::Attached(session_sequence_headers) => {
+                    Self::finish_segment(camera_config, segment.take()).await;
+                    sequence_headers = session_sequence_headers;
+                    continue;
+                }
+                PinnedSessionEvent::Detached => {
+                    Self::finish_segment(camera_config, segment.take()).await;
+                    continue;
+                }
+                PinnedSessionEvent::Tag(tag) => tag,
+            };
+            if sequence_headers.update(&tag) {
+                sequence_headers_changed |= tag.is_sequence_header();
+                continue;
+            }
+
+            if tag.is_keyframe() {
+                let segment_duration_ms = get_capturer_config()
+                    .ok()
+                    .and_then(|capturer_config| capturer_config.record.segment_duration)
+                    .unwrap_or(Duration::from_secs(5 * 60))
+                    .as_millis() as u32;
+                let rotate = segment.as_ref().is_none_or(|segment| {
+                    sequence_headers_changed || segment.duration_ms() >= segment_duration_ms
+                });
+                if rotate {
+                    Self::finish_segment(camera_config, segment.take()).await;
+                    sequence_headers_changed = false;
+                    segment = Self::create_segment(camera_id, &sequence_headers, &tag).await;
+                    continue;
+                }
+            }
+
+            if let Some(writer) = segment.as_mut()
+                && let Err(e) = writer.write(&tag).await
+            {
+                error!("摄像头{camera_id}写入录像分段失败: {e}");
+                Self::finish_segment(camera_config, segment.take()).await;
+            }
+        }
+        Self::finish_segment(camera_config, segment).await;
+    }
+
+    /// 从关键帧开始创建新分段
+    async fn create_segment(
+        camera_id: &str,
+        sequence_headers: &FlvSequenceHeaders,
+        keyframe: &FlvTag,
+    ) -> Option<FlvSegmentWriter> {
+        let result = async {
+            let part_path = RecordStore::create_part_path(camera_id, Utc::now()).await?;
+            debug!("摄像头{camera_id}开始录制分段: {part_path:?}");
+            FlvSegmentWriter::create(&part_path, sequence_headers, keyframe).await
+        }
+        .await;
+        result
+            .inspect_err(|e| error!("摄像头{camera_id}创建录像分段失败: {e}"))
+            .ok()
+    }
+
+    /// 结束分段的写入，并在后台完成分段(重命名及按需转封装)
+    async fn finish_segment(camera_config: &RecordCameraConfig, segment: Option<FlvSegmentWriter>) {
+        let Some(segment) = segment else {
+            return;
+        };
+        let camera_id = camera_config.id.clone();
+        let (part_path, duration_ms) = match segment.finish().await {
+            Ok(finished) => finished,
+            Err(e) => {
+                error!("摄像头{camera_id}结束录像分段失败: {e}");
+                return;
+            }
+        };
+        let format = match camera_config.format {
+            Some(format) => format,
+            None => match get_capturer_config() {
+                Ok(capturer_config) => capturer_config.record.format,
+                Err(e) => {
+                    warn!("读取录像格式失败，使用默认格式: {e}");
+                    Default::default()
+                }
+            },
+        };
+        // 转封装MP4耗时较长，放到后台执行，避免阻塞录像；
+        // 录像被取消(程序退出)时同样需要完成分段，程序退出时在宽限时间内等待其完成
+        get_background_tasks().spawn(async move {
+            match RecordStore::complete_segment(&part_path, duration_ms, format).await {
+                Ok(path) => info!("摄像头{camera_id}完成录像分段: {path:?}"),
+                Err(e) => error!("摄像头{camera_id}完成录像分段{part_path:?}失败: {e}"),
+            }
+        });
+    }
+}
@@ -0,0 +1,68 @@
+use crate::stream::flv_tag::{FlvSequenceHeaders, FlvTag};
+use bytes::BytesMut;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// FLV分段写入器
+///
+/// 分段从视频关键帧开始，先写入FLV文件头、脚本数据和序列头，
+/// 标签的时间戳改写为从0开始，使每个分段都可以独立播放
+pub struct FlvSegmentWriter {
+    /// 正在写入的文件路径
+    path: PathBuf,
+    /// 文件写入器
+    file: BufWriter<File>,
+    /// 第一个关键帧的原始时间戳
+    base_timestamp: u32,
+    /// 最后一个标签改写后的时间戳
+    last_timestamp: u32,
+    /// 编码标签的缓冲区
+    buf: BytesMut,
+}
+
+impl FlvSegmentWriter {
+    /// 创建分段文件，并从关键帧开始写入
+    pub async fn create(
+        path: &Path,
+        sequence_headers: &FlvSequenceHeaders,
+        keyframe: &FlvTag,
+    ) -> Result<Self, std::io::Error> {
+        let mut file = BufWriter::new(File::create(path).await?);
+        file.write_all(&sequence_headers.encode()).await?;
+        let mut writer = Self {
+            path: path.to_path_buf(),
+            file,
+            base_timestamp: keyframe.timestamp,
+            last_timestamp: 0,
+            buf: BytesMut::new(),
+        };
+        writer.write(keyframe).await?;
+        Ok(writer)
+    }
+
+    /// 已写入的时长(毫秒)
+    pub fn duration_ms(&self) -> u32 {
+        self.last_timestamp
+    }
+
+    /// 写入一个标签(脚本数据会被丢弃)
+    pub async fn write(&mut self, tag: &FlvTag) -> Result<(), std::io::Error> {
+        if tag.is_script() {
+            return Ok(());
+        }
+        let timestamp = tag.timestamp.saturating_sub(self.base_timestamp);
+        self.buf.clear();
+        tag.with_timestamp(timestamp).encode(&mut self.buf);
+        self.file.write_all(&self.buf).await?;
+        self.last_timestamp = self.last_timestamp.max(timestamp);
+        Ok(())
+    }
+
+    /// 结束写入，返回(文件路径, 时长毫秒)
+    pub async fn finish(mut self) -> Result<(PathBuf, u32), std::io::Error> {
+        self.file.flush().await?;
+        self.file.into_inner().sync_all().await?;
+        Ok((self.path, self.last_timestamp))
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+
+/// 任务的来源
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobSource {
+    /// 来自配置文件(热加载配置时会同步)
+    Config,
+    /// 通过接口创建(热加载配置时保留)
+    Api,
+}
+
+impl JobSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobSource::Config => "config",
+            JobSource::Api => "api",
+        }
+    }
+}
+
+/// 任务的调度方式
+#[derive(Debug, Clone)]
+pub enum JobSchedule {
+    /// 按cron表达式调度
+    Cron(Box<Schedule>),
+    /// 按固定间隔调度
+    Interval(std::time::Duration),
+}
+
+impl JobSchedule {
+    /// 计算下次运行的时间
+    ///
+    /// ## 参数
+    /// * `last_run_at` - 上次运行的时间(按固定间隔调度时以此为基准，从未运行过则以当前时间为基准)
+    pub fn next_run_at(&self, last_run_at: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        match self {
+            JobSchedule::Cron(schedule) => schedule.upcoming(Utc).next(),
+            JobSchedule::Interval(interval) => {
+                let interval = chrono::Duration::from_std(*interval).ok()?;
+                let now = Utc::now();
+                let next_run_at = last_run_at.unwrap_or(now) + interval;
+                Some(next_run_at.max(now))
+            }
+        }
+    }
+}
+
+/// 任务的运行状态
+#[derive(Debug, Clone, Default)]
+pub struct JobStatus {
+    /// 上次运行的时间
+    pub last_run_at: Option<DateTime<Utc>>,
+    /// 上次运行是否成功
+    pub last_success: Option<bool>,
+    /// 上次运行失败的原因
+    pub last_error: Option<String>,
+    /// 上次运行上传到OSS的对象引用
+    pub last_oss_obj_ref: Option<serde_json::Value>,
+    /// 下次运行的时间
+    pub next_run_at: Option<DateTime<Utc>>,
+    /// 累计运行次数
+    pub run_count: u64,
+    /// 累计失败次数
+    pub fail_count: u64,
+}
@@ -0,0 +1,375 @@
+use crate::config::capturer_config::{AclPermission, JobConfig};
+use crate::scheduler::job_eo::{JobSchedule, JobSource, JobStatus};
+use crate::scheduler::scheduler_error::SchedulerError;
+use crate::scheduler::snapshot_archive::SnapshotArchive;
+use crate::svc::capturer_svc::CapturerSvc;
+use crate::utils::task_utils::get_background_tasks;
+use crate::utils::url_utils::mask_stream_url;
+use crate::utils::webhook_utils::post_webhook;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use robotech::cfg::CfgError;
+use robotech::ro::{Ro, RoResult};
+use robotech::svc::SvcError;
+use rustc_hash::FxHashMap;
+use serde_json::json;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
+use tokio::time::sleep;
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::{debug, error, info, warn};
+
+/// 全局静态的任务调度器实例
+///
+/// 调度器在热加载配置时不会被替换，而是同步配置中的任务，以保留任务的运行状态和通过接口创建的任务
+static JOB_SCHEDULER: OnceLock<JobScheduler> = OnceLock::new();
+
+pub fn init_job_scheduler(jobs: Vec<JobConfig>) -> Result<(), CfgError> {
+    info!("初始化任务调度器");
+    JOB_SCHEDULER
+        .set(JobScheduler::new())
+        .map_err(|_| CfgError::Init("JobScheduler init failed".to_string()))?;
+    get_job_scheduler()?.sync_config_jobs(jobs);
+    Ok(())
+}
+
+pub fn get_job_scheduler() -> Result<&'static JobScheduler, CfgError> {
+    JOB_SCHEDULER.get().ok_or(CfgError::NotInit(
+        "JobScheduler not initialized".to_string(),
+    ))
+}
+
+pub fn update_job_scheduler(jobs: Vec<JobConfig>) -> Result<(), CfgError> {
+    get_job_scheduler()?.sync_config_jobs(jobs);
+    Ok(())
+}
+
+/// 已调度的任务
+pub struct ScheduledJob {
+    /// 任务配置
+    pub job_config: JobConfig,
+    /// 任务来源
+    pub source: JobSource,
+    /// 任务运行状态
+    pub status: Arc<RwLock<JobStatus>>,
+    /// 任务被移除时，取消任务的后台运行
+    _cancel_guard: DropGuard,
+}
+
+impl Drop for ScheduledJob {
+    fn drop(&mut self) {
+        debug!("任务{}停止调度", self.job_config.id);
+    }
+}
+
+/// 任务调度器
+///
+/// 每个任务在独立的后台任务中按cron表达式或固定间隔抓拍，上传到OSS，并可回调webhook；
+/// 后台任务由全局的后台任务跟踪，任务被移除或程序退出时退出
+pub struct JobScheduler {
+    /// 任务映射表，使用任务ID作为键
+    jobs: RwLock<FxHashMap<String, ScheduledJob>>,
+}
+
+impl JobScheduler {
+    fn new() -> Self {
+        Self {
+            jobs: RwLock::new(FxHashMap::default()),
+        }
+    }
+
+    /// 同步配置文件中的任务
+    ///
+    /// - 配置未变化的任务继续运行
+    /// - 配置变化的任务重新调度，保留运行状态
+    /// - 配置中已删除的任务停止调度
+    /// - 通过接口创建的任务不受影响(与配置中的任务ID相同时，以配置为准)
+    pub fn sync_config_jobs(&self, job_configs: Vec<JobConfig>) {
+        info!("同步配置中的{}个定时任务", job_configs.len());
+        let Ok(mut jobs_write_lock) = self.jobs.write() else {
+            error!("无法获取任务写锁");
+            return;
+        };
+
+        jobs_write_lock.retain(|id, job| {
+            job.source != JobSource::Config || job_configs.iter().any(|config| &config.id == id)
+        });
+
+        for job_config in job_configs {
+            let status = match jobs_write_lock.get(&job_config.id) {
+                Some(job) if job.source == JobSource::Config && job.job_config == job_config => {
+                    continue;
+                }
+                Some(job) => {
+                    if job.source == JobSource::Api {
+                        warn!("配置中的任务{}覆盖了通过接口创建的同名任务", job_config.id);
+                    }
+                    Arc::clone(&job.status)
+                }
+                None => Arc::new(RwLock::new(JobStatus::default())),
+            };
+
+            let id = job_config.id.clone();
+            match self.schedule(job_config, JobSource::Config, status) {
+                Ok(job) => {
+                    jobs_write_lock.insert(id, job);
+                }
+                Err(e) => {
+                    error!("调度任务失败: {e}");
+                    jobs_write_lock.remove(&id);
+                }
+            }
+        }
+    }
+
+    /// 添加通过接口创建的任务
+    pub fn add_api_job(&self, job_config: JobConfig) -> Result<(), SchedulerError> {
+        let mut jobs_write_lock = self
+            .jobs
+            .write()
+            .map_err(|e| SchedulerError::SchedulerLock(e.to_string()))?;
+        if jobs_write_lock.contains_key(&job_config.id) {
+            return Err(SchedulerError::JobExists(job_config.id));
+        }
+        let id = job_config.id.clone();
+        let job = self.schedule(
+            job_config,
+            JobSource::Api,
+            Arc::new(RwLock::new(JobStatus::default())),
+        )?;
+        jobs_write_lock.insert(id, job);
+        Ok(())
+    }
+
+    /// 删除通过接口创建的任务
+    pub fn remove_api_job(&self, id: &str) -> Result<(), SchedulerError> {
+        let mut jobs_write_lock = self
+            .jobs
+            .write()
+            .map_err(|e| SchedulerError::SchedulerLock(e.to_string()))?;
+        match jobs_write_lock.get(id) {
+            None => Err(SchedulerError::JobNotFound(id.to_string())),
+            Some(job) if job.source == JobSource::Config => {
+                Err(SchedulerError::JobNotRemovable(id.to_string()))
+            }
+            Some(_) => {
+                jobs_write_lock.remove(id);
+                Ok(())
+            }
+        }
+    }
+
+    /// 获取指定ID的任务的配置
+    pub fn get_job(&self, id: &str) -> Result<Option<JobConfig>, SchedulerError> {
+        let jobs_read_lock = self
+            .jobs
+            .read()
+            .map_err(|e| SchedulerError::SchedulerLock(e.to_string()))?;
+        Ok(jobs_read_lock.get(id).map(|job| job.job_config.clone()))
+    }
+
+    /// 获取所有任务的配置、来源及运行状态(按任务ID排序)
+    pub fn list_jobs(&self) -> Result<Vec<(JobConfig, JobSource, JobStatus)>, SchedulerError> {
+        let jobs_read_lock = self
+            .jobs
+            .read()
+            .map_err(|e| SchedulerError::SchedulerLock(e.to_string()))?;
+        let mut jobs: Vec<_> = jobs_read_lock
+            .values()
+            .map(|job| {
+                let status = job
+                    .status
+                    .read()
+                    .map(|status| status.clone())
+                    .unwrap_or_default();
+                (job.job_config.clone(), job.source, status)
+            })
+            .collect();
+        jobs.sort_by(|(a, ..), (b, ..)| a.id.cmp(&b.id));
+        Ok(jobs)
+    }
+
+    /// 启动任务的后台运行
+    fn schedule(
+        &self,
+        job_config: JobConfig,
+        source: JobSource,
+        status: Arc<RwLock<JobStatus>>,
+    ) -> Result<ScheduledJob, SchedulerError> {
+        let schedule = parse_schedule(&job_config)?;
+        check_target(&job_config)?;
+        info!("<定时任务{}>正在创建....", job_config.id);
+        let background_tasks = get_background_tasks();
+        let cancel_token = background_tasks.child_token();
+        background_tasks.spawn(Self::run_loop(
+            job_config.clone(),
+            schedule,
+            Arc::clone(&status),
+            cancel_token.clone(),
+        ));
+        Ok(ScheduledJob {
+            job_config,
+            source,
+            status,
+            _cancel_guard: cancel_token.drop_guard(),
+        })
+    }
+
+    /// 任务的运行循环，任务被移除或程序退出时退出(正在进行的抓拍随之取消)
+    async fn run_loop(
+        job_config: JobConfig,
+        schedule: JobSchedule,
+        status: Arc<RwLock<JobStatus>>,
+        cancel_token: CancellationToken,
+    ) {
+        let id = &job_config.id;
+        info!("<定时任务{id}>创建完成. 调度方式: {schedule:?}");
+        loop {
+            let last_run_at = status.read().ok().and_then(|status| status.last_run_at);
+            let Some(next_run_at) = schedule.next_run_at(last_run_at) else {
+                warn!("<定时任务{id}>没有下次运行的时间，停止调度");
+                break;
+            };
+            if let Ok(mut status_write_lock) = status.write() {
+                status_write_lock.next_run_at = Some(next_run_at);
+            }
+            debug!("<定时任务{id}>下次运行时间: {next_run_at}");
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = async {
+                    sleep((next_run_at - Utc::now()).to_std().unwrap_or_default()).await;
+                    Self::run_once(&job_config, &status).await;
+                } => {}
+            }
+        }
+        info!("<定时任务{id}>退出");
+    }
+
+    /// 抓拍并上传，按配置在本地归档抓拍的图片
+    async fn capture(
+        job_config: &JobConfig,
+        run_at: DateTime<Utc>,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        let stream_url = match (&job_config.camera_id, &job_config.stream_url) {
+            // 按摄像头抓拍时每次运行都按任务的用户检查权限，并使用摄像头当前登记的流地址
+            (Some(camera_id), _) => CapturerSvc::resolve_stream_url(
+                None,
+                Some(camera_id),
+                job_config.sub_stream,
+                job_config.user_id,
+                AclPermission::Snapshot,
+            )
+            .await
+            .map_err(|e| anyhow!("解析摄像头{camera_id}的流地址失败: {e}"))?,
+            (None, Some(stream_url)) => stream_url.clone(),
+            (None, None) => return Err(anyhow!("任务{}没有配置抓拍目标", job_config.id).into()),
+        };
+        let (jpeg_bytes, metadata) =
+            CapturerSvc::capture_jpeg(&stream_url, job_config.user_id).await?;
+        if job_config.archive
+            && let Err(e) = SnapshotArchive::save(&job_config.id, run_at, &jpeg_bytes).await
+        {
+            warn!("<定时任务{}>归档抓拍的图片失败: {e}", job_config.id);
+        }
+        CapturerSvc::upload_capture(
+            jpeg_bytes,
+            &metadata,
+            job_config.bucket.clone(),
+            Some(render_key_template(
+                &job_config.key_template,
+                &job_config.id,
+                run_at,
+            )),
+            None,
+            job_config.user_id,
+        )
+        .await
+    }
+
+    /// 运行一次任务: 抓拍、上传并回调webhook
+    async fn run_once(job_config: &JobConfig, status: &Arc<RwLock<JobStatus>>) {
+        let id = &job_config.id;
+        let run_at = Utc::now();
+        debug!("<定时任务{id}>开始运行");
+        let (success, msg, oss_obj_ref) = match Self::capture(job_config, run_at).await {
+            Ok(ro) => (matches!(ro.result, RoResult::Success), ro.msg, ro.extra),
+            Err(e) => (false, e.to_string(), None),
+        };
+        if success {
+            debug!("<定时任务{id}>运行成功");
+        } else {
+            warn!("<定时任务{id}>运行失败: {msg}");
+        }
+
+        if let Ok(mut status_write_lock) = status.write() {
+            status_write_lock.last_run_at = Some(run_at);
+            status_write_lock.last_success = Some(success);
+            status_write_lock.last_error = if success { None } else { Some(msg.clone()) };
+            status_write_lock.last_oss_obj_ref = oss_obj_ref.clone();
+            status_write_lock.run_count += 1;
+            if !success {
+                status_write_lock.fail_count += 1;
+            }
+        }
+
+        if let Some(webhook) = &job_config.webhook {
+            let payload = json!({
+                "jobId": id,
+                "streamUrl": job_config.stream_url.as_deref().map(mask_stream_url),
+                "cameraId": job_config.camera_id,
+                "runTs": run_at.timestamp_millis(),
+                "success": success,
+                "msg": msg,
+                "ossObjRef": oss_obj_ref,
+            });
+            match post_webhook(webhook, &payload).await {
+                Ok(()) => debug!("<定时任务{id}>回调webhook成功"),
+                Err(e) => warn!("<定时任务{id}>回调webhook失败: {e}"),
+            }
+        }
+    }
+}
+
+/// 解析任务的调度方式
+pub fn parse_schedule(job_config: &JobConfig) -> Result<JobSchedule, SchedulerError> {
+    let id = &job_config.id;
+    match (&job_config.cron, job_config.interval) {
+        (Some(cron), None) => Schedule::from_str(cron)
+            .map(|schedule| JobSchedule::Cron(Box::new(schedule)))
+            .map_err(|e| SchedulerError::InvalidSchedule(id.clone(), e.to_string())),
+        (None, Some(interval)) if !interval.is_zero() => Ok(JobSchedule::Interval(interval)),
+        (None, Some(_)) => Err(SchedulerError::InvalidSchedule(
+            id.clone(),
+            "间隔不能为0".to_string(),
+        )),
+        _ => Err(SchedulerError::InvalidSchedule(
+            id.clone(),
+            "cron和interval必须且只能配置一个".to_string(),
+        )),
+    }
+}
+
+/// 检查任务的抓拍目标
+pub fn check_target(job_config: &JobConfig) -> Result<(), SchedulerError> {
+    match (&job_config.stream_url, &job_config.camera_id) {
+        (Some(_), None) | (None, Some(_)) => Ok(()),
+        _ => Err(SchedulerError::InvalidTarget(
+            job_config.id.clone(),
+            "stream-url和camera-id必须且只能配置一个".to_string(),
+        )),
+    }
+}
+
+/// 渲染上传的文件名模板
+pub fn render_key_template(key_template: &str, job_id: &str, run_at: DateTime<Utc>) -> String {
+    key_template
+        .replace("{job_id}", job_id)
+        .replace("{yyyy}", &run_at.format("%Y").to_string())
+        .replace("{MM}", &run_at.format("%m").to_string())
+        .replace("{dd}", &run_at.format("%d").to_string())
+        .replace("{HH}", &run_at.format("%H").to_string())
+        .replace("{mm}", &run_at.format("%M").to_string())
+        .replace("{ss}", &run_at.format("%S").to_string())
+        .replace("{ts}", &run_at.timestamp_millis().to_string())
+}
@@ -0,0 +1,4 @@
+pub mod job_eo;
+pub mod job_scheduler;
+pub mod scheduler_error;
+pub mod snapshot_archive;
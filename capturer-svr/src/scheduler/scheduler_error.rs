@@ -0,0 +1,15 @@
+#[derive(Debug, thiserror::Error)]
+pub enum SchedulerError {
+    #[error("任务{0}的调度配置无效: {1}")]
+    InvalidSchedule(String, String),
+    #[error("任务{0}的抓拍目标无效: {1}")]
+    InvalidTarget(String, String),
+    #[error("任务{0}已存在")]
+    JobExists(String),
+    #[error("任务{0}不存在")]
+    JobNotFound(String),
+    #[error("任务{0}来自配置文件，不能通过接口删除")]
+    JobNotRemovable(String),
+    #[error("获取调度器失败: {0}")]
+    SchedulerLock(String),
+}
@@ -0,0 +1,167 @@
+use crate::config::capturer_config::get_capturer_config;
+use crate::utils::path_utils::to_safe_dir_name;
+use crate::utils::task_utils::get_background_tasks;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::fs;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+
+/// 清理任务是否已启动
+static CLEANUP_TASK_STARTED: OnceLock<()> = OnceLock::new();
+
+/// 日期目录名的格式
+const DATE_DIR_FORMAT: &str = "%Y%m%d";
+/// 图片文件名(不含扩展名)的格式
+const FILE_STEM_FORMAT: &str = "%H%M%S%3f";
+
+/// 初始化抓拍归档
+///
+/// 启动后台任务定期清理超过保留时长的归档图片。
+/// 后台任务每次清理时都会重新读取配置，所以热加载配置后无需重新初始化。
+pub fn init_snapshot_archive() {
+    info!("初始化抓拍归档");
+    if CLEANUP_TASK_STARTED.set(()).is_err() {
+        warn!("<清理过期归档>任务已经启动");
+        return;
+    }
+
+    debug!("<清理过期归档>任务正在创建....");
+    let background_tasks = get_background_tasks();
+    let cancel_token = background_tasks.child_token();
+    background_tasks.spawn(async move {
+        info!("<清理过期归档>任务创建完成.");
+        loop {
+            let cleanup_interval = get_capturer_config()
+                .ok()
+                .and_then(|capturer_config| capturer_config.snapshot_archive.cleanup_interval)
+                .unwrap_or(Duration::from_secs(60 * 60));
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = sleep(cleanup_interval) => {}
+            }
+            if let Err(e) = SnapshotArchive::cleanup_expired().await {
+                error!("清理过期归档失败: {e}");
+            }
+        }
+        info!("程序正在退出，<清理过期归档>任务退出");
+    });
+}
+
+/// 抓拍归档
+///
+/// 定时任务抓拍的图片按`<归档目录>/<任务ID>/<yyyyMMdd>/<HHmmssSSS>.jpg`保存在本地，
+/// 用于按时间范围生成延时视频
+pub struct SnapshotArchive;
+
+impl SnapshotArchive {
+    /// 保存抓拍的图片
+    pub async fn save(
+        job_id: &str,
+        captured_at: DateTime<Utc>,
+        jpeg_bytes: &[u8],
+    ) -> Result<PathBuf, std::io::Error> {
+        let date_dir = Self::job_dir(job_id)?.join(captured_at.format(DATE_DIR_FORMAT).to_string());
+        fs::create_dir_all(&date_dir).await?;
+        let path = date_dir.join(format!("{}.jpg", captured_at.format(FILE_STEM_FORMAT)));
+        fs::write(&path, jpeg_bytes).await?;
+        Ok(path)
+    }
+
+    /// 列出任务在时间范围内归档的图片(按抓拍时间排序)
+    pub async fn list(
+        job_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, PathBuf)>, std::io::Error> {
+        let job_dir = Self::job_dir(job_id)?;
+        let mut snapshots = Vec::new();
+        for (date, date_dir) in Self::list_date_dirs(&job_dir).await? {
+            if date < start.date_naive() || date > end.date_naive() {
+                continue;
+            }
+            let mut read_dir = fs::read_dir(&date_dir).await?;
+            while let Some(entry) = read_dir.next_entry().await? {
+                let path = entry.path();
+                let Some(captured_at) = path
+                    .file_stem()
+                    .and_then(|file_stem| file_stem.to_str())
+                    .and_then(|file_stem| {
+                        NaiveTime::parse_from_str(file_stem, FILE_STEM_FORMAT).ok()
+                    })
+                    .map(|time| Utc.from_utc_datetime(&NaiveDateTime::new(date, time)))
+                else {
+                    continue;
+                };
+                if captured_at >= start && captured_at <= end {
+                    snapshots.push((captured_at, path));
+                }
+            }
+        }
+        snapshots.sort_by_key(|(captured_at, _)| *captured_at);
+        Ok(snapshots)
+    }
+
+    /// 删除所有任务中超过保留时长的日期目录
+    async fn cleanup_expired() -> Result<(), std::io::Error> {
+        let snapshot_archive_config = get_capturer_config()
+            .map_err(std::io::Error::other)?
+            .snapshot_archive
+            .clone();
+        let Some(retention) = snapshot_archive_config.retention else {
+            return Ok(());
+        };
+        let cut_off_date = (Utc::now()
+            - chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::MAX))
+        .date_naive();
+
+        let archive_dir = PathBuf::from(&snapshot_archive_config.dir);
+        let mut read_dir = match fs::read_dir(&archive_dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        while let Some(job_entry) = read_dir.next_entry().await? {
+            for (date, date_dir) in Self::list_date_dirs(&job_entry.path()).await? {
+                if date < cut_off_date {
+                    info!("删除过期归档目录: {date_dir:?}");
+                    fs::remove_dir_all(&date_dir).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 列出任务归档目录下的日期目录
+    async fn list_date_dirs(job_dir: &Path) -> Result<Vec<(NaiveDate, PathBuf)>, std::io::Error> {
+        let mut date_dirs = Vec::new();
+        let mut read_dir = match fs::read_dir(job_dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(date_dirs),
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
+            if let Some(date) = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| NaiveDate::parse_from_str(file_name, DATE_DIR_FORMAT).ok())
+            {
+                date_dirs.push((date, path));
+            }
+        }
+        Ok(date_dirs)
+    }
+
+    /// 任务的归档目录(任务ID中除字母、数字、`-`和`_`以外的字符替换为`_`，避免越出归档目录)
+    fn job_dir(job_id: &str) -> Result<PathBuf, std::io::Error> {
+        let archive_dir = get_capturer_config()
+            .map_err(std::io::Error::other)?
+            .snapshot_archive
+            .dir
+            .clone();
+        Ok(PathBuf::from(archive_dir).join(to_safe_dir_name(job_id)))
+    }
+}
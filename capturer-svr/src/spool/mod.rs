@@ -0,0 +1,3 @@
+pub mod spool_eo;
+pub mod spool_error;
+pub mod upload_spool;
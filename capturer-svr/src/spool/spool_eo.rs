@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 暂存条目的元数据
+///
+/// 与上传的文件内容一起保存在暂存目录中(`<id>.json` + `<id>.dat`)，用于后台重试上传
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SpoolEntryMeta {
+    /// 暂存条目ID(同时也是文件名的前缀)
+    pub id: String,
+    /// 上传的存储桶
+    pub bucket: String,
+    /// 上传的文件名
+    pub file_name: String,
+    /// 上传的用户ID
+    pub user_id: u64,
+    /// 进入暂存的时间
+    pub created_at: DateTime<Utc>,
+    /// 已重试的次数
+    pub attempts: u32,
+    /// 下次重试的时间
+    pub next_retry_at: DateTime<Utc>,
+    /// 最后一次失败的原因
+    pub last_error: Option<String>,
+}
+
+/// 暂存目录的统计信息
+#[derive(Debug, Clone, Default)]
+pub struct SpoolStats {
+    /// 暂存条目数量
+    pub depth: usize,
+    /// 最早的暂存条目
+    pub oldest: Option<SpoolEntryMeta>,
+    /// 死信目录中的条目数量
+    pub dead_letter_depth: usize,
+}
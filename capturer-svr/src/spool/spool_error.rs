@@ -0,0 +1,12 @@
+use robotech::cfg::CfgError;
+use std::io::Error;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpoolError {
+    #[error("获取暂存配置失败: {0}")]
+    SpoolConfig(#[from] CfgError),
+    #[error("读写暂存目录失败: {0}")]
+    SpoolIo(#[from] Error),
+    #[error("解析暂存条目失败: {0}")]
+    SpoolParse(#[from] serde_json::Error),
+}
@@ -0,0 +1,393 @@
+use crate::config::capturer_config::{get_capturer_config, SpoolConfig};
+use crate::spool::spool_eo::{SpoolEntryMeta, SpoolStats};
+use crate::spool::spool_error::SpoolError;
+use crate::utils::task_utils::get_background_tasks;
+use chrono::Utc;
+use oss_api_client::api_client::get_oss_api_client;
+use robotech::ro::RoResult;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::fs;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+
+/// 重试任务是否已启动
+static RETRY_TASK_STARTED: OnceLock<()> = OnceLock::new();
+
+/// 暂存条目ID的序号(避免同一毫秒内的ID冲突)
+static ENTRY_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// 初始化上传暂存
+///
+/// 创建暂存目录，并启动后台任务定期重试上传暂存的抓拍。
+/// 后台任务每次扫描时都会重新读取配置，所以热加载配置后无需重新初始化。
+pub async fn init_upload_spool() -> Result<(), SpoolError> {
+    info!("初始化上传暂存");
+    let spool_dir = UploadSpool::spool_dir()?;
+    fs::create_dir_all(&spool_dir).await?;
+
+    if RETRY_TASK_STARTED.set(()).is_err() {
+        warn!("<重试上传暂存>任务已经启动");
+        return Ok(());
+    }
+
+    debug!("<重试上传暂存>任务正在创建....");
+    let background_tasks = get_background_tasks();
+    let cancel_token = background_tasks.child_token();
+    background_tasks.spawn(async move {
+        info!("<重试上传暂存>任务创建完成.");
+        loop {
+            let scan_interval = get_capturer_config()
+                .ok()
+                .and_then(|capturer_config| capturer_config.spool.scan_interval)
+                .unwrap_or(Duration::from_secs(5));
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = sleep(scan_interval) => {}
+            }
+            if let Err(e) = UploadSpool::retry_due_entries().await {
+                error!("重试上传暂存失败: {e}");
+            }
+        }
+        info!("程序正在退出，<重试上传暂存>任务退出");
+    });
+    Ok(())
+}
+
+/// 暂存目录中没有元数据的数据文件超过该时间后删除(写入暂存后上传期间进程退出留下的)
+const ORPHAN_DATA_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// 上传暂存
+///
+/// 上传前先把文件内容写入暂存目录，上传失败(请求失败或返回失败的结果)时再写入上传参数，
+/// 由后台任务按指数退避重试上传，上传成功后从暂存目录删除；
+/// 重试达到最大次数后移入死信目录，不再重试。
+pub struct UploadSpool;
+
+/// 已写入暂存目录、正在上传的文件
+///
+/// 只有数据文件，没有元数据文件，重试任务扫描时不可见；
+/// 上传失败时调用[`StagedUpload::spool`]进入重试，上传成功后调用[`StagedUpload::discard`]删除
+pub struct StagedUpload {
+    /// 暂存目录
+    spool_dir: PathBuf,
+    /// 进入重试时写入的元数据
+    meta: SpoolEntryMeta,
+}
+
+impl StagedUpload {
+    /// 上传失败，写入元数据文件，由后台任务重试上传
+    ///
+    /// ## 参数
+    /// * `error` - 上传失败的原因
+    ///
+    /// ## 返回值
+    /// 返回暂存条目的元数据，其中的`id`可作为待上传的引用返回给调用者
+    pub async fn spool(mut self, error: String) -> Result<SpoolEntryMeta, SpoolError> {
+        let spool_config = get_capturer_config()?.spool.clone();
+        self.meta.next_retry_at = Utc::now() + UploadSpool::retry_delay(&spool_config, 0);
+        self.meta.last_error = Some(error);
+        UploadSpool::write_meta(&self.spool_dir, &self.meta).await?;
+        info!(
+            "文件{}上传失败，已写入暂存: {}",
+            self.meta.file_name, self.meta.id
+        );
+        Ok(self.meta)
+    }
+
+    /// 暂存的数据文件的路径
+    pub fn data_path(&self) -> PathBuf {
+        UploadSpool::data_path(&self.spool_dir, &self.meta.id)
+    }
+
+    /// 上传成功，删除暂存的数据文件
+    pub async fn discard(self) {
+        let data_path = UploadSpool::data_path(&self.spool_dir, &self.meta.id);
+        if let Err(e) = fs::remove_file(&data_path).await {
+            warn!("删除暂存的数据文件{data_path:?}失败: {e}");
+        }
+    }
+}
+
+impl UploadSpool {
+    /// # 上传前将文件内容写入暂存目录
+    ///
+    /// ## 参数
+    /// * `bucket` - 上传的存储桶
+    /// * `file_name` - 上传的文件名
+    /// * `data` - 上传的文件内容(抓拍的图片、短视频或元数据文件)
+    /// * `user_id` - 上传的用户ID
+    pub async fn stage(
+        bucket: &str,
+        file_name: &str,
+        data: &[u8],
+        user_id: u64,
+    ) -> Result<StagedUpload, SpoolError> {
+        let spool_dir = Self::spool_dir()?;
+        fs::create_dir_all(&spool_dir).await?;
+        let meta = Self::new_meta(bucket, file_name, user_id);
+        // 扫描时只认元数据文件，数据文件写了一半或正在上传时都不会被重试
+        fs::write(Self::data_path(&spool_dir, &meta.id), data).await?;
+        Ok(StagedUpload { spool_dir, meta })
+    }
+
+    /// # 上传前将已有的文件移入暂存目录
+    ///
+    /// 文件较大(如会话的录像)时使用，不需要读入内存再写一遍；跨文件系统无法移动时复制后删除原文件
+    ///
+    /// ## 参数
+    /// * `bucket` - 上传的存储桶
+    /// * `file_name` - 上传的文件名
+    /// * `path` - 上传的文件，暂存成功后不再存在
+    /// * `user_id` - 上传的用户ID
+    pub async fn stage_file(
+        bucket: &str,
+        file_name: &str,
+        path: &Path,
+        user_id: u64,
+    ) -> Result<StagedUpload, SpoolError> {
+        let spool_dir = Self::spool_dir()?;
+        fs::create_dir_all(&spool_dir).await?;
+        let meta = Self::new_meta(bucket, file_name, user_id);
+        let data_path = Self::data_path(&spool_dir, &meta.id);
+        if let Err(e) = fs::rename(path, &data_path).await {
+            debug!("移动文件{path:?}到暂存目录失败，改为复制: {e}");
+            fs::copy(path, &data_path).await?;
+            if let Err(e) = fs::remove_file(path).await {
+                warn!("删除已暂存的文件{path:?}失败: {e}");
+            }
+        }
+        Ok(StagedUpload { spool_dir, meta })
+    }
+
+    /// 新的暂存条目的元数据
+    fn new_meta(bucket: &str, file_name: &str, user_id: u64) -> SpoolEntryMeta {
+        let now = Utc::now();
+        let id = format!(
+            "{}-{}",
+            now.timestamp_millis(),
+            ENTRY_SEQ.fetch_add(1, Ordering::Relaxed)
+        );
+        SpoolEntryMeta {
+            id,
+            bucket: bucket.to_string(),
+            file_name: file_name.to_string(),
+            user_id,
+            created_at: now,
+            attempts: 0,
+            next_retry_at: now,
+            last_error: None,
+        }
+    }
+
+    /// # 统计暂存目录
+    ///
+    /// ## 返回值
+    /// 返回暂存条目的数量、最早的暂存条目及死信的数量
+    pub async fn stats() -> Result<SpoolStats, SpoolError> {
+        let spool_config = get_capturer_config()?.spool.clone();
+        let metas = Self::list_metas(Path::new(&spool_config.dir)).await?;
+        let dead_letter_metas = Self::list_metas(Path::new(&spool_config.dead_letter_dir)).await?;
+        Ok(SpoolStats {
+            depth: metas.len(),
+            oldest: metas.into_iter().min_by_key(|meta| meta.created_at),
+            dead_letter_depth: dead_letter_metas.len(),
+        })
+    }
+
+    /// 重试上传所有已到重试时间的暂存条目
+    ///
+    /// 单个条目失败时只记录日志，不影响其它条目
+    async fn retry_due_entries() -> Result<(), SpoolError> {
+        let spool_config = get_capturer_config()?.spool.clone();
+        let spool_dir = PathBuf::from(&spool_config.dir);
+        let now = Utc::now();
+        for meta in Self::list_metas(&spool_dir).await? {
+            if meta.next_retry_at > now {
+                continue;
+            }
+            let id = meta.id.clone();
+            if let Err(e) = Self::retry_entry(&spool_config, &spool_dir, meta).await {
+                error!("重试上传暂存条目{id}失败: {e}");
+            }
+        }
+        Self::remove_orphan_data(&spool_dir).await;
+        Ok(())
+    }
+
+    /// 重试上传一个暂存条目
+    async fn retry_entry(
+        spool_config: &SpoolConfig,
+        spool_dir: &Path,
+        mut meta: SpoolEntryMeta,
+    ) -> Result<(), SpoolError> {
+        let id = meta.id.clone();
+        debug!("重试上传暂存条目: {id}");
+        let data = match fs::read(Self::data_path(spool_dir, &id)).await {
+            Ok(data) => data,
+            Err(e) => {
+                error!("读取暂存条目{id}的数据失败，删除该条目: {e}");
+                fs::remove_file(Self::meta_path(spool_dir, &id)).await?;
+                return Ok(());
+            }
+        };
+
+        match Self::upload(&meta, data).await {
+            Ok(()) => {
+                info!("暂存条目{id}上传成功");
+                fs::remove_file(Self::meta_path(spool_dir, &id)).await?;
+                if let Err(e) = fs::remove_file(Self::data_path(spool_dir, &id)).await {
+                    warn!("删除暂存条目{id}的数据失败: {e}");
+                }
+            }
+            Err(e) => {
+                meta.attempts += 1;
+                meta.last_error = Some(e.clone());
+                if spool_config.retry_max_attempts > 0
+                    && meta.attempts >= spool_config.retry_max_attempts
+                {
+                    error!(
+                        "暂存条目{id}已重试上传{}次仍失败，移入死信目录: {e}",
+                        meta.attempts
+                    );
+                    return Self::move_to_dead_letter(spool_config, spool_dir, &meta).await;
+                }
+                meta.next_retry_at = Utc::now() + Self::retry_delay(spool_config, meta.attempts);
+                warn!(
+                    "暂存条目{id}第{}次重试上传失败, 下次重试时间: {}, 原因: {e}",
+                    meta.attempts, meta.next_retry_at
+                );
+                Self::write_meta(spool_dir, &meta).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 将暂存条目移入死信目录(先移数据文件，再写元数据文件，最后删除暂存目录中的元数据文件)
+    async fn move_to_dead_letter(
+        spool_config: &SpoolConfig,
+        spool_dir: &Path,
+        meta: &SpoolEntryMeta,
+    ) -> Result<(), SpoolError> {
+        let dead_letter_dir = PathBuf::from(&spool_config.dead_letter_dir);
+        fs::create_dir_all(&dead_letter_dir).await?;
+        fs::rename(
+            Self::data_path(spool_dir, &meta.id),
+            Self::data_path(&dead_letter_dir, &meta.id),
+        )
+        .await?;
+        Self::write_meta(&dead_letter_dir, meta).await?;
+        fs::remove_file(Self::meta_path(spool_dir, &meta.id)).await?;
+        Ok(())
+    }
+
+    /// 删除没有元数据且超过最大保留时间的数据文件
+    async fn remove_orphan_data(spool_dir: &Path) {
+        let Ok(mut read_dir) = fs::read_dir(spool_dir).await else {
+            return;
+        };
+        while let Ok(Some(entry)) = read_dir.next_entry().await {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "dat")
+                || fs::try_exists(path.with_extension("json"))
+                    .await
+                    .unwrap_or(true)
+            {
+                continue;
+            }
+            let expired = entry
+                .metadata()
+                .await
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|elapsed| elapsed > ORPHAN_DATA_MAX_AGE);
+            if expired {
+                warn!("删除没有元数据的暂存数据文件: {path:?}");
+                if let Err(e) = fs::remove_file(&path).await {
+                    warn!("删除暂存数据文件{path:?}失败: {e}");
+                }
+            }
+        }
+    }
+
+    /// 上传暂存条目到OSS
+    async fn upload(meta: &SpoolEntryMeta, data: Vec<u8>) -> Result<(), String> {
+        let oss_api_client = get_oss_api_client().map_err(|e| e.to_string())?;
+        let oss_file_api_ro = oss_api_client
+            .file_client
+            .upload_file_content(&meta.bucket, &meta.file_name, data, meta.user_id)
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        if let RoResult::Success = oss_file_api_ro.result {
+            Ok(())
+        } else {
+            Err(oss_file_api_ro.msg)
+        }
+    }
+
+    /// 计算第`attempts`次失败后的重试间隔(指数退避，不超过最大间隔)
+    fn retry_delay(spool_config: &SpoolConfig, attempts: u32) -> chrono::Duration {
+        let initial = spool_config
+            .retry_initial_interval
+            .unwrap_or(Duration::from_secs(5));
+        let max = spool_config
+            .retry_max_interval
+            .unwrap_or(Duration::from_secs(10 * 60));
+        let delay = initial
+            .checked_mul(2u32.saturating_pow(attempts))
+            .unwrap_or(max)
+            .min(max);
+        chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX)
+    }
+
+    /// 列出暂存目录中所有条目的元数据
+    async fn list_metas(spool_dir: &Path) -> Result<Vec<SpoolEntryMeta>, SpoolError> {
+        let mut metas = Vec::new();
+        let mut read_dir = match fs::read_dir(spool_dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(metas),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let content = match fs::read(&path).await {
+                Ok(content) => content,
+                Err(e) => {
+                    warn!("读取暂存条目{path:?}失败: {e}");
+                    continue;
+                }
+            };
+            match serde_json::from_slice::<SpoolEntryMeta>(&content) {
+                Ok(meta) => metas.push(meta),
+                Err(e) => warn!("解析暂存条目{path:?}失败: {e}"),
+            }
+        }
+        Ok(metas)
+    }
+
+    /// 写入元数据文件(先写临时文件再重命名，保证原子性)
+    async fn write_meta(spool_dir: &Path, meta: &SpoolEntryMeta) -> Result<(), SpoolError> {
+        let tmp_path = spool_dir.join(format!("{}.json.tmp", meta.id));
+        fs::write(&tmp_path, serde_json::to_vec(meta)?).await?;
+        fs::rename(&tmp_path, Self::meta_path(spool_dir, &meta.id)).await?;
+        Ok(())
+    }
+
+    fn spool_dir() -> Result<PathBuf, SpoolError> {
+        Ok(PathBuf::from(&get_capturer_config()?.spool.dir))
+    }
+
+    fn data_path(spool_dir: &Path, id: &str) -> PathBuf {
+        spool_dir.join(format!("{id}.dat"))
+    }
+
+    fn meta_path(spool_dir: &Path, id: &str) -> PathBuf {
+        spool_dir.join(format!("{id}.json"))
+    }
+}
@@ -0,0 +1,69 @@
+use crate::stream::flv_tag::{FlvSequenceHeaders, FlvTag};
+use bytes::{Bytes, BytesMut};
+
+/// FLV片段录制器
+///
+/// 从中途订阅的标签中录制一段可独立播放的FLV:
+/// 从第一个视频关键帧开始录制，先写入FLV文件头、脚本数据和序列头，
+/// 再写入标签，并将时间戳改写为从0开始
+pub struct FlvClipRecorder {
+    /// FLV文件头、脚本数据和序列头，开始录制前收到的新序列头会覆盖订阅时的值
+    sequence_headers: FlvSequenceHeaders,
+    /// 已录制的数据
+    buf: BytesMut,
+    /// 第一个录制标签的原始时间戳，开始录制前为None
+    base_timestamp: Option<u32>,
+    /// 最后一个录制标签改写后的时间戳
+    last_timestamp: u32,
+}
+
+impl FlvClipRecorder {
+    pub fn new(sequence_headers: FlvSequenceHeaders) -> Self {
+        Self {
+            sequence_headers,
+            buf: BytesMut::new(),
+            base_timestamp: None,
+            last_timestamp: 0,
+        }
+    }
+
+    /// 是否已开始录制
+    pub fn is_started(&self) -> bool {
+        self.base_timestamp.is_some()
+    }
+
+    /// 已录制的时长(毫秒)
+    pub fn duration_ms(&self) -> u32 {
+        self.last_timestamp
+    }
+
+    /// 写入一个标签
+    ///
+    /// ## 返回值
+    /// 如果标签被写入，返回true；开始录制前的标签及脚本数据会被丢弃
+    pub fn push(&mut self, tag: &FlvTag) -> bool {
+        let base_timestamp = match self.base_timestamp {
+            Some(base_timestamp) => base_timestamp,
+            None => {
+                if self.sequence_headers.update(tag) || !tag.is_keyframe() {
+                    return false;
+                }
+                self.buf.extend_from_slice(&self.sequence_headers.encode());
+                self.base_timestamp = Some(tag.timestamp);
+                tag.timestamp
+            }
+        };
+        if tag.is_script() {
+            return false;
+        }
+        let timestamp = tag.timestamp.saturating_sub(base_timestamp);
+        tag.with_timestamp(timestamp).encode(&mut self.buf);
+        self.last_timestamp = self.last_timestamp.max(timestamp);
+        true
+    }
+
+    /// 结束录制，返回录制的FLV数据
+    pub fn finish(self) -> Bytes {
+        self.buf.freeze()
+    }
+}
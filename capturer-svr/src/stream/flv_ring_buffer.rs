@@ -0,0 +1,115 @@
+use crate::stream::flv_tag::{FlvSequenceHeaders, FlvTag};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tracing::warn;
+
+/// 时间戳回退超过该值(毫秒)时，认为流已重新开始，清空缓冲
+const TIMESTAMP_REWIND_TOLERANCE_MS: u32 = 1000;
+/// 每个跟随者最多积压的标签数(25fps的音视频约十几秒)，积压满时关闭该跟随者
+const FOLLOWER_CAPACITY: usize = 1024;
+
+/// FLV预录缓冲
+///
+/// 滚动保存最近一段时长的FLV标签(不含脚本数据和序列头，它们单独保存)，
+/// 用于导出事件发生前的视频
+pub struct FlvRingBuffer {
+    /// 缓冲的时长(毫秒)
+    duration_ms: u32,
+    /// 最近一次收到的FLV文件头、脚本数据和序列头
+    sequence_headers: FlvSequenceHeaders,
+    /// 缓冲的标签
+    tags: VecDeque<FlvTag>,
+    /// 跟随后续标签的接收者(导出事件发生后的视频)
+    followers: Vec<mpsc::Sender<FlvTag>>,
+}
+
+impl FlvRingBuffer {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration_ms: duration.as_millis().min(u32::MAX as u128) as u32,
+            sequence_headers: FlvSequenceHeaders::default(),
+            tags: VecDeque::new(),
+            followers: Vec::new(),
+        }
+    }
+
+    /// 缓冲的时长(毫秒)
+    pub fn duration_ms(&self) -> u32 {
+        self.duration_ms
+    }
+
+    /// 修改缓冲的时长
+    ///
+    /// 保留已缓冲的标签，写入下一个标签时淘汰超出新时长的标签
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration_ms = duration.as_millis().min(u32::MAX as u128) as u32;
+    }
+
+    /// 关联到新的会话
+    ///
+    /// 清空缓冲的标签，并使用会话当前的头部信息
+    pub fn attach(&mut self, sequence_headers: FlvSequenceHeaders) {
+        self.sequence_headers = sequence_headers;
+        self.tags.clear();
+    }
+
+    /// 写入一个标签，并淘汰超出缓冲时长的标签
+    pub fn push(&mut self, tag: FlvTag) {
+        // 跟随者处理不过来时丢弃后续的标签会导致导出的视频不连续，直接关闭该跟随者
+        self.followers
+            .retain(|follower| match follower.try_send(tag.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    warn!("预录缓冲的跟随者积压了{FOLLOWER_CAPACITY}个标签，关闭该跟随者");
+                    false
+                }
+                Err(TrySendError::Closed(_)) => false,
+            });
+        if self.sequence_headers.update(&tag) {
+            return;
+        }
+        if let Some(last) = self.tags.back()
+            && tag.timestamp.saturating_add(TIMESTAMP_REWIND_TOLERANCE_MS) < last.timestamp
+        {
+            self.tags.clear();
+        }
+        let newest_timestamp = tag.timestamp;
+        self.tags.push_back(tag);
+        while let Some(front) = self.tags.front()
+            && newest_timestamp.saturating_sub(front.timestamp) > self.duration_ms
+        {
+            self.tags.pop_front();
+        }
+    }
+
+    /// 截取事件前的标签，并开始跟随后续的标签
+    ///
+    /// 截取的标签从事件前`pre_ms`毫秒处或之前最近的关键帧开始(缓冲中没有这样的关键帧时从第一个关键帧开始)，
+    /// 保证导出的视频可以独立解码
+    ///
+    /// ## 返回值
+    /// 返回(头部信息, 事件前的标签, 后续标签的接收者)，接收者积压满[`FOLLOWER_CAPACITY`]个标签时被关闭
+    pub fn follow(
+        &mut self,
+        pre_ms: u32,
+    ) -> (FlvSequenceHeaders, Vec<FlvTag>, mpsc::Receiver<FlvTag>) {
+        let (follower, receiver) = mpsc::channel(FOLLOWER_CAPACITY);
+        self.followers.push(follower);
+
+        let cut_off_timestamp = self
+            .tags
+            .back()
+            .map(|last| last.timestamp.saturating_sub(pre_ms))
+            .unwrap_or_default();
+        let start = self
+            .tags
+            .iter()
+            .rposition(|tag| tag.is_keyframe() && tag.timestamp <= cut_off_timestamp)
+            .or_else(|| self.tags.iter().position(|tag| tag.is_keyframe()))
+            .unwrap_or(self.tags.len());
+        let tags = self.tags.iter().skip(start).cloned().collect();
+        (self.sequence_headers.clone(), tags, receiver)
+    }
+}
@@ -0,0 +1,112 @@
+use crate::stream::flv_tag::{FlvSequenceHeaders, FlvTag};
+
+/// 重新拉流后，第一个标签与上一个输出的标签之间的时间戳间隔(毫秒)，约为25fps的一帧
+const SPLICE_GAP_MS: u32 = 40;
+
+/// FLV拼接器
+///
+/// 会话的ffmpeg进程退出并重新拉流后，新进程输出的时间戳从0开始。
+/// 拼接器将各次拉流输出的标签改写为单调递增的时间戳，使观看者收到的是一条连续的流：
+/// - 重新拉流后丢弃视频关键帧之前的视频帧(解码需要从关键帧开始)
+/// - 脚本数据和序列头与当前的相同时不再重复发送，编码参数变化时才发送新的
+#[derive(Default)]
+pub struct FlvSplicer {
+    /// 当前这次拉流的时间戳偏移
+    offset: u32,
+    /// 当前这次拉流的第一个标签的原始时间戳
+    base: Option<u32>,
+    /// 上一个输出的标签的时间戳
+    last_timestamp: Option<u32>,
+    /// 是否处于重新拉流后、尚未收到视频关键帧的状态
+    awaiting_keyframe: bool,
+    /// 重新拉流的次数
+    splice_count: u64,
+}
+
+impl FlvSplicer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 重新拉流的次数
+    pub fn splice_count(&self) -> u64 {
+        self.splice_count
+    }
+
+    /// 是否可以原样转发(第一次拉流的时间戳不需要改写，也不需要等待关键帧)
+    pub fn is_pass_through(&self) -> bool {
+        self.splice_count == 0
+    }
+
+    /// 记录原样转发的标签的时间戳，用于之后重新拉流时接着计时
+    pub fn observe(&mut self, timestamp: u32) {
+        self.last_timestamp = Some(
+            self.last_timestamp
+                .map_or(timestamp, |last_timestamp| last_timestamp.max(timestamp)),
+        );
+    }
+
+    /// 开始拼接新一次拉流的输出
+    ///
+    /// 新的标签从上一个输出的标签之后继续计时
+    pub fn restart(&mut self) {
+        self.offset = self.last_timestamp.map_or(0, |last_timestamp| {
+            last_timestamp.saturating_add(SPLICE_GAP_MS)
+        });
+        self.base = None;
+        self.awaiting_keyframe = self.last_timestamp.is_some();
+        self.splice_count += 1;
+    }
+
+    /// 改写标签的时间戳
+    ///
+    /// ## 参数
+    /// * `tag` - ffmpeg输出的标签
+    /// * `sequence_headers` - 会话当前的脚本数据和序列头，用于判断重新拉流后的头部信息是否变化
+    ///
+    /// ## 返回值
+    /// 返回改写时间戳后的标签，需要丢弃时返回None
+    pub fn splice(&mut self, tag: FlvTag, sequence_headers: &FlvSequenceHeaders) -> Option<FlvTag> {
+        if self.splice_count > 0 && Self::is_unchanged_header(&tag, sequence_headers) {
+            return None;
+        }
+        if self.awaiting_keyframe && tag.is_video() && !tag.is_sequence_header() {
+            if !tag.is_keyframe() {
+                return None;
+            }
+            self.awaiting_keyframe = false;
+        }
+        // 第一次拉流保留原始的时间戳，与原样转发的数据一致
+        let first_timestamp = if self.splice_count == 0 {
+            0
+        } else {
+            tag.timestamp
+        };
+        let base = *self.base.get_or_insert(first_timestamp);
+        // 新进程的音视频可能不是从同一时刻开始的，拼接处不允许时间戳回退
+        let timestamp = self
+            .offset
+            .saturating_add(tag.timestamp.saturating_sub(base))
+            .max(self.last_timestamp.unwrap_or_default());
+        self.last_timestamp = Some(timestamp);
+        Some(tag.with_timestamp(timestamp))
+    }
+
+    /// 是否为与当前相同的脚本数据或序列头
+    fn is_unchanged_header(tag: &FlvTag, sequence_headers: &FlvSequenceHeaders) -> bool {
+        let current = if tag.is_script() {
+            &sequence_headers.script
+        } else if tag.is_sequence_header() {
+            if tag.is_video() {
+                &sequence_headers.video
+            } else {
+                &sequence_headers.audio
+            }
+        } else {
+            return false;
+        };
+        current
+            .as_ref()
+            .is_some_and(|current| current.data == tag.data)
+    }
+}
@@ -1,30 +1,30 @@
+use crate::quota::quota_manager::LiveStreamPermit;
 use async_stream::stream;
 use bytes::Bytes;
 use futures::Stream;
 use tracing::{debug, warn};
 use robotech::svc::SvcError;
-use std::sync::{Arc, OnceLock};
+use tokio::sync::broadcast;
 use tokio::sync::broadcast::Receiver;
-use tokio::sync::{broadcast, oneshot};
 
 pub struct FlvStream {
     data_receiver: Receiver<Bytes>,
-    header: Arc<OnceLock<Bytes>>,
-    cache_header_sender: Option<oneshot::Sender<Bytes>>,
-    is_init: bool,
+    /// 已存在的会话当前的头部信息(新会话为None，头部会直接从数据接收者中收到)
+    header: Option<Bytes>,
+    /// 观看直播的配额许可(随流一起释放)
+    _live_permit: Option<LiveStreamPermit>,
 }
 
 impl FlvStream {
     pub fn new(
         data_receiver: Receiver<Bytes>,
-        header: Arc<OnceLock<Bytes>>,
-        cache_header_sender: Option<oneshot::Sender<Bytes>>,
+        header: Option<Bytes>,
+        live_permit: Option<LiveStreamPermit>,
     ) -> Self {
         Self {
             data_receiver,
             header,
-            cache_header_sender,
-            is_init: true,
+            _live_permit: live_permit,
         }
     }
 
@@ -34,24 +34,15 @@ impl FlvStream {
             let mut this = self;
 
             // 处理初始头部数据
-            if this.is_init {
-                if let Some(header) = this.header.get() {
-                    debug!("要拉取的流已经存在，直接将缓存头部写入输出流: {:?}", header);
-                    this.is_init = false;
-                    yield Ok(header.clone());
-                }
+            if let Some(header) = this.header.take() {
+                debug!("要拉取的流已经存在，直接将缓存头部写入输出流: {:?}", header);
+                yield Ok(header);
             }
 
             // 持续接收数据
             loop {
                 match this.data_receiver.recv().await {
                     Ok(bytes) => {
-                        if this.is_init {
-                            this.is_init = false;
-                            if let Some(cache_header_sender) = this.cache_header_sender.take() {
-                                let _ = cache_header_sender.send(bytes.clone());
-                            }
-                        }
                         yield Ok(bytes);
                     }
                     Err(broadcast::error::RecvError::Closed) => {
@@ -0,0 +1,417 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tracing::warn;
+
+/// FLV文件头的签名
+const FLV_SIGNATURE: &[u8] = b"FLV";
+/// FLV的版本
+const FLV_VERSION: u8 = 1;
+/// FLV文件头的长度(不含PreviousTagSize0)
+const FLV_HEADER_LEN: usize = 9;
+/// FLV文件头中DataOffset的合理上限(规范中为9，留出扩展字段的余量)
+const FLV_MAX_DATA_OFFSET: usize = 1024;
+/// FLV标签头的长度
+const TAG_HEADER_LEN: usize = 11;
+/// PreviousTagSize字段的长度
+const PREVIOUS_TAG_SIZE_LEN: usize = 4;
+
+/// FLV标签类型: 音频
+pub const TAG_TYPE_AUDIO: u8 = 8;
+/// FLV标签类型: 视频
+pub const TAG_TYPE_VIDEO: u8 = 9;
+/// FLV标签类型: 脚本数据(onMetaData)
+pub const TAG_TYPE_SCRIPT: u8 = 18;
+
+/// FLV标签
+///
+/// 只保存标签类型、时间戳和标签数据，编码时重新生成标签头和PreviousTagSize
+#[derive(Debug, Clone)]
+pub struct FlvTag {
+    /// 标签类型
+    pub tag_type: u8,
+    /// 时间戳(毫秒)
+    pub timestamp: u32,
+    /// 标签数据
+    pub data: Bytes,
+}
+
+impl FlvTag {
+    pub fn is_video(&self) -> bool {
+        self.tag_type == TAG_TYPE_VIDEO
+    }
+
+    pub fn is_audio(&self) -> bool {
+        self.tag_type == TAG_TYPE_AUDIO
+    }
+
+    pub fn is_script(&self) -> bool {
+        self.tag_type == TAG_TYPE_SCRIPT
+    }
+
+    /// 是否为视频关键帧
+    pub fn is_keyframe(&self) -> bool {
+        self.is_video() && self.data.first().is_some_and(|b| b >> 4 == 1)
+    }
+
+    /// 是否为音视频的序列头(AVC/HEVC的解码配置或AAC的AudioSpecificConfig)
+    pub fn is_sequence_header(&self) -> bool {
+        is_sequence_header_data(self.tag_type, &self.data)
+    }
+
+    /// 复制标签并修改时间戳
+    pub fn with_timestamp(&self, timestamp: u32) -> Self {
+        Self {
+            tag_type: self.tag_type,
+            timestamp,
+            data: self.data.clone(),
+        }
+    }
+
+    /// 编码后的长度(标签头 + 标签数据 + PreviousTagSize)
+    pub fn encoded_len(&self) -> usize {
+        TAG_HEADER_LEN + self.data.len() + PREVIOUS_TAG_SIZE_LEN
+    }
+
+    /// 编码为FLV标签(包含其后的PreviousTagSize)
+    pub fn encode(&self, buf: &mut BytesMut) {
+        let data_size = self.data.len() as u32;
+        buf.reserve(self.encoded_len());
+        buf.put_u8(self.tag_type);
+        buf.put_uint(data_size as u64, 3);
+        buf.put_uint((self.timestamp & 0x00FF_FFFF) as u64, 3);
+        buf.put_u8((self.timestamp >> 24) as u8);
+        buf.put_uint(0, 3);
+        buf.put_slice(&self.data);
+        buf.put_u32(data_size + TAG_HEADER_LEN as u32);
+    }
+
+    /// 编码为FLV标签(包含其后的PreviousTagSize)
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(self.encoded_len());
+        self.encode(&mut buf);
+        buf.freeze()
+    }
+}
+
+/// 标签数据是否为音视频的序列头
+fn is_sequence_header_data(tag_type: u8, data: &[u8]) -> bool {
+    match tag_type {
+        // 视频: CodecID为7(AVC)或12(HEVC)，AVCPacketType为0
+        TAG_TYPE_VIDEO => data.len() >= 2 && matches!(data[0] & 0x0F, 7 | 12) && data[1] == 0,
+        // 音频: SoundFormat为10(AAC)，AACPacketType为0
+        TAG_TYPE_AUDIO => data.len() >= 2 && data[0] >> 4 == 10 && data[1] == 0,
+        _ => false,
+    }
+}
+
+/// 编码FLV文件头(包含PreviousTagSize0)
+pub fn encode_flv_header(has_audio: bool, has_video: bool) -> Bytes {
+    let mut buf = BytesMut::with_capacity(FLV_HEADER_LEN + PREVIOUS_TAG_SIZE_LEN);
+    buf.put_slice(FLV_SIGNATURE);
+    buf.put_u8(FLV_VERSION);
+    buf.put_u8((if has_audio { 0x04 } else { 0 }) | (if has_video { 0x01 } else { 0 }));
+    buf.put_u32(FLV_HEADER_LEN as u32);
+    buf.put_u32(0);
+    buf.freeze()
+}
+
+/// 播放一段FLV流所需的头部信息
+///
+/// 从流的中途开始输出时，需要先输出这些信息，播放器才能正确解码
+#[derive(Debug, Clone, Default)]
+pub struct FlvSequenceHeaders {
+    /// FLV文件头(包含PreviousTagSize0)
+    pub flv_header: Option<Bytes>,
+    /// 脚本数据标签(onMetaData)
+    pub script: Option<FlvTag>,
+    /// 视频序列头
+    pub video: Option<FlvTag>,
+    /// 音频序列头
+    pub audio: Option<FlvTag>,
+}
+
+impl FlvSequenceHeaders {
+    /// 记录标签中的头部信息
+    ///
+    /// ## 返回值
+    /// 如果该标签是脚本数据或序列头，返回true
+    pub fn update(&mut self, tag: &FlvTag) -> bool {
+        if tag.is_script() {
+            self.script = Some(tag.clone());
+        } else if tag.is_sequence_header() {
+            if tag.is_video() {
+                self.video = Some(tag.clone());
+            } else {
+                self.audio = Some(tag.clone());
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// 编码为FLV文件头及时间戳为0的脚本数据、序列头标签
+    pub fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        match &self.flv_header {
+            Some(flv_header) => buf.put_slice(flv_header),
+            None => buf.put_slice(&encode_flv_header(self.audio.is_some(), true)),
+        }
+        for tag in [&self.script, &self.video, &self.audio]
+            .into_iter()
+            .flatten()
+        {
+            tag.with_timestamp(0).encode(&mut buf);
+        }
+        buf.freeze()
+    }
+}
+
+/// 解复用器输出的数据
+#[derive(Debug, Clone)]
+pub enum FlvChunk {
+    /// 组装好的标签
+    Tag(FlvTag),
+    /// 原样转发的数据
+    Raw {
+        /// 连续的若干个完整的普通音视频标签(包含PreviousTagSize)，与输入共享内存
+        data: Bytes,
+        /// 其中最后一个标签的时间戳
+        last_timestamp: u32,
+    },
+}
+
+/// 标签的总长度(标签头 + 标签数据 + PreviousTagSize)，`b`至少包含完整的标签头
+fn tag_len(b: &[u8]) -> usize {
+    TAG_HEADER_LEN + u32::from_be_bytes([0, b[1], b[2], b[3]]) as usize + PREVIOUS_TAG_SIZE_LEN
+}
+
+/// 完整的标签(包含PreviousTagSize)的时间戳，以及是否为脚本数据或序列头
+fn peek_tag(tag_bytes: &[u8]) -> (u32, bool) {
+    let tag_type = tag_bytes[0] & 0x1F;
+    let timestamp = u32::from_be_bytes([tag_bytes[7], tag_bytes[4], tag_bytes[5], tag_bytes[6]]);
+    let data = &tag_bytes[TAG_HEADER_LEN..tag_bytes.len() - PREVIOUS_TAG_SIZE_LEN];
+    let is_header = tag_type == TAG_TYPE_SCRIPT || is_sequence_header_data(tag_type, data);
+    (timestamp, is_header)
+}
+
+/// 从完整的标签字节(包含PreviousTagSize)中取出标签
+fn decode_tag(mut tag_bytes: Bytes) -> FlvTag {
+    let tag_type = tag_bytes.get_u8() & 0x1F;
+    let data_size = tag_bytes.get_uint(3) as usize;
+    let timestamp = (tag_bytes.get_uint(3) as u32) | ((tag_bytes.get_u8() as u32) << 24);
+    tag_bytes.advance(3);
+    FlvTag {
+        tag_type,
+        timestamp,
+        data: tag_bytes.split_to(data_size),
+    }
+}
+
+/// FLV解复用器
+///
+/// 将任意切分的FLV字节流重新组装为FLV文件头和完整的标签
+#[derive(Default)]
+pub struct FlvDemuxer {
+    /// 未解析完的数据
+    buf: BytesMut,
+    /// FLV文件头(包含PreviousTagSize0)，解析到之前为None
+    flv_header: Option<Bytes>,
+    /// 是否需要重新定位到标签的起始位置
+    resyncing: bool,
+}
+
+impl FlvDemuxer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 已解析到的FLV文件头(包含PreviousTagSize0)
+    pub fn flv_header(&self) -> Option<&Bytes> {
+        self.flv_header.as_ref()
+    }
+
+    /// 丢弃未解析完的数据，并在后续输入中重新定位标签的起始位置
+    ///
+    /// 接收数据出现丢失(如广播通道滞后)时调用
+    pub fn resync(&mut self) {
+        self.buf.clear();
+        self.resyncing = self.flv_header.is_some();
+    }
+
+    /// 输入一段数据，返回其中所有完整的标签
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<FlvTag> {
+        self.buf.extend_from_slice(chunk);
+        let mut tags = Vec::new();
+
+        if self.flv_header.is_none() && !self.parse_flv_header() {
+            return tags;
+        }
+
+        if self.resyncing {
+            match self.find_tag_start() {
+                Some(pos) => {
+                    self.buf.advance(pos);
+                    self.resyncing = false;
+                }
+                None => return tags,
+            }
+        }
+
+        while self.buf.len() >= TAG_HEADER_LEN {
+            let tag_len = tag_len(&self.buf);
+            if self.buf.len() < tag_len {
+                break;
+            }
+            tags.push(decode_tag(self.buf.split_to(tag_len).freeze()));
+        }
+        tags
+    }
+
+    /// 输入一段数据，没有标签的使用者时原样转发
+    ///
+    /// - `assemble`为true时与[`push`](Self::push)相同，输出组装好的标签
+    /// - 为false时连续的普通音视频标签原样转发(与输入共享内存，不重新编码)，只组装脚本数据和序列头，用于记录头部信息
+    ///
+    /// 两种方式输出的都是完整的标签，只有跨越输入边界的标签需要复制
+    pub fn push_lazy(&mut self, mut chunk: Bytes, assemble: bool) -> Vec<FlvChunk> {
+        if assemble || self.flv_header.is_none() || self.resyncing {
+            return self.push(&chunk).into_iter().map(FlvChunk::Tag).collect();
+        }
+        let mut chunks = Vec::new();
+        // 先补齐上次输入中不完整的标签
+        if !self.buf.is_empty() {
+            match self.complete_buffered(&mut chunk) {
+                Some(tag_bytes) => chunks.push(Self::to_chunk(tag_bytes)),
+                None => return chunks,
+            }
+        }
+        // 以下chunk从标签的边界开始
+        loop {
+            let mut raw_len = 0;
+            let mut last_timestamp = 0;
+            while chunk.len() - raw_len >= TAG_HEADER_LEN {
+                let len = tag_len(&chunk[raw_len..]);
+                if chunk.len() - raw_len < len {
+                    break;
+                }
+                let (timestamp, is_header) = peek_tag(&chunk[raw_len..raw_len + len]);
+                if is_header {
+                    if raw_len == 0 {
+                        chunks.push(FlvChunk::Tag(decode_tag(chunk.split_to(len))));
+                        continue;
+                    }
+                    break;
+                }
+                raw_len += len;
+                last_timestamp = timestamp;
+            }
+            if raw_len == 0 {
+                break;
+            }
+            chunks.push(FlvChunk::Raw {
+                data: chunk.split_to(raw_len),
+                last_timestamp,
+            });
+        }
+        self.buf.extend_from_slice(&chunk);
+        chunks
+    }
+
+    /// 用输入补齐缓冲中从标签边界开始的不完整标签
+    ///
+    /// ## 返回值
+    /// 返回补齐的标签(包含PreviousTagSize)，数据还不够时返回None
+    fn complete_buffered(&mut self, chunk: &mut Bytes) -> Option<Bytes> {
+        if self.buf.len() < TAG_HEADER_LEN {
+            let len = (TAG_HEADER_LEN - self.buf.len()).min(chunk.len());
+            self.buf.extend_from_slice(&chunk.split_to(len));
+            if self.buf.len() < TAG_HEADER_LEN {
+                return None;
+            }
+        }
+        let tag_len = tag_len(&self.buf);
+        let len = tag_len.saturating_sub(self.buf.len()).min(chunk.len());
+        self.buf.extend_from_slice(&chunk.split_to(len));
+        if self.buf.len() < tag_len {
+            return None;
+        }
+        Some(self.buf.split_to(tag_len).freeze())
+    }
+
+    /// 脚本数据和序列头组装为标签，其它标签原样转发
+    fn to_chunk(tag_bytes: Bytes) -> FlvChunk {
+        match peek_tag(&tag_bytes) {
+            (_, true) => FlvChunk::Tag(decode_tag(tag_bytes)),
+            (last_timestamp, false) => FlvChunk::Raw {
+                data: tag_bytes,
+                last_timestamp,
+            },
+        }
+    }
+
+    /// 从缓冲的开头解析FLV文件头
+    ///
+    /// 签名或版本不正确时丢弃数据，直到找到下一个FLV签名
+    ///
+    /// ## 返回值
+    /// 解析到FLV文件头时返回true，数据还不够时返回false
+    fn parse_flv_header(&mut self) -> bool {
+        loop {
+            if self.buf.len() < FLV_HEADER_LEN {
+                return false;
+            }
+            let data_offset =
+                u32::from_be_bytes([self.buf[5], self.buf[6], self.buf[7], self.buf[8]]) as usize;
+            if self.buf.starts_with(FLV_SIGNATURE)
+                && self.buf[3] == FLV_VERSION
+                && (FLV_HEADER_LEN..=FLV_MAX_DATA_OFFSET).contains(&data_offset)
+            {
+                let header_len = data_offset + PREVIOUS_TAG_SIZE_LEN;
+                if self.buf.len() < header_len {
+                    return false;
+                }
+                self.flv_header = Some(self.buf.split_to(header_len).freeze());
+                return true;
+            }
+            // 跳过当前位置，丢弃到下一个FLV签名之前(没找到时保留可能是签名开头的尾部数据)
+            let skip = self.buf[1..]
+                .windows(FLV_SIGNATURE.len())
+                .position(|window| window == FLV_SIGNATURE)
+                .map_or(self.buf.len() + 1 - FLV_SIGNATURE.len(), |pos| pos + 1);
+            warn!("FLV文件头不正确，丢弃{skip}字节");
+            self.buf.advance(skip);
+        }
+    }
+    /// 查找第一个完整标签的起始位置
+    ///
+    /// 标签类型合法、StreamID为0，且标签后的PreviousTagSize与标签长度一致时，认为找到了标签的起始位置。
+    /// 没找到时只保留可能是标签开头的尾部数据。
+    fn find_tag_start(&mut self) -> Option<usize> {
+        let mut pos = 0;
+        while pos + TAG_HEADER_LEN <= self.buf.len() {
+            let b = &self.buf[pos..];
+            if matches!(
+                b[0] & 0x1F,
+                TAG_TYPE_AUDIO | TAG_TYPE_VIDEO | TAG_TYPE_SCRIPT
+            ) && b[8..11] == [0, 0, 0]
+            {
+                let data_size = u32::from_be_bytes([0, b[1], b[2], b[3]]) as usize;
+                let tag_len = TAG_HEADER_LEN + data_size + PREVIOUS_TAG_SIZE_LEN;
+                if b.len() < tag_len {
+                    // 数据还不够验证，等待后续输入
+                    self.buf.advance(pos);
+                    return None;
+                }
+                let previous_tag_size = &b[tag_len - PREVIOUS_TAG_SIZE_LEN..tag_len];
+                if u32::from_be_bytes(previous_tag_size.try_into().unwrap())
+                    == (TAG_HEADER_LEN + data_size) as u32
+                {
+                    return Some(pos);
+                }
+            }
+            pos += 1;
+        }
+        self.buf.advance(pos);
+        None
+    }
+}
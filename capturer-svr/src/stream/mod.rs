@@ -1,2 +1,9 @@
+pub mod flv_clip;
+pub mod flv_ring_buffer;
+pub mod flv_splicer;
 pub mod flv_stream;
+pub mod flv_tag;
+pub mod session_recording;
+pub mod session_supervisor;
+pub mod stream_eo;
 pub mod stream_manager;
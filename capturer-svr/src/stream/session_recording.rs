@@ -0,0 +1,185 @@
+use crate::record::segment_writer::FlvSegmentWriter;
+use crate::stream::flv_tag::{encode_flv_header, FlvDemuxer, FlvSequenceHeaders};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// 会话的按需录像
+///
+/// 订阅会话输出的FLV字节流(不另外启动ffmpeg)，解复用后从第一个视频关键帧开始写入文件，
+/// 停止录像后返回录制的文件。
+/// 录像被丢弃(如会话关闭)时终止写入并删除文件
+pub struct SessionRecording {
+    /// 开始录像的时间
+    pub started_at: DateTime<Utc>,
+    /// 上传的存储桶
+    pub bucket: Option<String>,
+    /// 开始录像的用户ID，只有该用户(或管理员)可以停止录像，并以该用户的身份上传
+    pub user_id: u64,
+    /// 录像文件的路径
+    path: PathBuf,
+    /// 停止录像的发送者
+    stop_sender: Option<oneshot::Sender<()>>,
+    /// 写入录像的后台运行句柄，返回录制的时长(毫秒)，没有录到关键帧时返回None
+    task: Option<JoinHandle<Result<Option<u32>, std::io::Error>>>,
+}
+
+impl SessionRecording {
+    /// 开始录像
+    ///
+    /// ## 参数
+    /// * `data_receiver` - 会话FLV字节流的接收者
+    /// * `sequence_headers` - 会话当前的FLV文件头、脚本数据和序列头
+    /// * `path` - 录像文件的路径
+    /// * `max_duration` - 录像的最大时长，超过后停止写入
+    /// * `bucket` - 上传的存储桶
+    /// * `user_id` - 开始录像的用户ID
+    pub fn start(
+        data_receiver: Receiver<Bytes>,
+        sequence_headers: FlvSequenceHeaders,
+        path: PathBuf,
+        max_duration: Option<Duration>,
+        bucket: Option<String>,
+        user_id: u64,
+    ) -> Self {
+        let (stop_sender, stop_receiver) = oneshot::channel();
+        let task = tokio::spawn(Self::write(
+            data_receiver,
+            sequence_headers,
+            path.clone(),
+            max_duration,
+            stop_receiver,
+        ));
+        Self {
+            started_at: Utc::now(),
+            bucket,
+            user_id,
+            path,
+            stop_sender: Some(stop_sender),
+            task: Some(task),
+        }
+    }
+
+    /// 停止录像
+    ///
+    /// ## 返回值
+    /// 返回(录像文件的路径, 录制的时长毫秒)，文件由调用者负责删除
+    pub async fn stop(mut self) -> Result<(PathBuf, u32), std::io::Error> {
+        if let Some(stop_sender) = self.stop_sender.take() {
+            let _ = stop_sender.send(());
+        }
+        let task = self
+            .task
+            .take()
+            .ok_or_else(|| std::io::Error::other("录像已停止"))?;
+        let duration_ms = task.await.map_err(std::io::Error::other)??;
+        let path = std::mem::take(&mut self.path);
+        match duration_ms {
+            Some(duration_ms) => Ok((path, duration_ms)),
+            None => {
+                let _ = tokio::fs::remove_file(&path).await;
+                Err(std::io::Error::other("没有录到视频关键帧"))
+            }
+        }
+    }
+
+    /// 将会话的FLV字节流写入录像文件，直到停止录像、会话结束或达到最大时长
+    async fn write(
+        mut data_receiver: Receiver<Bytes>,
+        mut sequence_headers: FlvSequenceHeaders,
+        path: PathBuf,
+        max_duration: Option<Duration>,
+        mut stop_receiver: oneshot::Receiver<()>,
+    ) -> Result<Option<u32>, std::io::Error> {
+        let max_duration_ms =
+            max_duration.map(|max_duration| max_duration.as_millis().min(u32::MAX as u128) as u32);
+        // 从中途订阅的字节流不以标签开头，先输入FLV文件头，再重新定位标签的起始位置
+        let mut demuxer = FlvDemuxer::new();
+        demuxer.push(
+            &sequence_headers
+                .flv_header
+                .clone()
+                .unwrap_or_else(|| encode_flv_header(true, true)),
+        );
+        demuxer.resync();
+        let mut writer: Option<FlvSegmentWriter> = None;
+        let mut reached_max_duration = false;
+        loop {
+            let chunk = tokio::select! {
+                _ = &mut stop_receiver => break,
+                result = data_receiver.recv() => match result {
+                    Ok(chunk) => chunk,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("录像{path:?}滞后，丢弃了{skipped}块数据");
+                        demuxer.resync();
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            };
+            for tag in demuxer.push(&chunk) {
+                if sequence_headers.update(&tag) && writer.is_none() {
+                    continue;
+                }
+                match writer.as_mut() {
+                    Some(writer) => writer.write(&tag).await?,
+                    None if tag.is_keyframe() => {
+                        debug!("录像{path:?}从关键帧开始写入");
+                        writer =
+                            Some(FlvSegmentWriter::create(&path, &sequence_headers, &tag).await?);
+                    }
+                    None => {}
+                }
+            }
+            if let Some(writer) = writer.as_ref()
+                && max_duration_ms
+                    .is_some_and(|max_duration_ms| writer.duration_ms() >= max_duration_ms)
+            {
+                warn!("录像{path:?}达到最大时长，停止写入");
+                reached_max_duration = true;
+                break;
+            }
+        }
+        let duration_ms = match writer {
+            Some(writer) => Some(writer.finish().await?.1),
+            None => None,
+        };
+        if reached_max_duration {
+            // 释放订阅，使会话可以空闲回收，再等待调用者停止录像
+            drop(data_receiver);
+            let _ = stop_receiver.await;
+        }
+        Ok(duration_ms)
+    }
+}
+
+impl Drop for SessionRecording {
+    /// 未停止的录像被丢弃时，终止写入并在后台删除录像文件(不在异步运行时中阻塞)
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            warn!("录像{:?}未停止就被丢弃", self.path);
+            task.abort();
+            let path = std::mem::take(&mut self.path);
+            match tokio::runtime::Handle::try_current() {
+                Ok(handle) => {
+                    handle.spawn(async move {
+                        // 等待写入任务结束，避免删除后又被写入
+                        let _ = task.await;
+                        if let Err(e) = tokio::fs::remove_file(&path).await {
+                            debug!("删除未停止的录像{path:?}失败: {e}");
+                        }
+                    });
+                }
+                Err(_) => {
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,841 @@
+use crate::camera::camera_registry::CameraRegistry;
+use crate::config::capturer_config::{
+    get_capturer_config, SessionConfig, StallAction, TranscodeConfig,
+};
+use crate::ffmpeg::ffmpeg_budget::{get_ffmpeg_budget, BudgetPermit, BudgetPool};
+use crate::ffmpeg::ffmpeg_cmd::FfmpegCmd;
+use crate::ffmpeg::ffmpeg_eo::StreamMetadata;
+use crate::ffmpeg::ffmpeg_error::FfmpegError;
+use crate::ffmpeg::ffmpeg_process::FfmpegProcess;
+use crate::ffmpeg::ffmpeg_session::FfmpegSession;
+use crate::stream::flv_splicer::FlvSplicer;
+use crate::stream::flv_tag::{FlvChunk, FlvDemuxer, FlvSequenceHeaders, FlvTag};
+use crate::stream::stream_eo::SessionEvent;
+use crate::utils::url_utils::mask_stream_url;
+use bytes::Bytes;
+use chrono::Utc;
+use rustc_hash::FxHashMap;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, RwLock, Weak};
+use std::time::Duration;
+use tokio::sync::broadcast::{Receiver, Sender};
+use tokio::sync::{broadcast, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, sleep_until, Instant};
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tokio_util::task::TaskTracker;
+use tracing::{debug, error, info, warn};
+
+/// 子进程持续输出标签达到该时长才算稳定，之后退出时不计入连续失败的次数
+const HEALTHY_RUN_DURATION: Duration = Duration::from_secs(30);
+
+/// 会话事件的广播(流管理器被重新创建后继续使用)
+static SESSION_EVENTS: LazyLock<Sender<SessionEvent>> = LazyLock::new(|| broadcast::channel(64).0);
+
+/// 累计拉流卡住的次数
+static STALLS_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// 累计重新拉流成功的次数
+static RESPAWNS_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// 订阅所有会话的事件
+pub fn subscribe_session_events() -> Receiver<SessionEvent> {
+    SESSION_EVENTS.subscribe()
+}
+
+/// 累计拉流卡住的次数
+pub fn stalls_total() -> u64 {
+    STALLS_TOTAL.load(Ordering::Relaxed)
+}
+
+/// 累计重新拉流成功的次数
+pub fn respawns_total() -> u64 {
+    RESPAWNS_TOTAL.load(Ordering::Relaxed)
+}
+
+/// 会话的一次拉流(一个ffmpeg子进程)
+///
+/// 持有子进程，被丢弃时终止子进程
+pub struct SourceRun {
+    /// 子进程ID
+    pub child_id: u32,
+    /// 拉流的地址(主码流或备用的流地址)
+    pub stream_url: String,
+    /// ffmpeg子进程
+    process: FfmpegProcess,
+    /// 子进程输出的原始FLV字节流的接收者
+    raw_receiver: Receiver<Bytes>,
+}
+
+impl SourceRun {
+    /// 按探测到的流信息启动ffmpeg拉流
+    pub async fn spawn(
+        url: &str,
+        stream_metadata: &StreamMetadata,
+        transcode_config: &TranscodeConfig,
+        cmd_read_buffer_size: usize,
+        cmd_channel_capacity: usize,
+    ) -> Result<Self, FfmpegError> {
+        let (raw_sender, raw_receiver) = broadcast::channel(cmd_channel_capacity);
+        let process = FfmpegCmd::pull_and_transcode_stream(
+            url,
+            stream_metadata,
+            transcode_config,
+            raw_sender,
+            cmd_read_buffer_size,
+        )
+        .await?;
+        let child_id = process.id();
+        debug!("ffmpeg child pid: {child_id}");
+        Ok(Self {
+            child_id,
+            stream_url: url.to_string(),
+            process,
+            raw_receiver,
+        })
+    }
+
+    /// 终止未交给监督者的拉流子进程，并等待其退出
+    pub async fn terminate(self) {
+        self.process.terminate().await;
+    }
+}
+
+/// 一次拉流结束的原因
+enum PumpOutcome {
+    /// 子进程退出或拉流卡住
+    Exited {
+        /// 子进程是否稳定地输出过标签(从第一个标签起持续了一段时间)
+        healthy: bool,
+        /// 是否因为拉流卡住而终止
+        stalled: bool,
+    },
+    /// 主码流已恢复或重新拉流，切换到新的子进程
+    Switched(SourceRun),
+    /// 没有空闲的ffmpeg进程预算的许可，已终止子进程，用原来的许可立即重新拉流(不计入失败)
+    Restart,
+}
+
+/// 会话的监督者
+///
+/// 将ffmpeg子进程输出的FLV字节流解复用、拼接后转发给会话的观看者。
+/// 子进程退出(如摄像头掉线)时保留会话及其数据发送者，按退避间隔重新拉流，
+/// 并将新进程的输出拼接到原来的流中，观看者的连接不会中断。
+/// 子进程超过一定时间没有输出数据时认为拉流卡住，按配置终止子进程后重新拉流或关闭会话。
+/// 摄像头配置了备用的流地址时，主码流拉流失败或卡住后按顺序切换到备用的流地址(子码流先切换到主码流)，
+/// 并定时探测会话的流地址，恢复后切回。
+/// 收到重新拉流的通知(如转码的配置变化)时，先启动新的子进程再终止旧的，观看者的连接不会中断；
+/// 新旧子进程同时运行时各自占用ffmpeg进程预算的许可，没有空闲的许可时先终止旧的子进程再重新拉流。
+/// 监督者是会话的子进程的唯一所有者：会话被删除(销毁)后终止子进程并确认其已被回收，之后不再重新拉流；
+/// 连续重新拉流失败达到上限时删除会话
+pub struct SessionSupervisor {
+    /// 会话ID
+    session_id: u64,
+    /// 流地址(会话的键，即主码流)
+    url: String,
+    /// 当前拉流的地址
+    active_stream_url: String,
+    /// 会话当前拉流的地址(与会话共享)
+    session_active_stream_url: Arc<RwLock<String>>,
+    /// 会话存储映射表
+    sessions: Weak<RwLock<FxHashMap<String, FfmpegSession>>>,
+    /// 会话的取消令牌(会话被销毁或流管理器被销毁时取消)
+    cancel_token: CancellationToken,
+    /// 会话的后台任务(如探测主码流)的跟踪器(与流管理器共享)
+    tracker: TaskTracker,
+    /// 会话当前的子进程ID
+    child_id: Arc<AtomicU32>,
+    /// 会话的数据发送者
+    data_sender: Arc<Sender<Bytes>>,
+    /// 会话的FLV标签发送者
+    tag_sender: Arc<Sender<FlvTag>>,
+    /// 会话最近一次解析到的FLV文件头、脚本数据和序列头
+    sequence_headers: Arc<RwLock<FlvSequenceHeaders>>,
+    /// 会话最近一次拉流时探测到的流媒体元数据
+    stream_metadata: Arc<RwLock<StreamMetadata>>,
+    /// 会话重新拉流的次数
+    respawn_count: Arc<AtomicU64>,
+    /// 会话最后一次收到子进程输出数据的时间戳(毫秒)
+    last_data_ts: Arc<AtomicI64>,
+    /// 通知重新拉流
+    restart_notify: Arc<Notify>,
+    /// 命令读取缓冲区大小
+    cmd_read_buffer_size: usize,
+    /// 命令广播通道容量
+    cmd_channel_capacity: usize,
+    /// 拼接各次拉流输出的拼接器
+    splicer: FlvSplicer,
+    /// 连续重新拉流失败(拉流失败或子进程没有稳定地输出标签就退出)的次数
+    failures: u32,
+    /// 会话占用的ffmpeg进程预算的池
+    budget_pool: Arc<RwLock<BudgetPool>>,
+    /// 会话占用的ffmpeg进程预算的许可，会话结束时归还
+    budget_permit: BudgetPermit,
+    /// 拉流转码的配置(与会话共享，流管理器的配置变化时更新)
+    transcode_config: Arc<RwLock<TranscodeConfig>>,
+    /// 监督者退出时通知等待的一方(在归还许可之后)
+    _exited_guard: DropGuard,
+}
+
+impl SessionSupervisor {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        session: &FfmpegSession,
+        url: &str,
+        sessions: Weak<RwLock<FxHashMap<String, FfmpegSession>>>,
+        cancel_token: CancellationToken,
+        tracker: TaskTracker,
+        cmd_read_buffer_size: usize,
+        cmd_channel_capacity: usize,
+        budget_permit: BudgetPermit,
+    ) -> Self {
+        let active_stream_url = session
+            .active_stream_url
+            .read()
+            .map(|active_stream_url| active_stream_url.clone())
+            .unwrap_or_else(|_| url.to_string());
+        Self {
+            session_id: session.id,
+            url: url.to_string(),
+            active_stream_url,
+            session_active_stream_url: Arc::clone(&session.active_stream_url),
+            sessions,
+            cancel_token,
+            tracker,
+            child_id: Arc::clone(&session.child_id),
+            data_sender: Arc::clone(&session.data_sender),
+            tag_sender: Arc::clone(&session.tag_sender),
+            sequence_headers: Arc::clone(&session.sequence_headers),
+            stream_metadata: Arc::clone(&session.stream_metadata),
+            respawn_count: Arc::clone(&session.respawn_count),
+            last_data_ts: Arc::clone(&session.last_data_ts),
+            restart_notify: Arc::clone(&session.restart_notify),
+            cmd_read_buffer_size,
+            cmd_channel_capacity,
+            splicer: FlvSplicer::new(),
+            failures: 0,
+            budget_pool: Arc::clone(&session.budget_pool),
+            budget_permit,
+            transcode_config: Arc::clone(&session.transcode_config),
+            _exited_guard: session.supervisor_exited.clone().drop_guard(),
+        }
+    }
+
+    /// 转发第一次拉流的输出，子进程退出后重新拉流，直到会话被删除
+    pub async fn run(mut self, source_run: SourceRun) {
+        let session_id = self.session_id;
+        info!("<监督会话{session_id}>任务创建完成.");
+        let mut source_run = source_run;
+        loop {
+            let (stalled, restart) = match self.pump(source_run).await {
+                PumpOutcome::Switched(next_source_run) => {
+                    source_run = next_source_run;
+                    continue;
+                }
+                PumpOutcome::Restart => (false, true),
+                PumpOutcome::Exited { healthy, stalled } => {
+                    // 只输出了一会儿就退出的也算失败，避免反复崩溃的流永远不会关闭
+                    if healthy {
+                        self.failures = 0;
+                    } else {
+                        self.failures += 1;
+                    }
+                    (stalled, false)
+                }
+            };
+            match self.respawn(stalled, restart).await {
+                Some(next_source_run) => source_run = next_source_run,
+                None => break,
+            }
+        }
+        info!("<监督会话{session_id}>任务退出");
+    }
+
+    /// 转发一次拉流的输出，直到子进程退出、拉流卡住、会话被删除、切回主码流或重新拉流
+    ///
+    /// 返回前终止子进程并确认其已被回收(切换到新的一次拉流时在后台终止，之后归还其占用的许可)
+    async fn pump(&mut self, source_run: SourceRun) -> PumpOutcome {
+        let SourceRun {
+            child_id,
+            stream_url,
+            mut process,
+            mut raw_receiver,
+        } = source_run;
+        let (stall_timeout, stall_action, primary_retry_interval) = get_capturer_config()
+            .map(|capturer_config| {
+                (
+                    capturer_config.session.stall_timeout.unwrap_or_default(),
+                    capturer_config.session.stall_action,
+                    capturer_config
+                        .session
+                        .primary_retry_interval
+                        .unwrap_or(Duration::from_secs(60)),
+                )
+            })
+            .unwrap_or((
+                Duration::ZERO,
+                StallAction::default(),
+                Duration::from_secs(60),
+            ));
+        let mut stall_deadline = Instant::now() + stall_timeout;
+        // 使用备用的流地址时，定时探测主码流(在后台探测，不阻塞转发)
+        let retry_primary = stream_url != self.url && !primary_retry_interval.is_zero();
+        let mut primary_retry_deadline = Instant::now() + primary_retry_interval;
+        let mut primary_probe: Option<JoinHandle<Result<StreamMetadata, FfmpegError>>> = None;
+        let mut demuxer = FlvDemuxer::new();
+        let mut first_produced_at: Option<Instant> = None;
+        let mut stalled = false;
+        loop {
+            let bytes = tokio::select! {
+                result = raw_receiver.recv() => match result {
+                    Ok(bytes) => bytes,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("子进程{child_id}的输出滞后，跳过{skipped}条数据并重新定位标签");
+                        demuxer.resync();
+                        continue;
+                    }
+                },
+                _ = process.wait_exit() => {
+                    debug!("检测到子进程{child_id}已经退出");
+                    break;
+                }
+                _ = self.cancel_token.cancelled() => {
+                    debug!("会话{}已被删除，终止子进程{child_id}", self.session_id);
+                    break;
+                }
+                _ = self.restart_notify.notified() => {
+                    info!("会话{}的拉流配置已变化，重新拉流", self.session_id);
+                    let Ok(stream_metadata) = self
+                        .stream_metadata
+                        .read()
+                        .map(|stream_metadata| stream_metadata.clone())
+                    else {
+                        warn!("无法获取 stream_metadata 读锁");
+                        continue;
+                    };
+                    let Some(budget_permit) = Self::try_acquire_budget(&stream_metadata) else {
+                        // 不能同时运行新旧子进程，先终止当前的子进程，再用原来的许可重新拉流
+                        info!(
+                            "会话{}没有空闲的ffmpeg进程预算，终止子进程{child_id}后重新拉流",
+                            self.session_id
+                        );
+                        process.terminate().await;
+                        return PumpOutcome::Restart;
+                    };
+                    let stream_url = self.active_stream_url.clone();
+                    if let Some((next_source_run, old_budget_permit)) = self
+                        .switch_source(&stream_url, stream_metadata, budget_permit)
+                        .await
+                    {
+                        info!("会话{}已重新拉流，终止子进程{child_id}", self.session_id);
+                        self.retire(process, old_budget_permit);
+                        return PumpOutcome::Switched(next_source_run);
+                    }
+                    continue;
+                }
+                _ = sleep_until(stall_deadline), if !stall_timeout.is_zero() => {
+                    self.handle_stall(child_id, stall_timeout, stall_action);
+                    stalled = true;
+                    break;
+                }
+                _ = sleep_until(primary_retry_deadline), if retry_primary && primary_probe.is_none() => {
+                    primary_retry_deadline = Instant::now() + primary_retry_interval;
+                    let url = self.url.clone();
+                    let cancel_token = self.cancel_token.clone();
+                    primary_probe = Some(self.tracker.spawn(async move {
+                        tokio::select! {
+                            _ = cancel_token.cancelled() => {
+                                Err(FfmpegError::FfmpegShutdown(mask_stream_url(&url)))
+                            }
+                            result = FfmpegCmd::probe_stream_info(&url) => result,
+                        }
+                    }));
+                    continue;
+                }
+                result = async {
+                    match primary_probe.as_mut() {
+                        Some(primary_probe) => primary_probe.await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    primary_probe = None;
+                    match result {
+                        Ok(Ok(stream_metadata)) => {
+                            // 没有空闲的许可时继续使用备用的流地址，之后再探测
+                            let Some(budget_permit) = Self::try_acquire_budget(&stream_metadata)
+                            else {
+                                debug!(
+                                    "会话{}的主码流已恢复，但没有空闲的ffmpeg进程预算，稍后再切回",
+                                    self.session_id
+                                );
+                                continue;
+                            };
+                            let url = self.url.clone();
+                            if let Some((next_source_run, old_budget_permit)) =
+                                self.switch_source(&url, stream_metadata, budget_permit).await
+                            {
+                                info!(
+                                    "会话{}的主码流已恢复，切回主码流，终止子进程{child_id}",
+                                    self.session_id
+                                );
+                                self.retire(process, old_budget_permit);
+                                return PumpOutcome::Switched(next_source_run);
+                            }
+                        }
+                        Ok(Err(e)) => debug!("会话{}的主码流仍不可用: {:?}", self.session_id, e),
+                        Err(e) => warn!("探测会话{}的主码流的任务异常: {}", self.session_id, e),
+                    }
+                    continue;
+                }
+            };
+            stall_deadline = Instant::now() + stall_timeout;
+            self.last_data_ts
+                .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+            if self.forward(&mut demuxer, bytes) {
+                first_produced_at.get_or_insert_with(Instant::now);
+            }
+        }
+        if let Some(primary_probe) = primary_probe {
+            primary_probe.abort();
+        }
+        // 转发子进程退出前已经读取到的数据
+        while let Ok(bytes) = raw_receiver.try_recv() {
+            if self.forward(&mut demuxer, bytes) {
+                first_produced_at.get_or_insert_with(Instant::now);
+            }
+        }
+        process.terminate().await;
+        let healthy = first_produced_at
+            .is_some_and(|first_produced_at| first_produced_at.elapsed() >= HEALTHY_RUN_DURATION);
+        PumpOutcome::Exited { healthy, stalled }
+    }
+
+    /// 用新的许可从指定的流地址启动新的子进程，由调用者终止当前的子进程
+    ///
+    /// 新旧子进程同时运行，各自占用许可，新的子进程启动后会话改为占用新的许可
+    ///
+    /// ## 返回值
+    /// 返回新的一次拉流及当前的子进程占用的许可(由调用者在子进程被回收后归还)，
+    /// 启动失败时返回None(继续使用当前的子进程，归还新的许可)
+    async fn switch_source(
+        &mut self,
+        stream_url: &str,
+        stream_metadata: StreamMetadata,
+        budget_permit: BudgetPermit,
+    ) -> Option<(SourceRun, BudgetPermit)> {
+        let source_run = match SourceRun::spawn(
+            stream_url,
+            &stream_metadata,
+            &self.transcode_config(),
+            self.cmd_read_buffer_size,
+            self.cmd_channel_capacity,
+        )
+        .await
+        {
+            Ok(source_run) => source_run,
+            Err(e) => {
+                warn!(
+                    "会话{}从{}启动新的子进程失败: {:?}",
+                    self.session_id,
+                    mask_stream_url(stream_url),
+                    e
+                );
+                return None;
+            }
+        };
+        let old_budget_permit = self.replace_budget_permit(budget_permit);
+        self.activate(&source_run, stream_metadata);
+        Some((source_run, old_budget_permit))
+    }
+
+    /// 在后台终止被替换的子进程，子进程被回收后再归还其占用的许可
+    fn retire(&self, process: FfmpegProcess, budget_permit: BudgetPermit) {
+        self.tracker.spawn(async move {
+            process.terminate().await;
+            drop(budget_permit);
+        });
+    }
+
+    /// 拉流转码的配置(最新的配置)
+    fn transcode_config(&self) -> TranscodeConfig {
+        self.transcode_config
+            .read()
+            .map(|transcode_config| transcode_config.clone())
+            .unwrap_or_default()
+    }
+
+    /// 处理卡住的拉流
+    ///
+    /// 发出拉流卡住的事件，按处理方式关闭会话；子进程由调用者终止(之后重新拉流)
+    fn handle_stall(&self, child_id: u32, stalled_for: Duration, stall_action: StallAction) {
+        warn!(
+            "会话{}的子进程{child_id}超过{stalled_for:?}没有输出数据，拉流卡住，处理方式: {}",
+            self.session_id,
+            stall_action.name()
+        );
+        STALLS_TOTAL.fetch_add(1, Ordering::Relaxed);
+        let _ = SESSION_EVENTS.send(SessionEvent::Stalled {
+            session_id: self.session_id,
+            stream_url: self.url.clone(),
+            child_id,
+            stalled_for,
+            action: stall_action,
+        });
+        if stall_action == StallAction::Close {
+            self.remove_session();
+        }
+    }
+
+    /// 解复用一段数据，拼接后发送给会话的观看者
+    ///
+    /// 第一次拉流且没有标签的使用者时，普通的音视频标签原样转发，只解析脚本数据和序列头
+    ///
+    /// ## 返回值
+    /// 解析到标签时返回true
+    fn forward(&mut self, demuxer: &mut FlvDemuxer, bytes: Bytes) -> bool {
+        let assemble = !self.splicer.is_pass_through() || self.tag_sender.receiver_count() > 0;
+        let chunks = demuxer.push_lazy(bytes, assemble);
+        let Ok(mut sequence_headers_write_lock) = self.sequence_headers.write() else {
+            warn!("无法获取 sequence_headers 写锁");
+            return !chunks.is_empty();
+        };
+        // 第一次拉流的FLV文件头直接发送给观看者，之后的拉流沿用第一次的文件头
+        if sequence_headers_write_lock.flv_header.is_none()
+            && let Some(flv_header) = demuxer.flv_header()
+        {
+            sequence_headers_write_lock.flv_header = Some(flv_header.clone());
+            let _ = self.data_sender.send(flv_header.clone());
+        }
+        let produced = !chunks.is_empty();
+        let mut spliced_chunks = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            let tag = match chunk {
+                FlvChunk::Tag(tag) => tag,
+                FlvChunk::Raw {
+                    data,
+                    last_timestamp,
+                } => {
+                    self.splicer.observe(last_timestamp);
+                    spliced_chunks.push(FlvChunk::Raw {
+                        data,
+                        last_timestamp,
+                    });
+                    continue;
+                }
+            };
+            let Some(tag) = self.splicer.splice(tag, &sequence_headers_write_lock) else {
+                continue;
+            };
+            if sequence_headers_write_lock.update(&tag) && self.splicer.splice_count() > 0 {
+                info!(
+                    "会话{}重新拉流后编码参数变化，重新发送头部信息",
+                    self.session_id
+                );
+            }
+            spliced_chunks.push(FlvChunk::Tag(tag));
+        }
+        drop(sequence_headers_write_lock);
+
+        for chunk in spliced_chunks {
+            match chunk {
+                FlvChunk::Tag(tag) => {
+                    if self.data_sender.receiver_count() > 0 {
+                        let _ = self.data_sender.send(tag.to_bytes());
+                    }
+                    if self.tag_sender.receiver_count() > 0 {
+                        let _ = self.tag_sender.send(tag);
+                    }
+                }
+                FlvChunk::Raw { data, .. } => {
+                    if self.data_sender.receiver_count() > 0 {
+                        let _ = self.data_sender.send(data);
+                    }
+                }
+            }
+        }
+        produced
+    }
+
+    /// 子进程退出后按退避间隔重新拉流
+    ///
+    /// 拉流卡住时先切换到下一个流地址，否则先重试当前的流地址；
+    /// 主动重新拉流(`restart`，如转码的配置变化)时立即拉流，之后失败时才按退避间隔重试
+    ///
+    /// ## 返回值
+    /// 重新拉流成功时返回新的一次拉流；会话已被删除或连续失败达到上限时返回None
+    async fn respawn(&mut self, stalled: bool, restart: bool) -> Option<SourceRun> {
+        let SessionConfig {
+            respawn_initial_interval,
+            respawn_max_interval,
+            respawn_max_attempts,
+            ..
+        } = get_capturer_config().ok()?.session.clone();
+        let initial = respawn_initial_interval.unwrap_or(Duration::from_secs(1));
+        let max = respawn_max_interval.unwrap_or(Duration::from_secs(30));
+        let mut skip_active = stalled;
+        let mut immediate = restart;
+        loop {
+            if !self.is_session_alive() {
+                debug!("会话{}已被删除，不再重新拉流", self.session_id);
+                return None;
+            }
+            if respawn_max_attempts > 0 && self.failures >= respawn_max_attempts {
+                error!(
+                    "会话{}连续{}次重新拉流失败，关闭会话",
+                    self.session_id, self.failures
+                );
+                self.remove_session();
+                return None;
+            }
+            if std::mem::take(&mut immediate) {
+                info!(
+                    "会话{}立即重新拉流: {}",
+                    self.session_id,
+                    mask_stream_url(&self.url)
+                );
+            } else {
+                let delay = initial
+                    .checked_mul(2u32.saturating_pow(self.failures))
+                    .unwrap_or(max)
+                    .min(max);
+                warn!(
+                    "会话{}的子进程已退出，{delay:?}后重新拉流: {}",
+                    self.session_id,
+                    mask_stream_url(&self.url)
+                );
+                tokio::select! {
+                    _ = sleep(delay) => {}
+                    _ = self.cancel_token.cancelled() => {}
+                }
+                if !self.is_session_alive() {
+                    debug!("会话{}已被删除，不再重新拉流", self.session_id);
+                    return None;
+                }
+            }
+            match self.spawn_source(std::mem::take(&mut skip_active)).await {
+                Ok(source_run) => {
+                    // 等待期间会话可能已被删除，此时会话不会再终止新的子进程
+                    if !self.is_session_alive() {
+                        source_run.process.terminate().await;
+                        return None;
+                    }
+                    return Some(source_run);
+                }
+                Err(e) => {
+                    error!("会话{}重新拉流失败: {:?}", self.session_id, e);
+                    self.failures += 1;
+                }
+            }
+        }
+    }
+
+    /// 重新探测流信息并启动新的ffmpeg子进程
+    ///
+    /// 从当前的流地址(或其下一个)开始，依次尝试会话的流地址及可以切换的流地址
+    async fn spawn_source(&mut self, skip_active: bool) -> Result<SourceRun, FfmpegError> {
+        let stream_urls = Self::stream_urls(&self.url);
+        let active_index = stream_urls
+            .iter()
+            .position(|stream_url| *stream_url == self.active_stream_url)
+            .unwrap_or_default();
+        let start = if skip_active {
+            active_index + 1
+        } else {
+            active_index
+        };
+        let mut last_error = None;
+        for offset in 0..stream_urls.len() {
+            let stream_url = &stream_urls[(start + offset) % stream_urls.len()];
+            let result = match FfmpegCmd::probe_stream_info(stream_url).await {
+                Ok(stream_metadata) => match self.rebudget(&stream_metadata).await {
+                    Ok(()) => SourceRun::spawn(
+                        stream_url,
+                        &stream_metadata,
+                        &self.transcode_config(),
+                        self.cmd_read_buffer_size,
+                        self.cmd_channel_capacity,
+                    )
+                    .await
+                    .map(|source_run| (source_run, stream_metadata)),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok((source_run, stream_metadata)) => {
+                    self.activate(&source_run, stream_metadata);
+                    self.respawn_count.fetch_add(1, Ordering::Relaxed);
+                    info!(
+                        "会话{}重新拉流成功，新的子进程: {}",
+                        self.session_id, source_run.child_id
+                    );
+                    RESPAWNS_TOTAL.fetch_add(1, Ordering::Relaxed);
+                    let _ = SESSION_EVENTS.send(SessionEvent::Respawned {
+                        session_id: self.session_id,
+                        stream_url: self.url.clone(),
+                        child_id: source_run.child_id,
+                    });
+                    return Ok(source_run);
+                }
+                Err(e) => {
+                    warn!(
+                        "会话{}从{}拉流失败: {:?}",
+                        self.session_id,
+                        mask_stream_url(stream_url),
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| FfmpegError::FfmpegSessionRead("没有可用的流地址".to_string())))
+    }
+
+    /// 按流信息确定会话占用的ffmpeg进程预算的池：需要转码时为转码会话池，否则为直通会话池
+    pub fn budget_pool_of(stream_metadata: &StreamMetadata) -> BudgetPool {
+        if FfmpegCmd::needs_transcode(stream_metadata) {
+            BudgetPool::Transcode
+        } else {
+            BudgetPool::Passthrough
+        }
+    }
+
+    /// 按新探测到的流信息调整会话占用的ffmpeg进程预算的池
+    ///
+    /// 重新拉流后编码可能变化(如摄像头改为H.265或切换到备用的流地址)，
+    /// 需要的池不同时先获取新池的许可再归还原来的许可，获取不到时返回错误
+    async fn rebudget(&mut self, stream_metadata: &StreamMetadata) -> Result<(), FfmpegError> {
+        let budget_pool = Self::budget_pool_of(stream_metadata);
+        let old_budget_pool = self.budget_permit.pool();
+        if budget_pool == old_budget_pool {
+            return Ok(());
+        }
+        let budget = get_ffmpeg_budget()?;
+        let budget_permit = match budget.try_acquire(budget_pool) {
+            Some(budget_permit) => budget_permit,
+            None => budget.acquire(budget_pool).await?,
+        };
+        self.replace_budget_permit(budget_permit);
+        Ok(())
+    }
+
+    /// 立即获取按流信息拉流需要的许可(替换子进程时新旧子进程同时运行)，没有空闲的许可时返回None
+    fn try_acquire_budget(stream_metadata: &StreamMetadata) -> Option<BudgetPermit> {
+        get_ffmpeg_budget()
+            .ok()?
+            .try_acquire(Self::budget_pool_of(stream_metadata))
+    }
+
+    /// 替换会话占用的许可，池变化时同步到会话，返回原来的许可
+    fn replace_budget_permit(&mut self, budget_permit: BudgetPermit) -> BudgetPermit {
+        let old_budget_pool = self.budget_permit.pool();
+        let budget_pool = budget_permit.pool();
+        if budget_pool != old_budget_pool {
+            info!(
+                "会话{}的编码变化，ffmpeg进程预算的池: {} -> {}",
+                self.session_id,
+                old_budget_pool.name(),
+                budget_pool.name()
+            );
+            if let Ok(mut budget_pool_write_lock) = self.budget_pool.write() {
+                *budget_pool_write_lock = budget_pool;
+            }
+        }
+        std::mem::replace(&mut self.budget_permit, budget_permit)
+    }
+
+    /// 切换到新的一次拉流
+    ///
+    /// 更新会话的子进程ID、流媒体元数据及当前拉流的地址，并开始拼接新进程的输出
+    fn activate(&mut self, source_run: &SourceRun, stream_metadata: StreamMetadata) {
+        if let Ok(mut stream_metadata_write_lock) = self.stream_metadata.write() {
+            *stream_metadata_write_lock = stream_metadata;
+        }
+        self.child_id.store(source_run.child_id, Ordering::Relaxed);
+        self.splicer.restart();
+        if source_run.stream_url != self.active_stream_url {
+            info!(
+                "会话{}切换拉流的地址: {} -> {}",
+                self.session_id,
+                mask_stream_url(&self.active_stream_url),
+                mask_stream_url(&source_run.stream_url)
+            );
+            self.active_stream_url = source_run.stream_url.clone();
+            if let Ok(mut session_active_stream_url) = self.session_active_stream_url.write() {
+                *session_active_stream_url = source_run.stream_url.clone();
+            }
+            let _ = SESSION_EVENTS.send(SessionEvent::SourceSwitched {
+                session_id: self.session_id,
+                stream_url: self.url.clone(),
+                active_stream_url: source_run.stream_url.clone(),
+            });
+        }
+    }
+
+    /// 依次探测会话的流地址及可以切换的流地址，返回第一个可用的流地址及探测到的流信息
+    ///
+    /// 都不可用时返回会话的流地址的错误
+    pub async fn probe_sources(url: &str) -> Result<(String, StreamMetadata), FfmpegError> {
+        let primary_error = match FfmpegCmd::probe_stream_info(url).await {
+            Ok(stream_metadata) => return Ok((url.to_string(), stream_metadata)),
+            Err(e) => e,
+        };
+        for stream_url in Self::stream_urls(url).into_iter().skip(1) {
+            match FfmpegCmd::probe_stream_info(&stream_url).await {
+                Ok(stream_metadata) => {
+                    warn!(
+                        "流地址{}不可用，切换到流地址{}",
+                        mask_stream_url(url),
+                        mask_stream_url(&stream_url)
+                    );
+                    return Ok((stream_url, stream_metadata));
+                }
+                Err(e) => warn!(
+                    "探测切换的流地址{}失败: {:?}",
+                    mask_stream_url(&stream_url),
+                    e
+                ),
+            }
+        }
+        Err(primary_error)
+    }
+
+    /// 会话可以拉流的地址：会话的流地址在前，之后是可以切换的流地址(子码流时含主码流)
+    fn stream_urls(url: &str) -> Vec<String> {
+        let mut stream_urls = vec![url.to_string()];
+        stream_urls.extend(CameraRegistry::fallback_stream_urls(url));
+        stream_urls
+    }
+
+    /// 会话是否仍在会话映射表中(会话的令牌已被取消时视为已被删除)
+    fn is_session_alive(&self) -> bool {
+        !self.cancel_token.is_cancelled()
+            && self.sessions.upgrade().is_some_and(|sessions| {
+                sessions.read().is_ok_and(|sessions_read_lock| {
+                    sessions_read_lock
+                        .get(&self.url)
+                        .is_some_and(|session| session.id == self.session_id)
+                })
+            })
+    }
+
+    /// 从会话映射表中删除会话(会话被删除时终止子进程)
+    fn remove_session(&self) {
+        let Some(sessions) = self.sessions.upgrade() else {
+            return;
+        };
+        let Ok(mut sessions_write_lock) = sessions.write() else {
+            warn!("无法获取 sessions 写锁");
+            return;
+        };
+        if sessions_write_lock
+            .get(&self.url)
+            .is_some_and(|session| session.id == self.session_id)
+        {
+            debug!("删除会话: {}", mask_stream_url(&self.url));
+            sessions_write_lock.remove(&self.url);
+        }
+    }
+}
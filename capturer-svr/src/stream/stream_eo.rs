@@ -0,0 +1,144 @@
+use crate::config::capturer_config::StallAction;
+use crate::ffmpeg::ffmpeg_budget::BudgetPool;
+use crate::stream::flv_tag::{FlvSequenceHeaders, FlvTag};
+use crate::utils::url_utils::mask_stream_url;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::time::Duration;
+
+/// 常驻会话的事件
+///
+/// 常驻会话在没有观看者时也保持拉流，断开后自动重新拉流，
+/// 订阅者通过事件得知会话的关联、断开及收到的标签
+#[derive(Debug)]
+pub enum PinnedSessionEvent {
+    /// 关联到会话(首次拉流或会话关闭后重新拉流)，携带会话当前的头部信息
+    Attached(FlvSequenceHeaders),
+    /// 会话的FLV标签
+    Tag(FlvTag),
+    /// 会话已断开
+    Detached,
+}
+
+/// 会话的事件
+///
+/// 由会话的监督者发出，通过[`subscribe_session_events`](crate::stream::session_supervisor::subscribe_session_events)订阅；
+/// 流管理器订阅后记录日志，并按配置回调webhook
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    /// 拉流卡住(子进程超过一定时间没有输出数据)
+    Stalled {
+        /// 会话ID
+        session_id: u64,
+        /// 流地址
+        stream_url: String,
+        /// 卡住的子进程ID
+        child_id: u32,
+        /// 没有输出数据的时长
+        stalled_for: Duration,
+        /// 处理方式
+        action: StallAction,
+    },
+    /// 子进程退出后重新拉流成功
+    Respawned {
+        /// 会话ID
+        session_id: u64,
+        /// 流地址
+        stream_url: String,
+        /// 新的子进程ID
+        child_id: u32,
+    },
+    /// 切换拉流的地址(主码流不可用时切换到备用的流地址，主码流恢复后切回)
+    SourceSwitched {
+        /// 会话ID
+        session_id: u64,
+        /// 流地址(主码流)
+        stream_url: String,
+        /// 切换后拉流的地址
+        active_stream_url: String,
+    },
+}
+
+impl SessionEvent {
+    /// 事件的名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            SessionEvent::Stalled { .. } => "stalled",
+            SessionEvent::Respawned { .. } => "respawned",
+            SessionEvent::SourceSwitched { .. } => "source-switched",
+        }
+    }
+
+    /// 回调webhook的内容(流地址已脱敏)
+    pub fn to_payload(&self) -> serde_json::Value {
+        let event_ts = Utc::now().timestamp_millis();
+        match self {
+            SessionEvent::Stalled {
+                session_id,
+                stream_url,
+                child_id,
+                stalled_for,
+                action,
+            } => json!({
+                "event": self.name(),
+                "eventTs": event_ts,
+                "sessionId": session_id,
+                "streamUrl": mask_stream_url(stream_url),
+                "childId": child_id,
+                "stalledMs": stalled_for.as_millis() as u64,
+                "action": action.name(),
+            }),
+            SessionEvent::Respawned {
+                session_id,
+                stream_url,
+                child_id,
+            } => json!({
+                "event": self.name(),
+                "eventTs": event_ts,
+                "sessionId": session_id,
+                "streamUrl": mask_stream_url(stream_url),
+                "childId": child_id,
+            }),
+            SessionEvent::SourceSwitched {
+                session_id,
+                stream_url,
+                active_stream_url,
+            } => json!({
+                "event": self.name(),
+                "eventTs": event_ts,
+                "sessionId": session_id,
+                "streamUrl": mask_stream_url(stream_url),
+                "activeStreamUrl": mask_stream_url(active_stream_url),
+            }),
+        }
+    }
+}
+
+/// 会话的概况
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    /// 会话ID
+    pub id: u64,
+    /// 流地址
+    pub stream_url: String,
+    /// 当前拉流的地址(主码流不可用时为备用的流地址)
+    pub active_stream_url: String,
+    /// 当前的子进程ID
+    pub child_id: u32,
+    /// 会话占用的ffmpeg进程预算的池
+    pub budget_pool: BudgetPool,
+    /// 子进程退出后重新拉流的次数
+    pub respawn_count: u64,
+    /// 最后一次收到子进程输出数据的时间，还没有收到数据时为None
+    pub last_data_at: Option<DateTime<Utc>>,
+    /// 会话创建的时间
+    pub created_at: DateTime<Utc>,
+    /// 字节流的接收者数量
+    pub receiver_count: usize,
+    /// 开始空闲的时间，活跃中为None
+    pub idle_since: Option<DateTime<Utc>>,
+    /// 按需录像开始的时间，没有录像时为None
+    pub recording_started_at: Option<DateTime<Utc>>,
+    /// 开始按需录像的用户ID，没有录像时为None
+    pub recording_user_id: Option<u64>,
+}
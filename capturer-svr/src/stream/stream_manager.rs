@@ -1,24 +1,41 @@
 use crate::config::capturer_config::{
-    get_capturer_config, CapturerConfig, CmdConfig, SessionConfig,
+    get_capturer_config, CameraConfig, CapturerConfig, CmdConfig, RingBufferConfig, SessionConfig,
+    TranscodeConfig,
 };
-use crate::ffmpeg::ffmpeg_cmd::FfmpegCmd;
+use crate::ffmpeg::ffmpeg_budget::{get_ffmpeg_budget, BudgetPool};
+use crate::ffmpeg::ffmpeg_eo::StreamMetadata;
 use crate::ffmpeg::ffmpeg_error::FfmpegError;
 use crate::ffmpeg::ffmpeg_session::FfmpegSession;
+use crate::stream::flv_ring_buffer::FlvRingBuffer;
+use crate::stream::flv_tag::{FlvSequenceHeaders, FlvTag};
+use crate::stream::session_recording::SessionRecording;
+use crate::stream::session_supervisor::{subscribe_session_events, SessionSupervisor, SourceRun};
+use crate::stream::stream_eo::{PinnedSessionEvent, SessionInfo};
+use crate::utils::url_utils::mask_stream_url;
+use crate::utils::webhook_utils::post_webhook;
 use arc_swap::ArcSwap;
 use bytes::Bytes;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use robotech::cfg::CfgError;
 use rustc_hash::FxHashMap;
-use std::sync::{Arc, OnceLock, RwLock};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, Weak};
 use std::time::Duration;
 use tokio::sync::broadcast::Receiver;
-use tokio::sync::{broadcast, oneshot};
-use tokio::time::interval;
+use tokio::sync::{broadcast, mpsc, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, sleep, timeout};
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tokio_util::task::TaskTracker;
 use tracing::{debug, error, info, trace, warn};
 
 /// 全局静态的流管理器实例
 static STREAM_MANAGER: OnceLock<ArcSwap<StreamManager>> = OnceLock::new();
 
+/// 会话ID的序号(流管理器被重新创建后继续递增，保证进程内唯一)
+static SESSION_SEQ: AtomicU64 = AtomicU64::new(1);
+
 pub fn init_stream_manager(capturer_config: CapturerConfig) -> Result<(), CfgError> {
     info!("初始化流管理器");
     STREAM_MANAGER
@@ -36,35 +53,65 @@ pub fn get_stream_manager() -> Result<Arc<StreamManager>, CfgError> {
         .clone())
 }
 
+/// 关闭流管理器(程序退出时)，见[`StreamManager::shutdown`]
+pub async fn shutdown_stream_manager() -> Result<(), CfgError> {
+    let shutdown_grace_period = get_capturer_config()?
+        .session
+        .shutdown_grace_period
+        .unwrap_or(Duration::from_secs(10));
+    get_stream_manager()?.shutdown(shutdown_grace_period).await;
+    Ok(())
+}
+
+/// 按新的配置更新流管理器
+///
+/// 不替换流管理器实例，正在进行的会话继续保留，见[`StreamManager::apply_config`]
 pub fn update_stream_manager(capturer_config: CapturerConfig) -> Result<(), CfgError> {
-    if let Some(swap) = STREAM_MANAGER.get() {
-        swap.store(Arc::new(StreamManager::new(capturer_config)?));
-        Ok(())
-    } else {
-        Err(CfgError::NotInit(
-            "StreamManager not initialized".to_string(),
-        ))
-    }
+    get_stream_manager()?.apply_config(capturer_config);
+    Ok(())
 }
 
 /// 流管理器，负责管理ffmpeg流会话
 ///
 /// 该管理器维护一个会话映射表，用于存储和管理所有活动的流会话。
 /// 它还负责定期清理过期的会话，并处理会话生命周期相关事件。
+/// 热加载配置时不替换流管理器，新的设置应用到现有的流管理器上，正在进行的会话继续保留。
 pub struct StreamManager {
+    /// 可以热加载的设置(与后台任务共享)
+    settings: Arc<ArcSwap<StreamSettings>>,
+    /// 会话存储映射表，使用URL作为键，FfmpegSession作为值
+    sessions: Arc<RwLock<FxHashMap<String, FfmpegSession>>>,
+    /// 预录缓冲映射表，使用URL作为键
+    ring_buffers: Mutex<FxHashMap<String, RingBufferStream>>,
+    /// 常驻拉流的摄像头映射表，使用摄像头ID作为键
+    always_on_cameras: Mutex<FxHashMap<String, AlwaysOnCamera>>,
+    /// 流管理器及其会话的后台任务
+    tasks: ManagerTasks,
+}
+
+/// 流管理器可以热加载的设置
+///
+/// 新的设置由之后的检查、重新拉流及新的会话使用
+#[derive(Debug, Clone, PartialEq)]
+struct StreamSettings {
     /// 命令读取缓冲区大小
     cmd_read_buffer_size: usize,
     /// 命令广播通道容量
     cmd_channel_capacity: usize,
-    /// 会话存储映射表，使用URL作为键，FfmpegSession作为值
-    sessions: Arc<RwLock<FxHashMap<String, FfmpegSession>>>,
+    /// 常驻会话断开后重新拉流的间隔
+    reconnect_interval: Duration,
+    /// 会话超时检查间隔
+    session_timeout_check_interval: Duration,
+    /// 会话超时时间
+    session_timeout_period: Duration,
+    /// 拉流转码的配置
+    transcode: TranscodeConfig,
+    /// 会话事件回调的地址
+    session_event_webhook: Option<String>,
 }
 
-impl StreamManager {
-    /// 创建一个新的流管理器实例
-    ///
-    /// 该函数会从配置中读取相关设置，并启动后台任务来定期清理过期会话。
-    pub fn new(capturer_config: CapturerConfig) -> Result<Self, CfgError> {
+impl StreamSettings {
+    fn new(capturer_config: &CapturerConfig) -> Self {
         let CmdConfig {
             read_buffer_size: cmd_read_buffer_size,
             channel_capacity: cmd_channel_capacity,
@@ -73,35 +120,255 @@ impl StreamManager {
         let SessionConfig {
             timeout_check_interval: Some(session_timeout_check_interval),
             timeout_period: Some(session_timeout_period),
+            reconnect_interval,
             ..
         } = capturer_config.session
         else {
             unreachable!("会话超时检查间隔和超时时间必须配置");
         };
+        Self {
+            cmd_read_buffer_size,
+            cmd_channel_capacity,
+            reconnect_interval: reconnect_interval.unwrap_or(Duration::from_secs(5)),
+            session_timeout_check_interval,
+            session_timeout_period,
+            transcode: capturer_config.transcode.clone(),
+            session_event_webhook: capturer_config.session.event_webhook.clone(),
+        }
+    }
+}
+
+/// 流管理器的后台任务
+///
+/// 流管理器及其会话的后台任务都由同一个跟踪器跟踪；
+/// 会话的取消令牌是流管理器的取消令牌的子令牌，流管理器被销毁时所有任务都会收到取消的通知
+#[derive(Clone)]
+struct ManagerTasks {
+    /// 流管理器的取消令牌
+    cancel_token: CancellationToken,
+    /// 后台任务的跟踪器
+    tracker: TaskTracker,
+}
+
+impl ManagerTasks {
+    /// 创建可以单独取消的后台任务(流管理器被销毁时同样会被取消)
+    fn child(&self) -> Self {
+        Self {
+            cancel_token: self.cancel_token.child_token(),
+            tracker: self.tracker.clone(),
+        }
+    }
+}
+
+/// 开启预录缓冲的流
+struct RingBufferStream {
+    /// 预录缓冲
+    ring_buffer: Arc<Mutex<FlvRingBuffer>>,
+    /// 不再预录缓冲时，取消保持常驻会话的任务
+    _cancel_guard: DropGuard,
+}
+
+/// 常驻拉流的摄像头
+struct AlwaysOnCamera {
+    /// 常驻拉流的流地址
+    stream_url: String,
+    /// 保持常驻会话的后台运行句柄
+    task: JoinHandle<()>,
+}
+
+impl Drop for AlwaysOnCamera {
+    /// 摄像头不再常驻拉流时，终止保持常驻会话的后台运行
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for StreamManager {
+    /// 流管理器被销毁后，通知后台任务退出
+    fn drop(&mut self) {
+        debug!("流管理器被销毁，取消后台任务");
+        self.tasks.cancel_token.cancel();
+        self.tasks.tracker.close();
+    }
+}
+
+impl StreamManager {
+    /// 创建一个新的流管理器实例
+    ///
+    /// 该函数会从配置中读取相关设置，并启动后台任务来定期清理过期会话，
+    /// 以及为开启预录缓冲的流和常驻拉流的摄像头保持常驻会话。
+    /// 流管理器被销毁后，这些后台任务及会话的后台任务都会被取消。
+    pub fn new(capturer_config: CapturerConfig) -> Result<Self, CfgError> {
+        let settings = Arc::new(ArcSwap::from_pointee(StreamSettings::new(&capturer_config)));
 
         // 创建会话容器
         let sessions: Arc<RwLock<FxHashMap<String, FfmpegSession>>> =
             Arc::new(RwLock::new(FxHashMap::default()));
+        let tasks = ManagerTasks {
+            cancel_token: CancellationToken::new(),
+            tracker: TaskTracker::new(),
+        };
 
         debug!("<定时清除过期会话>任务正在创建....");
-        let sessions_clone = Arc::clone(&sessions);
-        tokio::spawn(async move {
-            let mut interval = interval(session_timeout_check_interval);
+        let sessions_weak = Arc::downgrade(&sessions);
+        let cleanup_settings = Arc::clone(&settings);
+        let cancel_token = tasks.cancel_token.clone();
+        tasks.tracker.spawn(async move {
             info!(
-                "<定时清除过期会话>任务创建完成. 定时检查间隔: {session_timeout_check_interval:?}"
+                "<定时清除过期会话>任务创建完成. 定时检查间隔: {:?}",
+                cleanup_settings.load().session_timeout_check_interval
             );
             loop {
-                interval.tick().await;
-                Self::cleanup_expired_sessions(sessions_clone.clone(), session_timeout_period)
-                    .await;
+                // 每次检查前读取设置，热加载后的检查间隔和超时时间在下一次检查时生效
+                let session_timeout_check_interval =
+                    cleanup_settings.load().session_timeout_check_interval;
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    _ = sleep(session_timeout_check_interval) => {}
+                }
+                let Some(sessions) = sessions_weak.upgrade() else {
+                    break;
+                };
+                let session_timeout_period = cleanup_settings.load().session_timeout_period;
+                Self::cleanup_expired_sessions(sessions, session_timeout_period).await;
             }
+            info!("流管理器已被销毁，<定时清除过期会话>任务退出");
         });
 
-        Ok(Self {
-            cmd_read_buffer_size,
-            cmd_channel_capacity,
+        debug!("<转发会话事件>任务正在创建....");
+        let mut session_events = subscribe_session_events();
+        let events_settings = Arc::clone(&settings);
+        let cancel_token = tasks.cancel_token.clone();
+        tasks.tracker.spawn(async move {
+            info!("<转发会话事件>任务创建完成.");
+            loop {
+                let event = tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    result = session_events.recv() => match result {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("会话事件滞后，跳过{skipped}个事件");
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                };
+                let payload = event.to_payload();
+                debug!("会话事件: {payload}");
+                // 每次回调前读取设置，热加载后的回调地址立即生效
+                let Some(webhook) = events_settings.load().session_event_webhook.clone() else {
+                    continue;
+                };
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    result = post_webhook(&webhook, &payload) => match result {
+                        Ok(()) => debug!("会话事件<{}>回调webhook成功", event.name()),
+                        Err(e) => warn!("会话事件<{}>回调webhook失败: {e}", event.name()),
+                    },
+                }
+            }
+            info!("流管理器已被销毁，<转发会话事件>任务退出");
+        });
+
+        let stream_manager = Self {
+            settings,
             sessions,
-        })
+            ring_buffers: Mutex::new(FxHashMap::default()),
+            always_on_cameras: Mutex::new(FxHashMap::default()),
+            tasks,
+        };
+        // 为开启预录缓冲的流创建缓冲并保持常驻会话
+        stream_manager.sync_ring_buffers(capturer_config.ring_buffer);
+        // 先为配置文件中常驻拉流的摄像头保持常驻会话，数据库中的摄像头由摄像头登记同步
+        stream_manager.sync_always_on_cameras(capturer_config.cameras);
+        Ok(stream_manager)
+    }
+
+    /// 按新的配置更新流管理器(热加载配置)
+    ///
+    /// 不替换流管理器实例，正在进行的会话继续保留：
+    /// - 新的超时、重连间隔及命令缓冲等设置由之后的检查、重新拉流及新的会话使用
+    /// - 按新的配置同步预录缓冲的流，未变化的流保留已缓冲的标签
+    /// - 转码的配置变化时，正在转码的会话平滑地重新拉流(先启动新的进程再终止旧的，观看者的连接不中断)
+    ///
+    /// 常驻拉流的摄像头由摄像头登记同步
+    pub fn apply_config(&self, capturer_config: CapturerConfig) {
+        info!("流管理器应用新的配置");
+        let settings = StreamSettings::new(&capturer_config);
+        let old_settings = self.settings.swap(Arc::new(settings.clone()));
+        if old_settings.transcode != settings.transcode {
+            self.apply_transcode_config(&settings.transcode);
+        }
+        self.sync_ring_buffers(capturer_config.ring_buffer);
+    }
+
+    /// 更新所有会话的转码配置，并通知正在转码的会话按新的转码配置重新拉流
+    ///
+    /// 直通的会话之后重新拉流(如编码变化需要转码)时同样使用新的转码配置
+    fn apply_transcode_config(&self, transcode_config: &TranscodeConfig) {
+        let Ok(sessions_read_lock) = self.sessions.read() else {
+            warn!("无法获取会话读锁");
+            return;
+        };
+        let mut restart_count = 0;
+        for session in sessions_read_lock.values() {
+            if let Ok(mut transcode_config_write_lock) = session.transcode_config.write() {
+                *transcode_config_write_lock = transcode_config.clone();
+            }
+            if session.budget_pool() == BudgetPool::Transcode {
+                session.restart_notify.notify_one();
+                restart_count += 1;
+            }
+        }
+        info!("转码的配置已变化，{restart_count}个转码会话重新拉流");
+    }
+
+    /// 同步开启预录缓冲的流
+    ///
+    /// - 未变化的流继续缓冲，按新的配置调整缓冲的时长
+    /// - 新增的流创建缓冲并开始保持常驻会话
+    /// - 不再预录缓冲的流停止保持常驻会话(会话在没有观看者后按超时回收)
+    fn sync_ring_buffers(&self, ring_buffer_config: RingBufferConfig) {
+        let RingBufferConfig {
+            stream_urls,
+            duration,
+            ..
+        } = ring_buffer_config;
+        let duration = duration.unwrap_or(Duration::from_secs(30));
+        let Ok(mut ring_buffers_lock) = self.ring_buffers.lock() else {
+            error!("无法获取预录缓冲的锁");
+            return;
+        };
+        ring_buffers_lock.retain(|url, _| stream_urls.contains(url));
+        for url in stream_urls {
+            if let Some(ring_buffer_stream) = ring_buffers_lock.get(&url) {
+                if let Ok(mut ring_buffer_lock) = ring_buffer_stream.ring_buffer.lock() {
+                    ring_buffer_lock.set_duration(duration);
+                }
+                continue;
+            }
+            let ring_buffer = Arc::new(Mutex::new(FlvRingBuffer::new(duration)));
+            let tasks = self.tasks.child();
+            let events = Self::spawn_pinned_session(
+                url.clone(),
+                Arc::downgrade(&self.sessions),
+                Arc::clone(&self.settings),
+                tasks.clone(),
+            );
+            debug!("<预录缓冲{}>任务正在创建....", mask_stream_url(&url));
+            tasks.tracker.spawn(Self::fill_ring_buffer(
+                url.clone(),
+                events,
+                Arc::downgrade(&ring_buffer),
+            ));
+            ring_buffers_lock.insert(
+                url,
+                RingBufferStream {
+                    ring_buffer,
+                    _cancel_guard: tasks.cancel_token.drop_guard(),
+                },
+            );
+        }
     }
 
     /// 获取指定URL的命令接收者
@@ -117,8 +384,7 @@ impl StreamManager {
     ///
     /// 返回一个包含以下元素的元组：
     /// * `Receiver<Bytes>`: 命令接收者
-    /// * `Arc<OnceLock<Bytes>>`: 视频头部信息缓存
-    /// * `Option<oneshot::Sender<Bytes>>`: 缓存头部信息发送器（仅新会话有）
+    /// * `Option<Bytes>`: 已存在的会话当前的FLV文件头、脚本数据和序列头（新会话为None）
     ///
     /// # 错误处理
     ///
@@ -126,20 +392,69 @@ impl StreamManager {
     pub async fn get_cmd_receiver(
         &self,
         url: &str,
-    ) -> Result<
-        (
-            Receiver<Bytes>,
-            Arc<OnceLock<Bytes>>,
-            Option<oneshot::Sender<Bytes>>,
-        ),
-        FfmpegError,
-    > {
-        info!("获取命令接收者: {}", url);
+    ) -> Result<(Receiver<Bytes>, Option<Bytes>), FfmpegError> {
+        Self::open_cmd_receiver(&self.sessions, &self.settings.load_full(), url, &self.tasks).await
+    }
+
+    /// 关闭流管理器(程序退出时)
+    ///
+    /// 停止保持常驻会话及预录缓冲，关闭所有会话，并等待会话的ffmpeg进程被终止并回收；
+    /// 超过宽限时间仍有后台任务没有退出时不再等待。关闭后不再创建新的会话
+    pub async fn shutdown(&self, grace_period: Duration) {
+        info!("关闭流管理器，等待所有会话关闭");
+        if let Ok(mut always_on_cameras_lock) = self.always_on_cameras.lock() {
+            always_on_cameras_lock.clear();
+        }
+        if let Ok(mut ring_buffers_lock) = self.ring_buffers.lock() {
+            ring_buffers_lock.clear();
+        }
+        self.tasks.cancel_token.cancel();
+        self.tasks.tracker.close();
+        // 删除会话，会话的监督者终止子进程并确认其已被回收后退出
+        if let Ok(mut sessions_write_lock) = self.sessions.write() {
+            info!("关闭{}个会话", sessions_write_lock.len());
+            sessions_write_lock.clear();
+        }
+        match timeout(grace_period, self.tasks.tracker.wait()).await {
+            Ok(()) => info!("流管理器已关闭，所有会话的ffmpeg进程已退出"),
+            Err(_) => warn!(
+                "超过{grace_period:?}仍有{}个后台任务没有退出，不再等待",
+                self.tasks.tracker.len()
+            ),
+        }
+    }
+
+    /// 流管理器及其会话的后台任务的跟踪器
+    ///
+    /// 流管理器被销毁后跟踪器被关闭，可以等待所有后台任务退出
+    pub fn task_tracker(&self) -> TaskTracker {
+        self.tasks.tracker.clone()
+    }
+
+    /// 获取指定URL的命令接收者，不存在会话时创建新会话
+    ///
+    /// 不依赖流管理器实例，供后台任务在流管理器之外使用；
+    /// 新会话的后台任务随会话被删除或流管理器被销毁而取消
+    async fn open_cmd_receiver(
+        sessions: &Arc<RwLock<FxHashMap<String, FfmpegSession>>>,
+        settings: &StreamSettings,
+        url: &str,
+        tasks: &ManagerTasks,
+    ) -> Result<(Receiver<Bytes>, Option<Bytes>), FfmpegError> {
+        info!("获取命令接收者: {}", mask_stream_url(url));
+        // 流管理器关闭后不再创建新的会话
+        if tasks.cancel_token.is_cancelled() {
+            return Err(FfmpegError::FfmpegShutdown(mask_stream_url(url)));
+        }
+        let StreamSettings {
+            cmd_read_buffer_size,
+            cmd_channel_capacity,
+            ..
+        } = *settings;
         let capturer_config = get_capturer_config()?;
         let cmd_receiver_count_check_interval =
             capturer_config.cmd.receiver_count_check_interval.unwrap();
 
-        let sessions = &self.sessions;
         {
             debug!("获取会话读锁...");
             let sessions_read_lock = sessions.read().map_err(|e| {
@@ -147,98 +462,139 @@ impl StreamManager {
                 FfmpegError::FfmpegSessionRead("无法获取会话读锁".to_string())
             })?;
 
-            debug!("检查会话是否存在: {}", url);
+            debug!("检查会话是否存在: {}", mask_stream_url(url));
             if let Some(session) = sessions_read_lock.get(url) {
-                {
-                    debug!("获取 last_access_datetime 写锁...");
-                    let mut last_access_datetime_write_lock =
-                        session.last_access_datetime.write().map_err(|e| {
-                            error!("无法获取 last_access_datetime 写锁: {}", e);
-                            FfmpegError::FfmpegSessionRead(
-                                "无法获取 last_access_datetime 写锁".to_string(),
-                            )
-                        })?;
-                    debug!("更新最后访问时间为None（表示活跃中）: {:?}", Utc::now());
-                    *last_access_datetime_write_lock = None;
-                }
-
-                debug!("返回命令接收者...");
-                return Ok((
-                    session.data_sender.subscribe(),
-                    Arc::clone(&session.header),
-                    None,
-                ));
+                return Self::subscribe_session(session);
             }
         }
 
         debug!("创建新会话...");
-        let (data_sender, data_receiver) = broadcast::channel(self.cmd_channel_capacity);
-        let (process_exit_sender, process_exit_receiver) = oneshot::channel();
-        let (cache_header_sender, cache_header_receiver) = oneshot::channel();
-        let last_access_datetime = Arc::new(RwLock::new(None));
-
-        // 拉流并解码
-        let child = Arc::new(
-            FfmpegCmd::pull_and_transcode_stream(
-                url,
-                data_sender.clone(),
-                process_exit_sender,
-                self.cmd_read_buffer_size,
-            )
-            .await?,
-        );
+        // 先探测流信息(主码流不可用时探测备用的流地址)，按是否需要转码获取ffmpeg进程预算，
+        // 预算用尽时先关闭空闲的会话
+        let (source_url, stream_metadata) = SessionSupervisor::probe_sources(url).await?;
+        let budget_pool = SessionSupervisor::budget_pool_of(&stream_metadata);
+        let budget = get_ffmpeg_budget()?;
+        let budget_permit = match budget.try_acquire(budget_pool) {
+            Some(budget_permit) => budget_permit,
+            None => {
+                if capturer_config.budget.evict_idle_sessions
+                    && let Some(supervisor_exited) = Self::evict_idle_session(sessions, budget_pool)
+                {
+                    // 等待被关闭的会话终止子进程并归还许可，避免新的会话先排队超时
+                    let wait_timeout = capturer_config
+                        .budget
+                        .session_acquire_timeout
+                        .unwrap_or(Duration::from_secs(10));
+                    if timeout(wait_timeout, supervisor_exited.cancelled())
+                        .await
+                        .is_err()
+                    {
+                        warn!("等待被关闭的空闲会话退出超时");
+                    }
+                }
+                budget.acquire(budget_pool).await?
+            }
+        };
 
-        let child_id = child
-            .id()
-            .ok_or_else(|| FfmpegError::FfmpegSessionRead("无法获取子进程ID".to_string()))?;
-        debug!("ffmpeg child pid: {child_id}");
+        // 拉流并解码，之后由会话的监督者转发输出及重新拉流
+        let source_run = SourceRun::spawn(
+            &source_url,
+            &stream_metadata,
+            &settings.transcode,
+            cmd_read_buffer_size,
+            cmd_channel_capacity,
+        )
+        .await?;
+        let child_id = source_run.child_id;
 
         info!("<子进程{child_id}>会话正在创建....");
+        let (data_sender, data_receiver) = broadcast::channel(cmd_channel_capacity);
+        let (tag_sender, _) = broadcast::channel(cmd_channel_capacity);
         let data_sender = Arc::new(data_sender);
-        let header = Arc::new(OnceLock::new());
+        let session_id = SESSION_SEQ.fetch_add(1, Ordering::Relaxed);
+        let cancel_token = tasks.cancel_token.child_token();
         let session = FfmpegSession {
-            child_id,
-            last_access_datetime,
+            id: session_id,
+            created_at: Utc::now(),
+            child_id: Arc::new(AtomicU32::new(child_id)),
+            last_access_datetime: Arc::new(RwLock::new(None)),
             data_sender: Arc::clone(&data_sender),
-            header: Arc::clone(&header),
+            tag_sender: Arc::new(tag_sender),
+            sequence_headers: Arc::new(RwLock::new(FlvSequenceHeaders::default())),
+            stream_metadata: Arc::new(RwLock::new(stream_metadata)),
+            active_stream_url: Arc::new(RwLock::new(source_url)),
+            respawn_count: Arc::new(AtomicU64::new(0)),
+            last_data_ts: Arc::new(AtomicI64::new(0)),
+            budget_pool: Arc::new(RwLock::new(budget_pool)),
+            transcode_config: Arc::new(RwLock::new(settings.transcode.clone())),
+            recording: Arc::new(Mutex::new(None)),
+            restart_notify: Arc::new(Notify::new()),
+            cancel_guard: cancel_token.clone().drop_guard(),
+            supervisor_exited: CancellationToken::new(),
         };
+        let supervisor = SessionSupervisor::new(
+            &session,
+            url,
+            Arc::downgrade(sessions),
+            cancel_token.clone(),
+            tasks.tracker.clone(),
+            cmd_read_buffer_size,
+            cmd_channel_capacity,
+            budget_permit,
+        );
+        let tag_sender = Arc::clone(&session.tag_sender);
+        let last_access_datetime = Arc::clone(&session.last_access_datetime);
         // 插入新会话到会话映射表
-        {
+        let existing_subscription = {
             debug!("获取会话写锁...");
             let mut sessions_write_lock = sessions.write().map_err(|e| {
                 error!("无法获取会话写锁: {}", e);
                 FfmpegError::FfmpegSessionRead("无法获取会话写锁".to_string())
             })?;
-            sessions_write_lock.insert(url.to_string(), session.clone());
+            // 探测及拉流期间并发的请求可能已创建了同一URL的会话，此时复用已有的会话
+            if let Some(existing_session) = sessions_write_lock.get(url) {
+                Some(Self::subscribe_session(existing_session)?)
+            } else {
+                sessions_write_lock.insert(url.to_string(), session);
+                None
+            }
+        };
+        if let Some(existing_subscription) = existing_subscription {
+            info!("<子进程{child_id}>已有并发创建的会话，复用该会话并关闭新拉流的子进程");
+            source_run.terminate().await;
+            // 子进程退出后再归还ffmpeg进程预算的许可
+            drop(supervisor);
+            return Ok(existing_subscription);
         }
         info!("<子进程{child_id}>会话创建完成.");
 
         // 启动监听接收者数量的任务
-        info!("<监听子进程{child_id}接收者数量>任务正在创建....");
-        let session_clone = Arc::new(session);
-        tokio::spawn(async move {
+        info!("<监听会话{session_id}接收者数量>任务正在创建....");
+        tasks.tracker.spawn(async move {
             let mut interval = interval(cmd_receiver_count_check_interval);
             info!(
-                "<监听子进程{child_id}接收者数量>任务创建完成. 定时检查间隔: {cmd_receiver_count_check_interval:?}"
+                "<监听会话{session_id}接收者数量>任务创建完成. 定时检查间隔: {cmd_receiver_count_check_interval:?}"
             );
             loop {
-                interval.tick().await;
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
 
-                if data_sender.receiver_count() == 0 {
+                if data_sender.receiver_count() == 0 && tag_sender.receiver_count() == 0 {
                     trace!("获取 last_access_datetime 读锁...");
-                    let last_access_datetime = if let Ok(last_access_datetime_read_lock) =
-                        session_clone.last_access_datetime.read()
-                    {
-                        *last_access_datetime_read_lock
-                    } else {
-                        warn!("无法获取 last_access_datetime 读锁");
-                        continue;
-                    };
-                    if last_access_datetime.is_none() {
-                        info!("子进程{child_id}接收者数量==0，记录会话过期时间");
+                    let last_access_at =
+                        if let Ok(last_access_datetime_read_lock) = last_access_datetime.read() {
+                            *last_access_datetime_read_lock
+                        } else {
+                            warn!("无法获取 last_access_datetime 读锁");
+                            continue;
+                        };
+                    if last_access_at.is_none() {
+                        info!("会话{session_id}接收者数量==0，记录会话过期时间");
                         trace!("获取 last_access_datetime 写锁...");
                         if let Ok(mut last_access_datetime_write_lock) =
-                            session_clone.last_access_datetime.write()
+                            last_access_datetime.write()
                         {
                             let now = Utc::now();
                             debug!("更新最后访问时间为当前时间: {:?}", Utc::now());
@@ -249,77 +605,436 @@ impl StreamManager {
                     }
                 }
             }
+            info!("会话{session_id}已关闭，<监听会话{session_id}接收者数量>任务退出");
         });
 
-        // 启动监听缓存头部的任务
-        info!("<监听子进程{child_id}缓存头部>任务正在创建....");
-        let header_clone = Arc::clone(&header);
-        let cache_header_receiver_clone = cache_header_receiver; // 重命名变量避免混淆
-        tokio::spawn(async move {
-            info!("<监听子进程{child_id}缓存头部>任务创建完成.");
-            if let Ok(bytes) = cache_header_receiver_clone.await {
-                debug!("子进程{child_id}缓存头部: {:?}", bytes);
-                let _ = header_clone.set(bytes);
-            } else {
-                warn!("无法接收缓存头部数据");
+        // 启动监督会话的任务
+        info!("<监督会话{session_id}>任务正在创建....");
+        tasks.tracker.spawn(supervisor.run(source_run));
+
+        Ok((data_receiver, None))
+    }
+
+    /// 订阅已有的会话，标记会话为活跃中
+    ///
+    /// 中途加入的观看者先收到会话当前的头部信息，之后的数据都是完整的标签
+    fn subscribe_session(
+        session: &FfmpegSession,
+    ) -> Result<(Receiver<Bytes>, Option<Bytes>), FfmpegError> {
+        {
+            debug!("获取 last_access_datetime 写锁...");
+            let mut last_access_datetime_write_lock =
+                session.last_access_datetime.write().map_err(|e| {
+                    error!("无法获取 last_access_datetime 写锁: {}", e);
+                    FfmpegError::FfmpegSessionRead("无法获取 last_access_datetime 写锁".to_string())
+                })?;
+            debug!("更新最后访问时间为None（表示活跃中）: {:?}", Utc::now());
+            *last_access_datetime_write_lock = None;
+        }
+
+        debug!("返回命令接收者...");
+        let header = session
+            .sequence_headers
+            .read()
+            .ok()
+            .filter(|sequence_headers| sequence_headers.flv_header.is_some())
+            .map(|sequence_headers| sequence_headers.encode());
+        Ok((session.data_sender.subscribe(), header))
+    }
+
+    /// 获取指定URL的会话最近一次拉流时探测到的流媒体元数据
+    ///
+    /// 如果该URL没有正在进行的会话，则返回None
+    pub fn get_stream_metadata(&self, url: &str) -> Option<StreamMetadata> {
+        let sessions_read_lock = self.sessions.read().ok()?;
+        let session = sessions_read_lock.get(url)?;
+        let stream_metadata = session.stream_metadata.read().ok()?.clone();
+        Some(stream_metadata)
+    }
+
+    /// 列出正在进行的会话
+    pub fn list_sessions(&self) -> Vec<SessionInfo> {
+        let Ok(sessions_read_lock) = self.sessions.read() else {
+            warn!("无法获取会话读锁");
+            return Vec::new();
+        };
+        let mut session_infos: Vec<SessionInfo> = sessions_read_lock
+            .iter()
+            .map(|(url, session)| Self::session_info(url, session))
+            .collect();
+        session_infos.sort_by_key(|session_info| session_info.id);
+        session_infos
+    }
+
+    /// 获取指定ID的会话的概况
+    pub fn find_session(&self, id: u64) -> Result<SessionInfo, FfmpegError> {
+        let sessions_read_lock = self.sessions.read().map_err(|e| {
+            error!("无法获取会话读锁: {}", e);
+            FfmpegError::FfmpegSessionRead("无法获取会话读锁".to_string())
+        })?;
+        sessions_read_lock
+            .iter()
+            .find(|(_, session)| session.id == id)
+            .map(|(url, session)| Self::session_info(url, session))
+            .ok_or(FfmpegError::FfmpegSessionNotFound(id))
+    }
+
+    /// 在会话读锁内生成会话的概况
+    fn session_info(url: &str, session: &FfmpegSession) -> SessionInfo {
+        let recording = session.recording.lock().ok();
+        let recording = recording.as_ref().and_then(|recording| recording.as_ref());
+        SessionInfo {
+            id: session.id,
+            stream_url: url.to_string(),
+            active_stream_url: session
+                .active_stream_url
+                .read()
+                .map(|active_stream_url| active_stream_url.clone())
+                .unwrap_or_else(|_| url.to_string()),
+            child_id: session.child_id.load(Ordering::Relaxed),
+            budget_pool: session.budget_pool(),
+            respawn_count: session.respawn_count.load(Ordering::Relaxed),
+            last_data_at: Some(session.last_data_ts.load(Ordering::Relaxed))
+                .filter(|last_data_ts| *last_data_ts > 0)
+                .and_then(DateTime::from_timestamp_millis),
+            created_at: session.created_at,
+            receiver_count: session.data_sender.receiver_count(),
+            idle_since: session
+                .last_access_datetime
+                .read()
+                .ok()
+                .and_then(|last_access_datetime| *last_access_datetime),
+            recording_started_at: recording.map(|recording| recording.started_at),
+            recording_user_id: recording.map(|recording| recording.user_id),
+        }
+    }
+
+    /// 开始会话的按需录像
+    ///
+    /// 复用会话的字节流广播，不另外启动ffmpeg；录像期间会话保持活跃
+    ///
+    /// ## 返回值
+    /// 返回开始录像的时间
+    pub fn start_session_recording(
+        &self,
+        id: u64,
+        path: PathBuf,
+        max_duration: Option<Duration>,
+        bucket: Option<String>,
+        user_id: u64,
+    ) -> Result<DateTime<Utc>, FfmpegError> {
+        self.with_session(id, |session| {
+            let mut recording_lock = session
+                .recording
+                .lock()
+                .map_err(|_| FfmpegError::FfmpegSessionRecording("无法获取录像锁".to_string()))?;
+            if recording_lock.is_some() {
+                return Err(FfmpegError::FfmpegSessionRecording(format!(
+                    "会话{id}正在录像"
+                )));
             }
-        });
+            let sequence_headers = session
+                .sequence_headers
+                .read()
+                .map(|sequence_headers| sequence_headers.clone())
+                .unwrap_or_default();
+            let recording = SessionRecording::start(
+                session.data_sender.subscribe(),
+                sequence_headers,
+                path,
+                max_duration,
+                bucket,
+                user_id,
+            );
+            let started_at = recording.started_at;
+            *recording_lock = Some(recording);
+            Ok(started_at)
+        })
+    }
+
+    /// 取出会话正在进行的按需录像，由调用者停止录像
+    pub fn take_session_recording(&self, id: u64) -> Result<SessionRecording, FfmpegError> {
+        self.with_session(id, |session| {
+            session
+                .recording
+                .lock()
+                .map_err(|_| FfmpegError::FfmpegSessionRecording("无法获取录像锁".to_string()))?
+                .take()
+                .ok_or_else(|| FfmpegError::FfmpegSessionRecording(format!("会话{id}没有在录像")))
+        })
+    }
 
-        // 启动监听子进程退出的任务
-        info!("<监听子进程{child_id}退出>任务正在创建....");
-        let sessions_clone = Arc::clone(&self.sessions);
-        tokio::spawn(async move {
-            info!("<监听子进程{child_id}退出>任务创建完成.");
-            if let Ok(_) = process_exit_receiver.await {
-                debug!("检测到子进程{child_id}已经退出");
-                Self::remove_session_after_process_exit(sessions_clone, child_id).await;
+    /// 在会话读锁内访问指定ID的会话
+    ///
+    /// 会话不能克隆，只能在读锁内访问
+    fn with_session<T>(
+        &self,
+        id: u64,
+        f: impl FnOnce(&FfmpegSession) -> Result<T, FfmpegError>,
+    ) -> Result<T, FfmpegError> {
+        let sessions_read_lock = self.sessions.read().map_err(|e| {
+            error!("无法获取会话读锁: {}", e);
+            FfmpegError::FfmpegSessionRead("无法获取会话读锁".to_string())
+        })?;
+        let session = sessions_read_lock
+            .values()
+            .find(|session| session.id == id)
+            .ok_or(FfmpegError::FfmpegSessionNotFound(id))?;
+        f(session)
+    }
+
+    /// 订阅指定URL正在进行的会话的FLV标签
+    ///
+    /// 如果该URL没有正在进行的会话，则返回None；
+    /// 否则返回标签接收者及当前的FLV文件头、脚本数据和序列头，并将会话标记为活跃中
+    pub fn subscribe_tags(&self, url: &str) -> Option<(Receiver<FlvTag>, FlvSequenceHeaders)> {
+        if let Some(session) = self.sessions.read().ok()?.get(url) {
+            if let Ok(mut last_access_datetime_write_lock) = session.last_access_datetime.write() {
+                *last_access_datetime_write_lock = None;
             } else {
-                warn!("监听子进程退出通道异常关闭");
+                warn!("无法获取 last_access_datetime 写锁");
             }
-        });
+        }
+        let (_, tag_receiver, sequence_headers) =
+            Self::subscribe_session_tags(&self.sessions, url)?;
+        Some((tag_receiver, sequence_headers))
+    }
 
-        Ok((data_receiver, header, Some(cache_header_sender)))
+    /// 截取指定URL预录缓冲中事件前的标签，并开始跟随后续的标签
+    ///
+    /// 如果该URL没有开启预录缓冲，则返回None；
+    /// 否则返回(头部信息, 事件前的标签, 后续标签的接收者)，见[`FlvRingBuffer::follow`]
+    pub fn follow_ring_buffer(
+        &self,
+        url: &str,
+        pre_ms: u32,
+    ) -> Option<(FlvSequenceHeaders, Vec<FlvTag>, mpsc::Receiver<FlvTag>)> {
+        let ring_buffer = Arc::clone(&self.ring_buffers.lock().ok()?.get(url)?.ring_buffer);
+        let mut ring_buffer_lock = ring_buffer.lock().ok()?;
+        Some(ring_buffer_lock.follow(pre_ms))
     }
 
-    /// 进程退出后删除会话
+    /// 保持指定URL的常驻会话，并通过返回的接收者订阅会话的事件
     ///
-    /// 当ffmpeg子进程退出时，该函数会被调用以清理对应的会话记录。
+    /// 没有观看者时也保持拉流，会话断开后间隔一段时间重新拉流；
+    /// 接收者被丢弃或流管理器被销毁(此时接收者会收到None)后，停止保持会话
+    pub fn pin_session(&self, url: &str) -> mpsc::Receiver<PinnedSessionEvent> {
+        Self::spawn_pinned_session(
+            url.to_string(),
+            Arc::downgrade(&self.sessions),
+            Arc::clone(&self.settings),
+            self.tasks.clone(),
+        )
+    }
+
+    /// 启动保持常驻会话的任务，返回会话事件的接收者
     ///
-    /// # 参数
+    /// 接收者被丢弃或流管理器被销毁时，任务在等待重新拉流期间也会立即退出
+    fn spawn_pinned_session(
+        url: String,
+        sessions: Weak<RwLock<FxHashMap<String, FfmpegSession>>>,
+        settings: Arc<ArcSwap<StreamSettings>>,
+        tasks: ManagerTasks,
+    ) -> mpsc::Receiver<PinnedSessionEvent> {
+        let (event_sender, event_receiver) = mpsc::channel(settings.load().cmd_channel_capacity);
+        debug!("<常驻会话{}>任务正在创建....", mask_stream_url(&url));
+        let tracker = tasks.tracker.clone();
+        tracker.spawn(async move {
+            info!("<常驻会话{}>任务创建完成.", mask_stream_url(&url));
+            let cancel_token = tasks.cancel_token.clone();
+            let closed_sender = event_sender.clone();
+            tokio::select! {
+                _ = cancel_token.cancelled() => {}
+                _ = closed_sender.closed() => {}
+                _ = Self::run_pinned_session(
+                    &url,
+                    sessions,
+                    &settings,
+                    event_sender,
+                    &tasks,
+                ) => {}
+            }
+            info!("<常驻会话{}>任务退出", mask_stream_url(&url));
+        });
+        event_receiver
+    }
+
+    /// 保持常驻会话，并将会话的事件发送给订阅者
     ///
-    /// * `sessions`: 会话存储映射表的引用
-    /// * `child_id`: 退出的子进程ID
-    async fn remove_session_after_process_exit(
-        sessions: Arc<RwLock<FxHashMap<String, FfmpegSession>>>,
-        child_id: u32,
+    /// 每隔重连间隔检查一次会话是否仍然存在，会话断开后重新拉流。
+    /// 每次重新拉流时读取最新的配置
+    async fn run_pinned_session(
+        url: &str,
+        sessions: Weak<RwLock<FxHashMap<String, FfmpegSession>>>,
+        settings: &ArcSwap<StreamSettings>,
+        event_sender: mpsc::Sender<PinnedSessionEvent>,
+        tasks: &ManagerTasks,
     ) {
-        info!("子进程{child_id}退出后删除会话");
-        let mut remove_key = None;
+        loop {
+            let current_settings = settings.load_full();
+            let reconnect_interval = current_settings.reconnect_interval;
+            let Some(sessions_arc) = sessions.upgrade() else {
+                return;
+            };
+            // 只需要解复用后的标签，命令接收者直接丢弃
+            if let Err(e) =
+                Self::open_cmd_receiver(&sessions_arc, &current_settings, url, tasks).await
+            {
+                error!("常驻会话{}拉流失败: {:?}", mask_stream_url(&url), e);
+                drop(sessions_arc);
+                sleep(reconnect_interval).await;
+                continue;
+            }
+            let subscription = Self::subscribe_session_tags(&sessions_arc, url);
+            drop(sessions_arc);
+            let Some((session_id, mut tag_receiver, sequence_headers)) = subscription else {
+                sleep(reconnect_interval).await;
+                continue;
+            };
+            if event_sender
+                .send(PinnedSessionEvent::Attached(sequence_headers))
+                .await
+                .is_err()
+            {
+                return;
+            }
 
-        debug!("获取 sessions 读锁...");
-        if let Ok(sessions_read_lock) = sessions.read() {
-            debug!("查找要删除子进程{child_id}的会话");
-            for (key, session) in sessions_read_lock.iter() {
-                if session.child_id == child_id {
-                    remove_key = Some(key.clone());
-                    break;
+            info!("常驻会话{}关联到会话{session_id}", mask_stream_url(&url));
+            let mut check_interval = interval(reconnect_interval);
+            loop {
+                tokio::select! {
+                    result = tag_receiver.recv() => match result {
+                        Ok(tag) => {
+                            if event_sender.send(PinnedSessionEvent::Tag(tag)).await.is_err() {
+                                return;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("常驻会话{}滞后，跳过{skipped}个标签", mask_stream_url(&url));
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = check_interval.tick() => {
+                        let Some(sessions_arc) = sessions.upgrade() else {
+                            return;
+                        };
+                        let alive = sessions_arc.read().is_ok_and(|sessions_read_lock| {
+                            sessions_read_lock
+                                .get(url)
+                                .is_some_and(|session| session.id == session_id)
+                        });
+                        if !alive {
+                            break;
+                        }
+                    }
                 }
             }
-        } else {
-            warn!("无法获取 sessions 读锁");
+            warn!(
+                "常驻会话{}已断开，{reconnect_interval:?}后重新拉流",
+                mask_stream_url(&url)
+            );
+            if event_sender
+                .send(PinnedSessionEvent::Detached)
+                .await
+                .is_err()
+            {
+                return;
+            }
+            sleep(reconnect_interval).await;
+        }
+    }
+
+    /// 将常驻会话的标签写入预录缓冲
+    async fn fill_ring_buffer(
+        url: String,
+        mut events: mpsc::Receiver<PinnedSessionEvent>,
+        ring_buffer: Weak<Mutex<FlvRingBuffer>>,
+    ) {
+        info!("<预录缓冲{}>任务创建完成.", mask_stream_url(&url));
+        while let Some(event) = events.recv().await {
+            let Some(ring_buffer) = ring_buffer.upgrade() else {
+                break;
+            };
+            let Ok(mut ring_buffer_lock) = ring_buffer.lock() else {
+                warn!("无法获取预录缓冲{}的锁", mask_stream_url(&url));
+                continue;
+            };
+            match event {
+                PinnedSessionEvent::Attached(sequence_headers) => {
+                    ring_buffer_lock.attach(sequence_headers);
+                }
+                PinnedSessionEvent::Tag(tag) => ring_buffer_lock.push(tag),
+                PinnedSessionEvent::Detached => {}
+            }
+        }
+        info!(
+            "不再预录缓冲或流管理器已被销毁，<预录缓冲{}>任务退出",
+            mask_stream_url(&url)
+        );
+    }
+
+    /// 同步常驻拉流的摄像头
+    ///
+    /// - 流地址未变化的摄像头继续保持会话
+    /// - 新增或流地址变化的摄像头开始保持会话
+    /// - 不再常驻拉流的摄像头停止保持会话(会话在没有观看者后按超时回收)
+    pub fn sync_always_on_cameras(&self, cameras: Vec<CameraConfig>) {
+        let Ok(mut always_on_cameras_lock) = self.always_on_cameras.lock() else {
+            error!("无法获取常驻摄像头的锁");
             return;
+        };
+        let cameras: Vec<CameraConfig> = cameras
+            .into_iter()
+            .filter(|camera| camera.always_on)
+            .collect();
+        info!("同步{}个常驻拉流的摄像头", cameras.len());
+        always_on_cameras_lock.retain(|id, always_on_camera| {
+            cameras.iter().any(|camera| {
+                &camera.id == id && camera.main_stream_url == always_on_camera.stream_url
+            })
+        });
+        for camera in cameras {
+            if always_on_cameras_lock.contains_key(&camera.id) {
+                continue;
+            }
+            let events = self.pin_session(&camera.main_stream_url);
+            debug!("<常驻摄像头{}>任务正在创建....", camera.id);
+            let task = self
+                .tasks
+                .tracker
+                .spawn(Self::keep_always_on(camera.id.clone(), events));
+            always_on_cameras_lock.insert(
+                camera.id,
+                AlwaysOnCamera {
+                    stream_url: camera.main_stream_url,
+                    task,
+                },
+            );
         }
+    }
 
-        // 删除找到的会话
-        if let Some(remove_key) = remove_key {
-            debug!("删除会话: {}", remove_key);
-            debug!("获取 sessions 写锁...");
-            if let Ok(mut sessions_write_lock) = sessions.write() {
-                sessions_write_lock.remove(&remove_key);
-            } else {
-                warn!("无法获取 sessions 写锁");
+    /// 保持常驻摄像头的会话，只记录会话的关联和断开
+    async fn keep_always_on(camera_id: String, mut events: mpsc::Receiver<PinnedSessionEvent>) {
+        info!("<常驻摄像头{camera_id}>任务创建完成.");
+        while let Some(event) = events.recv().await {
+            match event {
+                PinnedSessionEvent::Attached(_) => info!("常驻摄像头{camera_id}已拉流"),
+                PinnedSessionEvent::Detached => warn!("常驻摄像头{camera_id}的会话已断开"),
+                PinnedSessionEvent::Tag(_) => {}
             }
         }
+        info!("<常驻摄像头{camera_id}>任务退出");
+    }
+
+    /// 订阅会话的FLV标签，返回(会话ID, 标签接收者, 当前的头部信息)
+    fn subscribe_session_tags(
+        sessions: &RwLock<FxHashMap<String, FfmpegSession>>,
+        url: &str,
+    ) -> Option<(u64, Receiver<FlvTag>, FlvSequenceHeaders)> {
+        let sessions_read_lock = sessions.read().ok()?;
+        let session = sessions_read_lock.get(url)?;
+        let sequence_headers = session.sequence_headers.read().ok()?.clone();
+        Some((session.id, session.tag_sender.subscribe(), sequence_headers))
     }
 
     /// 清理超过一定时间未访问的会话
@@ -343,17 +1058,18 @@ impl StreamManager {
             for (key, session) in sessions_read_lock.iter() {
                 debug!("获取 last_access_datetime 读锁...");
                 if let Ok(last_access_datetime_read_lock) = session.last_access_datetime.read() {
-                    debug!("检查会话{key}是否过期....");
+                    debug!("检查会话{}是否过期....", mask_stream_url(&key));
                     if let Some(last_access_datetime) = *last_access_datetime_read_lock {
                         let cut_off_datetime = last_access_datetime + timeout_period;
                         debug!(
-                            "会话{key}最后访问时间: {last_access_datetime}, 过期时间: {cut_off_datetime}"
+                            "会话{}最后访问时间: {last_access_datetime}, 过期时间: {cut_off_datetime}",
+                            mask_stream_url(&key)
                         );
                         if Utc::now() > cut_off_datetime {
-                            debug!("会话{key}已过期, 需要删除会话");
+                            debug!("会话{}已过期, 需要删除会话", mask_stream_url(&key));
                             expired_keys.push(key.clone());
                         } else {
-                            debug!("会话{key}未过期, 无需删除");
+                            debug!("会话{}未过期, 无需删除", mask_stream_url(&key));
                         }
                     }
                 } else {
@@ -366,22 +1082,63 @@ impl StreamManager {
             return;
         }
 
-        // 删除过期会话
-        if !expired_keys.is_empty() {
-            info!("<清理过期会话>任务正在创建....");
-            let sessions_clone = Arc::clone(&sessions);
-            tokio::spawn(async move {
-                info!("<清理过期会话>任务创建完成.");
-                for key in expired_keys {
-                    debug!("开始删除会话{key}....");
-                    if let Ok(mut sessions_write_lock) = sessions_clone.write() {
-                        sessions_write_lock.remove(&key);
-                    } else {
-                        warn!("无法获取 sessions 写锁");
-                    }
-                    debug!("会话{key}删除完成.");
+        // 删除过期会话(在定时清除过期会话的任务中直接删除，会话的监督者随之终止子进程)
+        for key in expired_keys {
+            debug!("开始删除会话{}....", mask_stream_url(&key));
+            if let Ok(mut sessions_write_lock) = sessions.write() {
+                sessions_write_lock.remove(&key);
+            } else {
+                warn!("无法获取 sessions 写锁");
+            }
+            debug!("会话{}删除完成.", mask_stream_url(&key));
+        }
+    }
+
+    /// 关闭一个空闲的会话(没有观看者、等待超时清除的会话)，为新会话腾出ffmpeg进程预算
+    ///
+    /// 会话所属的池已用尽时只关闭同一个池的会话，否则(全局总预算用尽)关闭任一个池的会话；
+    /// 优先关闭空闲最久的会话。会话被删除后ffmpeg进程退出，预算的许可随之归还；
+    /// 返回被关闭的会话的监督者退出的令牌，调用方等待其退出后再获取许可
+    fn evict_idle_session(
+        sessions: &Arc<RwLock<FxHashMap<String, FfmpegSession>>>,
+        budget_pool: BudgetPool,
+    ) -> Option<CancellationToken> {
+        let pool_exhausted = get_ffmpeg_budget()
+            .map(|budget| budget.is_exhausted(budget_pool))
+            .unwrap_or(false);
+        let Ok(mut sessions_write_lock) = sessions.write() else {
+            warn!("无法获取 sessions 写锁");
+            return None;
+        };
+        let idle_key = sessions_write_lock
+            .iter()
+            .filter(|(_, session)| !pool_exhausted || session.budget_pool() == budget_pool)
+            .filter_map(|(key, session)| {
+                let last_access_datetime = (*session.last_access_datetime.read().ok()?)?;
+                Some((key, last_access_datetime))
+            })
+            .min_by_key(|(_, last_access_datetime)| *last_access_datetime)
+            .map(|(key, _)| key.clone());
+        match idle_key {
+            Some(key) => {
+                info!(
+                    "ffmpeg进程预算<{}>已用尽，关闭空闲的会话{}",
+                    budget_pool.name(),
+                    mask_stream_url(&key)
+                );
+                let session = sessions_write_lock.remove(&key)?;
+                if let Ok(budget) = get_ffmpeg_budget() {
+                    budget.record_eviction();
                 }
-            });
+                Some(session.supervisor_exited.clone())
+            }
+            None => {
+                debug!(
+                    "ffmpeg进程预算<{}>已用尽，没有可关闭的空闲会话",
+                    budget_pool.name()
+                );
+                None
+            }
         }
     }
 }
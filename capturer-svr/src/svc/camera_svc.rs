@@ -0,0 +1,246 @@
+use crate::auth::auth_error::AuthError;
+use crate::auth::camera_acl::CameraAcl;
+use crate::camera::camera_eo::RegisteredCamera;
+use crate::camera::camera_registry::CameraRegistry;
+use crate::config::capturer_config::{AclPermission, CameraConfig};
+use crate::credential::credential_eo::Credential;
+use crate::credential::credential_store::get_credential_store;
+use crate::dto::camera_dto::{
+    CameraCreateDto, CameraCredentialDto, CameraListDto, CameraUpdateDto,
+};
+use crate::utils::url_utils::mask_stream_url;
+use crate::vo::camera_vo::CameraVo;
+use anyhow::anyhow;
+use robotech::ro::Ro;
+use robotech::svc::SvcError;
+
+pub struct CameraSvc;
+
+impl CameraSvc {
+    /// 列出摄像头(开启访问控制时只列出用户有权限的摄像头)
+    pub async fn list(dto: CameraListDto) -> Result<Ro<Vec<CameraVo>>, SvcError> {
+        let cameras = CameraRegistry::list()
+            .await
+            .map_err(|e| anyhow!("查询摄像头异常: {}", e))?;
+        let mut vos = vec![];
+        for registered in cameras.iter().filter(|registered| {
+            dto.group
+                .as_ref()
+                .is_none_or(|group| registered.camera.group.as_ref() == Some(group))
+                && dto
+                    .tag
+                    .as_ref()
+                    .is_none_or(|tag| registered.camera.tags.contains(tag))
+        }) {
+            if Self::can_access(dto._current_user_id, registered)? {
+                vos.push(Self::to_vo(registered));
+            }
+        }
+        Ok(Ro::success("查询成功".to_string()).extra(Some(vos)))
+    }
+
+    /// 获取摄像头，用户没有权限时与不存在一样处理，不暴露摄像头是否存在
+    pub async fn get(id: String, current_user_id: u64) -> Result<Ro<CameraVo>, SvcError> {
+        let camera = CameraRegistry::get(&id)
+            .await
+            .map_err(|e| anyhow!("查询摄像头异常: {}", e))?
+            .ok_or_else(|| anyhow!("摄像头{id}不存在"))?;
+        if !Self::can_access(current_user_id, &camera)? {
+            return Err(anyhow!("摄像头{id}不存在").into());
+        }
+        Ok(Ro::success("查询成功".to_string()).extra(Some(Self::to_vo(&camera))))
+    }
+
+    pub async fn create(dto: CameraCreateDto) -> Result<Ro<CameraVo>, SvcError> {
+        let camera = CameraRegistry::create(CameraConfig {
+            id: dto.id.unwrap(),
+            name: dto.name,
+            group: dto.group,
+            main_stream_url: dto.main_stream_url.unwrap(),
+            sub_stream_url: dto.sub_stream_url,
+            fallback_stream_urls: dto.fallback_stream_urls.unwrap_or_default(),
+            tags: dto.tags.unwrap_or_default(),
+            always_on: dto.always_on.unwrap_or(false),
+        })
+        .await
+        .map_err(|e| anyhow!("创建摄像头异常: {}", e))?;
+        Ok(Ro::success("创建成功".to_string()).extra(Some(Self::to_vo(&camera))))
+    }
+
+    pub async fn update(id: String, dto: CameraUpdateDto) -> Result<Ro<CameraVo>, SvcError> {
+        let camera = CameraRegistry::update(CameraConfig {
+            id,
+            name: dto.name,
+            group: dto.group,
+            main_stream_url: dto.main_stream_url.unwrap(),
+            sub_stream_url: dto.sub_stream_url,
+            fallback_stream_urls: dto.fallback_stream_urls.unwrap_or_default(),
+            tags: dto.tags.unwrap_or_default(),
+            always_on: dto.always_on.unwrap_or(false),
+        })
+        .await
+        .map_err(|e| anyhow!("修改摄像头异常: {}", e))?;
+        Ok(Ro::success("修改成功".to_string()).extra(Some(Self::to_vo(&camera))))
+    }
+
+    pub async fn delete(id: String) -> Result<Ro<String>, SvcError> {
+        CameraRegistry::delete(&id)
+            .await
+            .map_err(|e| anyhow!("删除摄像头异常: {}", e))?;
+        Ok(Ro::success("删除成功".to_string()).extra(Some(id)))
+    }
+
+    /// 保存摄像头的凭据(已有时覆盖)
+    pub async fn set_credential(
+        id: String,
+        dto: CameraCredentialDto,
+    ) -> Result<Ro<String>, SvcError> {
+        get_credential_store()
+            .map_err(|e| anyhow!("保存凭据异常: {}", e))?
+            .set(
+                &id,
+                Credential {
+                    username: dto.username.unwrap(),
+                    password: dto.password.unwrap(),
+                },
+            )
+            .await
+            .map_err(|e| anyhow!("保存凭据异常: {}", e))?;
+        Ok(Ro::success("保存成功".to_string()).extra(Some(id)))
+    }
+
+    /// 删除摄像头的凭据
+    pub async fn delete_credential(id: String) -> Result<Ro<String>, SvcError> {
+        let deleted = get_credential_store()
+            .map_err(|e| anyhow!("删除凭据异常: {}", e))?
+            .delete(&id)
+            .await
+            .map_err(|e| anyhow!("删除凭据异常: {}", e))?;
+        if !deleted {
+            return Err(anyhow!("摄像头{id}没有凭据").into());
+        }
+        Ok(Ro::success("删除成功".to_string()).extra(Some(id)))
+    }
+
+    /// 检查用户新增摄像头的权限
+    ///
+    /// 需要新的摄像头(按ID或分组)的管理权限；流地址已登记为用户不能管理的其它摄像头时拒绝，
+    /// 避免将其它租户的流地址登记到自己的分组下绕过访问控制。由控制器调用，没有权限时返回403
+    pub async fn check_create(user_id: u64, dto: &CameraCreateDto) -> Result<(), AuthError> {
+        let id = dto.id.as_deref().unwrap_or_default();
+        CameraAcl::check(user_id, id, dto.group.as_deref(), AclPermission::Manage)?;
+        Self::check_stream_urls(
+            user_id,
+            id,
+            dto.main_stream_url
+                .iter()
+                .chain(&dto.sub_stream_url)
+                .chain(dto.fallback_stream_urls.iter().flatten()),
+        )
+        .await
+    }
+
+    /// 检查用户修改摄像头的权限
+    ///
+    /// 需要摄像头原来的及修改后的分组的管理权限(不能移到不能管理的分组)，流地址的检查同新增
+    pub async fn check_update(
+        user_id: u64,
+        id: &str,
+        dto: &CameraUpdateDto,
+    ) -> Result<(), AuthError> {
+        Self::check_manage(user_id, id).await?;
+        CameraAcl::check(user_id, id, dto.group.as_deref(), AclPermission::Manage)?;
+        Self::check_stream_urls(
+            user_id,
+            id,
+            dto.main_stream_url
+                .iter()
+                .chain(&dto.sub_stream_url)
+                .chain(dto.fallback_stream_urls.iter().flatten()),
+        )
+        .await
+    }
+
+    /// 检查用户管理摄像头(删除摄像头、保存或删除凭据)的权限
+    ///
+    /// 用户没有摄像头的任何权限时与不存在一样处理(返回404)，有权限但不能管理时返回403
+    pub async fn check_manage(user_id: u64, id: &str) -> Result<(), AuthError> {
+        let registered = CameraRegistry::get(id)
+            .await
+            .map_err(|e| AuthError::AuthCameraQuery(e.to_string()))?
+            .ok_or_else(|| AuthError::AuthCameraNotFound(id.to_string()))?;
+        let group = registered.camera.group.as_deref();
+        if !CameraAcl::can_access(user_id, id, group)? {
+            return Err(AuthError::AuthCameraNotFound(id.to_string()));
+        }
+        CameraAcl::check(user_id, id, group, AclPermission::Manage)
+    }
+
+    /// 检查摄像头的流地址没有登记为用户不能管理的其它摄像头
+    async fn check_stream_urls(
+        user_id: u64,
+        id: &str,
+        stream_urls: impl Iterator<Item = &String>,
+    ) -> Result<(), AuthError> {
+        for stream_url in stream_urls {
+            let Some(registered) = CameraRegistry::find_by_stream_url(stream_url)
+                .await
+                .map_err(|e| AuthError::AuthCameraQuery(e.to_string()))?
+            else {
+                continue;
+            };
+            let camera = &registered.camera;
+            if camera.id == id {
+                continue;
+            }
+            match CameraAcl::check(
+                user_id,
+                &camera.id,
+                camera.group.as_deref(),
+                AclPermission::Manage,
+            ) {
+                Ok(()) => {}
+                Err(AuthError::AuthForbidden(_)) => {
+                    return Err(AuthError::AuthForbidden(format!(
+                        "流地址{}已登记为其它摄像头",
+                        mask_stream_url(stream_url)
+                    )));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn can_access(user_id: u64, registered: &RegisteredCamera) -> Result<bool, SvcError> {
+        CameraAcl::can_access(
+            user_id,
+            &registered.camera.id,
+            registered.camera.group.as_deref(),
+        )
+        .map_err(|e| anyhow!("检查摄像头权限异常: {}", e).into())
+    }
+
+    fn to_vo(registered: &RegisteredCamera) -> CameraVo {
+        let camera = &registered.camera;
+        CameraVo {
+            id: camera.id.clone(),
+            name: camera.name.clone(),
+            group: camera.group.clone(),
+            main_stream_url: mask_stream_url(&camera.main_stream_url),
+            sub_stream_url: camera.sub_stream_url.as_deref().map(mask_stream_url),
+            fallback_stream_urls: camera
+                .fallback_stream_urls
+                .iter()
+                .map(|stream_url| mask_stream_url(stream_url))
+                .collect(),
+            tags: camera.tags.clone(),
+            always_on: camera.always_on,
+            has_credential: get_credential_store()
+                .is_ok_and(|credential_store| credential_store.contains(&camera.id)),
+            source: registered.source.name().to_string(),
+            created_ts: registered.created_at.map(|at| at.timestamp_millis()),
+            updated_ts: registered.updated_at.map(|at| at.timestamp_millis()),
+        }
+    }
+}
@@ -1,64 +1,792 @@
-use crate::config::capturer_config::{get_capturer_config, OssConfig};
-use crate::dto::capturer_dto::{CapturerCaptureToJpegDto, CapturerGetStreamDto};
+use crate::auth::auth_error::AuthError;
+use crate::auth::camera_acl::CameraAcl;
+use crate::camera::camera_registry::CameraRegistry;
+use crate::config::capturer_config::{
+    get_capturer_config, AclPermission, CaptureConfig, OssConfig,
+};
+use crate::dto::capturer_dto::{
+    CapturerCaptureBatchDto, CapturerCaptureClipDto, CapturerCaptureEventClipDto,
+    CapturerCaptureToJpegDto,
+};
+use crate::ffmpeg::ffmpeg_budget::{get_ffmpeg_budget, BudgetPool};
 use crate::ffmpeg::ffmpeg_cmd::FfmpegCmd;
+use crate::ffmpeg::ffmpeg_eo::StreamMetadata;
+use crate::quota::quota_error::QuotaError;
+use crate::quota::quota_manager::{get_quota_manager, LiveStreamPermit};
+use crate::spool::spool_eo::SpoolStats;
+use crate::spool::upload_spool::{StagedUpload, UploadSpool};
+use crate::stream::flv_clip::FlvClipRecorder;
 use crate::stream::flv_stream::FlvStream;
+use crate::stream::flv_tag::{FlvSequenceHeaders, FlvTag};
 use crate::stream::stream_manager::get_stream_manager;
+use crate::utils::jpeg_utils::{embed_jpeg_xmp, read_jpeg_dimensions};
+use crate::utils::url_utils::mask_stream_url;
+use crate::vo::capturer_vo::{
+    CapturerCaptureBatchItemVo, CapturerCaptureBatchVo, CapturerCaptureMetadataVo,
+    CapturerSpoolStatsVo,
+};
 use anyhow::anyhow;
-use futures::Stream;
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream, StreamExt};
 use oss_api_client::api_client::get_oss_api_client;
 use robotech::ro::Ro;
 use robotech::ro::RoResult;
 use robotech::svc::SvcError;
-use std::sync::Arc;
-use tracing::debug;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::fs;
+use tokio::sync::broadcast;
+use tokio::time::{timeout_at, Instant};
+use tracing::{debug, warn};
 use wheel_rs::time_utils::now_ts;
 
+/// 从会话中录制短视频时，除录制时长外额外等待关键帧的时间
+const CLIP_KEYFRAME_WAIT: Duration = Duration::from_secs(10);
+
+/// 临时工作目录的序号，避免同一毫秒内的请求使用相同的目录
+static TEMP_DIR_SEQ: AtomicU64 = AtomicU64::new(0);
+
 pub struct CapturerSvc;
 
 impl CapturerSvc {
+    /// 抓拍图片并上传到OSS
+    ///
+    /// `stream_url`为控制器解析并检查过权限的流地址
     pub async fn capture_to_jpeg(
         dto: CapturerCaptureToJpegDto,
+        stream_url: String,
     ) -> Result<Ro<serde_json::Value>, SvcError> {
-        let capturer_config = get_capturer_config()?;
-        let oss_api_client = get_oss_api_client()?;
+        Self::capture_one(
+            stream_url,
+            dto.bucket,
+            None,
+            dto.sidecar,
+            dto._current_user_id,
+        )
+        .await
+    }
+
+    pub async fn capture_batch(
+        dto: CapturerCaptureBatchDto,
+    ) -> Result<Ro<CapturerCaptureBatchVo>, SvcError> {
+        let CaptureConfig {
+            batch_concurrency,
+            max_batch_size,
+            ..
+        } = get_capturer_config()?.capture.clone();
+        // 先按地址、再按摄像头排列要抓拍的目标: (流地址, 摄像头ID)
+        let targets: Vec<(Option<String>, Option<String>)> = dto
+            .stream_urls
+            .unwrap_or_default()
+            .into_iter()
+            .map(|stream_url| (Some(stream_url), None))
+            .chain(
+                dto.camera_ids
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|camera_id| (None, Some(camera_id))),
+            )
+            .collect();
+        if targets.is_empty() {
+            return Err(anyhow!("抓拍流的地址列表和摄像头ID列表不能都为空").into());
+        }
+        if targets.len() > max_batch_size {
+            return Err(
+                anyhow!("批量抓拍的流数量{}超过上限{max_batch_size}", targets.len()).into(),
+            );
+        }
+
+        debug!(
+            "批量抓拍{}个流, 并发数量: {batch_concurrency}",
+            targets.len()
+        );
+        let bucket = dto.bucket;
+        let sidecar = dto.sidecar;
+        let sub_stream = dto.sub_stream.unwrap_or(false);
+        let user_id = dto._current_user_id;
+        let items: Vec<CapturerCaptureBatchItemVo> = stream::iter(targets)
+            .map(|(stream_url, camera_id)| {
+                let bucket = bucket.clone();
+                async move {
+                    let stream_url = match Self::resolve_stream_url(
+                        stream_url,
+                        camera_id.as_deref(),
+                        sub_stream,
+                        user_id,
+                        AclPermission::Snapshot,
+                    )
+                    .await
+                    {
+                        Ok(stream_url) => stream_url,
+                        Err(e) => {
+                            return CapturerCaptureBatchItemVo {
+                                stream_url: String::new(),
+                                camera_id,
+                                success: false,
+                                msg: e.to_string(),
+                                oss_obj_ref: None,
+                            };
+                        }
+                    };
+                    let masked_stream_url = mask_stream_url(&stream_url);
+                    match Self::capture_one(stream_url, bucket, None, sidecar, user_id).await {
+                        Ok(ro) => CapturerCaptureBatchItemVo {
+                            stream_url: masked_stream_url,
+                            camera_id,
+                            success: matches!(ro.result, RoResult::Success),
+                            msg: ro.msg,
+                            oss_obj_ref: ro.extra,
+                        },
+                        Err(e) => CapturerCaptureBatchItemVo {
+                            stream_url: masked_stream_url,
+                            camera_id,
+                            success: false,
+                            msg: e.to_string(),
+                            oss_obj_ref: None,
+                        },
+                    }
+                }
+            })
+            .buffered(batch_concurrency.max(1))
+            .collect()
+            .await;
+
+        let success_count = items.iter().filter(|item| item.success).count();
+        let fail_count = items.len() - success_count;
+        Ok(Ro::success(format!(
+            "批量抓拍完成, 成功{success_count}个, 失败{fail_count}个"
+        ))
+        .extra(Some(CapturerCaptureBatchVo {
+            success_count,
+            fail_count,
+            items,
+        })))
+    }
+
+    /// 检查录制的时长是否超过配置的上限
+    ///
+    /// 控制器在占用抓拍配额前调用，参数无效的请求不消耗配额
+    pub fn check_clip_seconds(seconds: u32, name: &str) -> Result<(), SvcError> {
+        let max_clip_seconds = get_capturer_config()?.capture.max_clip_seconds;
+        if seconds > max_clip_seconds {
+            return Err(anyhow!("{name}{seconds}秒超过上限{max_clip_seconds}秒").into());
+        }
+        Ok(())
+    }
+
+    /// 录制短视频并上传到OSS
+    ///
+    /// 时长已由控制器检查；该地址有正在进行的会话时，直接从会话的FLV标签中录制再转封装，不再额外拉流；
+    /// 否则需获取ffmpeg进程预算中一次性抓拍的许可，由ffmpeg拉流录制
+    pub async fn capture_clip(
+        dto: CapturerCaptureClipDto,
+        stream_url: String,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        let seconds = dto.seconds.unwrap();
+        let (mp4_bytes, from_session) = match get_stream_manager()?.subscribe_tags(&stream_url) {
+            Some((tag_receiver, sequence_headers)) => {
+                debug!(
+                    "从正在进行的会话中录制短视频: {}",
+                    mask_stream_url(&stream_url)
+                );
+                let flv_bytes =
+                    Self::record_flv_clip(tag_receiver, sequence_headers, seconds).await?;
+                (Self::flv_to_mp4(flv_bytes).await?, true)
+            }
+            None => {
+                let _budget_permit = get_ffmpeg_budget()?
+                    .acquire(BudgetPool::Capture)
+                    .await
+                    .map_err(|e| anyhow!("录制异常: {}", e))?;
+                let mp4_bytes = Self::run_in_temp_dir("clip", |work_dir| async move {
+                    let output_path = work_dir.join("clip.mp4");
+                    FfmpegCmd::record_clip_to_mp4(
+                        &stream_url,
+                        seconds,
+                        &output_path.to_string_lossy(),
+                    )
+                    .await
+                    .map_err(|e| anyhow!("录制异常: {:?}", e))?;
+                    Ok(fs::read(&output_path)
+                        .await
+                        .map_err(|e| anyhow!("读取短视频异常: {:?}", e))?)
+                })
+                .await?;
+                (mp4_bytes, false)
+            }
+        };
+
+        let mut oss_file_api_ro =
+            Self::upload_clip(mp4_bytes, dto.bucket, dto._current_user_id).await?;
+        if let Some(serde_json::Value::Object(extra)) = oss_file_api_ro.extra.as_mut() {
+            extra.insert("seconds".to_string(), seconds.into());
+            extra.insert("fromSession".to_string(), from_session.into());
+        }
+        Ok(oss_file_api_ro)
+    }
+
+    /// 导出事件短视频并上传到OSS
+    ///
+    /// 事件后录制的时长已由控制器检查；视频从预录缓冲中事件前的关键帧开始，录制到事件后指定的时长为止
+    pub async fn capture_event_clip(
+        dto: CapturerCaptureEventClipDto,
+        stream_url: String,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        let post_seconds = dto.post_seconds.unwrap();
+        let pre_ms = dto
+            .pre_seconds
+            .map_or(u32::MAX, |pre_seconds| pre_seconds.saturating_mul(1000));
+
+        let (sequence_headers, pre_tags, mut follower) = get_stream_manager()?
+            .follow_ring_buffer(&stream_url, pre_ms)
+            .ok_or_else(|| anyhow!("该流没有开启预录缓冲"))?;
+        let mut recorder = FlvClipRecorder::new(sequence_headers);
+        for tag in &pre_tags {
+            recorder.push(tag);
+        }
+        let pre_duration_ms = recorder.duration_ms();
+
+        // 以触发时缓冲中最后一个标签的时间戳作为事件发生的时间
+        let mut event_timestamp = pre_tags.last().map(|tag| tag.timestamp);
+        let post_ms = post_seconds.saturating_mul(1000);
+        let deadline =
+            Instant::now() + Duration::from_secs(post_seconds as u64) + CLIP_KEYFRAME_WAIT;
+        loop {
+            match timeout_at(deadline, follower.recv()).await {
+                Ok(Some(tag)) => {
+                    let event_timestamp = *event_timestamp.get_or_insert(tag.timestamp);
+                    recorder.push(&tag);
+                    if tag.timestamp.saturating_sub(event_timestamp) >= post_ms {
+                        break;
+                    }
+                }
+                Ok(None) => {
+                    warn!(
+                        "预录缓冲已关闭或跟随滞后，事件短视频只录制了{}毫秒",
+                        recorder.duration_ms()
+                    );
+                    break;
+                }
+                Err(_) => {
+                    warn!("录制事件短视频超时，只录制了{}毫秒", recorder.duration_ms());
+                    break;
+                }
+            }
+        }
+        if !recorder.is_started() {
+            return Err(anyhow!("预录缓冲中没有等到视频关键帧").into());
+        }
+        let duration_ms = recorder.duration_ms();
+        let mp4_bytes = Self::flv_to_mp4(recorder.finish()).await?;
+
+        let mut oss_file_api_ro =
+            Self::upload_clip(mp4_bytes, dto.bucket, dto._current_user_id).await?;
+        if let Some(serde_json::Value::Object(extra)) = oss_file_api_ro.extra.as_mut() {
+            extra.insert("preDurationMs".to_string(), pre_duration_ms.into());
+            extra.insert("durationMs".to_string(), duration_ms.into());
+        }
+        Ok(oss_file_api_ro)
+    }
+
+    /// 从会话的FLV标签中录制指定时长的FLV
+    ///
+    /// 会话中断或超时(录制时长加上等待关键帧的时间)时，返回已录制的部分
+    async fn record_flv_clip(
+        mut tag_receiver: broadcast::Receiver<FlvTag>,
+        sequence_headers: FlvSequenceHeaders,
+        seconds: u32,
+    ) -> Result<bytes::Bytes, SvcError> {
+        let mut recorder = FlvClipRecorder::new(sequence_headers);
+        let duration_ms = seconds.saturating_mul(1000);
+        let deadline = Instant::now() + Duration::from_secs(seconds as u64) + CLIP_KEYFRAME_WAIT;
+        loop {
+            match timeout_at(deadline, tag_receiver.recv()).await {
+                Ok(Ok(tag)) => {
+                    recorder.push(&tag);
+                    if recorder.duration_ms() >= duration_ms {
+                        break;
+                    }
+                }
+                Ok(Err(broadcast::error::RecvError::Lagged(skipped))) => {
+                    warn!("录制短视频滞后，跳过{skipped}个标签");
+                }
+                Ok(Err(broadcast::error::RecvError::Closed)) => {
+                    warn!("会话已结束，短视频只录制了{}毫秒", recorder.duration_ms());
+                    break;
+                }
+                Err(_) => {
+                    warn!("录制短视频超时，只录制了{}毫秒", recorder.duration_ms());
+                    break;
+                }
+            }
+        }
+        if !recorder.is_started() {
+            return Err(anyhow!("会话中没有等到视频关键帧").into());
+        }
+        Ok(recorder.finish())
+    }
+
+    /// 将录制的FLV转封装为MP4，返回MP4的内容
+    async fn flv_to_mp4(flv_bytes: bytes::Bytes) -> Result<Vec<u8>, SvcError> {
+        let _budget_permit = get_ffmpeg_budget()?
+            .acquire(BudgetPool::Capture)
+            .await
+            .map_err(|e| anyhow!("转封装短视频异常: {}", e))?;
+        Self::run_in_temp_dir("clip", |work_dir| async move {
+            let input_path = work_dir.join("clip.flv");
+            let output_path = work_dir.join("clip.mp4");
+            fs::write(&input_path, flv_bytes)
+                .await
+                .map_err(|e| anyhow!("写入短视频临时文件异常: {:?}", e))?;
+            FfmpegCmd::remux_flv_to_mp4(
+                &input_path.to_string_lossy(),
+                &output_path.to_string_lossy(),
+            )
+            .await
+            .map_err(|e| anyhow!("转封装短视频异常: {:?}", e))?;
+            Ok(fs::read(&output_path)
+                .await
+                .map_err(|e| anyhow!("读取短视频异常: {:?}", e))?)
+        })
+        .await
+    }
+
+    /// 在临时工作目录中执行，执行完成后删除工作目录
+    pub async fn run_in_temp_dir<F, Fut, T>(prefix: &str, f: F) -> Result<T, SvcError>
+    where
+        F: FnOnce(PathBuf) -> Fut,
+        Fut: Future<Output = Result<T, SvcError>>,
+    {
+        let work_dir = std::env::temp_dir().join(format!(
+            "capturer-{prefix}-{}-{}-{}",
+            std::process::id(),
+            now_ts()?,
+            TEMP_DIR_SEQ.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&work_dir)
+            .await
+            .map_err(|e| anyhow!("创建临时目录异常: {:?}", e))?;
+        let result = f(work_dir.clone()).await;
+        if let Err(e) = fs::remove_dir_all(&work_dir).await {
+            warn!("删除临时目录{work_dir:?}失败: {e}");
+        }
+        result
+    }
+
+    /// 上传短视频到OSS
+    async fn upload_clip(
+        mp4_bytes: Vec<u8>,
+        bucket: Option<String>,
+        user_id: u64,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        let bucket = match bucket {
+            Some(bucket) => bucket,
+            None => get_capturer_config()?.oss.bucket.clone(),
+        };
+        let file_name = format!("{}.mp4", now_ts()?);
+        let oss_file_api_ro =
+            Self::upload_or_spool(&bucket, &file_name, mp4_bytes, user_id).await?;
+        Ok(if let RoResult::Success = oss_file_api_ro.result {
+            oss_file_api_ro.msg("录制成功".to_string())
+        } else {
+            let msg = oss_file_api_ro.msg.clone();
+            oss_file_api_ro.msg(format!("录制失败: {}", msg))
+        })
+    }
+
+    /// 抓拍单个流并上传到OSS
+    ///
+    /// `file_stem`为上传的文件名(不含扩展名)，未指定时使用当前时间戳
+    pub(crate) async fn capture_one(
+        stream_url: String,
+        bucket: Option<String>,
+        file_stem: Option<String>,
+        sidecar: Option<bool>,
+        user_id: u64,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        let (jpeg_bytes, metadata) = Self::capture_jpeg(stream_url.as_str(), user_id).await?;
+        Self::upload_capture(jpeg_bytes, &metadata, bucket, file_stem, sidecar, user_id).await
+    }
+
+    /// 抓拍单个流为JPEG，并构建抓拍的元数据
+    ///
+    /// 抓拍前需获取ffmpeg进程预算中一次性抓拍的许可，避免同时启动过多的ffmpeg进程；
+    /// 按配置在JPEG中嵌入XMP元数据
+    pub(crate) async fn capture_jpeg(
+        stream_url: &str,
+        user_id: u64,
+    ) -> Result<(Vec<u8>, CapturerCaptureMetadataVo), SvcError> {
         let OssConfig {
             jpeg_quality,
-            bucket,
-        } = capturer_config.oss.clone();
-        let jpeg_bytes = FfmpegCmd::capture_to_jpeg(dto.stream_url.unwrap().as_str(), jpeg_quality)
-            .await
-            .map_err(|e| anyhow!("抓拍异常: {:?}", e))?;
+            embed_xmp,
+            ..
+        } = get_capturer_config()?.oss.clone();
+        let captured_at = Utc::now();
+        let jpeg_bytes = {
+            let _budget_permit = get_ffmpeg_budget()?
+                .acquire(BudgetPool::Capture)
+                .await
+                .map_err(|e| anyhow!("抓拍异常: {}", e))?;
+            FfmpegCmd::capture_to_jpeg(stream_url, jpeg_quality)
+                .await
+                .map_err(|e| anyhow!("抓拍异常: {:?}", e))?
+        };
 
-        debug!("获取oss_file_api实例...");
-        let oss_file_api_ro = oss_api_client
-            .file_client
-            .upload_file_content(
-                dto.bucket.unwrap_or(bucket).as_str(),
-                &format!("{}.jpg", now_ts()?),
-                jpeg_bytes,
-                dto._current_user_id,
+        let metadata =
+            Self::build_capture_metadata(stream_url, &jpeg_bytes, captured_at, user_id).await?;
+        let jpeg_bytes = if embed_xmp {
+            embed_jpeg_xmp(jpeg_bytes, &metadata.to_xmp_fields())
+        } else {
+            jpeg_bytes
+        };
+        Ok((jpeg_bytes, metadata))
+    }
+
+    /// 上传抓拍的图片(及按需上传元数据文件)到OSS，并在返回的对象引用中附加元数据
+    pub(crate) async fn upload_capture(
+        jpeg_bytes: Vec<u8>,
+        metadata: &CapturerCaptureMetadataVo,
+        bucket: Option<String>,
+        file_stem: Option<String>,
+        sidecar: Option<bool>,
+        user_id: u64,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        let OssConfig {
+            bucket: default_bucket,
+            upload_sidecar,
+            ..
+        } = get_capturer_config()?.oss.clone();
+        let bucket = bucket.unwrap_or(default_bucket);
+        let file_stem = match file_stem {
+            Some(file_stem) => file_stem,
+            None => now_ts()?.to_string(),
+        };
+        let oss_file_api_ro = Self::upload_or_spool(
+            bucket.as_str(),
+            &format!("{file_stem}.jpg"),
+            jpeg_bytes,
+            user_id,
+        )
+        .await?;
+
+        if sidecar.unwrap_or(upload_sidecar) {
+            debug!("上传抓拍的元数据文件...");
+            let sidecar_bytes = serde_json::to_vec(metadata)
+                .map_err(|e| anyhow!("序列化抓拍元数据异常: {:?}", e))?;
+            let sidecar_ro = Self::upload_or_spool(
+                bucket.as_str(),
+                &format!("{file_stem}.json"),
+                sidecar_bytes,
+                user_id,
             )
             .await?;
+            if !matches!(sidecar_ro.result, RoResult::Success) {
+                warn!("上传抓拍的元数据文件失败: {}", sidecar_ro.msg);
+            }
+        }
 
-        Ok(if let RoResult::Success = oss_file_api_ro.result {
+        let mut oss_file_api_ro = if let RoResult::Success = oss_file_api_ro.result {
             oss_file_api_ro.msg("抓拍成功".to_string())
         } else {
             let msg = oss_file_api_ro.msg.clone();
             oss_file_api_ro.msg(format!("抓拍失败: {}", msg))
+        };
+        if let Some(serde_json::Value::Object(extra)) = oss_file_api_ro.extra.as_mut() {
+            extra.insert(
+                "metadata".to_string(),
+                serde_json::to_value(metadata)
+                    .map_err(|e| anyhow!("序列化抓拍元数据异常: {:?}", e))?,
+            );
+        }
+        Ok(oss_file_api_ro)
+    }
+
+    /// 构建抓拍的元数据
+    ///
+    /// 编解码器信息优先取自该地址正在进行的会话，没有会话时再用ffprobe探测；
+    /// 分辨率直接从抓拍的JPEG中读取
+    async fn build_capture_metadata(
+        stream_url: &str,
+        jpeg_bytes: &[u8],
+        captured_at: DateTime<Utc>,
+        user_id: u64,
+    ) -> Result<CapturerCaptureMetadataVo, SvcError> {
+        let stream_metadata = match get_stream_manager()?.get_stream_metadata(stream_url) {
+            Some(stream_metadata) => stream_metadata,
+            None => FfmpegCmd::probe_stream_info(stream_url)
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("探测流信息失败，元数据将缺少编解码器信息: {:?}", e);
+                    StreamMetadata::default()
+                }),
+        };
+        let (width, height) = match read_jpeg_dimensions(jpeg_bytes) {
+            Some((width, height)) => (Some(width), Some(height)),
+            None => (stream_metadata.width, stream_metadata.height),
+        };
+        let server_node = get_capturer_config()?
+            .node_name
+            .clone()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .unwrap_or_default();
+
+        Ok(CapturerCaptureMetadataVo {
+            source_url: mask_stream_url(stream_url),
+            width,
+            height,
+            video_codec: stream_metadata
+                .video_codec
+                .as_ref()
+                .map(|codec| codec.name().to_string()),
+            audio_codec: stream_metadata
+                .audio_codec
+                .as_ref()
+                .map(|codec| codec.name().to_string()),
+            fps: stream_metadata.fps,
+            captured_at: captured_at.to_rfc3339(),
+            captured_ts: captured_at.timestamp_millis(),
+            server_node,
+            user_id,
         })
     }
 
+    /// 上传文件到OSS，上传失败时写入暂存目录，由后台任务重试上传，并返回待上传的引用
+    ///
+    /// 上传前先将文件内容写入暂存目录，请求失败或返回失败的结果时都进入重试，不再在内存中复制一份文件内容；
+    /// 写入暂存目录失败时直接上传，上传失败时不能重试
+    pub(crate) async fn upload_or_spool(
+        bucket: &str,
+        file_name: &str,
+        data: Vec<u8>,
+        user_id: u64,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        // 先确认可以上传，避免写入暂存后留下无人处理的数据文件
+        get_oss_api_client()?;
+        Self::check_oss_quota(user_id, data.len() as u64)?;
+        let staged = match UploadSpool::stage(bucket, file_name, &data, user_id).await {
+            Ok(staged) => Some(staged),
+            Err(e) => {
+                warn!("文件{file_name}写入暂存失败，上传失败时不能重试: {e}");
+                None
+            }
+        };
+        Self::upload_staged(bucket, file_name, data, user_id, staged).await
+    }
+
+    /// 上传本地的文件到OSS，上传失败时由后台任务重试上传，并返回待上传的引用
+    ///
+    /// 用于较大的文件(如会话的录像)：文件直接移入暂存目录，只读取一次用于上传(OSS的客户端只接受内存中的内容)；
+    /// 移入暂存目录失败时直接读取上传并删除文件，上传失败时不能重试
+    pub(crate) async fn upload_file_or_spool(
+        bucket: &str,
+        file_name: &str,
+        path: &Path,
+        user_id: u64,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        let size = match fs::metadata(path).await {
+            Ok(metadata) => metadata.len(),
+            Err(e) => return Err(anyhow!("读取文件{path:?}异常: {:?}", e).into()),
+        };
+        // 不能上传时同样删除文件，调用方不再处理该文件
+        let checked = match get_oss_api_client() {
+            Ok(_) => Self::check_oss_quota(user_id, size),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = checked {
+            if let Err(e) = fs::remove_file(path).await {
+                warn!("删除文件{path:?}失败: {e}");
+            }
+            return Err(e);
+        }
+        let staged = match UploadSpool::stage_file(bucket, file_name, path, user_id).await {
+            Ok(staged) => Some(staged),
+            Err(e) => {
+                warn!("文件{path:?}移入暂存失败，上传失败时不能重试: {e}");
+                None
+            }
+        };
+        let data_path = staged
+            .as_ref()
+            .map_or_else(|| path.to_path_buf(), StagedUpload::data_path);
+        let result = fs::read(&data_path).await;
+        if staged.is_none()
+            && let Err(e) = fs::remove_file(path).await
+        {
+            warn!("删除文件{path:?}失败: {e}");
+        }
+        let data = match result {
+            Ok(data) => data,
+            Err(e) => {
+                if let Some(staged) = staged {
+                    staged.discard().await;
+                }
+                return Err(anyhow!("读取文件{data_path:?}异常: {:?}", e).into());
+            }
+        };
+        Self::upload_staged(bucket, file_name, data, user_id, staged).await
+    }
+
+    /// 上传已暂存的文件内容，成功时删除暂存的数据文件，失败时写入元数据进入重试
+    async fn upload_staged(
+        bucket: &str,
+        file_name: &str,
+        data: Vec<u8>,
+        user_id: u64,
+        staged: Option<StagedUpload>,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        let oss_api_client = get_oss_api_client()?;
+        let size = data.len();
+        debug!("上传文件{file_name}...");
+        let result = oss_api_client
+            .file_client
+            .upload_file_content(bucket, file_name, data, user_id)
+            .await;
+        let error = match &result {
+            Ok(oss_file_api_ro) if matches!(oss_file_api_ro.result, RoResult::Success) => None,
+            Ok(oss_file_api_ro) => Some(oss_file_api_ro.msg.clone()),
+            Err(e) => Some(format!("{:?}", e)),
+        };
+        let spool_entry_meta = match (error, staged) {
+            (None, staged) => {
+                if let Some(staged) = staged {
+                    staged.discard().await;
+                }
+                Self::record_oss_bytes(user_id, size).await;
+                return Ok(result.map_err(|e| anyhow!("上传文件异常: {:?}", e))?);
+            }
+            (Some(_), None) => return Ok(result.map_err(|e| anyhow!("上传文件异常: {:?}", e))?),
+            (Some(error), Some(staged)) => {
+                warn!("文件{file_name}上传失败，写入暂存: {error}");
+                staged
+                    .spool(error)
+                    .await
+                    .map_err(|e| anyhow!("写入暂存异常: {:?}", e))?
+            }
+        };
+        // 暂存的文件稍后会上传，同样计入用户的OSS配额
+        Self::record_oss_bytes(user_id, size).await;
+        Ok(Ro::success("等待上传".to_string()).extra(Some(json!({
+            "pending": true,
+            "spoolId": spool_entry_meta.id,
+            "bucket": spool_entry_meta.bucket,
+            "fileName": spool_entry_meta.file_name,
+        }))))
+    }
+
+    /// 占用用户的抓拍配额
+    ///
+    /// 控制器在抓拍及录制短视频前调用，超过配额时由控制器返回429
+    pub fn acquire_capture_quota(user_id: u64, count: usize) -> Result<(), QuotaError> {
+        get_quota_manager()?.acquire_captures(user_id, u32::try_from(count).unwrap_or(u32::MAX))
+    }
+
+    /// 检查用户是否还可以上传指定字节数的文件到OSS
+    ///
+    /// 所有上传(包括定时任务、延时视频及会话录像)在上传前都要检查，本月的字节数超过上限时返回错误
+    pub fn check_oss_quota(user_id: u64, bytes: u64) -> Result<(), SvcError> {
+        get_quota_manager()
+            .and_then(|quota_manager| quota_manager.check_oss_upload(user_id, bytes))
+            .map_err(|e| anyhow!("{}", e).into())
+    }
+
+    /// 占用用户观看直播的配额
+    ///
+    /// 控制器在获取直播流前调用，超过配额时由控制器返回429；返回的许可随直播流一起释放
+    pub fn acquire_live_quota(user_id: u64) -> Result<LiveStreamPermit, QuotaError> {
+        get_quota_manager()?.acquire_live_stream(user_id)
+    }
+
+    /// 累计用户上传到OSS的字节数，失败时只记录日志，不影响上传
+    async fn record_oss_bytes(user_id: u64, bytes: usize) {
+        let result = match get_quota_manager() {
+            Ok(quota_manager) => quota_manager.record_oss_bytes(user_id, bytes as u64).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("累计用户{user_id}上传到OSS的字节数失败: {e}");
+        }
+    }
+
     pub async fn stream(
-        dto: CapturerGetStreamDto,
+        stream_url: String,
+        live_permit: LiveStreamPermit,
     ) -> Result<impl Stream<Item = Result<bytes::Bytes, SvcError>>, SvcError> {
         debug!("获取stream_manager实例...");
-        let (data_receiver, header, cache_header_sender) = get_stream_manager()?
-            .get_cmd_receiver(dto.stream_url.unwrap().as_str())
+        let (data_receiver, header) = get_stream_manager()?
+            .get_cmd_receiver(stream_url.as_str())
             .await
             .map_err(|e| anyhow!("获取流异常: {:?}", e))?;
         debug!("获取flv_stream实例...");
-        let flv_stream = FlvStream::new(data_receiver, Arc::clone(&header), cache_header_sender);
+        let flv_stream = FlvStream::new(data_receiver, header, Some(live_permit));
         debug!("返回flv_stream...");
         Ok(flv_stream.into_stream())
     }
+
+    /// 解析流地址并检查用户的访问权限
+    ///
+    /// 指定了摄像头ID时使用登记的摄像头(配置文件或接口创建)的流地址，否则使用请求中的流地址；
+    /// 开启访问控制时，按流地址访问也要按地址对应的摄像头检查权限，防止通过猜测地址访问其它租户的摄像头。
+    /// 由控制器在占用配额前调用，没有权限时由控制器返回403
+    pub async fn resolve_stream_url(
+        stream_url: Option<String>,
+        camera_id: Option<&str>,
+        sub_stream: bool,
+        user_id: u64,
+        permission: AclPermission,
+    ) -> Result<String, AuthError> {
+        let (stream_url, camera) = match camera_id {
+            Some(camera_id) => {
+                let registered = CameraRegistry::get(camera_id)
+                    .await
+                    .map_err(|e| AuthError::AuthCameraQuery(e.to_string()))?
+                    .ok_or_else(|| AuthError::AuthCameraNotFound(camera_id.to_string()))?;
+                (
+                    registered.camera.stream_url(sub_stream).to_string(),
+                    Some(registered.camera),
+                )
+            }
+            None => {
+                let stream_url = stream_url.ok_or(AuthError::AuthNoTarget)?;
+                let camera = if get_capturer_config()?.acl.enabled {
+                    CameraRegistry::find_by_stream_url(&stream_url)
+                        .await
+                        .map_err(|e| AuthError::AuthCameraQuery(e.to_string()))?
+                        .map(|registered| registered.camera)
+                } else {
+                    None
+                };
+                (stream_url, camera)
+            }
+        };
+        match &camera {
+            Some(camera) => {
+                CameraAcl::check(user_id, &camera.id, camera.group.as_deref(), permission)?
+            }
+            None => CameraAcl::check_unregistered(user_id)?,
+        }
+        Ok(stream_url)
+    }
+
+    pub async fn spool_stats() -> Result<Ro<CapturerSpoolStatsVo>, SvcError> {
+        let SpoolStats {
+            depth,
+            oldest,
+            dead_letter_depth,
+        } = UploadSpool::stats()
+            .await
+            .map_err(|e| anyhow!("统计暂存异常: {:?}", e))?;
+        Ok(
+            Ro::success("查询成功".to_string()).extra(Some(CapturerSpoolStatsVo {
+                depth,
+                oldest_id: oldest.as_ref().map(|meta| meta.id.clone()),
+                oldest_created_ts: oldest
+                    .as_ref()
+                    .map(|meta| meta.created_at.timestamp_millis()),
+                oldest_attempts: oldest.as_ref().map(|meta| meta.attempts),
+                dead_letter_depth,
+            })),
+        )
+    }
 }
@@ -0,0 +1,218 @@
+use crate::auth::auth_error::AuthError;
+use crate::auth::camera_acl::CameraAcl;
+use crate::config::capturer_config::{
+    get_capturer_config, key_template_default, AclPermission, JobConfig,
+};
+use crate::dto::job_dto::{JobCreateDto, JobTimelapseDto};
+use crate::ffmpeg::ffmpeg_budget::{get_ffmpeg_budget, BudgetPool};
+use crate::ffmpeg::ffmpeg_cmd::FfmpegCmd;
+use crate::scheduler::job_eo::{JobSource, JobStatus};
+use crate::scheduler::job_scheduler::get_job_scheduler;
+use crate::scheduler::snapshot_archive::SnapshotArchive;
+use crate::svc::capturer_svc::CapturerSvc;
+use crate::utils::url_utils::mask_stream_url;
+use crate::vo::job_vo::JobStatusVo;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use robotech::ro::Ro;
+use robotech::svc::SvcError;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::info;
+
+pub struct JobSvc;
+
+impl JobSvc {
+    /// 创建任务
+    ///
+    /// `stream_url`为控制器按创建者的抓拍权限解析并检查过的流地址；
+    /// 按摄像头创建的任务只记录摄像头ID，每次运行时重新解析摄像头的流地址
+    pub async fn create(
+        dto: JobCreateDto,
+        stream_url: String,
+    ) -> Result<Ro<JobStatusVo>, SvcError> {
+        let (stream_url, camera_id) = match dto.camera_id {
+            Some(camera_id) => (None, Some(camera_id)),
+            None => (Some(stream_url), None),
+        };
+        let job_config = JobConfig {
+            id: dto.id.unwrap(),
+            stream_url,
+            camera_id,
+            sub_stream: dto.sub_stream.unwrap_or(false),
+            cron: dto.cron,
+            interval: dto.interval,
+            bucket: dto.bucket,
+            key_template: dto.key_template.unwrap_or_else(key_template_default),
+            webhook: dto.webhook,
+            user_id: dto._current_user_id,
+            archive: dto.archive.unwrap_or(false),
+        };
+        let vo = Self::to_vo(&job_config, JobSource::Api, &JobStatus::default());
+        get_job_scheduler()?
+            .add_api_job(job_config)
+            .map_err(|e| anyhow!("创建任务异常: {}", e))?;
+        Ok(Ro::success("创建成功".to_string()).extra(Some(vo)))
+    }
+
+    pub async fn delete(id: String) -> Result<Ro<String>, SvcError> {
+        get_job_scheduler()?
+            .remove_api_job(&id)
+            .map_err(|e| anyhow!("删除任务异常: {}", e))?;
+        Ok(Ro::success("删除成功".to_string()).extra(Some(id)))
+    }
+
+    /// 列出用户的任务的状态(管理员列出所有任务)
+    pub async fn list_status(user_id: u64) -> Result<Ro<Vec<JobStatusVo>>, SvcError> {
+        let is_admin =
+            CameraAcl::is_admin(user_id).map_err(|e| anyhow!("检查用户权限异常: {}", e))?;
+        let jobs = get_job_scheduler()?
+            .list_jobs()
+            .map_err(|e| anyhow!("查询任务异常: {}", e))?;
+        let vos = jobs
+            .iter()
+            .filter(|(job_config, ..)| is_admin || job_config.user_id == user_id)
+            .map(|(job_config, source, status)| Self::to_vo(job_config, *source, status))
+            .collect();
+        Ok(Ro::success("查询成功".to_string()).extra(Some(vos)))
+    }
+
+    /// 检查用户对任务的权限
+    ///
+    /// 只有任务的创建者或管理员可以操作任务(配置文件中的任务按配置的用户ID)；
+    /// `permission`不为空时还要按任务的摄像头(或流地址对应的摄像头)检查该权限。
+    /// 由控制器调用，没有权限时返回403，任务不存在时返回404
+    pub async fn check_job(
+        id: &str,
+        user_id: u64,
+        permission: Option<AclPermission>,
+    ) -> Result<(), AuthError> {
+        let job_config = get_job_scheduler()?
+            .get_job(id)
+            .map_err(|e| AuthError::AuthJobQuery(e.to_string()))?
+            .ok_or_else(|| AuthError::AuthJobNotFound(id.to_string()))?;
+        if job_config.user_id != user_id && !CameraAcl::is_admin(user_id)? {
+            return Err(AuthError::AuthForbidden(format!("任务{id}由其他用户创建")));
+        }
+        if let Some(permission) = permission {
+            CapturerSvc::resolve_stream_url(
+                job_config.stream_url,
+                job_config.camera_id.as_deref(),
+                job_config.sub_stream,
+                user_id,
+                permission,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// 将任务在时间范围内归档的图片合成为延时视频，并上传到OSS
+    pub async fn timelapse(
+        id: String,
+        dto: JobTimelapseDto,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        let start = DateTime::from_timestamp_millis(dto.start_ts.unwrap())
+            .ok_or_else(|| anyhow!("开始时间无效"))?;
+        let end = DateTime::from_timestamp_millis(dto.end_ts.unwrap())
+            .ok_or_else(|| anyhow!("结束时间无效"))?;
+        if start >= end {
+            return Err(anyhow!("开始时间必须早于结束时间").into());
+        }
+        // 本月上传到OSS的字节数已达上限时不再合成
+        CapturerSvc::check_oss_quota(dto._current_user_id, 0)?;
+        let fps = dto.fps.unwrap_or(25);
+        let max_frames = get_capturer_config()?
+            .snapshot_archive
+            .timelapse_max_frames
+            .max(1);
+
+        let snapshots = SnapshotArchive::list(&id, start, end)
+            .await
+            .map_err(|e| anyhow!("读取归档图片异常: {:?}", e))?;
+        if snapshots.is_empty() {
+            return Err(anyhow!("任务{id}在该时间范围内没有归档的图片").into());
+        }
+        // 超出最大帧数时均匀抽帧
+        let step = snapshots.len().div_ceil(max_frames);
+        let frames: Vec<_> = snapshots.into_iter().step_by(step).collect();
+        info!("任务{id}合成延时视频, 帧数: {}, 帧率: {fps}", frames.len());
+
+        let mp4_bytes = CapturerSvc::run_in_temp_dir("timelapse", |work_dir| {
+            Self::render_timelapse(work_dir, &frames, fps)
+        })
+        .await?;
+
+        let bucket = match dto.bucket {
+            Some(bucket) => bucket,
+            None => get_capturer_config()?.oss.bucket.clone(),
+        };
+        let file_name = format!(
+            "{id}/timelapse/{}-{}.mp4",
+            start.format("%Y%m%d%H%M%S"),
+            end.format("%Y%m%d%H%M%S")
+        );
+        let mut oss_file_api_ro =
+            CapturerSvc::upload_or_spool(&bucket, &file_name, mp4_bytes, dto._current_user_id)
+                .await?;
+        if let Some(serde_json::Value::Object(extra)) = oss_file_api_ro.extra.as_mut() {
+            extra.insert("frameCount".to_string(), frames.len().into());
+            extra.insert("fps".to_string(), fps.into());
+        }
+        Ok(oss_file_api_ro)
+    }
+
+    /// 在工作目录中按顺序链接归档图片并合成MP4，返回MP4的内容
+    async fn render_timelapse(
+        work_dir: PathBuf,
+        frames: &[(DateTime<Utc>, PathBuf)],
+        fps: u8,
+    ) -> Result<Vec<u8>, SvcError> {
+        let images_dir = work_dir.join("images");
+        fs::create_dir_all(&images_dir)
+            .await
+            .map_err(|e| anyhow!("创建延时视频临时目录异常: {:?}", e))?;
+        for (index, (_, path)) in frames.iter().enumerate() {
+            let absolute_path = fs::canonicalize(path)
+                .await
+                .map_err(|e| anyhow!("读取归档图片异常: {:?}", e))?;
+            fs::symlink(absolute_path, images_dir.join(format!("{index:06}.jpg")))
+                .await
+                .map_err(|e| anyhow!("链接归档图片异常: {:?}", e))?;
+        }
+
+        let output_path = work_dir.join("timelapse.mp4");
+        let _budget_permit = get_ffmpeg_budget()?
+            .acquire(BudgetPool::Capture)
+            .await
+            .map_err(|e| anyhow!("合成延时视频异常: {}", e))?;
+        FfmpegCmd::images_to_mp4(
+            &images_dir.to_string_lossy(),
+            fps,
+            &output_path.to_string_lossy(),
+        )
+        .await
+        .map_err(|e| anyhow!("合成延时视频异常: {:?}", e))?;
+        Ok(fs::read(&output_path)
+            .await
+            .map_err(|e| anyhow!("读取延时视频异常: {:?}", e))?)
+    }
+
+    fn to_vo(job_config: &JobConfig, source: JobSource, status: &JobStatus) -> JobStatusVo {
+        JobStatusVo {
+            id: job_config.id.clone(),
+            source: source.name().to_string(),
+            stream_url: job_config.stream_url.as_deref().map(mask_stream_url),
+            camera_id: job_config.camera_id.clone(),
+            cron: job_config.cron.clone(),
+            interval_secs: job_config.interval.map(|interval| interval.as_secs()),
+            last_run_ts: status.last_run_at.map(|at| at.timestamp_millis()),
+            last_success: status.last_success,
+            last_error: status.last_error.clone(),
+            last_oss_obj_ref: status.last_oss_obj_ref.clone(),
+            next_run_ts: status.next_run_at.map(|at| at.timestamp_millis()),
+            run_count: status.run_count,
+            fail_count: status.fail_count,
+        }
+    }
+}
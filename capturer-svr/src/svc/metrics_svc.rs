@@ -0,0 +1,112 @@
+use crate::ffmpeg::ffmpeg_budget::{get_ffmpeg_budget, BudgetPoolStats};
+use crate::stream::session_supervisor::{respawns_total, stalls_total};
+use crate::stream::stream_manager::get_stream_manager;
+use robotech::svc::SvcError;
+use std::fmt::Write;
+
+pub struct MetricsSvc;
+
+impl MetricsSvc {
+    /// 以Prometheus文本格式输出指标
+    pub async fn render() -> Result<String, SvcError> {
+        let mut text = String::new();
+        let budget = get_ffmpeg_budget()?;
+        let stats = budget.stats();
+        Self::write_pool_metric(
+            &mut text,
+            "capturer_ffmpeg_budget_capacity",
+            "ffmpeg进程预算的大小",
+            "gauge",
+            &stats,
+            |pool_stats| pool_stats.capacity as u64,
+        );
+        Self::write_pool_metric(
+            &mut text,
+            "capturer_ffmpeg_budget_in_use",
+            "正在使用的ffmpeg进程预算",
+            "gauge",
+            &stats,
+            |pool_stats| pool_stats.in_use as u64,
+        );
+        Self::write_pool_metric(
+            &mut text,
+            "capturer_ffmpeg_budget_waiting",
+            "排队等待ffmpeg进程预算的数量",
+            "gauge",
+            &stats,
+            |pool_stats| pool_stats.waiting as u64,
+        );
+        Self::write_pool_metric(
+            &mut text,
+            "capturer_ffmpeg_budget_acquired_total",
+            "累计获取ffmpeg进程预算的次数",
+            "counter",
+            &stats,
+            |pool_stats| pool_stats.acquired_total,
+        );
+        Self::write_pool_metric(
+            &mut text,
+            "capturer_ffmpeg_budget_timeouts_total",
+            "累计等待ffmpeg进程预算超时的次数",
+            "counter",
+            &stats,
+            |pool_stats| pool_stats.timeouts_total,
+        );
+        Self::write_metric(
+            &mut text,
+            "capturer_ffmpeg_budget_evictions_total",
+            "累计因ffmpeg进程预算用尽而关闭的空闲会话数量",
+            "counter",
+            budget.evictions_total(),
+        );
+        Self::write_metric(
+            &mut text,
+            "capturer_stream_sessions",
+            "正在进行的会话数量",
+            "gauge",
+            get_stream_manager()?.list_sessions().len() as u64,
+        );
+        Self::write_metric(
+            &mut text,
+            "capturer_stream_stalls_total",
+            "累计会话拉流卡住的次数",
+            "counter",
+            stalls_total(),
+        );
+        Self::write_metric(
+            &mut text,
+            "capturer_stream_respawns_total",
+            "累计会话重新拉流成功的次数",
+            "counter",
+            respawns_total(),
+        );
+        Ok(text)
+    }
+
+    /// 输出按池区分的指标(全局总预算的池名为`total`)
+    fn write_pool_metric(
+        text: &mut String,
+        name: &str,
+        help: &str,
+        metric_type: &str,
+        stats: &[BudgetPoolStats],
+        value: impl Fn(&BudgetPoolStats) -> u64,
+    ) {
+        let _ = writeln!(text, "# HELP {name} {help}");
+        let _ = writeln!(text, "# TYPE {name} {metric_type}");
+        for pool_stats in stats {
+            let _ = writeln!(
+                text,
+                "{name}{{pool=\"{}\"}} {}",
+                pool_stats.name,
+                value(pool_stats)
+            );
+        }
+    }
+
+    fn write_metric(text: &mut String, name: &str, help: &str, metric_type: &str, value: u64) {
+        let _ = writeln!(text, "# HELP {name} {help}");
+        let _ = writeln!(text, "# TYPE {name} {metric_type}");
+        let _ = writeln!(text, "{name} {value}");
+    }
+}
@@ -1 +1,8 @@
+pub mod camera_svc;
 pub mod capturer_svc;
+pub mod job_svc;
+pub mod metrics_svc;
+pub mod quota_svc;
+pub mod record_svc;
+pub mod session_svc;
+pub mod token_svc;
@@ -0,0 +1,49 @@
+use crate::config::capturer_config::get_capturer_config;
+use crate::dto::quota_dto::QuotaUsageDto;
+use crate::quota::quota_eo::QuotaUsage;
+use crate::quota::quota_manager::get_quota_manager;
+use crate::vo::quota_vo::QuotaUsageVo;
+use anyhow::anyhow;
+use robotech::ro::Ro;
+use robotech::svc::SvcError;
+
+pub struct QuotaSvc;
+
+impl QuotaSvc {
+    /// 查询用户的配额使用情况
+    ///
+    /// 只有管理员(访问控制中配置的管理员用户)可以查询其它用户
+    pub async fn usage(dto: QuotaUsageDto) -> Result<Ro<QuotaUsageVo>, SvcError> {
+        let user_id = dto.user_id.unwrap_or(dto._current_user_id);
+        if user_id != dto._current_user_id
+            && !get_capturer_config()?
+                .acl
+                .admin_users
+                .contains(&dto._current_user_id)
+        {
+            return Err(anyhow!("只有管理员可以查询其它用户的配额").into());
+        }
+        let QuotaUsage {
+            user_id,
+            month,
+            captures_last_minute,
+            live_streams,
+            monthly_oss_bytes,
+            limits,
+        } = get_quota_manager()
+            .and_then(|quota_manager| quota_manager.usage(user_id))
+            .map_err(|e| anyhow!("查询配额异常: {}", e))?;
+        Ok(
+            Ro::success("查询成功".to_string()).extra(Some(QuotaUsageVo {
+                user_id,
+                month,
+                captures_last_minute,
+                captures_per_minute_limit: limits.captures_per_minute,
+                live_streams,
+                max_live_streams_limit: limits.max_live_streams,
+                monthly_oss_bytes,
+                monthly_oss_bytes_limit: limits.monthly_oss_bytes,
+            })),
+        )
+    }
+}
@@ -0,0 +1,137 @@
+use crate::auth::auth_error::AuthError;
+use crate::auth::camera_acl::CameraAcl;
+use crate::auth::playback_token::{PlaybackTarget, PlaybackToken};
+use crate::camera::camera_registry::CameraRegistry;
+use crate::config::capturer_config::AclPermission;
+use crate::dto::record_dto::{RecordHlsSegmentDto, RecordListDto, RecordPlaybackDto};
+use crate::record::record_playback::RecordPlayback;
+use crate::record::record_store::RecordStore;
+use crate::utils::url_utils::encode_query_value;
+use crate::vo::record_vo::RecordSegmentVo;
+use anyhow::anyhow;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use robotech::ro::Ro;
+use robotech::svc::SvcError;
+
+pub struct RecordSvc;
+
+impl RecordSvc {
+    /// 列出摄像头在时间范围内的录像分段
+    pub async fn list(dto: RecordListDto) -> Result<Ro<Vec<RecordSegmentVo>>, SvcError> {
+        let start = Self::parse_ts(dto.start_ts.unwrap(), "开始时间")?;
+        let end = Self::parse_ts(dto.end_ts.unwrap(), "结束时间")?;
+        if start >= end {
+            return Err(anyhow!("开始时间必须早于结束时间").into());
+        }
+        let camera_id = dto.camera_id.unwrap();
+        let segments = RecordStore::list(&camera_id, start, end)
+            .await
+            .map_err(|e| anyhow!("查询录像异常: {:?}", e))?;
+        let vos = segments
+            .iter()
+            .map(|segment| RecordSegmentVo {
+                start_ts: segment.start.timestamp_millis(),
+                end_ts: segment.end().timestamp_millis(),
+                duration_ms: segment.duration_ms,
+                format: segment.format.extension().to_string(),
+                size: segment.size,
+            })
+            .collect();
+        Ok(Ro::success("查询成功".to_string()).extra(Some(vos)))
+    }
+
+    /// 从指定时间开始以HTTP-FLV回放录像
+    pub async fn playback_flv(
+        dto: RecordPlaybackDto,
+    ) -> Result<impl Stream<Item = Result<Bytes, SvcError>>, SvcError> {
+        let (camera_id, start, end) = Self::parse_playback(dto)?;
+        let stream = RecordPlayback::flv_stream(camera_id, start, end)
+            .map(|result| result.map_err(|e| anyhow!("回放录像异常: {:?}", e).into()));
+        Ok(stream)
+    }
+
+    /// 生成从指定时间开始回放录像的HLS播放列表
+    pub async fn playback_hls(dto: RecordPlaybackDto) -> Result<String, SvcError> {
+        let user_id = dto._current_user_id;
+        let has_token = dto.token.is_some();
+        let (camera_id, start, end) = Self::parse_playback(dto)?;
+        let end = end.unwrap_or_else(Utc::now);
+        // 按令牌认证时为分段签发新的令牌带入分段的地址，播放器请求分段时同样可以认证；
+        // 请求的令牌有效期较短，分段令牌的有效期按播放完整个列表的时长加上默认有效期
+        let token_query = if has_token {
+            let ttl = (end - start).to_std().unwrap_or_default()
+                + PlaybackToken::default_ttl().map_err(|e| anyhow!("签发分段令牌异常: {}", e))?;
+            let (token, _) = PlaybackToken::issue(
+                &PlaybackTarget::Camera(camera_id.clone()),
+                user_id,
+                Some(ttl),
+            )
+            .map_err(|e| anyhow!("签发分段令牌异常: {}", e))?;
+            format!("&token={}", encode_query_value(&token))
+        } else {
+            String::new()
+        };
+        let encoded_camera_id = encode_query_value(&camera_id);
+        RecordPlayback::hls_playlist(&camera_id, start, end, |segment, offset_ms| {
+            format!(
+                "segment.ts?cameraId={encoded_camera_id}&segmentTs={}&offsetMs={offset_ms}{token_query}",
+                segment.start.timestamp_millis()
+            )
+        })
+        .await
+        .map_err(|e| anyhow!("生成播放列表异常: {:?}", e).into())
+    }
+
+    /// 获取HLS回放的TS分段
+    pub async fn hls_segment(
+        dto: RecordHlsSegmentDto,
+    ) -> Result<impl Stream<Item = Result<Bytes, SvcError>>, SvcError> {
+        let segment_start = Self::parse_ts(dto.segment_ts.unwrap(), "分段的开始时间")?;
+        let camera_id = dto.camera_id.unwrap();
+        let stream = RecordPlayback::hls_segment(
+            &camera_id,
+            segment_start,
+            dto.offset_ms.unwrap_or_default(),
+        )
+        .await
+        .map_err(|e| anyhow!("获取录像分段异常: {:?}", e))?;
+        Ok(stream.map(|result| result.map_err(|e| anyhow!("读取录像分段异常: {:?}", e).into())))
+    }
+
+    /// 检查用户回放摄像头录像的权限
+    ///
+    /// 摄像头已被删除时仍可按摄像头ID的规则回放留存的录像；由控制器调用，没有权限时返回403
+    pub async fn check_playback(user_id: u64, camera_id: &str) -> Result<(), AuthError> {
+        let camera_group = CameraRegistry::get(camera_id)
+            .await
+            .map_err(|e| AuthError::AuthCameraQuery(e.to_string()))?
+            .and_then(|registered| registered.camera.group);
+        CameraAcl::check(
+            user_id,
+            camera_id,
+            camera_group.as_deref(),
+            AclPermission::Playback,
+        )
+    }
+
+    /// 解析回放的摄像头ID及时间范围
+    fn parse_playback(
+        dto: RecordPlaybackDto,
+    ) -> Result<(String, DateTime<Utc>, Option<DateTime<Utc>>), SvcError> {
+        let start = Self::parse_ts(dto.start_ts.unwrap(), "开始时间")?;
+        let end = dto
+            .end_ts
+            .map(|end_ts| Self::parse_ts(end_ts, "结束时间"))
+            .transpose()?;
+        if end.is_some_and(|end| start >= end) {
+            return Err(anyhow!("开始时间必须早于结束时间").into());
+        }
+        Ok((dto.camera_id.unwrap(), start, end))
+    }
+
+    fn parse_ts(ts: i64, name: &str) -> Result<DateTime<Utc>, SvcError> {
+        DateTime::from_timestamp_millis(ts).ok_or_else(|| anyhow!("{name}无效").into())
+    }
+}
@@ -0,0 +1,175 @@
+use crate::auth::auth_error::AuthError;
+use crate::auth::camera_acl::CameraAcl;
+use crate::camera::camera_registry::CameraRegistry;
+use crate::config::capturer_config::{get_capturer_config, AclPermission};
+use crate::dto::session_dto::SessionRecordStartDto;
+use crate::stream::stream_manager::get_stream_manager;
+use crate::svc::capturer_svc::CapturerSvc;
+use crate::utils::url_utils::mask_stream_url;
+use crate::vo::session_vo::SessionVo;
+use anyhow::anyhow;
+use robotech::ro::{Ro, RoResult};
+use robotech::svc::SvcError;
+use serde_json::json;
+use tokio::fs;
+use tracing::{info, warn};
+use wheel_rs::time_utils::now_ts;
+
+pub struct SessionSvc;
+
+impl SessionSvc {
+    /// 列出用户可以访问的正在进行的会话
+    ///
+    /// 只列出用户有摄像头任一权限的会话，没有登记为摄像头的流地址的会话只列给管理员；
+    /// 子进程ID只返回给管理员
+    pub async fn list(user_id: u64) -> Result<Ro<Vec<SessionVo>>, SvcError> {
+        let is_admin =
+            CameraAcl::is_admin(user_id).map_err(|e| anyhow!("检查用户权限异常: {}", e))?;
+        let cameras = if is_admin {
+            Vec::new()
+        } else {
+            CameraRegistry::list()
+                .await
+                .map_err(|e| anyhow!("查询摄像头异常: {}", e))?
+        };
+        let mut vos = Vec::new();
+        for session_info in get_stream_manager()?.list_sessions() {
+            if !is_admin {
+                let camera = cameras.iter().find(|registered| {
+                    registered.camera.main_stream_url == session_info.stream_url
+                        || registered.camera.sub_stream_url.as_deref()
+                            == Some(session_info.stream_url.as_str())
+                });
+                let can_access = match camera {
+                    Some(registered) => CameraAcl::can_access(
+                        user_id,
+                        &registered.camera.id,
+                        registered.camera.group.as_deref(),
+                    )
+                    .map_err(|e| anyhow!("检查摄像头权限异常: {}", e))?,
+                    None => false,
+                };
+                if !can_access {
+                    continue;
+                }
+            }
+            vos.push(SessionVo {
+                id: session_info.id,
+                stream_url: mask_stream_url(&session_info.stream_url),
+                active_stream_url: mask_stream_url(&session_info.active_stream_url),
+                child_id: Some(session_info.child_id).filter(|_| is_admin),
+                budget_pool: session_info.budget_pool.name().to_string(),
+                respawn_count: session_info.respawn_count,
+                last_data_ts: session_info.last_data_at.map(|at| at.timestamp_millis()),
+                created_ts: session_info.created_at.timestamp_millis(),
+                receiver_count: session_info.receiver_count,
+                idle_since_ts: session_info.idle_since.map(|at| at.timestamp_millis()),
+                recording_started_ts: session_info
+                    .recording_started_at
+                    .map(|at| at.timestamp_millis()),
+            });
+        }
+        Ok(Ro::success("查询成功".to_string()).extra(Some(vos)))
+    }
+
+    /// 检查用户对会话按需录像的权限
+    ///
+    /// 按会话的流地址对应的摄像头检查录制短视频的权限；会话正在录像时，只有开始录像的用户或管理员可以操作。
+    /// 由控制器调用，没有权限时返回403；会话不存在时不检查，由后续的操作返回错误
+    pub async fn check_record(id: u64, user_id: u64) -> Result<(), AuthError> {
+        let Ok(session_info) = get_stream_manager()?.find_session(id) else {
+            return Ok(());
+        };
+        CapturerSvc::resolve_stream_url(
+            Some(session_info.stream_url),
+            None,
+            false,
+            user_id,
+            AclPermission::Clip,
+        )
+        .await?;
+        if let Some(recording_user_id) = session_info.recording_user_id
+            && recording_user_id != user_id
+            && !CameraAcl::is_admin(user_id)?
+        {
+            return Err(AuthError::AuthForbidden(format!(
+                "会话{id}的录像由其他用户开始"
+            )));
+        }
+        Ok(())
+    }
+
+    /// 开始会话的按需录像
+    pub async fn start_record(
+        id: u64,
+        dto: SessionRecordStartDto,
+    ) -> Result<Ro<serde_json::Value>, SvcError> {
+        // 本月上传到OSS的字节数已达上限时录像无法上传，不再开始录像
+        CapturerSvc::check_oss_quota(dto._current_user_id, 0)?;
+        let max_record_duration = get_capturer_config()?.capture.max_record_duration;
+        let path = std::env::temp_dir().join(format!(
+            "capturer-record-{}-{id}-{}.flv",
+            std::process::id(),
+            now_ts()?
+        ));
+        let started_at = get_stream_manager()?
+            .start_session_recording(
+                id,
+                path,
+                max_record_duration,
+                dto.bucket,
+                dto._current_user_id,
+            )
+            .map_err(|e| anyhow!("开始录像异常: {}", e))?;
+        info!("会话{id}开始录像");
+        Ok(Ro::success("开始录像".to_string()).extra(Some(json!({
+            "sessionId": id,
+            "startedTs": started_at.timestamp_millis(),
+        }))))
+    }
+
+    /// 停止会话的按需录像，并将录像文件上传到OSS
+    ///
+    /// 以开始录像的用户的身份上传，返回的对象引用中附加录像的时长及大小
+    pub async fn stop_record(id: u64) -> Result<Ro<serde_json::Value>, SvcError> {
+        let recording = get_stream_manager()?
+            .take_session_recording(id)
+            .map_err(|e| anyhow!("停止录像异常: {}", e))?;
+        let user_id = recording.user_id;
+        let bucket = match recording.bucket.clone() {
+            Some(bucket) => bucket,
+            None => get_capturer_config()?.oss.bucket.clone(),
+        };
+        let (path, duration_ms) = recording
+            .stop()
+            .await
+            .map_err(|e| anyhow!("停止录像异常: {}", e))?;
+        info!("会话{id}停止录像: {path:?}");
+
+        let size = match fs::metadata(&path).await {
+            Ok(metadata) => metadata.len(),
+            Err(e) => {
+                if let Err(e) = fs::remove_file(&path).await {
+                    warn!("删除录像文件{path:?}失败: {e}");
+                }
+                return Err(anyhow!("读取录像文件异常: {:?}", e).into());
+            }
+        };
+
+        // 录像文件可能很大，直接移入暂存目录上传，不在内存中复制
+        let file_name = format!("{}.flv", now_ts()?);
+        let oss_file_api_ro =
+            CapturerSvc::upload_file_or_spool(&bucket, &file_name, &path, user_id).await?;
+        let mut oss_file_api_ro = if let RoResult::Success = oss_file_api_ro.result {
+            oss_file_api_ro.msg("录像成功".to_string())
+        } else {
+            let msg = oss_file_api_ro.msg.clone();
+            oss_file_api_ro.msg(format!("录像失败: {}", msg))
+        };
+        if let Some(serde_json::Value::Object(extra)) = oss_file_api_ro.extra.as_mut() {
+            extra.insert("durationMs".to_string(), duration_ms.into());
+            extra.insert("size".to_string(), size.into());
+        }
+        Ok(oss_file_api_ro)
+    }
+}
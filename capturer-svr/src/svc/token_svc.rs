@@ -0,0 +1,65 @@
+use crate::auth::auth_error::AuthError;
+use crate::auth::camera_acl::CameraAcl;
+use crate::auth::playback_token::{PlaybackTarget, PlaybackToken};
+use crate::camera::camera_registry::CameraRegistry;
+use crate::dto::token_dto::TokenPlaybackIssueDto;
+use crate::vo::token_vo::TokenPlaybackVo;
+use anyhow::anyhow;
+use robotech::ro::Ro;
+use robotech::svc::SvcError;
+use tracing::info;
+
+pub struct TokenSvc;
+
+impl TokenSvc {
+    /// 签发播放令牌
+    pub async fn issue_playback(
+        dto: TokenPlaybackIssueDto,
+    ) -> Result<Ro<TokenPlaybackVo>, SvcError> {
+        let target =
+            PlaybackTarget::from_request(dto.camera_id.as_deref(), dto.stream_url.as_deref())
+                .ok_or_else(|| anyhow!("直播流的地址和摄像头ID不能都为空"))?;
+        if let PlaybackTarget::Camera(camera_id) = &target {
+            let registered = CameraRegistry::get(camera_id)
+                .await
+                .map_err(|e| anyhow!("查询摄像头异常: {}", e))?
+                .ok_or_else(|| anyhow!("摄像头{camera_id}不存在"))?;
+            // 没有摄像头任何权限的用户不签发令牌(使用令牌时仍按具体权限检查)
+            if !CameraAcl::can_access(
+                dto._current_user_id,
+                camera_id,
+                registered.camera.group.as_deref(),
+            )
+            .map_err(|e| anyhow!("检查摄像头权限异常: {}", e))?
+            {
+                return Err(anyhow!("摄像头{camera_id}不存在").into());
+            }
+        }
+        let (token, expires_at) = PlaybackToken::issue(&target, dto._current_user_id, dto.ttl)
+            .map_err(|e| anyhow!("签发播放令牌异常: {}", e))?;
+        info!(
+            "为用户{}签发播放令牌，过期时间: {expires_at}",
+            dto._current_user_id
+        );
+        Ok(
+            Ro::success("签发成功".to_string()).extra(Some(TokenPlaybackVo {
+                token,
+                expires_ts: expires_at.timestamp_millis(),
+            })),
+        )
+    }
+
+    /// 校验播放令牌
+    ///
+    /// ## 返回值
+    /// 返回令牌所属的用户ID；由控制器调用，认证失败时按错误返回对应的状态码
+    pub fn verify_playback(
+        token: &str,
+        camera_id: Option<&str>,
+        stream_url: Option<&str>,
+    ) -> Result<u64, AuthError> {
+        let target =
+            PlaybackTarget::from_request(camera_id, stream_url).ok_or(AuthError::AuthNoTarget)?;
+        PlaybackToken::verify(token, &target)
+    }
+}
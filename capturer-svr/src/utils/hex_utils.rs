@@ -0,0 +1,30 @@
+/// # 编码为十六进制字符串(小写)
+///
+/// ## 示例
+/// ```
+/// use capturer_svr::utils::hex_utils::encode_hex;
+/// assert_eq!(encode_hex(&[0x01, 0xab, 0xff]), "01abff");
+/// ```
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// # 解码十六进制字符串
+///
+/// 长度为奇数或包含非十六进制字符时返回None
+///
+/// ## 示例
+/// ```
+/// use capturer_svr::utils::hex_utils::decode_hex;
+/// assert_eq!(decode_hex("01ABff"), Some(vec![0x01, 0xab, 0xff]));
+/// assert_eq!(decode_hex("0g"), None);
+/// ```
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
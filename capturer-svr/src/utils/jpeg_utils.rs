@@ -0,0 +1,115 @@
+/// JPEG的SOI标记
+const SOI: [u8; 2] = [0xFF, 0xD8];
+/// JPEG的APP0(JFIF)标记
+const APP0: u8 = 0xE0;
+/// JPEG的APP1(EXIF/XMP)标记
+const APP1: u8 = 0xE1;
+/// XMP在APP1段中的命名空间标识
+const XMP_NAMESPACE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// JPEG段的最大长度(包含长度字段本身)
+const MAX_SEGMENT_LEN: usize = 0xFFFF;
+
+/// # 读取JPEG图片的分辨率
+///
+/// 遍历JPEG的段，从SOF段中读取图片的宽高
+///
+/// ## 返回值
+/// 返回(宽, 高)，如果不是有效的JPEG或找不到SOF段则返回None
+pub fn read_jpeg_dimensions(jpeg: &[u8]) -> Option<(u32, u32)> {
+    if !jpeg.starts_with(&SOI) {
+        return None;
+    }
+    let mut pos = 2;
+    while pos + 4 <= jpeg.len() {
+        if jpeg[pos] != 0xFF {
+            return None;
+        }
+        let marker = jpeg[pos + 1];
+        let segment_len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        // SOF0~SOF15(排除DHT/JPG/DAC)
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            if pos + 9 > jpeg.len() {
+                return None;
+            }
+            let height = u16::from_be_bytes([jpeg[pos + 5], jpeg[pos + 6]]) as u32;
+            let width = u16::from_be_bytes([jpeg[pos + 7], jpeg[pos + 8]]) as u32;
+            return Some((width, height));
+        }
+        pos += 2 + segment_len;
+    }
+    None
+}
+
+/// # 在JPEG图片中嵌入XMP元数据
+///
+/// 在SOI(及紧随其后的JFIF段)之后插入一个APP1段，段内容为XMP数据包，
+/// 每个字段以`capturer:<name>`属性的形式写入。
+///
+/// ## 参数
+/// * `jpeg` - JPEG图片数据
+/// * `fields` - 要嵌入的字段(名称, 值)
+///
+/// ## 返回值
+/// 返回嵌入XMP后的JPEG图片数据，如果不是有效的JPEG或XMP数据过大，则原样返回
+pub fn embed_jpeg_xmp(jpeg: Vec<u8>, fields: &[(&str, String)]) -> Vec<u8> {
+    if !jpeg.starts_with(&SOI) {
+        return jpeg;
+    }
+
+    let packet = build_xmp_packet(fields);
+    let segment_len = 2 + XMP_NAMESPACE.len() + packet.len();
+    if segment_len > MAX_SEGMENT_LEN {
+        return jpeg;
+    }
+
+    // JFIF要求APP0段紧跟在SOI之后，所以XMP段插入在APP0段之后
+    let mut insert_pos = 2;
+    if jpeg.len() >= 6 && jpeg[2] == 0xFF && jpeg[3] == APP0 {
+        insert_pos = 4 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize;
+        if insert_pos > jpeg.len() {
+            return jpeg;
+        }
+    }
+
+    let mut result = Vec::with_capacity(jpeg.len() + segment_len + 2);
+    result.extend_from_slice(&jpeg[..insert_pos]);
+    result.extend_from_slice(&[0xFF, APP1]);
+    result.extend_from_slice(&(segment_len as u16).to_be_bytes());
+    result.extend_from_slice(XMP_NAMESPACE);
+    result.extend_from_slice(packet.as_bytes());
+    result.extend_from_slice(&jpeg[insert_pos..]);
+    result
+}
+
+/// 构建XMP数据包
+fn build_xmp_packet(fields: &[(&str, String)]) -> String {
+    let attributes = fields
+        .iter()
+        .map(|(name, value)| format!(" capturer:{name}=\"{}\"", escape_xml(value)))
+        .collect::<String>();
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+<rdf:Description rdf:about=\"\" xmlns:capturer=\"https://github.com/rusthing/capturer/ns/1.0/\"{attributes}/>\
+</rdf:RDF>\
+</x:xmpmeta>\
+<?xpacket end=\"w\"?>"
+    )
+}
+
+/// 转义XML属性值
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,6 @@
+pub mod hex_utils;
+pub mod jpeg_utils;
+pub mod path_utils;
+pub mod task_utils;
+pub mod url_utils;
+pub mod webhook_utils;
@@ -0,0 +1,20 @@
+/// # 将标识转换为安全的目录名
+///
+/// 除字母、数字、`-`和`_`以外的字符替换为`_`，避免越出上级目录。
+///
+/// ## 示例
+/// ```
+/// use capturer_svr::utils::path_utils::to_safe_dir_name;
+/// assert_eq!(to_safe_dir_name("../gate/1"), "___gate_1");
+/// ```
+pub fn to_safe_dir_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
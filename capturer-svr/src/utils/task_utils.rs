@@ -0,0 +1,80 @@
+use crate::config::capturer_config::get_capturer_config;
+use robotech::cfg::CfgError;
+use std::future::Future;
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{info, warn};
+
+/// 全局的后台任务(流管理器的会话之外的后台任务，如录像、定时任务、ffmpeg子进程的回收等)
+static BACKGROUND_TASKS: LazyLock<BackgroundTasks> = LazyLock::new(|| BackgroundTasks {
+    cancel_token: CancellationToken::new(),
+    tracker: TaskTracker::new(),
+});
+
+pub fn get_background_tasks() -> &'static BackgroundTasks {
+    &BACKGROUND_TASKS
+}
+
+/// 关闭后台任务(程序退出时，在关闭流管理器之后)，见[`BackgroundTasks::shutdown`]
+pub async fn shutdown_background_tasks() -> Result<(), CfgError> {
+    let shutdown_grace_period = get_capturer_config()?
+        .session
+        .shutdown_grace_period
+        .unwrap_or(Duration::from_secs(10));
+    get_background_tasks().shutdown(shutdown_grace_period).await;
+    Ok(())
+}
+
+/// 后台任务
+///
+/// 所有任务都由同一个跟踪器跟踪，程序退出时通过取消令牌通知任务退出，并等待其退出
+pub struct BackgroundTasks {
+    /// 程序退出时取消的令牌
+    cancel_token: CancellationToken,
+    /// 后台任务的跟踪器
+    tracker: TaskTracker,
+}
+
+impl BackgroundTasks {
+    /// 启动被跟踪的后台任务，任务应在取消令牌被取消后尽快退出
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tracker.spawn(task)
+    }
+
+    /// 创建可以单独取消的令牌(程序退出时同样会被取消)
+    pub fn child_token(&self) -> CancellationToken {
+        self.cancel_token.child_token()
+    }
+
+    /// 程序是否正在退出
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_token.is_cancelled()
+    }
+
+    /// 后台任务的跟踪器
+    pub fn tracker(&self) -> TaskTracker {
+        self.tracker.clone()
+    }
+
+    /// 通知所有后台任务退出，并等待其退出，超过宽限时间后不再等待
+    pub async fn shutdown(&self, grace_period: Duration) {
+        info!("关闭后台任务，等待{}个后台任务退出", self.tracker.len());
+        self.cancel_token.cancel();
+        self.tracker.close();
+        match timeout(grace_period, self.tracker.wait()).await {
+            Ok(()) => info!("所有后台任务已退出"),
+            Err(_) => warn!(
+                "超过{grace_period:?}仍有{}个后台任务没有退出，不再等待",
+                self.tracker.len()
+            ),
+        }
+    }
+}
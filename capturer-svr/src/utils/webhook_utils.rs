@@ -0,0 +1,30 @@
+use crate::policy::url_policy::UrlPolicy;
+use std::time::Duration;
+
+/// 回调webhook的超时时间
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// # 回调webhook
+///
+/// 先按流地址策略检查地址，再固定使用检查过的解析地址发送，且不跟随重定向，防止回调到内部服务(SSRF)
+pub async fn post_webhook(webhook: &str, payload: &serde_json::Value) -> Result<(), String> {
+    let resolved = UrlPolicy::check_webhook(webhook)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut client_builder = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(WEBHOOK_TIMEOUT);
+    if let Some((host, addr)) = resolved {
+        client_builder = client_builder.resolve(&host, addr);
+    }
+    client_builder
+        .build()
+        .map_err(|e| e.to_string())?
+        .post(webhook)
+        .json(payload)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
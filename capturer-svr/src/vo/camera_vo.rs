@@ -0,0 +1,33 @@
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use utoipa::ToSchema;
+
+#[skip_serializing_none]
+#[derive(ToSchema, Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraVo {
+    /// 摄像头ID
+    pub id: String,
+    /// 摄像头名称
+    pub name: Option<String>,
+    /// 分组
+    pub group: Option<String>,
+    /// 主码流的地址(已脱敏)
+    pub main_stream_url: String,
+    /// 子码流的地址(已脱敏)
+    pub sub_stream_url: Option<String>,
+    /// 备用的流地址(已脱敏)
+    pub fallback_stream_urls: Vec<String>,
+    /// 标签
+    pub tags: Vec<String>,
+    /// 是否常驻拉流
+    pub always_on: bool,
+    /// 是否保存了凭据(拉流时注入到流地址中，不会返回凭据本身)
+    pub has_credential: bool,
+    /// 摄像头来源(config: 配置文件; api: 接口创建)
+    pub source: String,
+    /// 创建的时间戳(毫秒)
+    pub created_ts: Option<i64>,
+    /// 最后修改的时间戳(毫秒)
+    pub updated_ts: Option<i64>,
+}
@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use utoipa::ToSchema;
 
@@ -9,3 +9,105 @@ pub struct CapturerCaptureVo {
     /// 数据
     pub data: Vec<u8>,
 }
+
+#[skip_serializing_none]
+#[derive(ToSchema, Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturerSpoolStatsVo {
+    /// 暂存中等待上传的抓拍数量
+    pub depth: usize,
+    /// 最早的暂存条目ID
+    pub oldest_id: Option<String>,
+    /// 最早的暂存条目进入暂存的时间戳(毫秒)
+    pub oldest_created_ts: Option<i64>,
+    /// 最早的暂存条目已重试的次数
+    pub oldest_attempts: Option<u32>,
+    /// 多次重试仍上传失败、移入死信目录的条目数量
+    pub dead_letter_depth: usize,
+}
+
+/// 抓拍的元数据
+///
+/// 会返回给调用者、嵌入到JPEG的XMP中，并可作为JSON文件上传到图片旁
+#[skip_serializing_none]
+#[derive(ToSchema, Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturerCaptureMetadataVo {
+    /// 抓拍流的地址(已脱敏)
+    pub source_url: String,
+    /// 图片宽度
+    pub width: Option<u32>,
+    /// 图片高度
+    pub height: Option<u32>,
+    /// 视频编解码器
+    pub video_codec: Option<String>,
+    /// 音频编解码器
+    pub audio_codec: Option<String>,
+    /// 帧率
+    pub fps: Option<u8>,
+    /// 抓拍时间(RFC3339格式)
+    pub captured_at: String,
+    /// 抓拍时间戳(毫秒)
+    pub captured_ts: i64,
+    /// 服务节点名称
+    pub server_node: String,
+    /// 抓拍的用户ID
+    pub user_id: u64,
+}
+
+impl CapturerCaptureMetadataVo {
+    /// 转换为嵌入XMP的字段
+    pub fn to_xmp_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("sourceUrl", self.source_url.clone()),
+            ("capturedAt", self.captured_at.clone()),
+            ("serverNode", self.server_node.clone()),
+            ("userId", self.user_id.to_string()),
+        ];
+        if let Some(width) = self.width {
+            fields.push(("width", width.to_string()));
+        }
+        if let Some(height) = self.height {
+            fields.push(("height", height.to_string()));
+        }
+        if let Some(video_codec) = &self.video_codec {
+            fields.push(("videoCodec", video_codec.clone()));
+        }
+        if let Some(audio_codec) = &self.audio_codec {
+            fields.push(("audioCodec", audio_codec.clone()));
+        }
+        if let Some(fps) = self.fps {
+            fields.push(("fps", fps.to_string()));
+        }
+        fields
+    }
+}
+
+/// 批量抓拍中单个流的抓拍结果
+#[skip_serializing_none]
+#[derive(ToSchema, Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturerCaptureBatchItemVo {
+    /// 抓拍流的地址(已脱敏)
+    pub stream_url: String,
+    /// 摄像头ID(按摄像头抓拍时)
+    pub camera_id: Option<String>,
+    /// 是否抓拍成功
+    pub success: bool,
+    /// 抓拍结果的消息(失败时为失败原因)
+    pub msg: String,
+    /// 上传到OSS的对象引用(上传失败转入暂存时为待上传的引用)
+    pub oss_obj_ref: Option<serde_json::Value>,
+}
+
+/// 批量抓拍的结果
+#[derive(ToSchema, Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturerCaptureBatchVo {
+    /// 成功数量
+    pub success_count: usize,
+    /// 失败数量
+    pub fail_count: usize,
+    /// 每个流的抓拍结果(与请求中的顺序一致)
+    pub items: Vec<CapturerCaptureBatchItemVo>,
+}
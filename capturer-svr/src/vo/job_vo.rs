@@ -0,0 +1,35 @@
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use utoipa::ToSchema;
+
+#[skip_serializing_none]
+#[derive(ToSchema, Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatusVo {
+    /// 任务ID
+    pub id: String,
+    /// 任务来源(config: 配置文件; api: 接口创建)
+    pub source: String,
+    /// 抓拍流的地址(已脱敏)
+    pub stream_url: Option<String>,
+    /// 抓拍的摄像头ID
+    pub camera_id: Option<String>,
+    /// cron表达式
+    pub cron: Option<String>,
+    /// 抓拍间隔(单位为秒)
+    pub interval_secs: Option<u64>,
+    /// 上次运行的时间戳(毫秒)
+    pub last_run_ts: Option<i64>,
+    /// 上次运行是否成功
+    pub last_success: Option<bool>,
+    /// 上次运行失败的原因
+    pub last_error: Option<String>,
+    /// 上次运行上传到OSS的对象引用
+    pub last_oss_obj_ref: Option<serde_json::Value>,
+    /// 下次运行的时间戳(毫秒)
+    pub next_run_ts: Option<i64>,
+    /// 累计运行次数
+    pub run_count: u64,
+    /// 累计失败次数
+    pub fail_count: u64,
+}
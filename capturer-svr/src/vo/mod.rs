@@ -1 +1,7 @@
+pub mod camera_vo;
 pub mod capturer_vo;
+pub mod job_vo;
+pub mod quota_vo;
+pub mod record_vo;
+pub mod session_vo;
+pub mod token_vo;
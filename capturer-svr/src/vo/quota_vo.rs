@@ -0,0 +1,25 @@
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use utoipa::ToSchema;
+
+#[skip_serializing_none]
+#[derive(ToSchema, Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaUsageVo {
+    /// 用户ID
+    pub user_id: u64,
+    /// 统计的月份(UTC，格式为`YYYY-MM`)
+    pub month: String,
+    /// 最近一分钟的抓拍次数
+    pub captures_last_minute: u32,
+    /// 每分钟的抓拍次数上限(不限制时为空)
+    pub captures_per_minute_limit: Option<u32>,
+    /// 正在观看的直播数量
+    pub live_streams: u32,
+    /// 同时观看直播的数量上限(不限制时为空)
+    pub max_live_streams_limit: Option<u32>,
+    /// 本月上传到OSS的字节数
+    pub monthly_oss_bytes: u64,
+    /// 每月上传到OSS的字节数上限(不限制时为空)
+    pub monthly_oss_bytes_limit: Option<u64>,
+}
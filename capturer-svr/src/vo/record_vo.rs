@@ -0,0 +1,19 @@
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use utoipa::ToSchema;
+
+#[skip_serializing_none]
+#[derive(ToSchema, Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordSegmentVo {
+    /// 开始时间戳(毫秒)
+    pub start_ts: i64,
+    /// 结束时间戳(毫秒)
+    pub end_ts: i64,
+    /// 时长(毫秒)
+    pub duration_ms: u32,
+    /// 文件格式(flv/mp4)
+    pub format: String,
+    /// 文件大小(字节)
+    pub size: u64,
+}
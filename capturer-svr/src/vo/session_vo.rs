@@ -0,0 +1,31 @@
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use utoipa::ToSchema;
+
+#[skip_serializing_none]
+#[derive(ToSchema, Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionVo {
+    /// 会话ID
+    pub id: u64,
+    /// 流地址(已脱敏)
+    pub stream_url: String,
+    /// 当前拉流的地址(已脱敏)，主码流不可用时为备用的流地址
+    pub active_stream_url: String,
+    /// 当前的ffmpeg子进程ID(只返回给管理员)
+    pub child_id: Option<u32>,
+    /// 占用的ffmpeg进程预算的池(transcode: 转码，passthrough: 直通)
+    pub budget_pool: String,
+    /// 子进程退出后重新拉流的次数
+    pub respawn_count: u64,
+    /// 最后一次收到ffmpeg输出数据的时间戳(毫秒)，还没有收到数据时不返回
+    pub last_data_ts: Option<i64>,
+    /// 会话创建的时间戳(毫秒)
+    pub created_ts: i64,
+    /// 观看者数量
+    pub receiver_count: usize,
+    /// 开始空闲的时间戳(毫秒)，活跃中不返回
+    pub idle_since_ts: Option<i64>,
+    /// 按需录像开始的时间戳(毫秒)，没有录像时不返回
+    pub recording_started_ts: Option<i64>,
+}
@@ -0,0 +1,13 @@
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use utoipa::ToSchema;
+
+#[skip_serializing_none]
+#[derive(ToSchema, Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPlaybackVo {
+    /// 播放令牌(作为查询参数token访问视频流)
+    pub token: String,
+    /// 过期的时间戳(毫秒)
+    pub expires_ts: i64,
+}
@@ -0,0 +1,12 @@
+use robotech::macros::api_doc;
+
+#[api_doc(
+    list_cameras,
+    get_camera,
+    create_camera,
+    update_camera,
+    delete_camera,
+    set_camera_credential,
+    delete_camera_credential
+)]
+pub struct CameraApiDoc;
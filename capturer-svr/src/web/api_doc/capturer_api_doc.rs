@@ -1,4 +1,11 @@
 use robotech::macros::api_doc;
 
-#[api_doc(capture_to_jpeg, stream)]
+#[api_doc(
+    capture_to_jpeg,
+    capture_batch,
+    capture_clip,
+    capture_event_clip,
+    stream,
+    spool_stats
+)]
 pub struct CapturerApiDoc;
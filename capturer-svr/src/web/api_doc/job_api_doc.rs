@@ -0,0 +1,4 @@
+use robotech::macros::api_doc;
+
+#[api_doc(create_job, delete_job, list_job_status, timelapse)]
+pub struct JobApiDoc;
@@ -0,0 +1,4 @@
+use robotech::macros::api_doc;
+
+#[api_doc(get_metrics)]
+pub struct MetricsApiDoc;
@@ -1 +1,8 @@
-pub mod capturer_api_doc;
\ No newline at end of file
+pub mod camera_api_doc;
+pub mod capturer_api_doc;
+pub mod job_api_doc;
+pub mod metrics_api_doc;
+pub mod quota_api_doc;
+pub mod record_api_doc;
+pub mod session_api_doc;
+pub mod token_api_doc;
@@ -0,0 +1,4 @@
+use robotech::macros::api_doc;
+
+#[api_doc(get_quota_usage)]
+pub struct QuotaApiDoc;
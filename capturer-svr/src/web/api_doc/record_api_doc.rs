@@ -0,0 +1,4 @@
+use robotech::macros::api_doc;
+
+#[api_doc(list_recordings, playback_flv, playback_hls, hls_segment)]
+pub struct RecordApiDoc;
@@ -0,0 +1,4 @@
+use robotech::macros::api_doc;
+
+#[api_doc(list_sessions, start_record, stop_record)]
+pub struct SessionApiDoc;
@@ -0,0 +1,4 @@
+use robotech::macros::api_doc;
+
+#[api_doc(issue_playback_token)]
+pub struct TokenApiDoc;
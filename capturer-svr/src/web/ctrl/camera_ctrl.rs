@@ -0,0 +1,171 @@
+use crate::dto::camera_dto::{
+    CameraCreateDto, CameraCredentialDto, CameraListDto, CameraUpdateDto,
+};
+use crate::svc::camera_svc::CameraSvc;
+use crate::vo::camera_vo::CameraVo;
+use crate::web::ctrl::error_response::auth_error_response;
+use axum::extract::{Path, Query};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use axum::{debug_handler, Json};
+use robotech::macros::log_call;
+use robotech::ro::Ro;
+use robotech::web::ctrl_utils::get_current_user_id;
+use robotech::web::CtrlError;
+use validator::Validate;
+
+#[utoipa::path(
+    get,
+    path = "/capturer/cameras",
+    responses((status = OK, body = Ro<Vec<CameraVo>>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn list_cameras(
+    headers: HeaderMap,
+    Query(mut dto): Query<CameraListDto>,
+) -> Result<Json<Ro<Vec<CameraVo>>>, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+
+    let result = CameraSvc::list(dto).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/capturer/cameras/{id}",
+    params(("id" = String, Path, description = "摄像头ID")),
+    responses((status = OK, body = Ro<CameraVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn get_camera(
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Ro<CameraVo>>, CtrlError> {
+    let current_user_id = get_current_user_id(&headers)?;
+
+    let result = CameraSvc::get(id, current_user_id).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/capturer/cameras",
+    responses((status = OK, body = Ro<CameraVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn create_camera(
+    headers: HeaderMap,
+    Json(mut dto): Json<CameraCreateDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+    // 需要摄像头的管理权限，没有权限时返回403
+    if let Err(e) = CameraSvc::check_create(dto._current_user_id, &dto).await {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = CameraSvc::create(dto).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    put,
+    path = "/capturer/cameras/{id}",
+    params(("id" = String, Path, description = "摄像头ID")),
+    responses((status = OK, body = Ro<CameraVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn update_camera(
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(mut dto): Json<CameraUpdateDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+    // 需要摄像头的管理权限，没有权限时返回403
+    if let Err(e) = CameraSvc::check_update(dto._current_user_id, &id, &dto).await {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = CameraSvc::update(id, dto).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/capturer/cameras/{id}",
+    params(("id" = String, Path, description = "摄像头ID")),
+    responses((status = OK, body = Ro<String>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn delete_camera(
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Response, CtrlError> {
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    let current_user_id = get_current_user_id(&headers)?;
+    // 需要摄像头的管理权限，没有权限时返回403
+    if let Err(e) = CameraSvc::check_manage(current_user_id, &id).await {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = CameraSvc::delete(id).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    put,
+    path = "/capturer/cameras/{id}/credential",
+    params(("id" = String, Path, description = "摄像头ID")),
+    responses((status = OK, body = Ro<String>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn set_camera_credential(
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(mut dto): Json<CameraCredentialDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+    // 需要摄像头的管理权限，没有权限时返回403
+    if let Err(e) = CameraSvc::check_manage(dto._current_user_id, &id).await {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = CameraSvc::set_credential(id, dto).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/capturer/cameras/{id}/credential",
+    params(("id" = String, Path, description = "摄像头ID")),
+    responses((status = OK, body = Ro<String>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn delete_camera_credential(
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Response, CtrlError> {
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    let current_user_id = get_current_user_id(&headers)?;
+    // 需要摄像头的管理权限，没有权限时返回403
+    if let Err(e) = CameraSvc::check_manage(current_user_id, &id).await {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = CameraSvc::delete_credential(id).await?;
+    Ok(Json(result).into_response())
+}
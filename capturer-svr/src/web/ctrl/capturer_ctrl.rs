@@ -1,5 +1,14 @@
-use crate::dto::capturer_dto::{CapturerCaptureToJpegDto, CapturerGetStreamDto};
+use crate::auth::camera_acl::CameraAcl;
+use crate::config::capturer_config::AclPermission;
+use crate::dto::capturer_dto::{
+    CapturerCaptureBatchDto, CapturerCaptureClipDto, CapturerCaptureEventClipDto,
+    CapturerCaptureToJpegDto, CapturerGetStreamDto,
+};
 use crate::svc::capturer_svc::CapturerSvc;
+use crate::vo::capturer_vo::{CapturerCaptureBatchVo, CapturerSpoolStatsVo};
+use crate::web::ctrl::error_response::auth_error_response;
+use crate::web::ctrl::quota_ctrl::quota_error_response;
+use crate::web::ctrl::token_ctrl::authenticate_playback;
 use axum::body::Body;
 use axum::extract::Query;
 use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
@@ -22,12 +31,132 @@ use validator::Validate;
 pub async fn capture_to_jpeg(
     headers: HeaderMap,
     Json(mut dto): Json<CapturerCaptureToJpegDto>,
-) -> Result<Json<Ro<serde_json::Value>>, CtrlError> {
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+    // 解析流地址并检查摄像头的权限，没有权限时返回403
+    let stream_url = match CapturerSvc::resolve_stream_url(
+        dto.stream_url.take(),
+        dto.camera_id.as_deref(),
+        dto.sub_stream.unwrap_or(false),
+        dto._current_user_id,
+        AclPermission::Snapshot,
+    )
+    .await
+    {
+        Ok(stream_url) => stream_url,
+        Err(e) => return Ok(auth_error_response(e)),
+    };
+    // 超过抓拍配额时返回429
+    if let Err(e) = CapturerSvc::acquire_capture_quota(dto._current_user_id, 1) {
+        return Ok(quota_error_response(e));
+    }
+
+    let result = CapturerSvc::capture_to_jpeg(dto, stream_url).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/capturer/capture_batch",
+    responses((status = OK, body = Ro<CapturerCaptureBatchVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn capture_batch(
+    headers: HeaderMap,
+    Json(mut dto): Json<CapturerCaptureBatchDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+    // 超过抓拍配额时返回429，本次的数量本身超过上限时返回400
+    if let Err(e) = CapturerSvc::acquire_capture_quota(
+        dto._current_user_id,
+        dto.stream_urls.as_ref().map_or(0, Vec::len) + dto.camera_ids.as_ref().map_or(0, Vec::len),
+    ) {
+        return Ok(quota_error_response(e));
+    }
+
+    let result = CapturerSvc::capture_batch(dto).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/capturer/capture_clip",
+    responses((status = OK, body = Ro<OssObjRefVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn capture_clip(
+    headers: HeaderMap,
+    Json(mut dto): Json<CapturerCaptureClipDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
     // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
     dto._current_user_id = get_current_user_id(&headers)?;
+    // 解析流地址并检查摄像头的权限，没有权限时返回403
+    let stream_url = match CapturerSvc::resolve_stream_url(
+        dto.stream_url.take(),
+        dto.camera_id.as_deref(),
+        dto.sub_stream.unwrap_or(false),
+        dto._current_user_id,
+        AclPermission::Clip,
+    )
+    .await
+    {
+        Ok(stream_url) => stream_url,
+        Err(e) => return Ok(auth_error_response(e)),
+    };
+    // 先检查录制的时长，参数无效的请求不消耗配额
+    CapturerSvc::check_clip_seconds(dto.seconds.unwrap(), "录制的时长")?;
+    // 超过抓拍配额时返回429
+    if let Err(e) = CapturerSvc::acquire_capture_quota(dto._current_user_id, 1) {
+        return Ok(quota_error_response(e));
+    }
 
-    let result = CapturerSvc::capture_to_jpeg(dto).await?;
-    Ok(Json(result))
+    let result = CapturerSvc::capture_clip(dto, stream_url).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/capturer/capture_event_clip",
+    responses((status = OK, body = Ro<OssObjRefVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn capture_event_clip(
+    headers: HeaderMap,
+    Json(mut dto): Json<CapturerCaptureEventClipDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+    // 解析流地址并检查摄像头的权限，没有权限时返回403
+    let stream_url = match CapturerSvc::resolve_stream_url(
+        dto.stream_url.take(),
+        dto.camera_id.as_deref(),
+        dto.sub_stream.unwrap_or(false),
+        dto._current_user_id,
+        AclPermission::Clip,
+    )
+    .await
+    {
+        Ok(stream_url) => stream_url,
+        Err(e) => return Ok(auth_error_response(e)),
+    };
+    // 先检查事件后录制的时长，参数无效的请求不消耗配额
+    CapturerSvc::check_clip_seconds(dto.post_seconds.unwrap(), "事件后录制的时长")?;
+    // 超过抓拍配额时返回429
+    if let Err(e) = CapturerSvc::acquire_capture_quota(dto._current_user_id, 1) {
+        return Ok(quota_error_response(e));
+    }
+
+    let result = CapturerSvc::capture_event_clip(dto, stream_url).await?;
+    Ok(Json(result).into_response())
 }
 
 #[utoipa::path(
@@ -39,16 +168,43 @@ pub async fn capture_to_jpeg(
 #[debug_handler]
 pub async fn stream(
     headers: HeaderMap,
-    Query(dto): Query<CapturerGetStreamDto>,
-    // req: HttpRequest,
+    Query(mut dto): Query<CapturerGetStreamDto>,
 ) -> Result<Response, CtrlError> {
     dto.validate()?;
 
-    // TODO: 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
-    // dto._current_user_id = get_current_user_id(&headers)?;
+    // 按播放令牌或header认证当前用户，认证失败时返回401
+    dto._current_user_id = match authenticate_playback(
+        &headers,
+        dto.token.as_deref(),
+        dto.camera_id.as_deref(),
+        dto.stream_url.as_deref(),
+    ) {
+        Ok(user_id) => user_id,
+        Err(response) => return Ok(response),
+    };
+
+    // 解析流地址并检查摄像头的权限，没有权限时返回403
+    let stream_url = match CapturerSvc::resolve_stream_url(
+        dto.stream_url.take(),
+        dto.camera_id.as_deref(),
+        dto.sub_stream.unwrap_or(false),
+        dto._current_user_id,
+        AclPermission::Live,
+    )
+    .await
+    {
+        Ok(stream_url) => stream_url,
+        Err(e) => return Ok(auth_error_response(e)),
+    };
+
+    // 超过观看直播的配额时返回429，许可随直播流一起释放
+    let live_permit = match CapturerSvc::acquire_live_quota(dto._current_user_id) {
+        Ok(live_permit) => live_permit,
+        Err(e) => return Ok(quota_error_response(e)),
+    };
 
     // 把 SvcError 转换为 CtrlError，再转为 Box<dyn std::error::Error + Send + Sync>
-    let stream = CapturerSvc::stream(dto).await?;
+    let stream = CapturerSvc::stream(stream_url, live_permit).await?;
 
     let body = Body::from_stream(stream);
 
@@ -66,3 +222,22 @@ pub async fn stream(
 
     Ok((StatusCode::OK, response_headers, body).into_response())
 }
+
+#[utoipa::path(
+    get,
+    path = "/capturer/spool/stats",
+    responses((status = OK, body = Ro<CapturerSpoolStatsVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn spool_stats(headers: HeaderMap) -> Result<Response, CtrlError> {
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    let current_user_id = get_current_user_id(&headers)?;
+    // 暂存的统计信息只给管理员查看，不是管理员时返回403
+    if let Err(e) = CameraAcl::check_admin(current_user_id) {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = CapturerSvc::spool_stats().await?;
+    Ok(Json(result).into_response())
+}
@@ -0,0 +1,31 @@
+use crate::auth::auth_error::AuthError;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use robotech::ro::Ro;
+
+/// 失败的响应
+///
+/// 响应体与其它接口一样为JSON格式的Ro，只是状态码不同
+pub(crate) fn fail_response(status: StatusCode, msg: String) -> Response {
+    (status, Json(Ro::<()>::fail(msg))).into_response()
+}
+
+/// 认证或授权错误的响应
+///
+/// 令牌无效返回401，没有权限返回403，摄像头或任务不存在返回404，缺少参数返回400，其它错误返回500
+pub(crate) fn auth_error_response(e: AuthError) -> Response {
+    let status = match e {
+        AuthError::AuthTokenFormat
+        | AuthError::AuthTokenSignature
+        | AuthError::AuthTokenExpired => StatusCode::UNAUTHORIZED,
+        AuthError::AuthForbidden(_) => StatusCode::FORBIDDEN,
+        AuthError::AuthCameraNotFound(_) | AuthError::AuthJobNotFound(_) => StatusCode::NOT_FOUND,
+        AuthError::AuthNoTarget => StatusCode::BAD_REQUEST,
+        AuthError::AuthConfig(_)
+        | AuthError::AuthNoSecret
+        | AuthError::AuthCameraQuery(_)
+        | AuthError::AuthJobQuery(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    fail_response(status, e.to_string())
+}
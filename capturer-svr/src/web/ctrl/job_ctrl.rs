@@ -0,0 +1,109 @@
+use crate::config::capturer_config::AclPermission;
+use crate::dto::job_dto::{JobCreateDto, JobTimelapseDto};
+use crate::svc::capturer_svc::CapturerSvc;
+use crate::svc::job_svc::JobSvc;
+use crate::vo::job_vo::JobStatusVo;
+use crate::web::ctrl::error_response::auth_error_response;
+use axum::extract::Path;
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use axum::{debug_handler, Json};
+use robotech::macros::log_call;
+use robotech::ro::Ro;
+use robotech::web::ctrl_utils::get_current_user_id;
+use robotech::web::CtrlError;
+use validator::Validate;
+
+#[utoipa::path(
+    post,
+    path = "/capturer/jobs",
+    responses((status = OK, body = Ro<JobStatusVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn create_job(
+    headers: HeaderMap,
+    Json(mut dto): Json<JobCreateDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+    // 任务按创建者的抓拍权限定时抓拍，没有权限时返回403
+    let stream_url = match CapturerSvc::resolve_stream_url(
+        dto.stream_url.take(),
+        dto.camera_id.as_deref(),
+        dto.sub_stream.unwrap_or(false),
+        dto._current_user_id,
+        AclPermission::Snapshot,
+    )
+    .await
+    {
+        Ok(stream_url) => stream_url,
+        Err(e) => return Ok(auth_error_response(e)),
+    };
+
+    let result = JobSvc::create(dto, stream_url).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/capturer/jobs/{id}",
+    params(("id" = String, Path, description = "任务ID")),
+    responses((status = OK, body = Ro<String>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn delete_job(headers: HeaderMap, Path(id): Path<String>) -> Result<Response, CtrlError> {
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    let current_user_id = get_current_user_id(&headers)?;
+    // 只有任务的创建者或管理员可以删除任务，没有权限时返回403
+    if let Err(e) = JobSvc::check_job(&id, current_user_id, None).await {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = JobSvc::delete(id).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/capturer/jobs/status",
+    responses((status = OK, body = Ro<Vec<JobStatusVo>>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn list_job_status(headers: HeaderMap) -> Result<Json<Ro<Vec<JobStatusVo>>>, CtrlError> {
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    let current_user_id = get_current_user_id(&headers)?;
+
+    let result = JobSvc::list_status(current_user_id).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/capturer/jobs/{id}/timelapse",
+    params(("id" = String, Path, description = "任务ID")),
+    responses((status = OK, body = Ro<serde_json::Value>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn timelapse(
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(mut dto): Json<JobTimelapseDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+    // 延时视频由归档的图片合成，需要任务的权限及摄像头回放的权限，没有权限时返回403
+    if let Err(e) =
+        JobSvc::check_job(&id, dto._current_user_id, Some(AclPermission::Playback)).await
+    {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = JobSvc::timelapse(id, dto).await?;
+    Ok(Json(result).into_response())
+}
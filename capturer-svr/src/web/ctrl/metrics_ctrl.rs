@@ -0,0 +1,26 @@
+use crate::svc::metrics_svc::MetricsSvc;
+use axum::debug_handler;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use robotech::macros::log_call;
+use robotech::web::CtrlError;
+
+#[utoipa::path(
+    get,
+    path = "/capturer/metrics",
+    responses((status = OK, body = String))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn get_metrics() -> Result<Response, CtrlError> {
+    let metrics = MetricsSvc::render().await?;
+    Ok((
+        StatusCode::OK,
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; version=0.0.4; charset=utf-8"),
+        )],
+        metrics,
+    )
+        .into_response())
+}
@@ -1 +1,9 @@
-pub mod capturer_ctrl;
\ No newline at end of file
+pub mod camera_ctrl;
+pub mod capturer_ctrl;
+pub mod error_response;
+pub mod job_ctrl;
+pub mod metrics_ctrl;
+pub mod quota_ctrl;
+pub mod record_ctrl;
+pub mod session_ctrl;
+pub mod token_ctrl;
@@ -0,0 +1,53 @@
+use crate::dto::quota_dto::QuotaUsageDto;
+use crate::quota::quota_error::QuotaError;
+use crate::svc::quota_svc::QuotaSvc;
+use crate::vo::quota_vo::QuotaUsageVo;
+use crate::web::ctrl::error_response::fail_response;
+use axum::extract::Query;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::Response;
+use axum::{debug_handler, Json};
+use robotech::macros::log_call;
+use robotech::ro::Ro;
+use robotech::web::ctrl_utils::get_current_user_id;
+use robotech::web::CtrlError;
+use validator::Validate;
+
+#[utoipa::path(
+    get,
+    path = "/capturer/quota/usage",
+    responses((status = OK, body = Ro<QuotaUsageVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn get_quota_usage(
+    headers: HeaderMap,
+    Query(mut dto): Query<QuotaUsageDto>,
+) -> Result<Json<Ro<QuotaUsageVo>>, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+
+    let result = QuotaSvc::usage(dto).await?;
+    Ok(Json(result))
+}
+
+/// 配额错误的响应
+///
+/// 超过配额时返回429，并在Retry-After中给出建议的重试等待秒数；本次的数量本身超过上限时返回400，
+/// 其它错误返回500；响应体与其它接口一样为JSON格式的Ro
+pub(crate) fn quota_error_response(e: QuotaError) -> Response {
+    if let QuotaError::QuotaCaptureCount { .. } = e {
+        return fail_response(StatusCode::BAD_REQUEST, e.to_string());
+    }
+    match e.retry_after_secs() {
+        Some(seconds) => {
+            let mut response = fail_response(StatusCode::TOO_MANY_REQUESTS, e.to_string());
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+            response
+        }
+        None => fail_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
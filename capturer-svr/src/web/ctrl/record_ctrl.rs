@@ -0,0 +1,187 @@
+use crate::dto::record_dto::{RecordHlsSegmentDto, RecordListDto, RecordPlaybackDto};
+use crate::svc::record_svc::RecordSvc;
+use crate::vo::record_vo::RecordSegmentVo;
+use crate::web::ctrl::error_response::auth_error_response;
+use crate::web::ctrl::token_ctrl::authenticate_playback;
+use axum::body::Body;
+use axum::extract::Query;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{debug_handler, Json};
+use robotech::macros::log_call;
+use robotech::ro::Ro;
+use robotech::web::ctrl_utils::get_current_user_id;
+use robotech::web::CtrlError;
+use validator::Validate;
+
+#[utoipa::path(
+    get,
+    path = "/capturer/recordings",
+    responses((status = OK, body = Ro<Vec<RecordSegmentVo>>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn list_recordings(
+    headers: HeaderMap,
+    Query(mut dto): Query<RecordListDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+    dto._current_user_id = get_current_user_id(&headers)?;
+    // 检查回放摄像头录像的权限，没有权限时返回403
+    if let Err(e) = RecordSvc::check_playback(
+        dto._current_user_id,
+        dto.camera_id.as_deref().unwrap_or_default(),
+    )
+    .await
+    {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = RecordSvc::list(dto).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/capturer/recordings/playback.flv",
+    responses((status = OK))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn playback_flv(
+    headers: HeaderMap,
+    Query(mut dto): Query<RecordPlaybackDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+
+    // 按播放令牌或header认证当前用户，认证失败时返回401
+    dto._current_user_id = match authenticate_playback(
+        &headers,
+        dto.token.as_deref(),
+        dto.camera_id.as_deref(),
+        None,
+    ) {
+        Ok(user_id) => user_id,
+        Err(response) => return Ok(response),
+    };
+    // 检查回放摄像头录像的权限，没有权限时返回403
+    if let Err(e) = RecordSvc::check_playback(
+        dto._current_user_id,
+        dto.camera_id.as_deref().unwrap_or_default(),
+    )
+    .await
+    {
+        return Ok(auth_error_response(e));
+    }
+
+    let stream = RecordSvc::playback_flv(dto).await?;
+    let body = Body::from_stream(stream);
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("video/x-flv"),
+    );
+    response_headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+    Ok((StatusCode::OK, response_headers, body).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/capturer/recordings/playback.m3u8",
+    responses((status = OK))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn playback_hls(
+    headers: HeaderMap,
+    Query(mut dto): Query<RecordPlaybackDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+
+    // 按播放令牌或header认证当前用户，认证失败时返回401
+    dto._current_user_id = match authenticate_playback(
+        &headers,
+        dto.token.as_deref(),
+        dto.camera_id.as_deref(),
+        None,
+    ) {
+        Ok(user_id) => user_id,
+        Err(response) => return Ok(response),
+    };
+    // 检查回放摄像头录像的权限，没有权限时返回403
+    if let Err(e) = RecordSvc::check_playback(
+        dto._current_user_id,
+        dto.camera_id.as_deref().unwrap_or_default(),
+    )
+    .await
+    {
+        return Ok(auth_error_response(e));
+    }
+
+    let playlist = RecordSvc::playback_hls(dto).await?;
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/vnd.apple.mpegurl"),
+    );
+    response_headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+    Ok((StatusCode::OK, response_headers, playlist).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/capturer/recordings/segment.ts",
+    responses((status = OK))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn hls_segment(
+    headers: HeaderMap,
+    Query(mut dto): Query<RecordHlsSegmentDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+
+    // 按播放令牌或header认证当前用户，认证失败时返回401
+    dto._current_user_id = match authenticate_playback(
+        &headers,
+        dto.token.as_deref(),
+        dto.camera_id.as_deref(),
+        None,
+    ) {
+        Ok(user_id) => user_id,
+        Err(response) => return Ok(response),
+    };
+    // 检查回放摄像头录像的权限，没有权限时返回403
+    if let Err(e) = RecordSvc::check_playback(
+        dto._current_user_id,
+        dto.camera_id.as_deref().unwrap_or_default(),
+    )
+    .await
+    {
+        return Ok(auth_error_response(e));
+    }
+
+    let stream = RecordSvc::hls_segment(dto).await?;
+    let body = Body::from_stream(stream);
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("video/mp2t"));
+    response_headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+
+    Ok((StatusCode::OK, response_headers, body).into_response())
+}
@@ -0,0 +1,74 @@
+use crate::dto::session_dto::SessionRecordStartDto;
+use crate::svc::session_svc::SessionSvc;
+use crate::vo::session_vo::SessionVo;
+use crate::web::ctrl::error_response::auth_error_response;
+use axum::extract::Path;
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use axum::{debug_handler, Json};
+use oss_api_client::vo::oss_obj_ref::OssObjRefVo;
+use robotech::macros::log_call;
+use robotech::ro::Ro;
+use robotech::web::ctrl_utils::get_current_user_id;
+use robotech::web::CtrlError;
+use validator::Validate;
+
+#[utoipa::path(
+    get,
+    path = "/capturer/sessions",
+    responses((status = OK, body = Ro<Vec<SessionVo>>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn list_sessions(headers: HeaderMap) -> Result<Json<Ro<Vec<SessionVo>>>, CtrlError> {
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    let current_user_id = get_current_user_id(&headers)?;
+
+    let result = SessionSvc::list(current_user_id).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/capturer/sessions/{id}/record/start",
+    params(("id" = u64, Path, description = "会话ID")),
+    responses((status = OK, body = Ro<serde_json::Value>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn start_record(
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+    Json(mut dto): Json<SessionRecordStartDto>,
+) -> Result<Response, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+    // 检查会话的摄像头的权限，没有权限时返回403
+    if let Err(e) = SessionSvc::check_record(id, dto._current_user_id).await {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = SessionSvc::start_record(id, dto).await?;
+    Ok(Json(result).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/capturer/sessions/{id}/record/stop",
+    params(("id" = u64, Path, description = "会话ID")),
+    responses((status = OK, body = Ro<OssObjRefVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn stop_record(headers: HeaderMap, Path(id): Path<u64>) -> Result<Response, CtrlError> {
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    let current_user_id = get_current_user_id(&headers)?;
+    // 检查会话的摄像头的权限，没有权限时返回403
+    if let Err(e) = SessionSvc::check_record(id, current_user_id).await {
+        return Ok(auth_error_response(e));
+    }
+
+    let result = SessionSvc::stop_record(id).await?;
+    Ok(Json(result).into_response())
+}
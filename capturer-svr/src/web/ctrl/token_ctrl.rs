@@ -0,0 +1,50 @@
+use crate::dto::token_dto::TokenPlaybackIssueDto;
+use crate::svc::token_svc::TokenSvc;
+use crate::vo::token_vo::TokenPlaybackVo;
+use crate::web::ctrl::error_response::{auth_error_response, fail_response};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use axum::{debug_handler, Json};
+use robotech::macros::log_call;
+use robotech::ro::Ro;
+use robotech::web::ctrl_utils::get_current_user_id;
+use robotech::web::CtrlError;
+use validator::Validate;
+
+#[utoipa::path(
+    post,
+    path = "/capturer/playback_token",
+    responses((status = OK, body = Ro<TokenPlaybackVo>))
+)]
+#[log_call]
+#[debug_handler]
+pub async fn issue_playback_token(
+    headers: HeaderMap,
+    Json(mut dto): Json<TokenPlaybackIssueDto>,
+) -> Result<Json<Ro<TokenPlaybackVo>>, CtrlError> {
+    dto.validate()?;
+    // 从header中解析当前用户ID，如果没有或解析失败则抛出ApiError
+    dto._current_user_id = get_current_user_id(&headers)?;
+
+    let result = TokenSvc::issue_playback(dto).await?;
+    Ok(Json(result))
+}
+
+/// 认证播放视频流的用户
+///
+/// 浏览器播放视频流时无法设置请求头，有播放令牌时按令牌认证，否则按请求头认证；
+/// 认证失败时返回401的响应(响应体与其它接口一样为JSON格式的Ro)
+pub(crate) fn authenticate_playback(
+    headers: &HeaderMap,
+    token: Option<&str>,
+    camera_id: Option<&str>,
+    stream_url: Option<&str>,
+) -> Result<u64, Response> {
+    match token {
+        Some(token) => {
+            TokenSvc::verify_playback(token, camera_id, stream_url).map_err(auth_error_response)
+        }
+        None => get_current_user_id(headers)
+            .map_err(|e| fail_response(StatusCode::UNAUTHORIZED, e.to_string())),
+    }
+}
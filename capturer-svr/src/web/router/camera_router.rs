@@ -0,0 +1,12 @@
+use robotech::macros::router;
+
+#[router(routes[
+    ("/capturer/cameras", get(list_cameras)),                                // 查询摄像头
+    ("/capturer/cameras", post(create_camera)),                              // 创建摄像头
+    ("/capturer/cameras/{id}", get(get_camera)),                             // 获取摄像头
+    ("/capturer/cameras/{id}", put(update_camera)),                          // 修改摄像头
+    ("/capturer/cameras/{id}", delete(delete_camera)),                       // 删除摄像头
+    ("/capturer/cameras/{id}/credential", put(set_camera_credential)),       // 保存摄像头凭据
+    ("/capturer/cameras/{id}/credential", delete(delete_camera_credential)), // 删除摄像头凭据
+])]
+struct CameraRouter;
@@ -2,6 +2,10 @@ use robotech::macros::router;
 
 #[router(routes[
     ("/capturer/capture_to_jpeg", post(capture_to_jpeg)),   // 抓拍图片
+    ("/capturer/capture_batch", post(capture_batch)),       // 批量抓拍图片
+    ("/capturer/capture_clip", post(capture_clip)),         // 录制短视频
+    ("/capturer/capture_event_clip", post(capture_event_clip)), // 导出事件短视频(含事件前的预录)
     ("/capturer/stream.live.flv", get(stream)),             // 直播视频流
+    ("/capturer/spool/stats", get(spool_stats)),            // 上传暂存统计
 ])]
 struct CapturerRouter;
@@ -0,0 +1,9 @@
+use robotech::macros::router;
+
+#[router(routes[
+    ("/capturer/jobs", post(create_job)),                   // 创建定时抓拍任务
+    ("/capturer/jobs/{id}", delete(delete_job)),            // 删除定时抓拍任务
+    ("/capturer/jobs/status", get(list_job_status)),        // 定时抓拍任务状态
+    ("/capturer/jobs/{id}/timelapse", post(timelapse)),     // 生成延时视频
+])]
+struct JobRouter;
@@ -0,0 +1,6 @@
+use robotech::macros::router;
+
+#[router(routes[
+    ("/capturer/metrics", get(get_metrics)), // Prometheus格式的指标
+])]
+struct MetricsRouter;
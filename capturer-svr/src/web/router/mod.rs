@@ -1 +1,8 @@
-pub mod capturer_router;
\ No newline at end of file
+pub mod camera_router;
+pub mod capturer_router;
+pub mod job_router;
+pub mod metrics_router;
+pub mod quota_router;
+pub mod record_router;
+pub mod session_router;
+pub mod token_router;
@@ -0,0 +1,6 @@
+use robotech::macros::router;
+
+#[router(routes[
+    ("/capturer/quota/usage", get(get_quota_usage)), // 查询配额使用情况
+])]
+struct QuotaRouter;
@@ -0,0 +1,9 @@
+use robotech::macros::router;
+
+#[router(routes[
+    ("/capturer/recordings", get(list_recordings)),                 // 查询录像分段
+    ("/capturer/recordings/playback.flv", get(playback_flv)),       // HTTP-FLV回放录像
+    ("/capturer/recordings/playback.m3u8", get(playback_hls)),      // HLS回放录像的播放列表
+    ("/capturer/recordings/segment.ts", get(hls_segment)),          // HLS回放录像的分段
+])]
+struct RecordRouter;
@@ -0,0 +1,8 @@
+use robotech::macros::router;
+
+#[router(routes[
+    ("/capturer/sessions", get(list_sessions)),                     // 正在进行的会话
+    ("/capturer/sessions/{id}/record/start", post(start_record)),   // 开始会话的按需录像
+    ("/capturer/sessions/{id}/record/stop", post(stop_record)),     // 停止会话的按需录像并上传
+])]
+struct SessionRouter;
@@ -0,0 +1,6 @@
+use robotech::macros::router;
+
+#[router(routes[
+    ("/capturer/playback_token", post(issue_playback_token)), // 签发播放令牌
+])]
+struct TokenRouter;
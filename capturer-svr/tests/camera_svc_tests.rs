@@ -0,0 +1,147 @@
+#[cfg(test)]
+#[ctor::ctor]
+fn init_tests() {
+    robotech::env::init_env();
+    robotech::log::init_log();
+}
+
+#[cfg(test)]
+mod tests {
+    use capturer_svr::auth::auth_error::AuthError;
+    use capturer_svr::camera::camera_store::init_camera_store;
+    use capturer_svr::config::capturer_config::{
+        init_capturer_config, AclConfig, AclPermission, AclRuleConfig, CapturerConfig,
+    };
+    use capturer_svr::dto::camera_dto::{CameraCreateDto, CameraUpdateDto};
+    use capturer_svr::ffmpeg::ffmpeg_budget::init_ffmpeg_budget;
+    use capturer_svr::stream::stream_manager::init_stream_manager;
+    use capturer_svr::svc::camera_svc::CameraSvc;
+    use tracing::info;
+
+    // 管理员
+    const ADMIN: u64 = 1;
+    // 租户A的管理者，可以管理分组tenant-a的摄像头
+    const OWNER_A: u64 = 2;
+    // 租户B的管理者，可以管理分组tenant-b的摄像头
+    const OWNER_B: u64 = 3;
+    // 租户A的观看者，只能观看分组tenant-a的摄像头
+    const VIEWER_A: u64 = 4;
+
+    const STREAM_URL_A: &str = "rtsp://10.0.0.1:554/tenant-a";
+
+    fn rule(user_id: u64, camera_group: &str, permissions: Vec<AclPermission>) -> AclRuleConfig {
+        AclRuleConfig {
+            users: vec![user_id],
+            roles: vec![],
+            cameras: vec![],
+            camera_groups: vec![camera_group.to_string()],
+            permissions,
+        }
+    }
+
+    fn create_dto(id: &str, group: &str, main_stream_url: &str) -> CameraCreateDto {
+        CameraCreateDto {
+            id: Some(id.to_string()),
+            name: None,
+            group: Some(group.to_string()),
+            main_stream_url: Some(main_stream_url.to_string()),
+            sub_stream_url: None,
+            fallback_stream_urls: None,
+            tags: None,
+            always_on: None,
+            _current_user_id: 0,
+        }
+    }
+
+    fn is_forbidden<T: std::fmt::Debug>(result: Result<T, AuthError>) -> bool {
+        matches!(result, Err(AuthError::AuthForbidden(_)))
+    }
+
+    #[tokio::test]
+    async fn test_non_owner_cannot_manage_camera() {
+        let db_path = std::env::temp_dir()
+            .join(format!("capturer-svr-camera-acl-{}.db", std::process::id()))
+            .display()
+            .to_string();
+        let _ = std::fs::remove_file(&db_path);
+        let mut capturer_config = CapturerConfig::default();
+        capturer_config.camera_store.db_path = db_path;
+        capturer_config.acl = AclConfig {
+            enabled: true,
+            admin_users: vec![ADMIN],
+            roles: vec![],
+            rules: vec![
+                rule(
+                    OWNER_A,
+                    "tenant-a",
+                    vec![AclPermission::Live, AclPermission::Manage],
+                ),
+                rule(OWNER_B, "tenant-b", vec![AclPermission::Manage]),
+                rule(VIEWER_A, "tenant-a", vec![AclPermission::Live]),
+            ],
+        };
+        init_capturer_config(capturer_config.clone()).expect("初始化配置失败");
+        init_ffmpeg_budget(&capturer_config).expect("初始化ffmpeg进程预算失败");
+        init_stream_manager(capturer_config).expect("初始化流管理器失败");
+        init_camera_store().expect("初始化摄像头存储失败");
+
+        // 租户A的管理者在自己的分组下新增摄像头
+        let dto = create_dto("cam-a", "tenant-a", STREAM_URL_A);
+        CameraSvc::check_create(OWNER_A, &dto)
+            .await
+            .expect("租户A的管理者应该可以新增摄像头");
+        CameraSvc::create(dto).await.expect("新增摄像头失败");
+
+        // 其它租户看不到摄像头(与不存在一样)，观看者不能管理
+        assert!(matches!(
+            CameraSvc::check_manage(OWNER_B, "cam-a").await,
+            Err(AuthError::AuthCameraNotFound(_))
+        ));
+        assert!(is_forbidden(
+            CameraSvc::check_manage(VIEWER_A, "cam-a").await
+        ));
+        CameraSvc::check_manage(OWNER_A, "cam-a")
+            .await
+            .expect("租户A的管理者应该可以管理摄像头");
+        CameraSvc::check_manage(ADMIN, "cam-a")
+            .await
+            .expect("管理员应该可以管理摄像头");
+
+        // 不能修改其它租户的摄像头，也不能把摄像头移到不能管理的分组
+        let update_dto = CameraUpdateDto {
+            name: None,
+            group: Some("tenant-b".to_string()),
+            main_stream_url: Some(STREAM_URL_A.to_string()),
+            sub_stream_url: None,
+            fallback_stream_urls: None,
+            tags: None,
+            always_on: None,
+            _current_user_id: 0,
+        };
+        assert!(CameraSvc::check_update(OWNER_B, "cam-a", &update_dto)
+            .await
+            .is_err());
+        assert!(is_forbidden(
+            CameraSvc::check_update(OWNER_A, "cam-a", &update_dto).await
+        ));
+
+        // 不能在其它租户的分组下新增摄像头，也不能把其它租户的流地址登记到自己的分组下
+        assert!(is_forbidden(
+            CameraSvc::check_create(
+                OWNER_B,
+                &create_dto("cam-b", "tenant-a", "rtsp://10.0.0.2/b")
+            )
+            .await
+        ));
+        assert!(is_forbidden(
+            CameraSvc::check_create(OWNER_B, &create_dto("cam-b", "tenant-b", STREAM_URL_A)).await
+        ));
+        CameraSvc::check_create(
+            OWNER_B,
+            &create_dto("cam-b", "tenant-b", "rtsp://10.0.0.2/b"),
+        )
+        .await
+        .expect("租户B的管理者应该可以在自己的分组下新增摄像头");
+        info!("其它租户及观看者不能管理摄像头");
+    }
+}
@@ -0,0 +1,58 @@
+#[cfg(test)]
+#[ctor::ctor]
+fn init_tests() {
+    robotech::env::init_env();
+    robotech::log::init_log();
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use capturer_svr::ffmpeg::ffmpeg_process::FfmpegProcess;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+    use std::time::Duration;
+    use tracing::info;
+
+    /// 进程是否仍在运行(已退出的僵尸进程的cmdline为空)
+    fn is_running(pid: u32) -> bool {
+        std::fs::read(format!("/proc/{pid}/cmdline")).is_ok_and(|cmdline| !cmdline.is_empty())
+    }
+
+    #[tokio::test]
+    async fn test_sweep_orphans() {
+        // 本测试专用的标记，只清理本测试启动的进程，不影响本机的其它进程
+        let marker = format!("capturer-svr-test-{}", std::process::id());
+
+        // 本实例启动的子进程不是遗留的子进程
+        let mut child = Command::new("sleep")
+            .arg0(FfmpegProcess::instance_marker(&marker))
+            .arg("30")
+            .process_group(0)
+            .spawn()
+            .expect("启动子进程失败");
+
+        // 模拟上一个实例遗留的子进程: 记录的实例的进程ID已被复用(启动时间不同)，
+        // 后台的子shell保留argv[0]的标记，外层shell退出后被收养
+        let output = Command::new("sh")
+            .arg0(format!("{marker}:{}:0", std::process::id()))
+            .args(["-c", "(sleep 30; true) >/dev/null 2>&1 & echo $!"])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .output()
+            .expect("启动遗留的子进程失败");
+        let orphan_pid: u32 = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .expect("无法获取遗留的子进程ID");
+        assert!(is_running(orphan_pid));
+
+        let swept = FfmpegProcess::sweep_marked_orphans(&marker, Duration::from_secs(2)).await;
+        info!("终止了{swept}个遗留的子进程");
+        assert_eq!(swept, 1);
+        assert!(!is_running(orphan_pid));
+        assert!(child.try_wait().expect("检查子进程失败").is_none());
+
+        child.kill().expect("终止子进程失败");
+        child.wait().expect("回收子进程失败");
+    }
+}
@@ -0,0 +1,190 @@
+#[cfg(test)]
+#[ctor::ctor]
+fn init_tests() {
+    robotech::env::init_env();
+    robotech::log::init_log();
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use capturer_svr::stream::flv_splicer::FlvSplicer;
+    use capturer_svr::stream::flv_tag::{
+        FlvSequenceHeaders, FlvTag, TAG_TYPE_AUDIO, TAG_TYPE_SCRIPT, TAG_TYPE_VIDEO,
+    };
+
+    fn tag(tag_type: u8, timestamp: u32, data: &'static [u8]) -> FlvTag {
+        FlvTag {
+            tag_type,
+            timestamp,
+            data: Bytes::from_static(data),
+        }
+    }
+
+    fn script(timestamp: u32) -> FlvTag {
+        tag(TAG_TYPE_SCRIPT, timestamp, b"onMetaData")
+    }
+
+    fn video_header(timestamp: u32, config: &'static [u8]) -> FlvTag {
+        tag(TAG_TYPE_VIDEO, timestamp, config)
+    }
+
+    fn audio_header(timestamp: u32) -> FlvTag {
+        tag(TAG_TYPE_AUDIO, timestamp, &[0xAF, 0, 0x12, 0x10])
+    }
+
+    fn keyframe(timestamp: u32) -> FlvTag {
+        tag(TAG_TYPE_VIDEO, timestamp, &[0x17, 1, 0, 0, 0])
+    }
+
+    fn inter_frame(timestamp: u32) -> FlvTag {
+        tag(TAG_TYPE_VIDEO, timestamp, &[0x27, 1, 0, 0, 0])
+    }
+
+    fn audio(timestamp: u32) -> FlvTag {
+        tag(TAG_TYPE_AUDIO, timestamp, &[0xAF, 1, 0x21])
+    }
+
+    /// 与会话的监督者一样拼接一次拉流的标签，返回输出的标签
+    fn splice_run(
+        splicer: &mut FlvSplicer,
+        sequence_headers: &mut FlvSequenceHeaders,
+        tags: Vec<FlvTag>,
+    ) -> Vec<FlvTag> {
+        let mut output = Vec::new();
+        for tag in tags {
+            if let Some(tag) = splicer.splice(tag, sequence_headers) {
+                sequence_headers.update(&tag);
+                output.push(tag);
+            }
+        }
+        output
+    }
+
+    fn timestamps(tags: &[FlvTag]) -> Vec<u32> {
+        tags.iter().map(|tag| tag.timestamp).collect()
+    }
+
+    #[test]
+    fn test_first_run_keeps_original_timestamps() {
+        let mut splicer = FlvSplicer::new();
+        let mut sequence_headers = FlvSequenceHeaders::default();
+        assert!(splicer.is_pass_through());
+        let output = splice_run(
+            &mut splicer,
+            &mut sequence_headers,
+            vec![
+                script(0),
+                video_header(0, &[0x17, 0, 1]),
+                inter_frame(1000),
+                keyframe(1040),
+            ],
+        );
+        // 第一次拉流不等待关键帧，时间戳与原样转发的数据一致
+        assert_eq!(timestamps(&output), vec![0, 0, 1000, 1040]);
+    }
+
+    #[test]
+    fn test_timestamps_stay_monotonic_across_splices() {
+        let mut splicer = FlvSplicer::new();
+        let mut sequence_headers = FlvSequenceHeaders::default();
+        // 第一次拉流原样转发，只记录时间戳
+        splicer.observe(2000);
+        splicer.observe(1960);
+
+        splicer.restart();
+        assert!(!splicer.is_pass_through());
+        let output = splice_run(
+            &mut splicer,
+            &mut sequence_headers,
+            vec![keyframe(100), audio(50), inter_frame(140), audio(150)],
+        );
+        // 从上一个标签之后继续计时，音频先于视频开始时不回退
+        assert_eq!(timestamps(&output), vec![2040, 2040, 2080, 2090]);
+
+        splicer.restart();
+        let output = splice_run(
+            &mut splicer,
+            &mut sequence_headers,
+            vec![keyframe(0), inter_frame(40)],
+        );
+        assert_eq!(timestamps(&output), vec![2130, 2170]);
+        assert_eq!(splicer.splice_count(), 2);
+    }
+
+    #[test]
+    fn test_drops_video_before_keyframe_after_restart() {
+        let mut splicer = FlvSplicer::new();
+        let mut sequence_headers = FlvSequenceHeaders::default();
+        splice_run(&mut splicer, &mut sequence_headers, vec![keyframe(0)]);
+
+        splicer.restart();
+        let output = splice_run(
+            &mut splicer,
+            &mut sequence_headers,
+            vec![
+                inter_frame(0),
+                audio(10),
+                inter_frame(40),
+                keyframe(80),
+                inter_frame(120),
+            ],
+        );
+        // 关键帧之前的视频帧被丢弃，音频照常输出，关键帧之后的视频帧都输出
+        let kinds: Vec<_> = output
+            .iter()
+            .map(|tag| (tag.is_video(), tag.is_keyframe()))
+            .collect();
+        assert_eq!(kinds, vec![(false, false), (true, true), (true, false)]);
+    }
+
+    #[test]
+    fn test_resends_only_changed_headers() {
+        let mut splicer = FlvSplicer::new();
+        let mut sequence_headers = FlvSequenceHeaders::default();
+        splice_run(
+            &mut splicer,
+            &mut sequence_headers,
+            vec![
+                script(0),
+                video_header(0, &[0x17, 0, 1]),
+                audio_header(0),
+                keyframe(0),
+            ],
+        );
+
+        // 头部信息相同时不再重复发送
+        splicer.restart();
+        let output = splice_run(
+            &mut splicer,
+            &mut sequence_headers,
+            vec![
+                script(0),
+                video_header(0, &[0x17, 0, 1]),
+                audio_header(0),
+                keyframe(0),
+            ],
+        );
+        assert_eq!(output.len(), 1);
+        assert!(output[0].is_keyframe());
+
+        // 视频的编码参数变化时发送新的序列头
+        splicer.restart();
+        let output = splice_run(
+            &mut splicer,
+            &mut sequence_headers,
+            vec![
+                script(0),
+                video_header(0, &[0x17, 0, 2]),
+                audio_header(0),
+                keyframe(0),
+            ],
+        );
+        assert_eq!(output.len(), 2);
+        assert!(output[0].is_sequence_header());
+        assert_eq!(
+            sequence_headers.video.map(|video| video.data),
+            Some(Bytes::from_static(&[0x17, 0, 2]))
+        );
+    }
+}
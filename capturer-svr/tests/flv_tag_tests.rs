@@ -0,0 +1,105 @@
+#[cfg(test)]
+#[ctor::ctor]
+fn init_tests() {
+    robotech::env::init_env();
+    robotech::log::init_log();
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use capturer_svr::stream::flv_tag::{
+        encode_flv_header, FlvChunk, FlvDemuxer, FlvTag, TAG_TYPE_AUDIO, TAG_TYPE_SCRIPT,
+        TAG_TYPE_VIDEO,
+    };
+
+    fn tag(tag_type: u8, timestamp: u32, data: &'static [u8]) -> FlvTag {
+        FlvTag {
+            tag_type,
+            timestamp,
+            data: Bytes::from_static(data),
+        }
+    }
+
+    /// FLV文件头及脚本数据、序列头和若干普通音视频标签
+    fn flv_stream() -> Vec<u8> {
+        let mut stream = encode_flv_header(true, true).to_vec();
+        for tag in [
+            tag(TAG_TYPE_SCRIPT, 0, b"onMetaData"),
+            tag(TAG_TYPE_VIDEO, 0, &[0x17, 0, 1, 2]),
+            tag(TAG_TYPE_AUDIO, 0, &[0xAF, 0, 3]),
+            tag(TAG_TYPE_VIDEO, 0, &[0x17, 1, 4, 4, 4, 4]),
+            tag(TAG_TYPE_AUDIO, 20, &[0xAF, 1, 5]),
+            tag(TAG_TYPE_VIDEO, 40, &[0x27, 1, 6]),
+            tag(TAG_TYPE_VIDEO, 80, &[0x27, 1, 7, 7]),
+        ] {
+            stream.extend_from_slice(&tag.to_bytes());
+        }
+        stream
+    }
+
+    #[test]
+    fn test_push_lazy_passes_through_media_tags() {
+        let stream = flv_stream();
+        let header_len = encode_flv_header(true, true).len();
+        for chunk_size in 1..=stream.len() {
+            for assemble_every in [0, 1, 2] {
+                let mut demuxer = FlvDemuxer::new();
+                let mut output = Vec::new();
+                let mut header_tags = 0;
+                let mut last_timestamp = 0;
+                for (i, chunk) in stream.chunks(chunk_size).enumerate() {
+                    // 0: 始终原样转发; 1: 始终组装; 2: 交替切换
+                    let assemble = assemble_every == 1 || (assemble_every == 2 && i % 2 == 0);
+                    for flv_chunk in demuxer.push_lazy(Bytes::copy_from_slice(chunk), assemble) {
+                        match flv_chunk {
+                            FlvChunk::Tag(tag) => {
+                                if tag.is_script() || tag.is_sequence_header() {
+                                    header_tags += 1;
+                                }
+                                last_timestamp = tag.timestamp;
+                                output.extend_from_slice(&tag.to_bytes());
+                            }
+                            FlvChunk::Raw {
+                                data,
+                                last_timestamp: timestamp,
+                            } => {
+                                assert!(!assemble, "组装时不应该原样转发");
+                                last_timestamp = timestamp;
+                                output.extend_from_slice(&data);
+                            }
+                        }
+                    }
+                }
+                // 无论如何切分和切换，输出的都是完整且不变的标签
+                assert_eq!(output, stream[header_len..], "chunk_size: {chunk_size}");
+                assert_eq!(header_tags, 3);
+                assert_eq!(last_timestamp, 80);
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_skips_data_before_flv_signature() {
+        let mut stream = b"[flv @ 0x1] FLV2 garbage".to_vec();
+        stream.extend_from_slice(&flv_stream());
+        let mut demuxer = FlvDemuxer::new();
+        let tags = demuxer.push(&stream);
+        assert_eq!(
+            demuxer.flv_header().map(|flv_header| flv_header.to_vec()),
+            Some(encode_flv_header(true, true).to_vec())
+        );
+        assert_eq!(tags.len(), 7);
+        assert!(tags[0].is_script());
+    }
+
+    #[test]
+    fn test_push_waits_for_complete_flv_header() {
+        let mut demuxer = FlvDemuxer::new();
+        assert!(demuxer.push(b"xxF").is_empty());
+        assert!(demuxer.flv_header().is_none());
+        let stream = flv_stream();
+        demuxer.push(&stream);
+        assert!(demuxer.flv_header().is_some());
+    }
+}
@@ -0,0 +1,159 @@
+#[cfg(test)]
+#[ctor::ctor]
+fn init_tests() {
+    robotech::env::init_env();
+    robotech::log::init_log();
+}
+
+#[cfg(test)]
+mod tests {
+    use capturer_svr::auth::auth_error::AuthError;
+    use capturer_svr::camera::camera_store::init_camera_store;
+    use capturer_svr::config::capturer_config::{
+        init_capturer_config, AclConfig, AclPermission, AclRuleConfig, CapturerConfig, JobConfig,
+    };
+    use capturer_svr::dto::camera_dto::CameraCreateDto;
+    use capturer_svr::dto::job_dto::JobCreateDto;
+    use capturer_svr::ffmpeg::ffmpeg_budget::init_ffmpeg_budget;
+    use capturer_svr::scheduler::job_scheduler::{get_job_scheduler, init_job_scheduler};
+    use capturer_svr::scheduler::scheduler_error::SchedulerError;
+    use capturer_svr::stream::stream_manager::init_stream_manager;
+    use capturer_svr::svc::camera_svc::CameraSvc;
+    use capturer_svr::svc::capturer_svc::CapturerSvc;
+    use capturer_svr::svc::job_svc::JobSvc;
+    use std::time::Duration;
+    use tracing::info;
+
+    // 租户A的用户，可以抓拍分组tenant-a的摄像头
+    const USER_A: u64 = 2;
+    // 租户B的用户，可以抓拍分组tenant-b的摄像头
+    const USER_B: u64 = 3;
+
+    const STREAM_URL_A: &str = "rtsp://10.0.0.1:554/tenant-a";
+
+    fn rule(user_id: u64, camera_group: &str) -> AclRuleConfig {
+        AclRuleConfig {
+            users: vec![user_id],
+            roles: vec![],
+            cameras: vec![],
+            camera_groups: vec![camera_group.to_string()],
+            permissions: vec![AclPermission::Snapshot],
+        }
+    }
+
+    fn job_config(id: &str, stream_url: Option<&str>, camera_id: Option<&str>) -> JobConfig {
+        JobConfig {
+            id: id.to_string(),
+            stream_url: stream_url.map(str::to_string),
+            camera_id: camera_id.map(str::to_string),
+            sub_stream: false,
+            cron: None,
+            interval: Some(Duration::from_secs(60 * 60)),
+            bucket: None,
+            key_template: "{job_id}/{ts}".to_string(),
+            webhook: None,
+            user_id: USER_A,
+            archive: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_job_by_camera_id() {
+        let db_path = std::env::temp_dir()
+            .join(format!("capturer-svr-job-camera-{}.db", std::process::id()))
+            .display()
+            .to_string();
+        let _ = std::fs::remove_file(&db_path);
+        let mut capturer_config = CapturerConfig::default();
+        capturer_config.camera_store.db_path = db_path;
+        capturer_config.acl = AclConfig {
+            enabled: true,
+            admin_users: vec![],
+            roles: vec![],
+            rules: vec![rule(USER_A, "tenant-a"), rule(USER_B, "tenant-b")],
+        };
+        init_capturer_config(capturer_config.clone()).expect("初始化配置失败");
+        init_ffmpeg_budget(&capturer_config).expect("初始化ffmpeg进程预算失败");
+        init_stream_manager(capturer_config).expect("初始化流管理器失败");
+        init_camera_store().expect("初始化摄像头存储失败");
+        init_job_scheduler(vec![]).expect("初始化任务调度器失败");
+        CameraSvc::create(CameraCreateDto {
+            id: Some("cam-a".to_string()),
+            name: None,
+            group: Some("tenant-a".to_string()),
+            main_stream_url: Some(STREAM_URL_A.to_string()),
+            sub_stream_url: None,
+            fallback_stream_urls: None,
+            tags: None,
+            always_on: None,
+            _current_user_id: 0,
+        })
+        .await
+        .expect("新增摄像头失败");
+
+        // 按摄像头ID创建任务时按摄像头的分组检查抓拍权限
+        assert!(matches!(
+            CapturerSvc::resolve_stream_url(
+                None,
+                Some("cam-a"),
+                false,
+                USER_B,
+                AclPermission::Snapshot
+            )
+            .await,
+            Err(AuthError::AuthForbidden(_))
+        ));
+        let stream_url = CapturerSvc::resolve_stream_url(
+            None,
+            Some("cam-a"),
+            false,
+            USER_A,
+            AclPermission::Snapshot,
+        )
+        .await
+        .expect("租户A的用户应该可以抓拍摄像头");
+        assert_eq!(stream_url, STREAM_URL_A);
+
+        // 任务只记录摄像头ID，每次运行时重新解析摄像头的流地址
+        let dto = JobCreateDto {
+            id: Some("job-cam-a".to_string()),
+            stream_url: None,
+            camera_id: Some("cam-a".to_string()),
+            sub_stream: None,
+            cron: None,
+            interval: Some(Duration::from_secs(60 * 60)),
+            bucket: None,
+            key_template: None,
+            webhook: None,
+            archive: None,
+            _current_user_id: USER_A,
+        };
+        JobSvc::create(dto, stream_url).await.expect("创建任务失败");
+        let created_job_config = get_job_scheduler()
+            .expect("获取任务调度器失败")
+            .get_job("job-cam-a")
+            .expect("查询任务失败")
+            .expect("任务不存在");
+        assert_eq!(created_job_config.camera_id.as_deref(), Some("cam-a"));
+        assert_eq!(created_job_config.stream_url, None);
+        JobSvc::check_job("job-cam-a", USER_A, Some(AclPermission::Snapshot))
+            .await
+            .expect("任务的创建者应该可以操作任务");
+        assert!(matches!(
+            JobSvc::check_job("job-cam-a", USER_B, None).await,
+            Err(AuthError::AuthForbidden(_))
+        ));
+
+        // 流地址和摄像头ID必须且只能配置一个
+        let job_scheduler = get_job_scheduler().expect("获取任务调度器失败");
+        assert!(matches!(
+            job_scheduler.add_api_job(job_config("job-both", Some(STREAM_URL_A), Some("cam-a"))),
+            Err(SchedulerError::InvalidTarget(..))
+        ));
+        assert!(matches!(
+            job_scheduler.add_api_job(job_config("job-none", None, None)),
+            Err(SchedulerError::InvalidTarget(..))
+        ));
+        info!("按摄像头ID创建的任务已按摄像头检查抓拍权限");
+    }
+}
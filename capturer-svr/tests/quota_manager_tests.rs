@@ -0,0 +1,85 @@
+#[cfg(test)]
+#[ctor::ctor]
+fn init_tests() {
+    robotech::env::init_env();
+    robotech::log::init_log();
+}
+
+#[cfg(test)]
+mod tests {
+    use capturer_svr::quota::quota_error::QuotaError;
+    use capturer_svr::quota::quota_manager::{until_next_month, CaptureWindow, CAPTURE_WINDOW};
+    use chrono::{TimeZone, Utc};
+    use std::time::{Duration, Instant};
+
+    fn retry_after(result: Result<(), QuotaError>) -> Duration {
+        match result {
+            Err(QuotaError::QuotaCaptureRate { retry_after, .. }) => retry_after,
+            other => panic!("应该超过抓拍次数的上限: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_capture_window_retry_after() {
+        let start = Instant::now();
+        let mut window = CaptureWindow::default();
+        window.acquire(start, 2, 3).unwrap();
+        window
+            .acquire(start + Duration::from_secs(10), 1, 3)
+            .unwrap();
+        assert_eq!(window.count(start + Duration::from_secs(10)), 3);
+
+        // 需要1次时等最早的抓拍移出窗口，需要3次时等第3次抓拍移出窗口
+        let now = start + Duration::from_secs(20);
+        assert_eq!(
+            retry_after(window.acquire(now, 1, 3)),
+            CAPTURE_WINDOW - Duration::from_secs(20)
+        );
+        assert_eq!(
+            retry_after(window.acquire(now, 3, 3)),
+            CAPTURE_WINDOW - Duration::from_secs(10)
+        );
+        // 超过上限的请求不占用次数
+        assert_eq!(window.count(now), 3);
+
+        // 最早的2次移出窗口后可以再抓拍2次
+        let now = start + CAPTURE_WINDOW;
+        window.acquire(now, 2, 3).unwrap();
+        assert_eq!(window.count(now), 3);
+        assert!(!window.is_expired(now));
+        assert!(window.is_expired(now + CAPTURE_WINDOW));
+    }
+
+    #[test]
+    fn test_capture_window_rejects_count_over_limit() {
+        let mut window = CaptureWindow::default();
+        let result = window.acquire(Instant::now(), 4, 3);
+        assert!(matches!(
+            result,
+            Err(QuotaError::QuotaCaptureCount { count: 4, limit: 3 })
+        ));
+        assert!(result.unwrap_err().retry_after().is_none());
+    }
+
+    #[test]
+    fn test_retry_after_secs_rounds_up() {
+        let error = |retry_after| QuotaError::QuotaCaptureRate {
+            limit: 1,
+            retry_after,
+        };
+        assert_eq!(error(Duration::from_millis(1)).retry_after_secs(), Some(1));
+        assert_eq!(error(Duration::from_secs(2)).retry_after_secs(), Some(2));
+        assert_eq!(
+            error(Duration::from_millis(2001)).retry_after_secs(),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_until_next_month() {
+        let now = Utc.with_ymd_and_hms(2025, 12, 31, 23, 59, 0).unwrap();
+        assert_eq!(until_next_month(now), Duration::from_secs(60));
+        let now = Utc.with_ymd_and_hms(2025, 2, 28, 0, 0, 0).unwrap();
+        assert_eq!(until_next_month(now), Duration::from_secs(24 * 3600));
+    }
+}
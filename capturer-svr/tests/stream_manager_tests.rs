@@ -0,0 +1,120 @@
+#[cfg(test)]
+#[ctor::ctor]
+fn init_tests() {
+    robotech::env::init_env();
+    robotech::log::init_log();
+}
+
+#[cfg(test)]
+mod tests {
+    use capturer_svr::config::capturer_config::CapturerConfig;
+    use capturer_svr::ffmpeg::ffmpeg_error::FfmpegError;
+    use capturer_svr::stream::stream_manager::StreamManager;
+    use std::time::Duration;
+    use tokio::time::{sleep, timeout};
+    use tracing::info;
+
+    // 不可达的流地址，常驻会话会一直等待重新拉流
+    const UNREACHABLE_URL: &str = "rtsp://127.0.0.1:1/unreachable";
+
+    // 流管理器本身的后台任务(定时清除过期会话、转发会话事件)的数量
+    const MANAGER_TASKS: usize = 2;
+
+    #[tokio::test]
+    async fn test_manager_tasks_stop_after_drop() {
+        let stream_manager =
+            StreamManager::new(CapturerConfig::default()).expect("创建流管理器失败");
+        let tracker = stream_manager.task_tracker();
+        // 定时清除过期会话及转发会话事件的任务
+        assert_eq!(tracker.len(), MANAGER_TASKS);
+
+        drop(stream_manager);
+        timeout(Duration::from_secs(5), tracker.wait())
+            .await
+            .expect("流管理器被销毁后后台任务没有退出");
+        assert!(tracker.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_session_task_stops_after_drop() {
+        let stream_manager =
+            StreamManager::new(CapturerConfig::default()).expect("创建流管理器失败");
+        let tracker = stream_manager.task_tracker();
+        let mut events = stream_manager.pin_session(UNREACHABLE_URL);
+        // 保持常驻会话的任务(会话创建成功时还有会话的任务)
+        assert!(tracker.len() > MANAGER_TASKS);
+
+        // 常驻会话在等待重新拉流期间也要立即退出
+        drop(stream_manager);
+        timeout(Duration::from_secs(5), tracker.wait())
+            .await
+            .expect("流管理器被销毁后常驻会话的任务没有退出");
+        assert!(events.recv().await.is_none());
+        info!("常驻会话的任务已退出");
+    }
+
+    #[tokio::test]
+    async fn test_pinned_session_task_stops_after_receiver_dropped() {
+        let stream_manager =
+            StreamManager::new(CapturerConfig::default()).expect("创建流管理器失败");
+        let tracker = stream_manager.task_tracker();
+        let events = stream_manager.pin_session(UNREACHABLE_URL);
+        assert!(tracker.len() > MANAGER_TASKS);
+
+        drop(events);
+        timeout(Duration::from_secs(5), async {
+            while tracker.len() > MANAGER_TASKS {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("接收者被丢弃后常驻会话的任务没有退出");
+        // 只剩流管理器本身的任务
+        assert_eq!(tracker.len(), MANAGER_TASKS);
+    }
+
+    #[tokio::test]
+    async fn test_apply_config_syncs_ring_buffers() {
+        let stream_manager =
+            StreamManager::new(CapturerConfig::default()).expect("创建流管理器失败");
+        let tracker = stream_manager.task_tracker();
+        assert_eq!(tracker.len(), MANAGER_TASKS);
+
+        // 新增预录缓冲的流：保持常驻会话及填充缓冲的任务
+        let mut capturer_config = CapturerConfig::default();
+        capturer_config.ring_buffer.stream_urls = vec![UNREACHABLE_URL.to_string()];
+        stream_manager.apply_config(capturer_config.clone());
+        let ring_buffer_tasks = tracker.len();
+        assert!(ring_buffer_tasks >= MANAGER_TASKS + 2);
+
+        // 配置未变化时保留原来的任务(重新创建时会先启动新的任务)
+        stream_manager.apply_config(capturer_config);
+        assert!(tracker.len() <= ring_buffer_tasks);
+
+        // 不再预录缓冲后任务退出，流管理器本身不被替换
+        stream_manager.apply_config(CapturerConfig::default());
+        timeout(Duration::from_secs(5), async {
+            while tracker.len() > MANAGER_TASKS {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("不再预录缓冲后常驻会话的任务没有退出");
+        assert_eq!(tracker.len(), MANAGER_TASKS);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_tasks_and_rejects_new_sessions() {
+        let stream_manager =
+            StreamManager::new(CapturerConfig::default()).expect("创建流管理器失败");
+        let tracker = stream_manager.task_tracker();
+        let mut events = stream_manager.pin_session(UNREACHABLE_URL);
+
+        stream_manager.shutdown(Duration::from_secs(5)).await;
+        assert!(tracker.is_empty());
+        assert!(events.recv().await.is_none());
+        // 关闭后不再创建新的会话
+        let result = stream_manager.get_cmd_receiver(UNREACHABLE_URL).await;
+        assert!(matches!(result, Err(FfmpegError::FfmpegShutdown(_))));
+    }
+}
@@ -0,0 +1,186 @@
+#[cfg(test)]
+#[ctor::ctor]
+fn init_tests() {
+    robotech::env::init_env();
+    robotech::log::init_log();
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use capturer_svr::config::capturer_config::{init_capturer_config, CapturerConfig};
+    use capturer_svr::ffmpeg::ffmpeg_budget::{init_ffmpeg_budget, BudgetPool};
+    use capturer_svr::stream::stream_manager::StreamManager;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use std::sync::LazyLock;
+    use std::time::Duration;
+    use tokio::time::{sleep, timeout};
+    use tracing::info;
+
+    // 假的拉流地址，由假的ffprobe和ffmpeg"拉流"
+    const FAKE_URL: &str = "rtsp://127.0.0.1:8554/fake";
+
+    // 假的H.265拉流地址(需要转码)
+    const FAKE_HEVC_URL: &str = "rtsp://127.0.0.1:8554/fake-hevc";
+
+    // 流管理器本身的后台任务(定时清除过期会话、转发会话事件)的数量
+    const MANAGER_TASKS: usize = 2;
+
+    // 假的ffprobe: 输出视频流的信息(地址含hevc时为H.265，否则为H.264)
+    const FAKE_FFPROBE: &str = r#"#!/bin/sh
+case "$*" in
+  *hevc*) codec=hevc ;;
+  *) codec=h264 ;;
+esac
+echo '{"programs":[],"streams":[{"codec_type":"video","codec_name":"'$codec'","width":640,"height":360,"r_frame_rate":"25/1"}]}'
+"#;
+
+    // 假的ffmpeg: 输出FLV文件头后一直运行，直到被终止
+    const FAKE_FFMPEG: &str = r#"#!/bin/sh
+printf 'FLV\001\001\000\000\000\011\000\000\000\000'
+exec sleep 60
+"#;
+
+    /// 进程是否仍在运行(已退出的僵尸进程的cmdline为空)
+    fn is_running(pid: u32) -> bool {
+        std::fs::read(format!("/proc/{pid}/cmdline")).is_ok_and(|cmdline| !cmdline.is_empty())
+    }
+
+    fn write_script(dir: &Path, name: &str, content: &str) {
+        let path = dir.join(name);
+        std::fs::write(&path, content).expect("写入假的命令失败");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .expect("设置假的命令的权限失败");
+    }
+
+    /// 测试共用的配置：第一次使用时将假的ffprobe和ffmpeg放在PATH的最前面(本测试文件的测试都使用假的命令)，
+    /// 并初始化全局的配置及ffmpeg进程预算
+    static CAPTURER_CONFIG: LazyLock<CapturerConfig> = LazyLock::new(|| {
+        let dir =
+            std::env::temp_dir().join(format!("capturer-svr-fake-source-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("创建假的命令的目录失败");
+        write_script(&dir, "ffprobe", FAKE_FFPROBE);
+        write_script(&dir, "ffmpeg", FAKE_FFMPEG);
+        let path = std::env::var("PATH").unwrap_or_default();
+        // SAFETY: 在启动任何子进程之前设置，之后不再修改环境变量
+        unsafe { std::env::set_var("PATH", format!("{}:{path}", dir.display())) };
+
+        let mut capturer_config = CapturerConfig::default();
+        // 假的拉流地址为本机地址
+        capturer_config.url_policy.denied_cidrs = vec![];
+        // 没有接收者后立即过期，尽快删除会话
+        capturer_config.cmd.receiver_count_check_interval = Some(Duration::from_millis(100));
+        capturer_config.session.timeout_check_interval = Some(Duration::from_millis(100));
+        capturer_config.session.timeout_period = Some(Duration::ZERO);
+        // 只有一个转码会话的许可，转码的配置变化时先终止旧的子进程再主动重新拉流；
+        // 失败后重新拉流的退避间隔远长于等待的超时，主动重新拉流时不应等待退避间隔
+        capturer_config.budget.transcode_sessions = 1;
+        capturer_config.session.respawn_initial_interval = Some(Duration::from_secs(60));
+        init_capturer_config(capturer_config.clone()).expect("初始化配置失败");
+        init_ffmpeg_budget(&capturer_config).expect("初始化ffmpeg进程预算失败");
+        capturer_config
+    });
+
+    #[tokio::test]
+    async fn test_session_tasks_stop_after_session_removed() {
+        let stream_manager = StreamManager::new(CAPTURER_CONFIG.clone()).expect("创建流管理器失败");
+        let tracker = stream_manager.task_tracker();
+        let (receiver, _) = stream_manager
+            .get_cmd_receiver(FAKE_URL)
+            .await
+            .expect("创建会话失败");
+        let sessions = stream_manager.list_sessions();
+        assert_eq!(sessions.len(), 1);
+        let child_id = sessions[0].child_id;
+        assert!(is_running(child_id));
+        // 监督会话及监听会话接收者数量的任务
+        assert_eq!(tracker.len(), MANAGER_TASKS + 2);
+
+        // 没有接收者后会话过期被删除，会话的任务退出，子进程被终止并回收
+        drop(receiver);
+        timeout(Duration::from_secs(10), async {
+            while tracker.len() > MANAGER_TASKS {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("会话被删除后会话的任务没有退出");
+        assert!(stream_manager.list_sessions().is_empty());
+        assert!(!is_running(child_id));
+        info!("会话被删除后会话的任务已退出，子进程{child_id}已被回收");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_open_shares_one_session() {
+        let stream_manager = StreamManager::new(CAPTURER_CONFIG.clone()).expect("创建流管理器失败");
+        let tracker = stream_manager.task_tracker();
+        // 两个请求并发地打开同一地址，都在探测及拉流后才插入会话
+        let (first, second) = tokio::join!(
+            stream_manager.get_cmd_receiver(FAKE_URL),
+            stream_manager.get_cmd_receiver(FAKE_URL)
+        );
+        let (first_receiver, _) = first.expect("创建会话失败");
+        let (second_receiver, _) = second.expect("创建会话失败");
+
+        // 只保留一个会话，两个请求都订阅该会话
+        let sessions = stream_manager.list_sessions();
+        assert_eq!(sessions.len(), 1);
+        assert!(is_running(sessions[0].child_id));
+        assert_eq!(tracker.len(), MANAGER_TASKS + 2);
+
+        // 两个接收者都关闭后会话过期被删除
+        drop(first_receiver);
+        drop(second_receiver);
+        timeout(Duration::from_secs(10), async {
+            while tracker.len() > MANAGER_TASKS {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("会话被删除后会话的任务没有退出");
+        assert!(stream_manager.list_sessions().is_empty());
+        info!("并发打开同一地址只创建了一个会话");
+    }
+
+    #[tokio::test]
+    async fn test_transcode_config_changed_restarts_session() {
+        let stream_manager = StreamManager::new(CAPTURER_CONFIG.clone()).expect("创建流管理器失败");
+        let (_receiver, _) = stream_manager
+            .get_cmd_receiver(FAKE_HEVC_URL)
+            .await
+            .expect("创建会话失败");
+        let sessions = stream_manager.list_sessions();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].budget_pool, BudgetPool::Transcode);
+        let session_id = sessions[0].id;
+        let child_id = sessions[0].child_id;
+
+        // 转码的配置变化后，会话用新的子进程重新拉流，会话不变，旧的子进程被终止并回收
+        let mut capturer_config = CAPTURER_CONFIG.clone();
+        capturer_config.transcode.crf += 1;
+        stream_manager.apply_config(capturer_config);
+        let new_child_id = timeout(Duration::from_secs(10), async {
+            loop {
+                let sessions = stream_manager.list_sessions();
+                if let Some(session) = sessions.first()
+                    && session.child_id != child_id
+                {
+                    assert_eq!(session.id, session_id);
+                    return session.child_id;
+                }
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("转码的配置变化后会话没有重新拉流");
+        assert!(is_running(new_child_id));
+        timeout(Duration::from_secs(10), async {
+            while is_running(child_id) {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("重新拉流后旧的子进程没有被回收");
+        info!("转码的配置变化后会话已重新拉流，子进程: {child_id} -> {new_child_id}");
+    }
+}